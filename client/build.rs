@@ -1,4 +1,4 @@
-use std::{io::Write, path::Path};
+use std::{ffi::OsString, path::Path};
 
 use fs_extra::copy_items;
 use fs_extra::dir::CopyOptions;
@@ -7,84 +7,86 @@ use shaderc::{Compiler, ShaderKind};
 
 const ASSETS_DIR: &str = "res";
 const BUILD_DIR_ENV_NAME: &str = "OUT_DIR";
+const SHADER_SOURCE_PATTERNS: [(&str, ShaderKind); 3] = [
+    ("res/shaders/*.vert", ShaderKind::Vertex),
+    ("res/shaders/*.frag", ShaderKind::Fragment),
+    ("res/shaders/*.comp", ShaderKind::Compute),
+];
 
+/// Gets the name of the compiled SPIR-V for a shader source, which is the source's name with `.spv` appended (eg. `basic.vert.spv`)
+fn spirv_file_name(source_path: &Path) -> OsString {
+    let mut file_name = source_path.file_name().unwrap().to_os_string();
+    file_name.push(".spv");
+    file_name
+}
+
+/// Compiles a GLSL shader to SPIR-V, writing it into the output directory as `<name>.spv`
+///
+/// If the shader can't be read, compiled, or written, returns `Err` with the reason, including the compiler's log of errors
 fn compile_shader_file(
     compiler: &Compiler,
     path: &Path,
     shader_kind: ShaderKind,
-) -> Result<(), &'static str> {
-    // TODO - Make this actually compile shaders
+    output_dir: &Path,
+) -> Result<(), String> {
     let file_path = path.to_str().unwrap();
-    let file_contents = std::fs::read(file_path).expect("Failed to read shader source file");
-    let source =
-        String::from_utf8(file_contents).expect("Shader source file contains invalid characters");
+    let source = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read {} ({})", file_path, error))?;
     let compilation_result = compiler
         .compile_into_spirv(source.as_str(), shader_kind, file_path, "main", None)
-        .expect("Shader file could not be compiled");
-    let spirv = compilation_result.as_binary_u8();
-
-    let mut file_writer = std::fs::OpenOptions::new()
-        .truncate(true)
-        .write(true)
-        .open(path.join(".spv"))
-        .expect("Failed to open writer for SPIR-V file");
-    file_writer
-        .write_all(spirv)
-        .expect("Failed to write spirv file");
+        .map_err(|error| format!("Failed to compile {}:\n{}", file_path, error))?;
+    if compilation_result.get_num_warnings() > 0 {
+        for warning in compilation_result.get_warning_messages().lines() {
+            println!("cargo:warning={}", warning);
+        }
+    }
 
-    Ok(())
+    let spirv_path = output_dir.join(spirv_file_name(path));
+    std::fs::write(&spirv_path, compilation_result.as_binary_u8())
+        .map_err(|error| format!("Failed to write {} ({})", spirv_path.display(), error))
 }
 
-fn compile_shader_files() -> Result<(), &'static str> {
-    let compiler = Compiler::new().expect("Failed to create shader compiler");
-
-    for vertex_src in glob("client/res/shaders/*.vert").unwrap() {
-        match vertex_src {
-            Ok(path) => {
-                if compile_shader_file(&compiler, &path, ShaderKind::Vertex).is_err() {
-                    return Err("Failed to compile a vertex shader");
-                }
-            }
-            Err(_error) => {
-                return Err("A glob match was invalid");
+/// Compiles every shader source into the output directory, next to the assets copied alongside the executable
+///
+/// If any shaders fail to compile, returns `Err` with the errors from all of them
+fn compile_shader_files(compiler: &Compiler, output_dir: &Path) -> Result<(), String> {
+    let mut errors = vec![];
+    for (pattern, shader_kind) in SHADER_SOURCE_PATTERNS {
+        for shader_src in glob(pattern).unwrap() {
+            let path = shader_src.map_err(|_error| String::from("A glob match was invalid"))?;
+            println!("cargo:rerun-if-changed={}", path.display());
+            if let Err(error) = compile_shader_file(compiler, &path, shader_kind, output_dir) {
+                errors.push(error);
             }
         }
     }
 
-    for fragment_src in glob("client/res/shaders/*.frag").unwrap() {
-        match fragment_src {
-            Ok(path) => {
-                if compile_shader_file(&compiler, &path, ShaderKind::Fragment).is_err() {
-                    return Err("Failed to compile a fragment shader");
-                }
-            }
-            Err(_error) => {
-                return Err("A glob match was invalid");
-            }
-        }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
     }
-
-    Ok(())
 }
 
 fn main() -> Result<(), String> {
-    // println!("cargo:rerun-if-changed={}/*", ASSETS_DIR);
-
-    let compilation_result = compile_shader_files();
-    if compilation_result.is_err() {
-        return Err(String::from("Failed to compile all shaders"));
-    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", ASSETS_DIR);
 
     let out_dir = std::env::var(BUILD_DIR_ENV_NAME).unwrap();
     let build_dir = format!("{}/../../..", out_dir);
     let copy_options = CopyOptions::new().overwrite(true);
-    let copy_result = copy_items(&[ASSETS_DIR], &build_dir, &copy_options);
-
-    match copy_result {
-        Ok(_count) => Ok(()),
-        Err(error) => Err(format!(
+    copy_items(&[ASSETS_DIR], &build_dir, &copy_options).map_err(|error| {
+        format!(
             "Failed to copy files to {} (error code {})",
             build_dir, error
-        )),
-    }
+        )
+    })?;
+
+    let compiler = Compiler::new().ok_or_else(|| {
+        String::from(
+            "The shader compiler (shaderc) could not be initialised. Install the shaderc native library (or the Vulkan SDK)",
+        )
+    })?;
+    let shader_output_dir = Path::new(&build_dir).join(ASSETS_DIR).join("shaders");
+    compile_shader_files(&compiler, &shader_output_dir)
 }
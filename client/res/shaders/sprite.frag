@@ -0,0 +1,13 @@
+#version 450
+
+layout (location = 0) in vec2 fragUv;
+layout (location = 1) in vec4 fragColour;
+layout (location = 2) flat in uint fragTextureId;
+
+layout (set = 1, binding = 0) uniform sampler2DArray spriteTextures;
+
+layout (location = 0) out vec4 outColour;
+
+void main() {
+    outColour = texture(spriteTextures, vec3(fragUv, float(fragTextureId))) * fragColour;
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout (location = 0) in vec2 inPosition;
+layout (location = 1) in vec2 inUv;
+layout (location = 2) in vec4 inColour;
+layout (location = 3) in uint inTextureId;
+
+layout (location = 0) out vec2 fragUv;
+layout (location = 1) out vec4 fragColour;
+layout (location = 2) flat out uint fragTextureId;
+
+void main() {
+    gl_Position = vec4(inPosition, 0.0, 1.0);
+    fragUv = inUv;
+    fragColour = inColour;
+    fragTextureId = inTextureId;
+}
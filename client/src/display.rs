@@ -0,0 +1,134 @@
+use tracing::{debug, info};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window};
+
+/// A resolution and refresh rate that a monitor can be switched to for exclusive fullscreen
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoMode {
+    pub size: PhysicalSize<u32>,
+    /// The number of bits per pixel
+    pub bit_depth: u16,
+    /// The refresh rate, in thousandths of a hertz
+    pub refresh_rate_millihertz: u32,
+    handle: winit::monitor::VideoMode,
+}
+
+impl From<winit::monitor::VideoMode> for VideoMode {
+    fn from(handle: winit::monitor::VideoMode) -> Self {
+        Self {
+            size: handle.size(),
+            bit_depth: handle.bit_depth(),
+            refresh_rate_millihertz: handle.refresh_rate_millihertz(),
+            handle,
+        }
+    }
+}
+
+/// A monitor connected to the system, and the video modes it supports
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+    /// A human-readable name for the monitor, or `None` if it's been disconnected
+    pub name: Option<String>,
+    /// The monitor's current resolution
+    pub size: PhysicalSize<u32>,
+    /// The position of the monitor's top-left corner on the desktop
+    pub position: PhysicalPosition<i32>,
+    /// The ratio of physical pixels to logical pixels on the monitor
+    pub scale_factor: f64,
+    /// The monitor's current refresh rate in thousandths of a hertz, or `None` if it couldn't be determined
+    pub refresh_rate_millihertz: Option<u32>,
+    /// The video modes that can be used for exclusive fullscreen on the monitor
+    pub video_modes: Vec<VideoMode>,
+    handle: MonitorHandle,
+}
+
+impl From<MonitorHandle> for Monitor {
+    fn from(handle: MonitorHandle) -> Self {
+        Self {
+            name: handle.name(),
+            size: handle.size(),
+            position: handle.position(),
+            scale_factor: handle.scale_factor(),
+            refresh_rate_millihertz: handle.refresh_rate_millihertz(),
+            video_modes: handle.video_modes().map(VideoMode::from).collect(),
+            handle,
+        }
+    }
+}
+
+/// How the window is shown on the desktop
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum DisplayMode {
+    /// A regular window with decorations
+    #[default]
+    Windowed,
+    /// A borderless window covering the whole of a monitor, at the monitor's current resolution
+    Borderless(Monitor),
+    /// Exclusive control of a monitor, switched to the given video mode
+    Exclusive(VideoMode),
+}
+
+/// Lists the monitors connected to the system, with the primary monitor first if it's known
+///
+/// # Arguments
+///
+/// * `window`: Any window, which is used to query the monitors
+pub fn available_monitors(window: &Window) -> Vec<Monitor> {
+    let primary_monitor = window.primary_monitor();
+    let mut monitors: Vec<Monitor> = window.available_monitors().map(Monitor::from).collect();
+    monitors.sort_by_key(|monitor| Some(&monitor.handle) != primary_monitor.as_ref());
+
+    debug!("Found {} monitor(s)", monitors.len());
+    monitors
+}
+
+/// Gets the monitor the window is currently on, or `None` if it can't be determined
+///
+/// # Arguments
+///
+/// * `window`: The window to look for
+pub fn current_monitor(window: &Window) -> Option<Monitor> {
+    window.current_monitor().map(Monitor::from)
+}
+
+/// Switches the window between windowed, borderless fullscreen, and exclusive fullscreen, or moves it to another monitor.
+/// The window's size changes as a result, so the renderer's swapchain needs to be recreated afterwards
+///
+/// # Arguments
+///
+/// * `window`: The window to change
+/// * `display_mode`: How the window should be shown
+///
+/// # Examples
+///
+/// ```no_run
+/// # use winit::window::Window;
+/// # fn example(window: &Window) {
+/// use client::display::{available_monitors, set_display_mode, DisplayMode};
+///
+/// let monitors = available_monitors(&window);
+/// // Go fullscreen on the second monitor, at its highest refresh rate
+/// let video_mode = monitors[1]
+///     .video_modes
+///     .iter()
+///     .filter(|mode| mode.size == monitors[1].size)
+///     .max_by_key(|mode| mode.refresh_rate_millihertz)
+///     .unwrap();
+/// set_display_mode(&window, &DisplayMode::Exclusive(video_mode.clone()));
+/// # }
+/// ```
+pub fn set_display_mode(window: &Window, display_mode: &DisplayMode) {
+    let fullscreen = match display_mode {
+        DisplayMode::Windowed => None,
+        DisplayMode::Borderless(monitor) => {
+            Some(Fullscreen::Borderless(Some(monitor.handle.clone())))
+        }
+        DisplayMode::Exclusive(video_mode) => {
+            Some(Fullscreen::Exclusive(video_mode.handle.clone()))
+        }
+    };
+
+    info!("Switching display mode to {:?}", display_mode);
+    window.set_fullscreen(fullscreen);
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use winit::event::{DeviceEvent, ElementState, WindowEvent};
+use winit::keyboard::Key;
+use winit::window::{CursorGrabMode, Window};
+
+/// A mouse button, as recorded in an `InputEvent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => MouseButton::Left,
+            winit::event::MouseButton::Right => MouseButton::Right,
+            winit::event::MouseButton::Middle => MouseButton::Middle,
+            winit::event::MouseButton::Back => MouseButton::Back,
+            winit::event::MouseButton::Forward => MouseButton::Forward,
+            winit::event::MouseButton::Other(id) => MouseButton::Other(id),
+        }
+    }
+}
+
+/// The subset of winit's events that affect input, in a form that can be recorded to a file and replayed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    /// The window gained or lost focus
+    Focused(bool),
+    /// A key was pressed or released. The key is the character it types, or the name of the key if it doesn't type one (eg. `"Escape"`)
+    Key {
+        key: String,
+        pressed: bool,
+        repeat: bool,
+    },
+    /// A mouse button was pressed or released
+    MouseButton { button: MouseButton, pressed: bool },
+    /// The mouse moved, in unspecified units
+    MouseMotion { delta: (f64, f64) },
+}
+
+impl InputEvent {
+    /// Converts a window event to an `InputEvent`, or `None` if it doesn't affect input
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: The event received from the event loop
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::Focused(focused) => Some(InputEvent::Focused(*focused)),
+            WindowEvent::KeyboardInput { event, .. } => Some(InputEvent::Key {
+                key: key_name(&event.logical_key),
+                pressed: event.state == ElementState::Pressed,
+                repeat: event.repeat,
+            }),
+            WindowEvent::MouseInput { state, button, .. } => Some(InputEvent::MouseButton {
+                button: MouseButton::from(*button),
+                pressed: *state == ElementState::Pressed,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Converts a device event to an `InputEvent`, or `None` if it doesn't affect input
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: The event received from the event loop
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            DeviceEvent::MouseMotion { delta } => Some(InputEvent::MouseMotion { delta: *delta }),
+            _ => None,
+        }
+    }
+}
+
+/// Gets the name of a key as used by `InputEvent::Key`, which is the character it types, or its name if it doesn't type one
+///
+/// # Arguments
+///
+/// * `key`: The logical key, as reported by winit
+pub fn key_name(key: &Key) -> String {
+    match key {
+        Key::Character(character) => character.to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// Input gathered from window and device events, for game logic to read once per frame.
+/// Every input event should go through [`InputState::handle_event()`], so that input can be recorded and replayed
+#[derive(Default)]
+pub struct InputState {
+    mouse_delta: (f64, f64),
+    cursor_grabbed: bool,
+    key_presses: Vec<String>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grabs or releases the cursor, hiding it whilst it's grabbed so that mouse movement can be used for looking around.
+    /// Locking the cursor in place is preferred, but if the platform doesn't support it then the cursor is confined to the window instead
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window to grab the cursor in
+    /// * `grabbed`: Whether the cursor should be grabbed
+    pub fn set_cursor_grabbed(&mut self, window: &Window, grabbed: bool) {
+        if grabbed {
+            // X11 doesn't support locking and macOS doesn't support confining, so try both
+            if let Err(error) = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+            {
+                warn!("Failed to grab the cursor ({})", error);
+                return;
+            }
+        } else if let Err(error) = window.set_cursor_grab(CursorGrabMode::None) {
+            warn!("Failed to release the cursor ({})", error);
+        }
+
+        debug!("Cursor grabbed: {}", grabbed);
+        window.set_cursor_visible(!grabbed);
+        self.cursor_grabbed = grabbed;
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    /// Gets whether the cursor is currently grabbed
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    /// Updates the input state from an event. Clicking grabs the cursor and Escape releases it
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window the input is for
+    /// * `event`: The event, either converted from a winit event or replayed from a recording
+    pub fn handle_event(&mut self, window: &Window, event: &InputEvent) {
+        match event {
+            InputEvent::Focused(focused) => self.handle_focus_changed(window, *focused),
+            InputEvent::Key {
+                key,
+                pressed: true,
+                repeat: false,
+            } => {
+                if key == "Escape" && self.cursor_grabbed {
+                    self.set_cursor_grabbed(window, false);
+                } else {
+                    self.key_presses.push(key.clone());
+                }
+            }
+            InputEvent::Key { .. } => {}
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                pressed: true,
+            } if !self.cursor_grabbed => self.set_cursor_grabbed(window, true),
+            InputEvent::MouseButton { .. } => {}
+            InputEvent::MouseMotion { delta } => self.handle_mouse_motion(*delta),
+        }
+    }
+
+    /// Gets the keys pressed since this was last called (ignoring key repeats), in the order they were pressed, and resets them
+    pub fn take_key_presses(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.key_presses)
+    }
+
+    /// Handles `WindowEvent::Focused`. Platforms release the grab when the window loses focus, so it has to be taken again when focus returns
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window whose focus changed
+    /// * `focused`: Whether the window is now focused
+    pub fn handle_focus_changed(&mut self, window: &Window, focused: bool) {
+        if focused && self.cursor_grabbed {
+            self.set_cursor_grabbed(window, true);
+        }
+    }
+
+    /// Handles `DeviceEvent::MouseMotion`, accumulating raw mouse movement whilst the cursor is grabbed
+    ///
+    /// # Arguments
+    ///
+    /// * `delta`: The change in position, in unspecified units
+    pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
+        if self.cursor_grabbed {
+            self.mouse_delta.0 += delta.0;
+            self.mouse_delta.1 += delta.1;
+        }
+    }
+
+    /// Gets the mouse movement accumulated since this was last called, and resets it
+    pub fn take_mouse_delta(&mut self) -> (f64, f64) {
+        std::mem::take(&mut self.mouse_delta)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod display;
+pub mod input;
+pub mod renderer;
+pub mod replay;
@@ -1,27 +1,56 @@
-use crate::renderer::VertexRenderer;
+use client::input::{InputEvent, InputState};
+use client::renderer::{GraphicsSettings, Renderer, VertexRenderer};
+use client::replay::{InputPlayback, InputRecorder};
 use std::path::Path;
 use std::process::ExitCode;
-use std::time::{Duration, SystemTime};
-use tracing::{debug, debug_span, error, info};
+use tracing::{debug, debug_span, error, info, warn};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use winit::event::{Event, WindowEvent};
+use winit::window::Window;
 
-mod renderer;
+/// Where the player's graphics settings are kept, relative to the working directory
+const SETTINGS_PATH: &str = "settings.toml";
+/// Setting this to a path records the session's input to that file when the window is closed
+const RECORD_INPUT_ENV_NAME: &str = "CLIENT_RECORD_INPUT";
+/// Setting this to the path of a recording replays its input instead of the live input, exiting once it's finished
+const REPLAY_INPUT_ENV_NAME: &str = "CLIENT_REPLAY_INPUT";
+/// Setting this to a list of log levels by target (eg. `client=trace,ash=warn`) overrides which logs are shown
+const LOG_FILTER_ENV_NAME: &str = "GAME_LOG";
 
 fn main() -> ExitCode {
     let span = debug_span!("Client");
     let _guard = span.enter();
 
-    // TODO - Env override for logging level
     #[cfg(debug_assertions)]
     let error_level = tracing::Level::DEBUG;
     #[cfg(not(debug_assertions))]
     let error_level = tracing::Level::ERROR;
 
+    let log_filter = std::env::var(LOG_FILTER_ENV_NAME)
+        .ok()
+        .map(|filter| (filter.parse::<Targets>(), filter));
+    let targets = match &log_filter {
+        Some((Ok(targets), _)) => targets.clone(),
+        _ => Targets::new().with_default(error_level),
+    };
+
     tracing_subscriber::fmt()
-        .with_max_level(error_level)
+        .with_max_level(tracing::Level::TRACE)
         .with_target(true)
+        .finish()
+        .with(targets)
         .init();
 
+    // This can only be logged once the subscriber exists
+    if let Some((Err(error), filter)) = log_filter {
+        warn!(
+            "Ignoring invalid {} filter {:?} ({}), using the default level {}",
+            LOG_FILTER_ENV_NAME, filter, error, error_level
+        );
+    }
+
     info!("Client using Common {}", common::version());
 
     let event_loop = winit::event_loop::EventLoopBuilder::new().build().unwrap();
@@ -34,7 +63,15 @@ fn main() -> ExitCode {
         .build(&event_loop)
         .unwrap();
 
-    let mut renderer = VertexRenderer::new("survival-game", (0, 1, 0), &window);
+    let settings = GraphicsSettings::load(Path::new(SETTINGS_PATH));
+    let mut renderer =
+        match VertexRenderer::with_settings("survival-game", (0, 1, 0), &window, &settings) {
+            Ok(renderer) => renderer,
+            Err(error) => {
+                error!("Failed to create renderer: {}", error);
+                return ExitCode::FAILURE;
+            }
+        };
     if let Err(error_message) = renderer.load_shader(
         Path::new("res/shaders/test_triangle.vert.spv"),
         Path::new("res/shaders/test_triangle.frag.spv"),
@@ -44,34 +81,149 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    const TARGET_FRAME_TIME: Duration = Duration::new(0, 1000000000 / 60);
+    let record_input_path = std::env::var(RECORD_INPUT_ENV_NAME).ok();
+    let mut input_recorder = record_input_path.as_ref().map(|_| InputRecorder::new());
+    let mut input_playback = match std::env::var(REPLAY_INPUT_ENV_NAME) {
+        Ok(path) => match InputPlayback::load(Path::new(&path)) {
+            Ok(playback) => Some(playback),
+            Err(error_message) => {
+                error!("Failed to load input recording: {}", error_message);
+                return ExitCode::FAILURE;
+            }
+        },
+        Err(_) => None,
+    };
+
+    let mut input_state = InputState::new();
+    let mut grayscale = false;
+
     let _ = event_loop.run(|event, _window_target, control_flow| {
-        let start_time = SystemTime::now();
         control_flow.set_poll();
         match event {
             Event::WindowEvent { event, .. } => {
-                if event == WindowEvent::CloseRequested {
-                    control_flow.set_exit();
+                if let Some(input_event) = InputEvent::from_window_event(&event) {
+                    handle_live_input(
+                        &window,
+                        &mut input_state,
+                        input_recorder.as_mut(),
+                        input_playback.is_some(),
+                        &input_event,
+                    );
+                }
+
+                match event {
+                    WindowEvent::CloseRequested => {
+                        // Saving writes back any values that were clamped, as well as creating the file on the first run
+                        if let Err(error_message) = settings.save(Path::new(SETTINGS_PATH)) {
+                            error!("Failed to save graphics settings: {}", error_message);
+                        }
+                        if let (Some(recorder), Some(path)) =
+                            (input_recorder.as_ref(), record_input_path.as_ref())
+                        {
+                            if let Err(error_message) = recorder.save(Path::new(path)) {
+                                error!("Failed to save input recording: {}", error_message);
+                            }
+                        }
+                        control_flow.set_exit();
+                    }
+                    WindowEvent::Resized(new_size) => {
+                        debug!("Window resized to {}x{}", new_size.width, new_size.height);
+                        renderer.resize(new_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        info!("Window scale factor changed to {}", scale_factor);
+                        renderer.set_scale_factor(scale_factor);
+                        // The scale factor usually changes because the window moved to another monitor
+                        renderer.update_refresh_rate(&window);
+                    }
+                    WindowEvent::Moved(_) => renderer.update_refresh_rate(&window),
+                    WindowEvent::Focused(focused) => renderer.set_focused(focused),
+                    _ => {}
+                }
+            }
+            Event::DeviceEvent { event, .. } => {
+                if let Some(input_event) = InputEvent::from_device_event(&event) {
+                    handle_live_input(
+                        &window,
+                        &mut input_state,
+                        input_recorder.as_mut(),
+                        input_playback.is_some(),
+                        &input_event,
+                    );
                 }
             }
             Event::RedrawRequested(_id) => {
-                renderer.render(&window);
-                debug!("Redraw");
+                // The window's new size isn't known until after events like `ScaleFactorChanged` have been handled, so recreate just before rendering
+                if renderer.needs_recreate() {
+                    if let Err(error) = renderer.recreate(&window) {
+                        error!("Failed to recreate swapchain: {}", error);
+                        control_flow.set_exit();
+                        return;
+                    }
+                }
+
+                if let Some(playback) = input_playback.as_mut() {
+                    for input_event in playback.take_frame_events() {
+                        input_state.handle_event(&window, &input_event);
+                    }
+                    if playback.is_finished() {
+                        info!("Finished replaying input");
+                        control_flow.set_exit();
+                    }
+                }
+
+                for key in input_state.take_key_presses() {
+                    if key == "g" {
+                        grayscale = !grayscale;
+                        let shader_path =
+                            grayscale.then(|| Path::new("res/shaders/grayscale.comp.spv"));
+                        if let Err(error_message) = renderer.set_post_process(shader_path) {
+                            error!("Failed to set grayscale post-process: {}", error_message);
+                        }
+                    }
+                }
+                let mouse_delta = input_state.take_mouse_delta();
+                if mouse_delta != (0.0, 0.0) {
+                    debug!("Mouse moved by {:?}", mouse_delta);
+                }
+                let frame_outcome = renderer.render();
+                debug!("Redraw ({:?})", frame_outcome);
+
+                if let Some(recorder) = input_recorder.as_mut() {
+                    recorder.next_frame();
+                }
             }
             _ => {}
         }
 
         window.request_redraw();
-
-        let current_time = SystemTime::now();
-        while let Ok(time_to_sleep) = current_time.duration_since(start_time) {
-            debug!(
-                "Sleeping for {} ms",
-                (TARGET_FRAME_TIME - time_to_sleep).as_millis()
-            );
-            std::thread::sleep(TARGET_FRAME_TIME - time_to_sleep);
-        }
     });
 
     ExitCode::SUCCESS
 }
+
+/// Passes an event from the event loop to the input state, recording it if input is being recorded.
+/// Whilst a recording is being replayed, live input is ignored so that it doesn't change the outcome
+///
+/// # Arguments
+///
+/// * `window`: The window the input is for
+/// * `input_state`: The input state to update
+/// * `input_recorder`: The recorder to add the event to, if input is being recorded
+/// * `replaying`: Whether a recording is being replayed
+/// * `input_event`: The event received from the event loop
+fn handle_live_input(
+    window: &Window,
+    input_state: &mut InputState,
+    input_recorder: Option<&mut InputRecorder>,
+    replaying: bool,
+    input_event: &InputEvent,
+) {
+    if replaying {
+        return;
+    }
+    if let Some(recorder) = input_recorder {
+        recorder.record(input_event);
+    }
+    input_state.handle_event(window, input_event);
+}
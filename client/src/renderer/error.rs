@@ -0,0 +1,95 @@
+use std::ffi::CStr;
+use std::fmt;
+
+use ash::vk;
+
+/// Failures that stop a Vulkan context from being created, usually because Vulkan isn't installed properly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextError {
+    /// The Vulkan loader library couldn't be found or loaded
+    LoaderMissing,
+    /// The driver refused to create an instance
+    InstanceCreation(vk::Result),
+    /// An instance layer was enabled that the loader couldn't find
+    LayerUnavailable,
+    /// An instance extension needed to present to a window isn't available, eg. there's no surface extension for the display server
+    ExtensionUnavailable(&'static CStr),
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContextError::LoaderMissing => write!(
+                f,
+                "Vulkan couldn't be loaded, check that a Vulkan driver is installed"
+            ),
+            ContextError::InstanceCreation(result) => write!(
+                f,
+                "The Vulkan driver couldn't create an instance ({}), check that it's up to date",
+                result
+            ),
+            ContextError::LayerUnavailable => {
+                write!(f, "A Vulkan layer that was enabled isn't installed")
+            }
+            ContextError::ExtensionUnavailable(extension) => write!(
+                f,
+                "The Vulkan driver doesn't support {:?}, which is needed to draw to a window",
+                extension
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ContextError {}
+
+/// Failures that leave the renderer unable to draw, usually because of the driver or windowing system
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RendererError {
+    /// The Vulkan context couldn't be created
+    Context(ContextError),
+    /// The requested physical device doesn't exist or can't be used
+    AdapterUnavailable(&'static str),
+    /// The physical device doesn't support some of the requested features, which are named
+    MissingFeatures(Vec<&'static str>),
+    /// The surface reported no formats that images could be presented in
+    NoSurfaceFormats,
+    /// The surface reported no ways of presenting images
+    NoPresentModes,
+    /// The swapchain was created without any images to render to
+    NoSwapchainImages,
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            RendererError::Context(error) => return write!(f, "{}", error),
+            RendererError::AdapterUnavailable(reason) => reason,
+            RendererError::MissingFeatures(features) => {
+                return write!(
+                    f,
+                    "The GPU does not support the features {}",
+                    features.join(", ")
+                )
+            }
+            RendererError::NoSurfaceFormats => "The surface does not support any image formats",
+            RendererError::NoPresentModes => "The surface does not support any present modes",
+            RendererError::NoSwapchainImages => "The swapchain was created with no images",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererError::Context(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ContextError> for RendererError {
+    fn from(error: ContextError) -> Self {
+        RendererError::Context(error)
+    }
+}
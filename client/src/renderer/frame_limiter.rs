@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+/// The frame cap used whilst the window is focused if the display's refresh rate is unknown, unless another is set
+pub(crate) const DEFAULT_FRAME_CAP: u32 = 60;
+/// The frame cap used whilst the window isn't focused, unless another is set. Kept low to save power when the game is in the background
+pub(crate) const DEFAULT_UNFOCUSED_FRAME_CAP: u32 = 10;
+
+/// Sleeps between frames so that no more than a set number are rendered each second.
+/// By default frames are paced to the display's refresh rate, falling back to the frame cap if the refresh rate is unknown.
+/// A separate, usually lower, cap applies whilst the window isn't focused
+pub struct FrameLimiter {
+    frame_cap: Option<u32>,
+    unfocused_frame_cap: Option<u32>,
+    match_refresh_rate: bool,
+    refresh_rate_millihertz: Option<u32>,
+    focused: bool,
+    next_frame: Instant,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self {
+            frame_cap: Some(DEFAULT_FRAME_CAP),
+            unfocused_frame_cap: Some(DEFAULT_UNFOCUSED_FRAME_CAP),
+            match_refresh_rate: true,
+            refresh_rate_millihertz: None,
+            focused: true,
+            next_frame: Instant::now(),
+        }
+    }
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum frames per second whilst the window is focused.
+    /// If the limiter matches the display's refresh rate, this is only used whilst the refresh rate is unknown
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_cap`: The maximum frames per second, or `None` to render as fast as possible
+    pub fn set_frame_cap(&mut self, frame_cap: Option<u32>) {
+        self.frame_cap = frame_cap;
+    }
+
+    /// Sets whether frames are paced to the display's refresh rate whilst the window is focused, rather than the frame cap
+    ///
+    /// # Arguments
+    ///
+    /// * `match_refresh_rate`: Whether to follow the refresh rate given to [`FrameLimiter::set_refresh_rate()`]
+    pub fn set_match_refresh_rate(&mut self, match_refresh_rate: bool) {
+        self.match_refresh_rate = match_refresh_rate;
+    }
+
+    /// Records the refresh rate of the display the window is on, which should be updated whenever the window changes monitor
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh_rate_millihertz`: The refresh rate in thousandths of a hertz, or `None` if it's unknown
+    pub fn set_refresh_rate(&mut self, refresh_rate_millihertz: Option<u32>) {
+        self.refresh_rate_millihertz = refresh_rate_millihertz.filter(|rate| *rate > 0);
+    }
+
+    /// Sets the maximum frames per second whilst the window isn't focused
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_cap`: The maximum frames per second, or `None` to use the same cap as when focused
+    pub fn set_unfocused_frame_cap(&mut self, frame_cap: Option<u32>) {
+        self.unfocused_frame_cap = frame_cap;
+    }
+
+    /// Records whether the window is focused, as reported by `WindowEvent::Focused`
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Gets the frame cap that currently applies, given whether the window is focused, rounded to the nearest frame per second
+    pub fn current_frame_cap(&self) -> Option<u32> {
+        self.current_frame_rate_millihertz()
+            .map(|frame_rate| (frame_rate + 500) / 1000)
+    }
+
+    /// Gets the refresh rate last given to [`FrameLimiter::set_refresh_rate()`], in thousandths of a hertz
+    pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.refresh_rate_millihertz
+    }
+
+    /// Gets the number of frames that should be rendered every thousand seconds, or `None` if there's no cap.
+    /// Refresh rates aren't whole numbers of hertz (eg. 59.94Hz), so this keeps the extra precision
+    fn current_frame_rate_millihertz(&self) -> Option<u32> {
+        let focused_frame_rate = if self.match_refresh_rate {
+            self.refresh_rate_millihertz.or(self
+                .frame_cap
+                .map(|frame_cap| frame_cap.saturating_mul(1000)))
+        } else {
+            self.frame_cap
+                .map(|frame_cap| frame_cap.saturating_mul(1000))
+        };
+
+        if self.focused {
+            focused_frame_rate
+        } else {
+            self.unfocused_frame_cap
+                .map(|frame_cap| frame_cap.saturating_mul(1000))
+                .or(focused_frame_rate)
+        }
+        .filter(|frame_rate| *frame_rate > 0)
+    }
+
+    /// Sleeps until the next frame is due, if there's a frame cap
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        let Some(frame_time) = self
+            .current_frame_rate_millihertz()
+            .map(|frame_rate| Duration::from_secs(1000) / frame_rate)
+        else {
+            self.next_frame = now;
+            return;
+        };
+
+        if self.next_frame > now {
+            let time_to_sleep = self.next_frame - now;
+            debug!("Sleeping for {} ms", time_to_sleep.as_millis());
+            std::thread::sleep(time_to_sleep);
+            self.next_frame += frame_time;
+        } else {
+            // Don't try to catch up after a slow frame, as that would just render a burst of frames
+            self.next_frame = now + frame_time;
+        }
+    }
+}
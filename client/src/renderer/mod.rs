@@ -1,5 +1,78 @@
+use std::path::Path;
+
 pub mod vulkan;
 
+mod error;
+mod frame_limiter;
+mod null_renderer;
+mod settings;
 mod vertex_renderer;
 
-pub use vertex_renderer::VertexRenderer;
+pub use error::{ContextError, RendererError};
+pub use frame_limiter::FrameLimiter;
+pub use null_renderer::NullRenderer;
+pub use settings::{GraphicsSettings, WindowMode};
+pub use vertex_renderer::{SwapchainRecreatedCallback, VertexRenderer};
+
+use crate::renderer::vulkan::Vertex;
+
+/// What happened to a frame passed to [`Renderer::render()`], so that game logic which runs once per rendered frame can decide
+/// whether to advance time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameOutcome {
+    /// The frame was rendered and presented
+    Presented,
+    /// Nothing was rendered, eg. because the window is minimised or no swapchain image became available in time
+    Skipped,
+    /// The swapchain no longer matches the window, so the frame was dropped and the swapchain needs recreating before the next one
+    Recreated,
+}
+
+/// Identifies vertices uploaded with [`Renderer::upload_vertices()`], which are only valid for the renderer that uploaded them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle(pub(crate) usize);
+
+/// The interface shared by all renderer backends, so that game code doesn't need to know which one it's driving
+pub trait Renderer {
+    /// Loads a pair of shaders and creates a pipeline from them, referencable by the name provided
+    ///
+    /// If a pipeline already exists with the given name, or the pipeline couldn't be created, returns `Err`. Backends may instead
+    /// substitute a built-in pipeline when the shaders can't be loaded, so that the window still opens and shows the problem
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the asset root
+    /// * `fragment_shader_path`: A `Path` which references a compiled SPIR-V fragment shader, relative to the asset root
+    /// * `shader_name`: The name that the pipeline should be referencable as later
+    fn load_shader(
+        &mut self,
+        vertex_shader_path: &Path,
+        fragment_shader_path: &Path,
+        shader_name: String,
+    ) -> Result<(), &'static str>;
+
+    /// Uploads vertices so that they can be drawn with [`Renderer::draw()`], and returns a handle to them
+    ///
+    /// If there are no vertices, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices`: The vertices to upload, as a triangle list
+    fn upload_vertices(&mut self, vertices: &[Vertex]) -> Result<MeshHandle, &'static str>;
+
+    /// Queues vertices to be drawn with a pipeline in the next frame. Draws are made in the order they're queued, and the queue is
+    /// emptied by each call to [`Renderer::render()`], whether or not the frame was presented
+    ///
+    /// If the vertices weren't uploaded by this renderer or no pipeline exists with the given name, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh`: The vertices to draw
+    /// * `pipeline_name`: The name the pipeline was loaded with by [`Renderer::load_shader()`]
+    fn draw(&mut self, mesh: MeshHandle, pipeline_name: &str) -> Result<(), &'static str>;
+
+    /// Renders and presents a single frame
+    ///
+    /// Returns whether the frame was presented, or why it wasn't
+    fn render(&mut self) -> FrameOutcome;
+}
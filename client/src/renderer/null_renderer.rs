@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::renderer::vulkan::Vertex;
+use crate::renderer::{FrameOutcome, MeshHandle, Renderer};
+
+/// A renderer which accepts every call as a no-op and never touches Vulkan.
+/// This allows game logic to be run without a GPU (eg. in headless CI)
+///
+/// # Examples
+///
+/// ```
+/// use client::renderer::{NullRenderer, Renderer};
+/// use client::renderer::vulkan::Vertex;
+/// use std::path::Path;
+///
+/// let mut renderer = NullRenderer::new();
+/// renderer
+///     .load_shader(Path::new("vertex_shader.spv"), Path::new("fragment_shader.spv"), String::from("my_shader"))
+///     .unwrap();
+/// let triangle = renderer
+///     .upload_vertices(&[
+///         Vertex { position: [0.0, -0.5], color: [1.0, 0.0, 0.0] },
+///         Vertex { position: [0.5, 0.5], color: [0.0, 1.0, 0.0] },
+///         Vertex { position: [-0.5, 0.5], color: [0.0, 0.0, 1.0] },
+///     ])
+///     .unwrap();
+/// renderer.draw(triangle, "my_shader").unwrap();
+/// renderer.render();
+/// ```
+#[derive(Default)]
+pub struct NullRenderer {
+    shader_names: HashSet<String>,
+    mesh_count: usize,
+    queued_draw_count: usize,
+}
+
+impl NullRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the number of draws queued for the next frame, so that tests can check what game logic would have drawn
+    pub fn queued_draw_count(&self) -> usize {
+        self.queued_draw_count
+    }
+}
+
+impl Renderer for NullRenderer {
+    fn load_shader(
+        &mut self,
+        _vertex_shader_path: &Path,
+        _fragment_shader_path: &Path,
+        shader_name: String,
+    ) -> Result<(), &'static str> {
+        // Shader names are still tracked so that callers see the same errors as they would with a real backend
+        if self.shader_names.insert(shader_name) {
+            Ok(())
+        } else {
+            Err("A pipeline already exists with the specified name")
+        }
+    }
+
+    fn upload_vertices(&mut self, vertices: &[Vertex]) -> Result<MeshHandle, &'static str> {
+        if vertices.is_empty() {
+            return Err("A mesh needs at least one vertex");
+        }
+        self.mesh_count += 1;
+        Ok(MeshHandle(self.mesh_count - 1))
+    }
+
+    fn draw(&mut self, mesh: MeshHandle, pipeline_name: &str) -> Result<(), &'static str> {
+        if mesh.0 >= self.mesh_count {
+            return Err("No mesh exists with the specified handle");
+        }
+        if !self.shader_names.contains(pipeline_name) {
+            return Err("No pipeline exists with the specified name");
+        }
+        self.queued_draw_count += 1;
+        Ok(())
+    }
+
+    fn render(&mut self) -> FrameOutcome {
+        self.queued_draw_count = 0;
+        FrameOutcome::Presented
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: [Vertex; 3] = [
+        Vertex {
+            position: [0.0, -0.5],
+            color: [1.0, 0.0, 0.0],
+        },
+        Vertex {
+            position: [0.5, 0.5],
+            color: [0.0, 1.0, 0.0],
+        },
+        Vertex {
+            position: [-0.5, 0.5],
+            color: [0.0, 0.0, 1.0],
+        },
+    ];
+
+    fn load_basic_shader(renderer: &mut NullRenderer) -> Result<(), &'static str> {
+        renderer.load_shader(
+            Path::new("basic.vert.spv"),
+            Path::new("basic.frag.spv"),
+            String::from("basic"),
+        )
+    }
+
+    #[test]
+    fn draws_are_queued_until_rendered() {
+        let mut renderer = NullRenderer::new();
+        load_basic_shader(&mut renderer).unwrap();
+        let triangle = renderer.upload_vertices(&TRIANGLE).unwrap();
+
+        renderer.draw(triangle, "basic").unwrap();
+        renderer.draw(triangle, "basic").unwrap();
+        assert_eq!(renderer.queued_draw_count(), 2);
+
+        assert_eq!(renderer.render(), FrameOutcome::Presented);
+        assert_eq!(renderer.queued_draw_count(), 0);
+    }
+
+    #[test]
+    fn shader_names_must_be_unique() {
+        let mut renderer = NullRenderer::new();
+        assert!(load_basic_shader(&mut renderer).is_ok());
+        assert!(load_basic_shader(&mut renderer).is_err());
+    }
+
+    #[test]
+    fn empty_meshes_are_rejected() {
+        let mut renderer = NullRenderer::new();
+        assert!(renderer.upload_vertices(&[]).is_err());
+    }
+
+    #[test]
+    fn unknown_meshes_and_pipelines_are_rejected() {
+        let mut renderer = NullRenderer::new();
+        load_basic_shader(&mut renderer).unwrap();
+        let triangle = renderer.upload_vertices(&TRIANGLE).unwrap();
+
+        assert!(renderer.draw(MeshHandle(1), "basic").is_err());
+        assert!(renderer.draw(triangle, "missing").is_err());
+        assert_eq!(renderer.queued_draw_count(), 0);
+    }
+}
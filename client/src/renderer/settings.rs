@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::display::{self, DisplayMode};
+use crate::renderer::frame_limiter::{DEFAULT_FRAME_CAP, DEFAULT_UNFOCUSED_FRAME_CAP};
+use crate::renderer::vulkan::{
+    BufferingMode, DeviceParameters, PowerPreference, PresentMode, RequestedFeatures,
+};
+
+/// The lowest frame cap that can be set whilst focused, so that a typo in the settings file can't leave the game unresponsive
+const MIN_FRAME_CAP: u32 = 10;
+
+/// How the window is shown on the desktop, as stored in the settings file.
+/// The monitor and video mode are resolved against whichever monitor the window is on when the settings are applied
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Exclusive,
+}
+
+/// The graphics options a player can change, which are saved to disk so that they persist across runs.
+/// Any option missing from the settings file takes its default value
+///
+/// # Examples
+///
+/// ```no_run
+/// # use client::renderer::RendererError;
+/// # use winit::window::Window;
+/// # fn example(window: &Window) -> Result<(), RendererError> {
+/// use std::path::Path;
+/// use client::renderer::{GraphicsSettings, VertexRenderer};
+///
+/// let settings = GraphicsSettings::load(Path::new("settings.toml"));
+/// let renderer = VertexRenderer::with_settings("my-application", (1, 4, 2), &window, &settings)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Whether to favour discrete or integrated GPUs when no GPU index is given
+    pub power_preference: PowerPreference,
+    /// The index of the physical device to use, or `None` to guess which is best
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu_index: Option<usize>,
+    /// The PCI vendor ID of the GPU to favour when no GPU index is given (eg. `0x10DE` for NVIDIA), or `None` for no preference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_vendor_id: Option<u32>,
+    /// The optional GPU features to enable, eg. `geometry_shader` for pipelines with a geometry stage.
+    /// Any which the GPU doesn't support are left disabled
+    pub features: RequestedFeatures,
+    /// The present mode to use, or `None` to use the one implied by the buffering mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub present_mode: Option<PresentMode>,
+    pub buffering_mode: BufferingMode,
+    /// Whether frames are paced to the monitor's refresh rate whilst the window is focused, rather than the frame cap
+    pub match_refresh_rate: bool,
+    /// The maximum frames per second whilst the window is focused, or 0 to render as fast as possible.
+    /// If `match_refresh_rate` is set, this is only used when the refresh rate is unknown
+    pub frame_cap: u32,
+    /// The maximum frames per second whilst the window isn't focused, or 0 to use the same cap as when focused
+    pub unfocused_frame_cap: u32,
+    pub window_mode: WindowMode,
+    /// The resolution to switch the monitor to in exclusive fullscreen, or `None` to keep the monitor's current resolution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<[u32; 2]>,
+    /// The refresh rate to switch the monitor to in exclusive fullscreen in thousandths of a hertz, or `None` for the highest available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_rate_millihertz: Option<u32>,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            power_preference: PowerPreference::default(),
+            gpu_index: None,
+            preferred_vendor_id: None,
+            features: RequestedFeatures::default(),
+            present_mode: None,
+            buffering_mode: BufferingMode::default(),
+            match_refresh_rate: true,
+            frame_cap: DEFAULT_FRAME_CAP,
+            unfocused_frame_cap: DEFAULT_UNFOCUSED_FRAME_CAP,
+            window_mode: WindowMode::default(),
+            resolution: None,
+            refresh_rate_millihertz: None,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    /// Loads the settings from a TOML file, clamping any invalid values.
+    ///
+    /// If the file doesn't exist or can't be parsed, the default settings are used instead (with a warning if it couldn't be parsed)
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the settings file
+    pub fn load(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No settings file at {:?}, using the defaults", path);
+                return Self::default();
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to read settings file {:?} ({}), using the defaults",
+                    path, error
+                );
+                return Self::default();
+            }
+        };
+
+        let mut settings: Self = match toml::from_str(&contents) {
+            Ok(settings) => settings,
+            Err(error) => {
+                warn!(
+                    "Failed to parse settings file {:?} ({}), using the defaults",
+                    path, error
+                );
+                return Self::default();
+            }
+        };
+        settings.clamp();
+
+        info!("Loaded graphics settings from {:?}", path);
+        settings
+    }
+
+    /// Saves the settings to a TOML file, replacing it if it exists
+    ///
+    /// If the file can't be written, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the settings file
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|_| "Failed to serialize the graphics settings")?;
+        std::fs::write(path, contents).map_err(|_| "Failed to write the settings file")?;
+
+        debug!("Saved graphics settings to {:?}", path);
+        Ok(())
+    }
+
+    /// Replaces any values which can never be valid with the nearest valid value, logging a warning for each
+    fn clamp(&mut self) {
+        if self.frame_cap != 0 && self.frame_cap < MIN_FRAME_CAP {
+            warn!(
+                "A frame cap of {} is too low, so {} will be used instead",
+                self.frame_cap, MIN_FRAME_CAP
+            );
+            self.frame_cap = MIN_FRAME_CAP;
+        }
+
+        if self
+            .resolution
+            .is_some_and(|[width, height]| width == 0 || height == 0)
+        {
+            warn!(
+                "Ignoring the resolution {:?} as it has no area",
+                self.resolution.unwrap()
+            );
+            self.resolution = None;
+        }
+
+        if self.refresh_rate_millihertz == Some(0) {
+            warn!("Ignoring a refresh rate of 0");
+            self.refresh_rate_millihertz = None;
+        }
+    }
+
+    /// Gets the parameters to create the device with
+    pub fn device_parameters(&self) -> DeviceParameters {
+        DeviceParameters {
+            power_preference: self.power_preference,
+            gpu_index: self.gpu_index,
+            preferred_vendor_id: self.preferred_vendor_id,
+            requested_features: self.features,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the frame cap whilst focused, as passed to [`VertexRenderer::set_frame_cap()`](crate::renderer::VertexRenderer::set_frame_cap)
+    pub fn frame_cap(&self) -> Option<u32> {
+        (self.frame_cap != 0).then_some(self.frame_cap)
+    }
+
+    /// Gets the frame cap whilst unfocused, as passed to [`VertexRenderer::set_unfocused_frame_cap()`](crate::renderer::VertexRenderer::set_unfocused_frame_cap)
+    pub fn unfocused_frame_cap(&self) -> Option<u32> {
+        (self.unfocused_frame_cap != 0).then_some(self.unfocused_frame_cap)
+    }
+
+    /// Resolves the window mode against the monitor the window is on.
+    /// If the requested resolution and refresh rate aren't available, the closest video mode is used instead, and if the monitor
+    /// can't be determined, the window stays windowed
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window the settings are being applied to
+    pub fn display_mode(&self, window: &winit::window::Window) -> DisplayMode {
+        if self.window_mode == WindowMode::Windowed {
+            return DisplayMode::Windowed;
+        }
+
+        let Some(monitor) = display::current_monitor(window) else {
+            warn!("The window's monitor couldn't be determined, so it will stay windowed");
+            return DisplayMode::Windowed;
+        };
+
+        if self.window_mode == WindowMode::Borderless {
+            return DisplayMode::Borderless(monitor);
+        }
+
+        let [width, height] = self
+            .resolution
+            .unwrap_or([monitor.size.width, monitor.size.height]);
+        // Prefer the closest resolution, then the closest refresh rate (or the highest, if none was requested)
+        let video_mode = monitor.video_modes.iter().min_by_key(|mode| {
+            let size_difference =
+                mode.size.width.abs_diff(width) as u64 + mode.size.height.abs_diff(height) as u64;
+            let refresh_rate_difference = match self.refresh_rate_millihertz {
+                Some(refresh_rate) => mode.refresh_rate_millihertz.abs_diff(refresh_rate),
+                None => u32::MAX - mode.refresh_rate_millihertz,
+            };
+            (size_difference, refresh_rate_difference)
+        });
+
+        match video_mode {
+            Some(video_mode) => {
+                if video_mode.size.width != width
+                    || video_mode.size.height != height
+                    || self.refresh_rate_millihertz.is_some_and(|refresh_rate| {
+                        refresh_rate != video_mode.refresh_rate_millihertz
+                    })
+                {
+                    warn!(
+                        "The requested video mode isn't available, so {}x{} at {}mHz will be used instead",
+                        video_mode.size.width,
+                        video_mode.size.height,
+                        video_mode.refresh_rate_millihertz
+                    );
+                }
+                DisplayMode::Exclusive(video_mode.clone())
+            }
+            None => {
+                warn!("The monitor has no video modes to use for exclusive fullscreen, so borderless fullscreen will be used instead");
+                DisplayMode::Borderless(monitor)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requested_features_reach_the_device_parameters() {
+        let settings: GraphicsSettings =
+            toml::from_str("[features]\ngeometry_shader = true\n").unwrap();
+
+        let requested_features = settings.device_parameters().requested_features;
+        assert!(requested_features.geometry_shader);
+        assert!(!requested_features.sampler_anisotropy);
+    }
+
+    #[test]
+    fn missing_features_can_be_dropped() {
+        let features = RequestedFeatures {
+            geometry_shader: true,
+            multiview: true,
+            ..Default::default()
+        };
+
+        let features = features.without(&["multiview"]);
+        assert_eq!(
+            features,
+            RequestedFeatures {
+                geometry_shader: true,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -1,36 +1,731 @@
 use std::ops::DerefMut;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use crate::renderer::vulkan::{Context, Device, Surface};
+use ash::vk;
+use common::color::Color;
+use tracing::{error, info, warn};
+
+use crate::display::{self, DisplayMode};
+use crate::renderer::vulkan::{
+    decode_frame, readback_texel_size, Buffer, BufferHandle, Context, Device, DeviceParameters,
+    MemoryBudget, PipelineParameters, PostProcess, PresentMode, RenderTarget, Surface,
+    SurfaceCapabilities, Vertex,
+};
+use crate::renderer::{
+    FrameLimiter, FrameOutcome, GraphicsSettings, MeshHandle, Renderer, RendererError,
+};
+
+/// The triangle drawn every frame, in clockwise order
+const TRIANGLE_VERTICES: [Vertex; 3] = [
+    Vertex {
+        position: [0.0, -0.5],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, 0.5],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, 0.5],
+        color: [0.0, 0.0, 1.0],
+    },
+];
+
+/// The pipeline frames are drawn with until [`VertexRenderer::set_pipeline()`] selects another
+const DEFAULT_PIPELINE_NAME: &str = "basic";
+
+/// Called after the swapchain has been recreated with the device and the swapchain's new extent, so that resources sized to match it
+/// (eg. render targets or depth buffers) can be rebuilt
+pub type SwapchainRecreatedCallback = Box<dyn FnMut(&Device, vk::Extent2D)>;
 
 pub struct VertexRenderer {
     // These must stay in order as objects are dropped in the order they're declared
-    // Surface depends on device, which depends on context
+    // The callback, post-process, readback, and surface depend on device, which depends on context
+    on_swapchain_recreated: Option<SwapchainRecreatedCallback>,
+    post_process: Option<PostProcess>,
+    /// The buffer the last frame rendered after `request_frame_readback()` was copied into, with the format and size it was copied at
+    frame_readback: Option<(Buffer, vk::Format, vk::Extent2D)>,
     surface: Surface,
     device: Arc<RwLock<Device>>,
-    _context: Context,
+    context: Context,
+    scale_factor: f64,
+    frame_limiter: FrameLimiter,
+    /// Whether the window had no area when the swapchain was last recreated, in which case frames are skipped
+    minimised: bool,
+    triangle: BufferHandle,
+    /// The name of the pipeline each frame's surface render pass begins with
+    pipeline_name: String,
+    /// The vertex buffer and vertex count of each mesh, indexed by `MeshHandle`
+    meshes: Vec<(BufferHandle, u32)>,
+    /// The meshes to draw in the next frame presented, and the pipeline to draw each with
+    queued_draws: Vec<(MeshHandle, String)>,
+    /// Whether the next frame rendered should be copied into `frame_readback`
+    read_back_next_frame: bool,
 }
 
 impl VertexRenderer {
+    /// Constructs a new `VertexRenderer` which draws to `window`
+    ///
+    /// If the window's surface can't be presented to, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `application_name`: The name of the application, as reported to the driver
+    /// * `application_version`: The version of the application, as a 3-tuple of `u32`s
+    /// * `window`: The `Window` to render to
     pub fn new(
         application_name: &str,
         application_version: (u32, u32, u32),
         window: &winit::window::Window,
-    ) -> Self {
-        let context = Context::new(application_name, application_version);
+    ) -> Result<Self, RendererError> {
+        Self::with_settings(
+            application_name,
+            application_version,
+            window,
+            &GraphicsSettings::default(),
+        )
+    }
+
+    /// Constructs a new `VertexRenderer` which draws to `window`, applying the player's graphics settings.
+    /// Settings which the device or monitor don't support fall back to the closest supported option, with a warning
+    ///
+    /// If the window's surface can't be presented to, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `application_name`: The name of the application, as reported to the driver
+    /// * `application_version`: The version of the application, as a 3-tuple of `u32`s
+    /// * `window`: The `Window` to render to
+    /// * `settings`: The graphics settings to apply, usually loaded with [`GraphicsSettings::load()`]
+    pub fn with_settings(
+        application_name: &str,
+        application_version: (u32, u32, u32),
+        window: &winit::window::Window,
+        settings: &GraphicsSettings,
+    ) -> Result<Self, RendererError> {
+        // The window's size changes with the display mode, so it has to be switched before the swapchain is created
+        let display_mode = settings.display_mode(window);
+        if display_mode != DisplayMode::Windowed {
+            display::set_display_mode(window, &display_mode);
+        }
+
+        let context = Context::try_new(application_name, application_version)?;
         let mut surface = Surface::new(&context, window);
-        let device = Arc::new(RwLock::new(Device::new(&context, &surface)));
-        surface.create_swapchain(&context, &device, window);
+        let device_parameters = settings.device_parameters();
+        let device = match Device::new(&context, &surface, &device_parameters) {
+            Err(RendererError::MissingFeatures(missing_features)) => {
+                warn!(
+                    "The GPU doesn't support {}, so they will be left disabled",
+                    missing_features.join(", ")
+                );
+                let device_parameters = DeviceParameters {
+                    requested_features: device_parameters
+                        .requested_features
+                        .without(&missing_features),
+                    ..device_parameters
+                };
+                Device::new(&context, &surface, &device_parameters)?
+            }
+            result => result?,
+        };
+        let device = Arc::new(RwLock::new(device));
+        surface.set_buffering_mode(settings.buffering_mode);
+        surface.set_preferred_present_mode(settings.present_mode);
+        surface.create_swapchain(&context, &device, window)?;
+        let triangle = device
+            .write()
+            .unwrap()
+            .create_vertex_buffer(&TRIANGLE_VERTICES);
+
+        let mut frame_limiter = FrameLimiter::new();
+        frame_limiter.set_refresh_rate(current_refresh_rate(window));
+        frame_limiter.set_match_refresh_rate(settings.match_refresh_rate);
+        frame_limiter.set_frame_cap(settings.frame_cap());
+        frame_limiter.set_unfocused_frame_cap(settings.unfocused_frame_cap());
 
-        Self {
+        Ok(Self {
+            on_swapchain_recreated: None,
+            post_process: None,
+            frame_readback: None,
             surface,
             device,
-            _context: context,
+            context,
+            scale_factor: window.scale_factor(),
+            frame_limiter,
+            minimised: false,
+            triangle,
+            pipeline_name: String::from(DEFAULT_PIPELINE_NAME),
+            meshes: Vec::new(),
+            queued_draws: Vec::new(),
+            read_back_next_frame: false,
+        })
+    }
+
+    /// Sets how long to wait for a swapchain image before skipping the frame
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout`: The maximum time to wait. `Duration::MAX` waits indefinitely
+    pub fn set_acquire_timeout(&mut self, timeout: Duration) {
+        self.surface.set_acquire_timeout(timeout);
+    }
+
+    /// Sets whether rendering a frame blocks until it's been presented, so that frames are evenly paced for video capture and
+    /// reproducible benchmarks at the cost of throughput
+    ///
+    /// # Arguments
+    ///
+    /// * `deterministic_present`: Whether to wait for each frame to be presented
+    pub fn set_deterministic_present(&mut self, deterministic_present: bool) {
+        self.surface
+            .set_deterministic_present(deterministic_present);
+    }
+
+    /// Sets how many frames may be queued ahead of the display, to bound input latency. This uses VK_KHR_present_wait when it's
+    /// available, and otherwise waits for earlier frames to finish rendering
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frame_latency`: The maximum number of queued frames, or `None` to only be limited by the frames in flight
+    pub fn set_max_frame_latency(&mut self, max_frame_latency: Option<u32>) {
+        self.surface.set_max_frame_latency(max_frame_latency);
+    }
+
+    /// Sets a compute shader to run over each frame before it's presented (eg. `res/shaders/grayscale.comp.spv`), or removes it.
+    /// The swapchain is recreated with storage usage the next time [`VertexRenderer::recreate()`] is called, and the post-process
+    /// is skipped if the surface doesn't support it. Replacing or removing a post-process waits for the GPU to go idle first,
+    /// since frames in flight may still be using it
+    ///
+    /// If the shader file doesn't exist, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `shader_path`: A `Path` which references a compiled SPIR-V compute shader, relative to the asset root, or `None` to remove the post-process
+    pub fn set_post_process(&mut self, shader_path: Option<&Path>) -> Result<(), &'static str> {
+        let device_guard = self.device.read();
+        let device = device_guard.unwrap();
+        let post_process = match shader_path {
+            Some(shader_path) => Some(device.create_post_process(shader_path)?),
+            None => None,
+        };
+
+        // Frames in flight may still be running the old post-process, which is destroyed when it's replaced
+        if self.post_process.is_some() {
+            unsafe { device.logical_device.device_wait_idle() }
+                .expect("Device was removed whilst waiting to replace the post-process");
+        }
+        drop(device);
+        self.post_process = post_process;
+        self.surface.set_storage_usage(self.post_process.is_some());
+        Ok(())
+    }
+
+    /// Sets the directory that shader and other asset paths are resolved relative to, which defaults to the executable's directory.
+    /// This only affects assets loaded afterwards
+    ///
+    /// # Arguments
+    ///
+    /// * `asset_root`: The directory to resolve asset paths relative to
+    pub fn set_asset_root(&mut self, asset_root: PathBuf) {
+        self.device.write().unwrap().set_asset_root(asset_root);
+    }
+
+    /// Gets the ratio of physical pixels to logical pixels for the window being rendered to, so that UI can be sized to match
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Records the window's new scale factor, as reported by `WindowEvent::ScaleFactorChanged`.
+    /// The window's physical size usually changes along with its scale factor, so the swapchain is marked as needing to be recreated
+    ///
+    /// # Arguments
+    ///
+    /// * `scale_factor`: The new ratio of physical pixels to logical pixels
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.surface.request_recreate();
+    }
+
+    /// Records the window's new physical size, as reported by `WindowEvent::Resized`, and marks the swapchain as needing to be
+    /// recreated to match it. Whilst either dimension is 0 (eg. the window is minimised), frames are skipped rather than rendered
+    ///
+    /// # Arguments
+    ///
+    /// * `new_size`: The new physical size of the window
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.minimised = new_size.width == 0 || new_size.height == 0;
+        self.surface.request_recreate();
+    }
+
+    /// Switches how frames are handed to the display (eg. to toggle vsync from the options menu), and marks the swapchain as needing
+    /// to be recreated to use it
+    ///
+    /// If the surface doesn't support the present mode, returns `Err` and the current present mode is kept
+    ///
+    /// # Arguments
+    ///
+    /// * `present_mode`: One of the present modes returned by [`VertexRenderer::surface_capabilities()`]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::VertexRenderer;
+    /// # use tracing::warn;
+    /// # fn example(renderer: &mut VertexRenderer, vsync_enabled: bool) {
+    /// use client::renderer::vulkan::PresentMode;
+    ///
+    /// let present_mode = if vsync_enabled { PresentMode::Fifo } else { PresentMode::Immediate };
+    /// if let Err(error) = renderer.set_present_mode(present_mode) {
+    ///     warn!("Couldn't change the present mode: {}", error);
+    /// }
+    /// # }
+    /// ```
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> Result<(), &'static str> {
+        if !self
+            .surface_capabilities()
+            .present_modes
+            .contains(&present_mode)
+        {
+            return Err("The surface doesn't support the present mode");
+        }
+
+        info!("Switching present mode to {}", present_mode);
+        self.surface.set_preferred_present_mode(Some(present_mode));
+        self.surface.request_recreate();
+        Ok(())
+    }
+
+    /// Switches the window between windowed and fullscreen, or moves it to another monitor, and marks the swapchain as needing to be
+    /// recreated to match the window's new size
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window being rendered to
+    /// * `display_mode`: How the window should be shown
+    pub fn set_display_mode(&mut self, window: &winit::window::Window, display_mode: &DisplayMode) {
+        display::set_display_mode(window, display_mode);
+        self.surface.request_recreate();
+
+        // The switch may not have happened yet, so the monitor can't be asked for its new refresh rate
+        match display_mode {
+            DisplayMode::Exclusive(video_mode) => self
+                .frame_limiter
+                .set_refresh_rate(Some(video_mode.refresh_rate_millihertz)),
+            _ => self.update_refresh_rate(window),
+        }
+    }
+
+    /// Re-detects the refresh rate of the monitor the window is on, which frames are paced to by default.
+    /// This should be called whenever the window may have moved to another monitor (eg. on `WindowEvent::Moved`)
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window being rendered to
+    pub fn update_refresh_rate(&mut self, window: &winit::window::Window) {
+        let refresh_rate = current_refresh_rate(window);
+        if refresh_rate != self.frame_limiter.refresh_rate_millihertz() {
+            info!("Display refresh rate changed to {:?} mHz", refresh_rate);
+            self.frame_limiter.set_refresh_rate(refresh_rate);
+        }
+    }
+
+    /// Gets the refresh rate of the monitor the window is on in thousandths of a hertz, as last detected, or `None` if it's unknown
+    pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.frame_limiter.refresh_rate_millihertz()
+    }
+
+    /// Sets whether frames are paced to the monitor's refresh rate, which is the default.
+    /// If the refresh rate is unknown, or this is turned off, the frame cap is used instead
+    ///
+    /// # Arguments
+    ///
+    /// * `match_refresh_rate`: Whether to follow the monitor's refresh rate
+    pub fn set_match_refresh_rate(&mut self, match_refresh_rate: bool) {
+        self.frame_limiter
+            .set_match_refresh_rate(match_refresh_rate);
+    }
+
+    /// Gets whether the swapchain no longer matches the window (eg. after a resize) and [`VertexRenderer::recreate()`] should be called.
+    /// This lets the application choose when the stall caused by recreating the swapchain happens
+    pub fn needs_recreate(&self) -> bool {
+        self.surface.needs_recreate()
+    }
+
+    /// Recreates the swapchain at the window's current size
+    ///
+    /// If the window's surface can no longer be presented to, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window being rendered to
+    pub fn recreate(&mut self, window: &winit::window::Window) -> Result<(), RendererError> {
+        self.recreate_swapchain(window)
+    }
+
+    /// Recreates the swapchain so that rendering matches the window's physical size, then calls the callback set with
+    /// [`VertexRenderer::set_on_swapchain_recreated()`]
+    ///
+    /// If the window's surface can no longer be presented to, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: The window being rendered to, which has been resized or moved to a display with a different scale factor
+    pub fn recreate_swapchain(
+        &mut self,
+        window: &winit::window::Window,
+    ) -> Result<(), RendererError> {
+        let old_extent = self.surface.extent();
+        let window_size = window.inner_size();
+        self.minimised = window_size.width == 0 || window_size.height == 0;
+        self.surface.recreate_swapchain(&self.context, window)?;
+
+        // Nothing needs rebuilding if the size is unchanged, including when recreation was skipped as the window is minimised
+        let new_extent = self.surface.extent();
+        if new_extent == old_extent {
+            return Ok(());
+        }
+        if let (Some(callback), Some(new_extent)) =
+            (self.on_swapchain_recreated.as_mut(), new_extent)
+        {
+            callback(&self.device.read().unwrap(), new_extent);
+        }
+        Ok(())
+    }
+
+    /// Sets a callback to run whenever the swapchain is recreated at a new size, so that resources sized to match it can be rebuilt
+    ///
+    /// # Arguments
+    ///
+    /// * `callback`: The callback to run, or `None` to stop calling the current one
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use ash::vk;
+    /// # use client::renderer::VertexRenderer;
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(renderer: &mut VertexRenderer, device: &Device, format: vk::Format, extent: vk::Extent2D, clear_color: [f32; 4]) {
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let scene_target = Rc::new(RefCell::new(device.create_render_target(format, extent, clear_color)));
+    /// let callback_target = scene_target.clone();
+    /// renderer.set_on_swapchain_recreated(Some(Box::new(move |device, new_extent| {
+    ///     *callback_target.borrow_mut() = device.create_render_target(format, new_extent, clear_color);
+    /// })));
+    /// # }
+    /// ```
+    pub fn set_on_swapchain_recreated(&mut self, callback: Option<SwapchainRecreatedCallback>) {
+        self.on_swapchain_recreated = callback;
+    }
+
+    /// Sets the colour the window is cleared to at the start of each frame
+    ///
+    /// # Arguments
+    ///
+    /// * `color`: The clear colour
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.surface.set_clear_color(color);
+    }
+
+    /// Sets the maximum frames per second whilst the window is focused, instead of matching the monitor's refresh rate
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_cap`: The maximum frames per second, or `None` to render as fast as possible
+    pub fn set_frame_cap(&mut self, frame_cap: Option<u32>) {
+        self.frame_limiter.set_match_refresh_rate(false);
+        self.frame_limiter.set_frame_cap(frame_cap);
+    }
+
+    /// Sets the maximum frames per second whilst the window isn't focused, to save power when the game is in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_cap`: The maximum frames per second, or `None` to use the same cap as when focused
+    pub fn set_unfocused_frame_cap(&mut self, frame_cap: Option<u32>) {
+        self.frame_limiter.set_unfocused_frame_cap(frame_cap);
+    }
+
+    /// Records whether the window is focused, as reported by `WindowEvent::Focused`, so that the right frame cap is applied
+    pub fn set_focused(&mut self, focused: bool) {
+        self.frame_limiter.set_focused(focused);
+    }
+
+    /// Gets the number of frames that have been presented so far, which only ever increases
+    pub fn frame_number(&self) -> u64 {
+        self.surface.frame_number()
+    }
+
+    /// Gets whether a frame can be started without waiting for the GPU, without blocking.
+    /// See [`Surface::is_frame_in_flight_complete()`]
+    pub fn is_frame_in_flight_complete(&self) -> bool {
+        self.surface.is_frame_in_flight_complete()
+    }
+
+    /// Gets the formats, present modes, and image limits that the window's surface supports
+    pub fn surface_capabilities(&self) -> SurfaceCapabilities {
+        self.surface.capabilities()
+    }
+
+    /// Gets how much memory is in use and how much is available in each of the GPU's memory heaps
+    pub fn memory_budget(&self) -> MemoryBudget {
+        self.device.read().unwrap().memory_budget()
+    }
+
+    /// Builds a plain-text report of the Vulkan instance, every adapter, and the surface's formats and present modes, for pasting
+    /// into bug reports
+    pub fn diagnostics_report(&self) -> String {
+        self.context.diagnostics_report(Some(&self.surface))
+    }
+
+    /// Selects the pipeline that frames presented to the window are drawn with, which is `"basic"` until this is called
+    ///
+    /// If no pipeline exists with the given name, returns `Err` and keeps the current pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline_name`: The name the pipeline was loaded with by [`Renderer::load_shader()`]
+    pub fn set_pipeline(&mut self, pipeline_name: &str) -> Result<(), &'static str> {
+        if self
+            .device
+            .read()
+            .unwrap()
+            .find_pipeline(pipeline_name)
+            .is_none()
+        {
+            return Err("No pipeline exists with the specified name");
+        }
+        self.pipeline_name = String::from(pipeline_name);
+        Ok(())
+    }
+
+    /// Renders a frame into a render target without presenting it, so that several frames can be accumulated (eg. for temporal
+    /// anti-aliasing) before [`VertexRenderer::present()`] shows the result. This isn't limited by the frame cap
+    ///
+    /// If no pipeline exists with the given name, returns `Err` without rendering anything
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The render target to draw to
+    /// * `pipeline_name`: The name of a pipeline whose render pass is compatible with the target
+    pub fn render_offscreen(
+        &mut self,
+        target: &RenderTarget,
+        pipeline_name: &str,
+    ) -> Result<(), &'static str> {
+        {
+            let device_guard = self.device.write();
+            let mut device_lock = device_guard.unwrap();
+            let device = device_lock.deref_mut();
+
+            let pipeline = device
+                .find_pipeline(pipeline_name)
+                .ok_or("No pipeline exists with the specified name")?;
+            let current_frame_index = self.surface.get_current_frame_index();
+            device.begin_offscreen_frame(current_frame_index, &self.surface);
+            device.begin_render_target_pass(current_frame_index, target, pipeline);
+            self.draw_triangle(device, current_frame_index);
+            device.end_render_target_pass(current_frame_index);
+            device.end_offscreen_frame(current_frame_index);
+        }
+
+        self.surface.submit_offscreen();
+        Ok(())
+    }
+
+    /// Renders a frame to the window and presents it, without waiting for the frame cap.
+    /// [`Renderer::render()`] waits for the frame cap and then calls this
+    ///
+    /// Returns whether the frame was presented, or why it wasn't
+    pub fn present(&mut self) -> FrameOutcome {
+        self.present_frame(None)
+    }
+
+    /// Asks for the next frame rendered to be copied out as it's shown on screen, so that it can then be read with
+    /// [`VertexRenderer::read_frame()`]. The copy is recorded into the frame's own command buffer, so the frame is drawn and
+    /// presented as usual. If the next frame is skipped, the frame after it is copied instead
+    ///
+    /// If the swapchain images can't be copied from or their format can't be read back (eg. HDR formats), returns `Err`
+    pub fn request_frame_readback(&mut self) -> Result<(), &'static str> {
+        if !self.surface.is_transfer_source_enabled() {
+            return Err("The swapchain images can't be copied from");
+        }
+        let Some(format) = self.surface.format() else {
+            return Err("The swapchain hasn't been created");
+        };
+        if readback_texel_size(format).is_none() {
+            return Err("Frames can't be read back in the swapchain's format");
+        }
+
+        self.read_back_next_frame = true;
+        Ok(())
+    }
+
+    /// Gets the last frame rendered after [`VertexRenderer::request_frame_readback()`], as it was shown on screen, eg. so that a
+    /// test can compare it against a golden image. This doesn't render anything itself, but waits for the device to finish all
+    /// submitted work, so it stalls the CPU and shouldn't be called every frame
+    ///
+    /// If no frame has been copied since the readback was requested (or since the last call), returns `Err`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::{Renderer, VertexRenderer};
+    /// # fn example(renderer: &mut VertexRenderer) -> Result<(), Box<dyn std::error::Error>> {
+    /// renderer.request_frame_readback()?;
+    /// renderer.render();
+    /// let frame = renderer.read_frame()?;
+    /// let golden = image::open("tests/golden/triangle.png")?.into_rgba8();
+    /// let matches = frame
+    ///     .pixels()
+    ///     .zip(golden.pixels())
+    ///     .all(|(actual, expected)| actual.0.iter().zip(expected.0).all(|(a, e)| a.abs_diff(e) <= 2));
+    /// # assert!(matches);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_frame(&mut self) -> Result<image::RgbaImage, &'static str> {
+        let (readback, format, extent) = self
+            .frame_readback
+            .take()
+            .ok_or("No frame has been rendered since the readback was requested")?;
+        let device = self.device.read().unwrap();
+        unsafe { device.logical_device.device_wait_idle() }
+            .expect("Device was removed whilst waiting to read a frame");
+
+        decode_frame(
+            format,
+            extent,
+            readback
+                .mapped_slice()
+                .expect("Readback buffer should be host-visible"),
+        )
+    }
+
+    /// Renders a frame to the window and presents only the regions that changed, without waiting for the frame cap.
+    /// If the device doesn't support presenting regions, the whole window is presented instead
+    ///
+    /// # Arguments
+    ///
+    /// * `dirty_rects`: The regions of the window which changed since the last frame, in physical pixels
+    ///
+    /// # Returns
+    ///
+    /// Whether the frame was presented, or why it wasn't
+    pub fn present_dirty_rects(&mut self, dirty_rects: &[vk::Rect2D]) -> FrameOutcome {
+        self.present_frame(Some(dirty_rects))
+    }
+
+    /// Renders a frame to the window and presents it. The draws queued with [`Renderer::draw()`] are made and then forgotten, or the
+    /// placeholder triangle is drawn if there aren't any. If a readback has been requested, the frame is also copied into
+    /// `frame_readback` once it's been drawn
+    ///
+    /// Returns whether the frame was presented, or why it wasn't
+    ///
+    /// # Arguments
+    ///
+    /// * `dirty_rects`: The regions of the window which changed since the last frame, or `None` if the whole window may have changed
+    fn present_frame(&mut self, dirty_rects: Option<&[vk::Rect2D]>) -> FrameOutcome {
+        let queued_draws = std::mem::take(&mut self.queued_draws);
+        if self.minimised {
+            return FrameOutcome::Skipped;
+        }
+
+        let next_image = {
+            let device_guard = self.device.write();
+            let mut device_lock = device_guard.unwrap();
+            let device = device_lock.deref_mut();
+
+            let Some(pipeline) = device.find_pipeline(&self.pipeline_name) else {
+                return FrameOutcome::Skipped;
+            };
+            let current_frame_index = self.surface.get_current_frame_index();
+            let Some(next_frame_index) =
+                device.begin_graphics_render_pass(current_frame_index, &mut self.surface, pipeline)
+            else {
+                // The acquire either timed out or found the swapchain out of date
+                let outcome = if self.surface.needs_recreate() {
+                    FrameOutcome::Recreated
+                } else {
+                    FrameOutcome::Skipped
+                };
+                return outcome;
+            };
+            if queued_draws.is_empty() {
+                self.draw_triangle(device, current_frame_index);
+            }
+            for (mesh, pipeline_name) in &queued_draws {
+                // Pipelines are checked when the draw is queued, but may be for a different kind of render pass
+                if let Err(error) = device.bind_pipeline(current_frame_index, pipeline_name) {
+                    error!("Failed to draw with pipeline {} ({})", pipeline_name, error);
+                    continue;
+                }
+                let (buffer, vertex_count) = self.meshes[mesh.0];
+                let buffer = device
+                    .get_buffer(buffer)
+                    .expect("A mesh's vertex buffer should exist for as long as the renderer");
+                device.bind_vertex_buffer(current_frame_index, buffer);
+                device.draw_vertices(current_frame_index, vertex_count);
+            }
+            device.end_surface_render_pass(current_frame_index);
+            if let Some(post_process) = self.post_process.as_ref() {
+                // The swapchain may not have been recreated with storage usage yet
+                let _ = device.dispatch_post_process(
+                    current_frame_index,
+                    &self.surface,
+                    next_frame_index,
+                    post_process,
+                );
+            }
+            if self.read_back_next_frame {
+                // The swapchain may have been recreated in a format that can't be read back since the readback was requested
+                match device.record_frame_readback(
+                    current_frame_index,
+                    &self.surface,
+                    next_frame_index,
+                ) {
+                    Ok(readback) => {
+                        self.frame_readback = Some((
+                            readback,
+                            self.surface.format().unwrap(),
+                            self.surface.extent().unwrap(),
+                        ));
+                    }
+                    Err(error) => error!("Failed to read back the frame ({})", error),
+                }
+                self.read_back_next_frame = false;
+            }
+            device.end_frame(current_frame_index);
+            next_frame_index
+        };
+
+        if self.surface.flip_buffers(next_image, dirty_rects) {
+            FrameOutcome::Presented
+        } else {
+            FrameOutcome::Recreated
         }
     }
 
-    pub fn load_shader(
+    /// Binds the triangle's vertex buffer and draws it, whilst a render pass is active
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The device recording the frame
+    /// * `current_frame_index`: The index of the frame being recorded
+    fn draw_triangle(&self, device: &mut Device, current_frame_index: usize) {
+        let triangle = device
+            .get_buffer(self.triangle)
+            .expect("The triangle's vertex buffer should exist for as long as the renderer");
+        device.bind_vertex_buffer(current_frame_index, triangle);
+        device.draw_vertices(current_frame_index, TRIANGLE_VERTICES.len() as u32);
+    }
+}
+
+impl Renderer for VertexRenderer {
+    fn load_shader(
         &mut self,
         vertex_shader_path: &Path,
         fragment_shader_path: &Path,
@@ -40,16 +735,31 @@ impl VertexRenderer {
         let mut device_lock = device_guard.unwrap();
         let device = device_lock.deref_mut();
 
-        match device.create_pipeline(
-            &self.surface,
-            vertex_shader_path,
-            fragment_shader_path,
-            shader_name.clone(),
-        ) {
+        // If the shaders can't be loaded, the fallback pipeline takes their place so that the window still opens and shows the problem
+        let pipeline = device
+            .create_pipeline(
+                &self.surface,
+                vertex_shader_path,
+                fragment_shader_path,
+                shader_name.clone(),
+                &PipelineParameters {
+                    vertex_bindings: vec![Vertex::binding_description()],
+                    vertex_attributes: Vertex::attribute_descriptions().to_vec(),
+                    ..Default::default()
+                },
+            )
+            .or_else(|error| {
+                error!(
+                    "Failed to create pipeline {} from {:?} and {:?} ({}), so using the fallback pipeline",
+                    shader_name, vertex_shader_path, fragment_shader_path, error
+                );
+                device.create_fallback_pipeline(&self.surface, shader_name.clone())
+            });
+        match pipeline {
             Err(_error) => Err("Failed to create pipeline on device"),
-            Ok(_) => {
+            Ok(handle) => {
                 let pipeline = device
-                    .get_pipeline(shader_name.as_str())
+                    .get_pipeline(handle)
                     .expect("Failed to get pipeline after creation");
                 self.surface
                     .create_framebuffers_for_pipeline(device, pipeline);
@@ -58,22 +768,35 @@ impl VertexRenderer {
         }
     }
 
-    pub fn render(&mut self, window: &winit::window::Window) {
-        let next_image = {
-            let device_guard = self.device.write();
-            let mut device_lock = device_guard.unwrap();
-            let device = device_lock.deref_mut();
+    fn upload_vertices(&mut self, vertices: &[Vertex]) -> Result<MeshHandle, &'static str> {
+        if vertices.is_empty() {
+            return Err("A mesh needs at least one vertex");
+        }
+        let buffer = self.device.write().unwrap().create_vertex_buffer(vertices);
+        self.meshes.push((buffer, vertices.len() as u32));
+        Ok(MeshHandle(self.meshes.len() - 1))
+    }
 
-            let current_frame_index = self.surface.get_current_frame_index();
-            let next_frame_index =
-                device.begin_graphics_render_pass(current_frame_index, &mut self.surface, "basic");
-            device.draw_vertices(current_frame_index, 3);
-            device.end_graphics_render_pass(current_frame_index);
-            next_frame_index
-        };
+    fn draw(&mut self, mesh: MeshHandle, pipeline_name: &str) -> Result<(), &'static str> {
+        if mesh.0 >= self.meshes.len() {
+            return Err("No mesh exists with the specified handle");
+        }
+        if self
+            .device
+            .read()
+            .unwrap()
+            .find_pipeline(pipeline_name)
+            .is_none()
+        {
+            return Err("No pipeline exists with the specified name");
+        }
+        self.queued_draws.push((mesh, String::from(pipeline_name)));
+        Ok(())
+    }
 
-        window.request_redraw();
-        self.surface.flip_buffers(next_image);
+    fn render(&mut self) -> FrameOutcome {
+        self.frame_limiter.wait();
+        self.present()
     }
 }
 
@@ -89,3 +812,14 @@ impl Drop for VertexRenderer {
         .expect("Device was removed during cleanup");
     }
 }
+
+/// Gets the refresh rate of the monitor the window is on, in thousandths of a hertz
+///
+/// # Arguments
+///
+/// * `window`: The window to find the monitor of
+fn current_refresh_rate(window: &winit::window::Window) -> Option<u32> {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+}
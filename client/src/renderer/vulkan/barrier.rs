@@ -0,0 +1,128 @@
+use ash::vk;
+
+/// Records a pipeline barrier which moves an image from one layout to another.
+/// The access and stage masks on each side of the barrier are derived from the layouts, so that the image isn't used in its new layout until
+/// everything using it in its old layout has finished
+///
+/// This is a free function rather than a method on `Device` so that it can be used whilst recording transfers with [`Device::submit_transfer_async()`]
+///
+/// # Arguments
+///
+/// * `device`: The logical device that the command buffer belongs to
+/// * `command_buffer`: A command buffer in the recording state
+/// * `image`: The image to transition
+/// * `subresource_range`: The mip levels and array layers to transition
+/// * `old_layout`: The layout the image is currently in, or `UNDEFINED` if its contents can be discarded
+/// * `new_layout`: The layout to move the image to
+///
+/// # Examples
+///
+/// ```no_run
+/// # use client::renderer::vulkan::Device;
+/// # use ash::vk;
+/// # fn example(device: &mut Device, image: vk::Image, subresource_range: vk::ImageSubresourceRange) {
+/// use ash::vk;
+/// use client::renderer::vulkan::record_image_layout_transition;
+///
+/// device.submit_transfer_async(|logical_device, command_buffer| {
+///     record_image_layout_transition(
+///         logical_device,
+///         command_buffer,
+///         image,
+///         subresource_range,
+///         vk::ImageLayout::UNDEFINED,
+///         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+///     );
+/// });
+/// # }
+/// ```
+pub fn record_image_layout_transition(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) {
+    let (src_access_mask, src_stage_mask) = get_layout_access_and_stage(old_layout);
+    let (dst_access_mask, dst_stage_mask) = get_layout_access_and_stage(new_layout);
+
+    let image_memory_barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .build();
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier],
+        )
+    };
+}
+
+/// Gets the memory accesses and pipeline stages that use an image whilst it's in a layout.
+/// When transitioning out of a layout these are what must finish, and when transitioning into one these are what must wait
+///
+/// Layouts without a specific use fall back to all commands, which is always correct but may stall more than necessary
+///
+/// # Arguments
+///
+/// * `layout`: The image layout
+fn get_layout_access_and_stage(
+    layout: vk::ImageLayout,
+) -> (vk::AccessFlags, vk::PipelineStageFlags) {
+    match layout {
+        // Nothing needs to finish before the contents are discarded
+        vk::ImageLayout::UNDEFINED | vk::ImageLayout::PREINITIALIZED => (
+            vk::AccessFlags::empty(),
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+        ),
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+            vk::AccessFlags::TRANSFER_READ,
+            vk::PipelineStageFlags::TRANSFER,
+        ),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::PipelineStageFlags::TRANSFER,
+        ),
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+            vk::AccessFlags::SHADER_READ,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => (
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        ),
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL => (
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        ),
+        vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL => (
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::SHADER_READ,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+        // The presentation engine synchronises with the semaphores rather than the barrier
+        vk::ImageLayout::PRESENT_SRC_KHR => (
+            vk::AccessFlags::empty(),
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        ),
+        // `GENERAL` can be used for anything, so everything has to be synchronised
+        _ => (
+            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+        ),
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use common::color::Color;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::renderer::vulkan::{Sprite, SpriteBatch};
+
+/// The character drawn in place of any the font doesn't have, if the font has it
+const REPLACEMENT_CHARACTER: char = '?';
+
+/// The position and metrics of a single glyph in the atlas, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+struct GlyphMetrics {
+    character: char,
+    /// The left edge of the glyph in the atlas
+    x: u32,
+    /// The top edge of the glyph in the atlas
+    y: u32,
+    width: u32,
+    height: u32,
+    /// How far right of the pen position the glyph is drawn
+    #[serde(default)]
+    x_offset: f32,
+    /// How far below the top of the line the glyph is drawn
+    #[serde(default)]
+    y_offset: f32,
+    /// How far the pen moves right after drawing the glyph
+    advance: f32,
+}
+
+/// An adjustment to the space between a pair of characters, eg. to tuck "V" under the arm of "A"
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+struct KerningPair {
+    first: char,
+    second: char,
+    /// The change in advance between the characters, in pixels, which is usually negative
+    amount: f32,
+}
+
+/// The contents of a font metrics file
+#[derive(Debug, Deserialize)]
+struct FontMetrics {
+    /// The distance between the tops of consecutive lines, in pixels
+    line_height: f32,
+    /// The width and height of the atlas texture, in pixels
+    atlas_size: [u32; 2],
+    glyphs: Vec<GlyphMetrics>,
+    #[serde(default)]
+    kerning: Vec<KerningPair>,
+}
+
+/// A font whose glyphs are pre-rendered into an atlas texture, for drawing HUD text through a [`SpriteBatch`].
+/// The atlas is sampled like any other sprite texture, so it must be uploaded as a layer of the sprite texture array, and the
+/// metrics describe where each glyph is within it
+///
+/// The metrics are loaded from a TOML file, with every measurement in pixels:
+///
+/// ```toml
+/// line_height = 18
+/// atlas_size = [256, 256]
+///
+/// [[glyphs]]
+/// character = "A"
+/// x = 0
+/// y = 0
+/// width = 11
+/// height = 14
+/// y_offset = 2
+/// advance = 12
+///
+/// [[kerning]]
+/// first = "A"
+/// second = "V"
+/// amount = -1.5
+/// ```
+pub struct BitmapFont {
+    line_height: f32,
+    atlas_size: [f32; 2],
+    glyphs: HashMap<char, GlyphMetrics>,
+    kerning: HashMap<(char, char), f32>,
+    texture_id: u32,
+}
+
+impl BitmapFont {
+    /// Loads a font's metrics from a TOML file
+    ///
+    /// If the file can't be read or isn't valid font metrics, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the metrics file
+    /// * `texture_id`: The layer of the sprite texture array which holds the font's atlas
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use client::renderer::vulkan::Device;
+    /// # const HUD_FONT_LAYER: u32 = 0;
+    /// # fn example(device: &Device) -> Result<(), &'static str> {
+    /// use client::renderer::vulkan::BitmapFont;
+    ///
+    /// let font = BitmapFont::load(&device.resolve_asset_path(Path::new("res/fonts/hud.toml")), HUD_FONT_LAYER)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load(path: &Path, texture_id: u32) -> Result<Self, &'static str> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|_| "Failed to read the font metrics")?;
+        let metrics: FontMetrics = toml::from_str(&contents).map_err(|error| {
+            warn!("Failed to parse font metrics {:?} ({})", path, error);
+            "The font metrics are invalid"
+        })?;
+
+        info!(
+            "Loaded {} glyphs and {} kerning pairs from {:?}",
+            metrics.glyphs.len(),
+            metrics.kerning.len(),
+            path
+        );
+        Self::from_metrics(metrics, texture_id)
+    }
+
+    /// Constructs a font from metrics which have already been parsed
+    ///
+    /// If the atlas has no area, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics`: The contents of the font metrics file
+    /// * `texture_id`: The layer of the sprite texture array which holds the font's atlas
+    fn from_metrics(metrics: FontMetrics, texture_id: u32) -> Result<Self, &'static str> {
+        if metrics.atlas_size.contains(&0) {
+            return Err("The font atlas has no area");
+        }
+
+        Ok(Self {
+            line_height: metrics.line_height,
+            atlas_size: metrics.atlas_size.map(|size| size as f32),
+            glyphs: metrics
+                .glyphs
+                .into_iter()
+                .map(|glyph| (glyph.character, glyph))
+                .collect(),
+            kerning: metrics
+                .kerning
+                .into_iter()
+                .map(|pair| ((pair.first, pair.second), pair.amount))
+                .collect(),
+            texture_id,
+        })
+    }
+
+    /// Gets the distance between the tops of consecutive lines at a scale of 1, in pixels
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Adds a sprite to a batch for each glyph of some text. Newlines start a new line at the original horizontal position, and
+    /// characters the font doesn't have are drawn as `?` (or skipped, if it doesn't have that either)
+    ///
+    /// # Arguments
+    ///
+    /// * `batch`: The batch to add the glyphs to
+    /// * `text`: The text to draw
+    /// * `position`: The top-left corner of the first line, in pixels
+    /// * `scale`: How much the glyphs are scaled up from their size in the atlas, which should be a whole number to keep them sharp
+    /// * `color`: The colour of the text
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{BitmapFont, Device, SpriteBatch, TextureHandle};
+    /// # fn example(
+    /// #     mut device: Device,
+    /// #     mut batch: SpriteBatch,
+    /// #     font: &BitmapFont,
+    /// #     sprites: TextureHandle,
+    /// #     current_frame_index: usize,
+    /// #     fps: u32,
+    /// #     ping: u32,
+    /// # ) -> Result<(), &'static str> {
+    /// use common::color::Color;
+    ///
+    /// batch.begin([1280.0, 720.0]);
+    /// font.draw_text(&mut batch, &format!("FPS: {}\nPing: {}ms", fps, ping), [8.0, 8.0], 2.0, Color::WHITE);
+    /// // `sprites` is the texture array holding the font's atlas
+    /// batch.flush(&mut device, current_frame_index, sprites)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn draw_text(
+        &self,
+        batch: &mut SpriteBatch,
+        text: &str,
+        position: [f32; 2],
+        scale: f32,
+        color: Color,
+    ) {
+        let mut pen = position;
+        let mut previous = None;
+
+        for character in text.chars() {
+            if character == '\n' {
+                pen = [position[0], pen[1] + self.line_height * scale];
+                previous = None;
+                continue;
+            }
+
+            let Some(glyph) = self
+                .glyphs
+                .get(&character)
+                .or_else(|| self.glyphs.get(&REPLACEMENT_CHARACTER))
+            else {
+                continue;
+            };
+
+            if let Some(previous) = previous {
+                pen[0] += self
+                    .kerning
+                    .get(&(previous, glyph.character))
+                    .unwrap_or(&0.0)
+                    * scale;
+            }
+
+            // Glyphs such as spaces only move the pen
+            if glyph.width > 0 && glyph.height > 0 {
+                batch.draw(Sprite {
+                    position: [
+                        pen[0] + glyph.x_offset * scale,
+                        pen[1] + glyph.y_offset * scale,
+                    ],
+                    size: [glyph.width as f32 * scale, glyph.height as f32 * scale],
+                    uv_min: [
+                        glyph.x as f32 / self.atlas_size[0],
+                        glyph.y as f32 / self.atlas_size[1],
+                    ],
+                    uv_max: [
+                        (glyph.x + glyph.width) as f32 / self.atlas_size[0],
+                        (glyph.y + glyph.height) as f32 / self.atlas_size[1],
+                    ],
+                    color,
+                    texture_id: self.texture_id,
+                });
+            }
+
+            pen[0] += glyph.advance * scale;
+            previous = Some(glyph.character);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT_LAYER: u32 = 3;
+
+    fn test_font() -> BitmapFont {
+        let metrics = toml::from_str(
+            r#"
+            line_height = 18
+            atlas_size = [256, 128]
+
+            [[glyphs]]
+            character = "A"
+            x = 32
+            y = 64
+            width = 11
+            height = 14
+            advance = 12
+            "#,
+        )
+        .unwrap();
+        BitmapFont::from_metrics(metrics, FONT_LAYER).unwrap()
+    }
+
+    #[test]
+    fn glyph_uvs_and_layer_reach_the_sprite_vertices() {
+        let mut batch = SpriteBatch::new();
+        batch.begin([1280.0, 720.0]);
+        test_font().draw_text(&mut batch, "A", [0.0, 0.0], 1.0, Color::WHITE);
+
+        // The vertices are the corners of the glyph's rectangle in the atlas, normalised by the atlas size
+        let vertices = batch.vertices();
+        assert_eq!(vertices.len(), 6);
+        let uv_min = [32.0 / 256.0, 64.0 / 128.0];
+        let uv_max = [43.0 / 256.0, 78.0 / 128.0];
+        for vertex in vertices {
+            assert!(
+                vertex.uv == [uv_min[0], uv_min[1]]
+                    || vertex.uv == [uv_max[0], uv_min[1]]
+                    || vertex.uv == [uv_min[0], uv_max[1]]
+                    || vertex.uv == [uv_max[0], uv_max[1]]
+            );
+            assert_eq!(vertex.texture_id, FONT_LAYER);
+        }
+        assert_eq!(vertices[0].uv, uv_min);
+        assert_eq!(vertices[2].uv, uv_max);
+    }
+
+    #[test]
+    fn missing_glyphs_without_a_replacement_are_skipped() {
+        let mut batch = SpriteBatch::new();
+        batch.begin([1280.0, 720.0]);
+        test_font().draw_text(&mut batch, "AB A", [0.0, 0.0], 1.0, Color::WHITE);
+
+        assert_eq!(batch.len(), 2);
+    }
+}
@@ -0,0 +1,272 @@
+use ash::vk;
+
+use crate::renderer::vulkan::handle::Slab;
+use crate::renderer::vulkan::{Buffer, DepthBias, Pipeline, PipelineHandle, SupportedFeatures};
+
+/// Records commands into a frame's graphics command buffer, for custom passes which need more than the fixed begin/draw/end sequence.
+/// A `CommandRecorder` is only handed out by [`Device::record_graphics()`](crate::renderer::vulkan::Device::record_graphics), and
+/// can't outlive the closure it's given to
+pub struct CommandRecorder<'a> {
+    device: &'a ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipelines: &'a Slab<Pipeline>,
+    enabled_features: SupportedFeatures,
+    line_width_range: [f32; 2],
+}
+
+impl<'a> CommandRecorder<'a> {
+    /// Constructs a new `CommandRecorder` for a command buffer in the recording state
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The logical device that the command buffer belongs to
+    /// * `command_buffer`: The command buffer to record into
+    /// * `pipelines`: The device's pipelines, so that they can be bound by handle
+    /// * `enabled_features`: The optional features enabled on the device, which limit the dynamic state that can be set
+    /// * `line_width_range`: The narrowest and widest lines the device supports
+    pub(crate) fn new(
+        device: &'a ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pipelines: &'a Slab<Pipeline>,
+        enabled_features: SupportedFeatures,
+        line_width_range: [f32; 2],
+    ) -> Self {
+        Self {
+            device,
+            command_buffer,
+            pipelines,
+            enabled_features,
+            line_width_range,
+        }
+    }
+
+    /// Binds a graphics pipeline for the draws that follow
+    ///
+    /// If the pipeline has been destroyed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline`: The handle returned when the pipeline was created
+    pub fn bind_pipeline(&mut self, pipeline: PipelineHandle) -> Result<(), &'static str> {
+        let pipeline = self
+            .pipelines
+            .get(pipeline)
+            .ok_or("The pipeline has been destroyed")?;
+        unsafe {
+            self.device.cmd_bind_pipeline(
+                self.command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline,
+            )
+        };
+        Ok(())
+    }
+
+    /// Binds a vertex buffer to binding 0 for the draws that follow
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: A buffer created with `VERTEX_BUFFER` usage
+    pub fn bind_vertex_buffer(&mut self, buffer: &Buffer) {
+        unsafe {
+            self.device
+                .cmd_bind_vertex_buffers(self.command_buffer, 0, &[buffer.buffer], &[0])
+        };
+    }
+
+    /// Binds an index buffer of `u32` indices for the indexed draws that follow
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: A buffer created with `INDEX_BUFFER` usage
+    pub fn bind_index_buffer(&mut self, buffer: &Buffer) {
+        unsafe {
+            self.device.cmd_bind_index_buffer(
+                self.command_buffer,
+                buffer.buffer,
+                0,
+                vk::IndexType::UINT32,
+            )
+        };
+    }
+
+    /// Sets the viewport for the draws that follow
+    ///
+    /// # Arguments
+    ///
+    /// * `viewport`: The area of the framebuffer to draw to, and the range of depths to map to
+    pub fn set_viewport(&mut self, viewport: vk::Viewport) {
+        unsafe {
+            self.device
+                .cmd_set_viewport(self.command_buffer, 0, &[viewport])
+        };
+    }
+
+    /// Sets the scissor rectangle for the draws that follow, outside of which nothing is drawn
+    ///
+    /// # Arguments
+    ///
+    /// * `scissor`: The area of the framebuffer that can be drawn to
+    pub fn set_scissor(&mut self, scissor: vk::Rect2D) {
+        unsafe {
+            self.device
+                .cmd_set_scissor(self.command_buffer, 0, &[scissor])
+        };
+    }
+
+    /// Sets the depth bias for the draws that follow, for pipelines created with the `DepthBias` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `depth_bias`: The depth bias to apply. A clamp other than 0 is ignored unless `depth_bias_clamp` is enabled
+    pub fn set_depth_bias(&mut self, depth_bias: DepthBias) {
+        let clamp = if self.enabled_features.depth_bias_clamp {
+            depth_bias.clamp
+        } else {
+            0.0
+        };
+        unsafe {
+            self.device.cmd_set_depth_bias(
+                self.command_buffer,
+                depth_bias.constant_factor,
+                clamp,
+                depth_bias.slope_factor,
+            )
+        };
+    }
+
+    /// Sets the width of lines for the draws that follow, for pipelines created with the `LineWidth` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `line_width`: The width in pixels, which is clamped to the range the device supports. Widths other than 1 are ignored
+    ///   unless `wide_lines` was requested when creating the device
+    pub fn set_line_width(&mut self, line_width: f32) {
+        let line_width = if self.enabled_features.wide_lines {
+            line_width.clamp(self.line_width_range[0], self.line_width_range[1])
+        } else {
+            1.0
+        };
+        unsafe {
+            self.device
+                .cmd_set_line_width(self.command_buffer, line_width)
+        };
+    }
+
+    /// Sets the constant colour used by blend factors such as `CONSTANT_COLOR` for the draws that follow, for pipelines created with
+    /// the `BlendConstants` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `blend_constants`: The red, green, blue, and alpha components of the constant colour
+    pub fn set_blend_constants(&mut self, blend_constants: [f32; 4]) {
+        unsafe {
+            self.device
+                .cmd_set_blend_constants(self.command_buffer, &blend_constants)
+        };
+    }
+
+    /// Sets the value that stencil tests compare against for the draws that follow, for pipelines created with the
+    /// `StencilReference` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `face_mask`: Whether to set the reference for front faces, back faces, or both
+    /// * `reference`: The stencil reference value
+    pub fn set_stencil_reference(&mut self, face_mask: vk::StencilFaceFlags, reference: u32) {
+        unsafe {
+            self.device
+                .cmd_set_stencil_reference(self.command_buffer, face_mask, reference)
+        };
+    }
+
+    /// Clears a region of the render pass's first colour attachment, without ending the pass.
+    /// Unlike the clear at the start of a pass, this can be limited to part of the framebuffer (eg. one viewport of a split screen)
+    ///
+    /// # Arguments
+    ///
+    /// * `rect`: The region of the attachment to clear
+    /// * `color`: The colour to clear the region to
+    pub fn clear_attachment(&mut self, rect: vk::Rect2D, color: [f32; 4]) {
+        let attachments = [vk::ClearAttachment {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            color_attachment: 0,
+            clear_value: vk::ClearValue {
+                color: vk::ClearColorValue { float32: color },
+            },
+        }];
+        let rects = [vk::ClearRect {
+            rect,
+            base_array_layer: 0,
+            layer_count: 1,
+        }];
+        unsafe {
+            self.device
+                .cmd_clear_attachments(self.command_buffer, &attachments, &rects)
+        };
+    }
+
+    /// Records a non-indexed draw
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_count`: The number of vertices to draw
+    /// * `instance_count`: The number of instances to draw
+    /// * `first_vertex`: The index of the first vertex
+    /// * `first_instance`: The index of the first instance
+    pub fn draw(
+        &mut self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.cmd_draw(
+                self.command_buffer,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            )
+        };
+    }
+
+    /// Records an indexed draw, reading indices from the bound index buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `index_count`: The number of indices to draw
+    /// * `instance_count`: The number of instances to draw
+    /// * `first_index`: The position of the first index in the index buffer
+    /// * `vertex_offset`: The value added to each index before it's used to look up a vertex
+    /// * `first_instance`: The index of the first instance
+    pub fn draw_indexed(
+        &mut self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.cmd_draw_indexed(
+                self.command_buffer,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            )
+        };
+    }
+
+    /// Gets the logical device and the command buffer being recorded, for commands which don't have a wrapper
+    ///
+    /// # Safety
+    ///
+    /// The command buffer must be left in the recording state, inside the same render pass it was in, and mustn't be submitted
+    pub unsafe fn raw(&self) -> (&ash::Device, vk::CommandBuffer) {
+        (self.device, self.command_buffer)
+    }
+}
@@ -0,0 +1,136 @@
+use std::ffi::CString;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::pipeline::{create_shader_module, read_spirv};
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::Device;
+
+/// A compute shader which game code can dispatch on the compute queue through [`Device::dispatch_compute()`] (eg. for simulation).
+/// The shader reads and writes storage buffers at set 0, one per binding starting from binding 0
+pub struct ComputePipeline {
+    device: Weak<ash::Device>,
+    storage_buffer_count: u32,
+    pub(crate) descriptor_set_layout: vk::DescriptorSetLayout,
+    pub(crate) pipeline_layout: vk::PipelineLayout,
+    pub(crate) pipeline: vk::Pipeline,
+}
+
+impl ComputePipeline {
+    /// Constructs a new `ComputePipeline` from a compute shader.
+    /// Note that the recommended way to create a compute pipeline is through [`Device::create_compute_pipeline()`] rather than using
+    /// `ComputePipeline::new()` directly
+    ///
+    /// If the shader file doesn't exist or isn't valid SPIR-V, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the pipeline on
+    /// * `shader_path`: A `Path` which references a compiled SPIR-V compute shader, relative to the asset root
+    /// * `storage_buffer_count`: The number of storage buffers the shader binds at set 0
+    pub fn new(
+        device: &Device,
+        shader_path: &Path,
+        storage_buffer_count: u32,
+    ) -> Result<Self, &'static str> {
+        let span = debug_span!("Vulkan/ComputePipeline");
+        let _guard = span.enter();
+
+        let code = read_spirv(&device.resolve_asset_path(shader_path))?;
+        let shader_module = create_shader_module(device, &code);
+
+        let bindings: Vec<vk::DescriptorSetLayoutBinding> = (0..storage_buffer_count)
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .build()
+            })
+            .collect();
+        let descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+        let descriptor_set_layout = unsafe {
+            device
+                .logical_device
+                .create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+        }
+        .expect("Failed to create compute descriptor set layout");
+
+        let set_layouts = [descriptor_set_layout];
+        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .build();
+        let pipeline_layout = unsafe {
+            device
+                .logical_device
+                .create_pipeline_layout(&pipeline_layout_create_info, None)
+        }
+        .expect("Failed to create compute pipeline layout");
+
+        let entry_point = CString::new("main").unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(shader_module)
+            .name(entry_point.as_c_str())
+            .build();
+        let pipeline_create_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage)
+            .layout(pipeline_layout)
+            .build();
+
+        debug!("Creating compute pipeline from {:?}", shader_path);
+        let pipeline = *unsafe {
+            device.logical_device.create_compute_pipelines(
+                device.pipeline_cache(),
+                &[pipeline_create_info],
+                None,
+            )
+        }
+        .expect("Failed to create compute pipeline")
+        .first()
+        .unwrap();
+        track_created(ResourceKind::Pipeline, 1);
+
+        unsafe {
+            device
+                .logical_device
+                .destroy_shader_module(shader_module, None)
+        };
+
+        Ok(ComputePipeline {
+            device: Rc::downgrade(&device.logical_device),
+            storage_buffer_count,
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+
+    /// Gets the number of storage buffers the shader binds, which must match the number passed to each dispatch
+    pub fn storage_buffer_count(&self) -> u32 {
+        self.storage_buffer_count
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~ComputePipeline");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying compute pipeline");
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+        track_destroyed(ResourceKind::Pipeline, 1);
+    }
+}
@@ -1,18 +1,84 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::fmt::Write;
 
 use ash::extensions;
 use ash::vk;
-use tracing::{debug, debug_span};
+use tracing::{debug, debug_span, error, info, trace, warn};
+
+use crate::renderer::vulkan::{SupportedFeatures, Surface};
+use crate::renderer::ContextError;
+
+/// Setting this to `1` enables the validation layer, and setting it to `0` disables it, regardless of build type
+const VALIDATION_ENV_NAME: &str = "CLIENT_VULKAN_VALIDATION";
+
+/// The optional device extensions that a `Device` uses when they're supported, which are listed in the diagnostics report
+const OPTIONAL_DEVICE_EXTENSIONS: [&CStr; 5] = [
+    vk::ExtMemoryBudgetFn::name(),
+    vk::KhrPresentIdFn::name(),
+    vk::KhrPresentWaitFn::name(),
+    vk::KhrIncrementalPresentFn::name(),
+    vk::KhrPortabilitySubsetFn::name(),
+];
+
+/// How severe a validation message must be for the debug messenger to log it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugMessageSeverity {
+    /// Diagnostic messages from the loader, layers, and driver, logged at trace level
+    Verbose,
+    /// Informational messages such as resource details, logged at debug level
+    Info,
+    /// Likely mistakes or poor use of the API, logged at warn level
+    #[default]
+    Warning,
+    /// Invalid use of the API that may cause crashes, logged at error level
+    Error,
+}
+
+impl DebugMessageSeverity {
+    /// Gets the severities which are at least as severe as this one, for filtering messages in the messenger
+    fn and_above(self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        [
+            (
+                Self::Verbose,
+                vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE,
+            ),
+            (Self::Info, vk::DebugUtilsMessageSeverityFlagsEXT::INFO),
+            (
+                Self::Warning,
+                vk::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+            ),
+            (Self::Error, vk::DebugUtilsMessageSeverityFlagsEXT::ERROR),
+        ]
+        .into_iter()
+        .filter(|(severity, _)| *severity >= self)
+        .fold(
+            vk::DebugUtilsMessageSeverityFlagsEXT::empty(),
+            |flags, (_, flag)| flags | flag,
+        )
+    }
+}
+
+/// Options which control how the Vulkan instance is created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextParameters {
+    /// The least severe validation message that's logged. Messages are only logged when the validation layer is enabled (which it
+    /// is by default in debug builds) and `VK_EXT_debug_utils` is available
+    pub minimum_debug_severity: DebugMessageSeverity,
+}
 
 pub struct Context {
     pub application_name: CString,
     pub engine_name: CString,
     pub entry_point: ash::Entry,
     pub instance: ash::Instance,
+    enabled_layers: Vec<CString>,
+    enabled_extensions: Vec<&'static CStr>,
+    // `None` if validation is disabled or VK_EXT_debug_utils isn't available
+    debug_messenger: Option<(extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>,
 }
 
 impl Context {
-    /// Constructs a new Context
+    /// Constructs a new Context, panicking if it can't be created. Use [`Context::try_new()`] to handle machines without Vulkan
     ///
     /// # Arguments
     ///
@@ -21,17 +87,93 @@ impl Context {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// use client::renderer::vulkan::Context;
     ///
-    /// let context = Context::new("my-application", (1.4.2));
+    /// let context = Context::new("my-application", (1, 4, 2));
     /// ```
     pub fn new(application_name: &str, application_version: (u32, u32, u32)) -> Self {
+        Self::try_new(application_name, application_version)
+            .expect("Failed to create a Vulkan context")
+    }
+
+    /// Constructs a new Context
+    ///
+    /// If Vulkan can't be loaded or the instance can't be created, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `application_name`: The name of the application that the context will be used by, as a `str`
+    /// * `application_version`: The version of the application that the context will be used by, as a 3-tuple of `u32`s
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::process::ExitCode;
+    /// # use tracing::error;
+    /// # fn main() -> ExitCode {
+    /// use client::renderer::vulkan::Context;
+    ///
+    /// let context = match Context::try_new("my-application", (1, 4, 2)) {
+    ///     Ok(context) => context,
+    ///     Err(error) => {
+    ///         error!("{}", error);
+    ///         return ExitCode::FAILURE;
+    ///     }
+    /// };
+    /// # ExitCode::SUCCESS
+    /// # }
+    /// ```
+    pub fn try_new(
+        application_name: &str,
+        application_version: (u32, u32, u32),
+    ) -> Result<Self, ContextError> {
+        Self::try_with_parameters(
+            application_name,
+            application_version,
+            &ContextParameters::default(),
+        )
+    }
+
+    /// Constructs a new Context with the given options
+    ///
+    /// If Vulkan can't be loaded or the instance can't be created, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `application_name`: The name of the application that the context will be used by, as a `str`
+    /// * `application_version`: The version of the application that the context will be used by, as a 3-tuple of `u32`s
+    /// * `parameters`: Options which control how the instance is created
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::ContextError;
+    /// # fn example() -> Result<(), ContextError> {
+    /// use client::renderer::vulkan::{Context, ContextParameters, DebugMessageSeverity};
+    ///
+    /// // Only log validation errors, muting the warnings about performance
+    /// let context = Context::try_with_parameters(
+    ///     "my-application",
+    ///     (1, 4, 2),
+    ///     &ContextParameters { minimum_debug_severity: DebugMessageSeverity::Error },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_with_parameters(
+        application_name: &str,
+        application_version: (u32, u32, u32),
+        parameters: &ContextParameters,
+    ) -> Result<Self, ContextError> {
         let span = debug_span!("Vulkan/Context");
         let _guard = span.enter();
 
         debug!("Loading Vulkan dynamically");
-        let entry_point = unsafe { ash::Entry::load() }.expect("Failed to load Vulkan libraries");
+        let entry_point = unsafe { ash::Entry::load() }.map_err(|error| {
+            warn!("Failed to load Vulkan libraries ({})", error);
+            ContextError::LoaderMissing
+        })?;
         debug!("Loaded successfully");
 
         let engine_name = CString::new("engine").unwrap();
@@ -52,42 +194,366 @@ impl Context {
 
         let validation_layer_name = CString::new("VK_LAYER_KHRONOS_validation").unwrap();
 
+        let mut enabled_layers = vec![];
+        if is_validation_requested() {
+            if is_layer_available(&entry_point, validation_layer_name.as_c_str())? {
+                enabled_layers.push(validation_layer_name.clone());
+            } else {
+                warn!(
+                    "Validation was requested, but {:?} is not available",
+                    validation_layer_name
+                );
+            }
+        }
+
         // TODO - Figure out if it's worth just targeting Wayland on Unix
         // KDE will be moving to a Wayland default session with Plasma 6 (Steam Deck uses KDE)
         // GNOME already favours Wayland
         // XWayland exists to bridge the gap, but nothing going the other way
         // NVIDIA Wayland drivers aren't particularly mature, AMD are fine, Intel is untested
 
-        // TODO - Test for extensions before using them (albeit if we don't have surface then we're a bit scuppered anyway)
+        let available_extensions = available_instance_extensions(&entry_point)?;
+        let is_extension_available = |name: &CStr| {
+            available_extensions
+                .iter()
+                .any(|available| available.as_c_str() == name)
+        };
+
+        let required_extensions = [
+            extensions::khr::Surface::name(),
+            #[cfg(target_os = "windows")]
+            extensions::khr::Win32Surface::name(),
+            #[cfg(target_os = "macos")]
+            extensions::ext::MetalSurface::name(),
+        ];
+        let mut enabled_extensions = vec![];
+        for extension in required_extensions {
+            if !is_extension_available(extension) {
+                return Err(ContextError::ExtensionUnavailable(extension));
+            }
+            enabled_extensions.push(extension);
+        }
+
+        // Linux systems may only expose the surface extension for the display server they run, so only the available ones are
+        // enabled, and at least one is needed
+        #[cfg(target_os = "linux")]
+        {
+            let display_server_extensions = [
+                extensions::khr::XcbSurface::name(),
+                extensions::khr::WaylandSurface::name(),
+            ];
+            let available_display_server_extensions: Vec<&'static CStr> = display_server_extensions
+                .into_iter()
+                .filter(|extension| is_extension_available(extension))
+                .collect();
+            if available_display_server_extensions.is_empty() {
+                return Err(ContextError::ExtensionUnavailable(
+                    display_server_extensions[0],
+                ));
+            }
+            enabled_extensions.extend(available_display_server_extensions);
+        }
+
+        // Validation messages only go to stdout unless a messenger routes them into the log
+        let debug_utils_enabled = !enabled_layers.is_empty()
+            && is_extension_available(extensions::ext::DebugUtils::name());
+        if debug_utils_enabled {
+            enabled_extensions.push(extensions::ext::DebugUtils::name());
+        }
+
+        #[allow(unused_mut)]
+        let mut instance_create_flags = vk::InstanceCreateFlags::empty();
+
+        // MoltenVK is a portability implementation rather than a fully conformant one, so newer loaders only list its devices if
+        // the instance opts in to portability enumeration
+        #[cfg(target_os = "macos")]
+        if is_extension_available(vk::KhrPortabilityEnumerationFn::name()) {
+            enabled_extensions.push(vk::KhrPortabilityEnumerationFn::name());
+            instance_create_flags |= vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR;
+            info!("Enabled portability enumeration");
+        } else {
+            debug!("VK_KHR_portability_enumeration is unavailable, so only conformant devices will be listed");
+        }
+
+        let enabled_extension_names: Vec<*const std::ffi::c_char> = enabled_extensions
+            .iter()
+            .map(|extension| extension.as_ptr())
+            .collect();
+        let enabled_layer_names: Vec<*const std::ffi::c_char> =
+            enabled_layers.iter().map(|layer| layer.as_ptr()).collect();
         let instance_create_info = vk::InstanceCreateInfo::builder()
+            .flags(instance_create_flags)
             .application_info(&application_info)
-            .enabled_extension_names(&[
-                extensions::khr::Surface::name().as_ptr(),
-                #[cfg(target_os = "windows")]
-                extensions::khr::Win32Surface::name().as_ptr(),
-                #[cfg(target_os = "linux")]
-                extensions::khr::XcbSurface::name().as_ptr(),
-                #[cfg(target_os = "linux")]
-                extensions::khr::WaylandSurface::name().as_ptr(),
-                #[cfg(target_os = "macos")]
-                extensions::ext::MetalSurface::name().as_ptr(),
-            ])
-            .enabled_layer_names(&[
-                #[cfg(debug_assertions)]
-                validation_layer_name.as_ptr(),
-            ])
+            .enabled_extension_names(&enabled_extension_names)
+            .enabled_layer_names(&enabled_layer_names)
             .build();
 
         debug!("Creating Vulkan Instance");
         let instance = unsafe { entry_point.create_instance(&instance_create_info, None) }
-            .expect("Failed to create a Vulkan instance");
+            .map_err(|result| match result {
+                vk::Result::ERROR_LAYER_NOT_PRESENT => ContextError::LayerUnavailable,
+                result => ContextError::InstanceCreation(result),
+            })?;
         debug!("Created successfully");
 
-        Context {
+        let debug_messenger = if debug_utils_enabled {
+            let debug_utils = extensions::ext::DebugUtils::new(&entry_point, &instance);
+            let messenger_create_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+                .message_severity(parameters.minimum_debug_severity.and_above())
+                .message_type(
+                    vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                        | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                        | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+                )
+                .pfn_user_callback(Some(log_debug_message))
+                .build();
+            match unsafe { debug_utils.create_debug_utils_messenger(&messenger_create_info, None) }
+            {
+                Ok(messenger) => {
+                    info!(
+                        "Logging validation messages at {:?} and above",
+                        parameters.minimum_debug_severity
+                    );
+                    Some((debug_utils, messenger))
+                }
+                Err(error) => {
+                    warn!("Failed to create debug messenger ({})", error);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if enabled_layers.is_empty() {
+            info!("No instance layers enabled");
+        }
+        for layer in &enabled_layers {
+            info!("Enabled instance layer {:?}", layer);
+        }
+
+        Ok(Context {
             application_name,
             engine_name,
             entry_point,
             instance,
+            enabled_layers,
+            enabled_extensions,
+            debug_messenger,
+        })
+    }
+
+    /// Gets the number of physical devices available, which can be passed by index to [`Context::device_features()`]
+    pub fn physical_device_count(&self) -> usize {
+        unsafe { self.instance.enumerate_physical_devices() }
+            .map(|physical_devices| physical_devices.len())
+            .unwrap_or(0)
+    }
+
+    /// Gets the optional features supported by a physical device, so that they can be checked before a `Device` is created
+    ///
+    /// Returns `None` if there is no physical device with the index
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The index of the physical device, in the same order as `CLIENT_GPU_INDEX`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use client::renderer::vulkan::Context;
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// for index in 0..context.physical_device_count() {
+    ///     let features = context.device_features(index).unwrap();
+    ///     println!("Device {} supports anisotropy: {}", index, features.sampler_anisotropy);
+    /// }
+    /// ```
+    pub fn device_features(&self, index: usize) -> Option<SupportedFeatures> {
+        let physical_devices = unsafe { self.instance.enumerate_physical_devices() }.ok()?;
+        let physical_device = physical_devices.get(index)?;
+        Some(SupportedFeatures::query(&self.instance, *physical_device))
+    }
+
+    /// Builds a plain-text report of the instance and every physical device, for pasting into bug reports. It covers the enabled
+    /// instance layers and extensions, and each device's properties, memory heaps, queue families, optional features and extensions,
+    /// and (if a surface is given) the surface formats and present modes it supports
+    ///
+    /// # Arguments
+    ///
+    /// * `surface`: The `Surface` being presented to, or `None` to leave out surface support
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Surface;
+    /// # fn example(surface: &Surface) -> std::io::Result<()> {
+    /// use client::renderer::vulkan::Context;
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// std::fs::write("vulkan-report.txt", context.diagnostics_report(Some(surface)))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diagnostics_report(&self, surface: Option<&Surface>) -> String {
+        let mut report = String::new();
+        // Writing to a `String` can't fail, so the results are ignored throughout
+        let _ = writeln!(
+            report,
+            "Vulkan instance version: {}",
+            format_version(
+                self.entry_point
+                    .try_enumerate_instance_version()
+                    .ok()
+                    .flatten()
+                    .unwrap_or(vk::API_VERSION_1_0)
+            )
+        );
+        let _ = writeln!(report, "Enabled instance layers: {:?}", self.enabled_layers);
+        let _ = writeln!(
+            report,
+            "Enabled instance extensions: {:?}",
+            self.enabled_extensions
+        );
+
+        let physical_devices = match unsafe { self.instance.enumerate_physical_devices() } {
+            Ok(physical_devices) => physical_devices,
+            Err(error) => {
+                let _ = writeln!(report, "Failed to enumerate physical devices ({})", error);
+                return report;
+            }
+        };
+        for (index, physical_device) in physical_devices.iter().enumerate() {
+            let _ = writeln!(report);
+            self.write_physical_device_report(&mut report, index, *physical_device, surface);
+        }
+        report
+    }
+
+    /// Appends the section of the diagnostics report describing a single physical device
+    ///
+    /// # Arguments
+    ///
+    /// * `report`: The report to append to
+    /// * `index`: The index of the physical device, in the same order as `CLIENT_GPU_INDEX`
+    /// * `physical_device`: The physical device to describe
+    /// * `surface`: The `Surface` being presented to, if any
+    fn write_physical_device_report(
+        &self,
+        report: &mut String,
+        index: usize,
+        physical_device: vk::PhysicalDevice,
+        surface: Option<&Surface>,
+    ) {
+        let properties = unsafe {
+            self.instance
+                .get_physical_device_properties(physical_device)
+        };
+        let name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) };
+        let _ = writeln!(report, "Physical device {}: {:?}", index, name);
+        let _ = writeln!(report, "  Type: {:?}", properties.device_type);
+        let _ = writeln!(
+            report,
+            "  Vendor ID: {:#06X}, device ID: {:#06X}",
+            properties.vendor_id, properties.device_id
+        );
+        let _ = writeln!(
+            report,
+            "  API version: {}, driver version: {:#X}",
+            format_version(properties.api_version),
+            properties.driver_version
+        );
+
+        let memory_properties = unsafe {
+            self.instance
+                .get_physical_device_memory_properties(physical_device)
+        };
+        for (heap_index, heap) in memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+        {
+            let _ = writeln!(
+                report,
+                "  Memory heap {}: {} MiB, {:?}",
+                heap_index,
+                heap.size / (1024 * 1024),
+                heap.flags
+            );
+        }
+
+        let queue_families = unsafe {
+            self.instance
+                .get_physical_device_queue_family_properties(physical_device)
+        };
+        for (family_index, queue_family) in queue_families.iter().enumerate() {
+            let presentable = surface.map(|surface| {
+                unsafe {
+                    surface
+                        .surface_extension
+                        .get_physical_device_surface_support(
+                            physical_device,
+                            family_index as u32,
+                            surface.surface,
+                        )
+                }
+                .unwrap_or(false)
+            });
+            let _ = writeln!(
+                report,
+                "  Queue family {}: {} queues, {:?}{}",
+                family_index,
+                queue_family.queue_count,
+                queue_family.queue_flags,
+                match presentable {
+                    Some(true) => ", can present",
+                    Some(false) => ", can't present",
+                    None => "",
+                }
+            );
+        }
+
+        let _ = writeln!(
+            report,
+            "  Features: {:?}",
+            SupportedFeatures::query(&self.instance, physical_device)
+        );
+
+        let supported_extensions = unsafe {
+            self.instance
+                .enumerate_device_extension_properties(physical_device)
+        }
+        .unwrap_or_default();
+        for extension in OPTIONAL_DEVICE_EXTENSIONS {
+            let is_supported = supported_extensions.iter().any(|supported| {
+                (unsafe { CStr::from_ptr(supported.extension_name.as_ptr()) }) == extension
+            });
+            let _ = writeln!(report, "  {:?} supported: {}", extension, is_supported);
+        }
+
+        if let Some(surface) = surface {
+            let formats = unsafe {
+                surface
+                    .surface_extension
+                    .get_physical_device_surface_formats(physical_device, surface.surface)
+            }
+            .unwrap_or_default();
+            let _ = writeln!(
+                report,
+                "  Surface formats: {:?}",
+                formats
+                    .iter()
+                    .map(|format| (format.format, format.color_space))
+                    .collect::<Vec<_>>()
+            );
+
+            let present_modes = unsafe {
+                surface
+                    .surface_extension
+                    .get_physical_device_surface_present_modes(physical_device, surface.surface)
+            }
+            .unwrap_or_default();
+            let _ = writeln!(report, "  Present modes: {:?}", present_modes);
         }
     }
 }
@@ -97,6 +563,11 @@ impl Drop for Context {
         let span = debug_span!("Vulkan/~Context");
         let _guard = span.enter();
 
+        if let Some((debug_utils, messenger)) = self.debug_messenger.take() {
+            debug!("Destroying debug messenger");
+            unsafe { debug_utils.destroy_debug_utils_messenger(messenger, None) };
+        }
+
         debug!("Destroying instance");
         unsafe {
             self.instance.destroy_instance(None);
@@ -104,3 +575,123 @@ impl Drop for Context {
         debug!("Successfully destroyed instance");
     }
 }
+
+/// Logs a message from the validation layer at the level matching its severity, along with the names of the objects involved
+///
+/// # Arguments
+///
+/// * `severity`: How severe the message is
+/// * `message_type`: Whether the message is about validation, performance, or something else
+/// * `callback_data`: The message and the objects it concerns
+/// * `_user_data`: Unused
+unsafe extern "system" fn log_debug_message(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut std::ffi::c_void,
+) -> vk::Bool32 {
+    let Some(callback_data) = callback_data.as_ref() else {
+        return vk::FALSE;
+    };
+    let message = if callback_data.p_message.is_null() {
+        std::borrow::Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(callback_data.p_message).to_string_lossy()
+    };
+    let objects = if callback_data.p_objects.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(callback_data.p_objects, callback_data.object_count as usize)
+    };
+    let object_names: Vec<String> = objects
+        .iter()
+        .filter(|object| !object.p_object_name.is_null())
+        .map(|object| {
+            format!(
+                "{:?} {:?}",
+                object.object_type,
+                CStr::from_ptr(object.p_object_name)
+            )
+        })
+        .collect();
+
+    if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        error!("[{:?}] {} {:?}", message_type, message, object_names);
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        warn!("[{:?}] {} {:?}", message_type, message, object_names);
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        debug!("[{:?}] {} {:?}", message_type, message, object_names);
+    } else {
+        trace!("[{:?}] {} {:?}", message_type, message, object_names);
+    }
+
+    // The call that triggered the message should carry on as normal
+    vk::FALSE
+}
+
+/// Formats a packed Vulkan version number as `major.minor.patch`
+///
+/// # Arguments
+///
+/// * `version`: The version, as packed by `vk::make_api_version()`
+fn format_version(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        vk::api_version_major(version),
+        vk::api_version_minor(version),
+        vk::api_version_patch(version)
+    )
+}
+
+/// Checks whether the validation layer should be enabled.
+/// The `CLIENT_VULKAN_VALIDATION` environment variable takes priority, and otherwise validation is enabled in debug builds only
+fn is_validation_requested() -> bool {
+    match std::env::var(VALIDATION_ENV_NAME) {
+        Ok(value) if value == "1" => true,
+        Ok(value) if value == "0" => false,
+        Ok(value) => {
+            warn!(
+                "Ignoring {} as {:?} is not 0 or 1",
+                VALIDATION_ENV_NAME, value
+            );
+            cfg!(debug_assertions)
+        }
+        Err(_) => cfg!(debug_assertions),
+    }
+}
+
+/// Checks whether an instance layer is available on the system
+///
+/// If the layers couldn't be enumerated, returns `Err`
+///
+/// # Arguments
+///
+/// * `entry_point`: The loaded Vulkan entry point to query
+/// * `layer_name`: The name of the layer to look for
+fn is_layer_available(entry_point: &ash::Entry, layer_name: &CStr) -> Result<bool, ContextError> {
+    let available_layers = entry_point
+        .enumerate_instance_layer_properties()
+        .map_err(ContextError::InstanceCreation)?;
+
+    Ok(available_layers
+        .iter()
+        .any(|layer| unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) } == layer_name))
+}
+
+/// Gets the names of the instance extensions available on the system
+///
+/// If the extensions couldn't be enumerated, returns `Err`
+///
+/// # Arguments
+///
+/// * `entry_point`: The loaded Vulkan entry point to query
+fn available_instance_extensions(entry_point: &ash::Entry) -> Result<Vec<CString>, ContextError> {
+    let available_extensions = entry_point
+        .enumerate_instance_extension_properties(None)
+        .map_err(ContextError::InstanceCreation)?;
+
+    Ok(available_extensions
+        .iter()
+        .map(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }.to_owned())
+        .collect())
+}
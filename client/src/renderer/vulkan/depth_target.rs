@@ -0,0 +1,299 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::pipeline::multiview_mask;
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{DepthMode, Device, Image};
+
+/// An offscreen depth image with no colour attachments, which can be rendered to (eg. from a light's point of view for shadow mapping)
+/// and then sampled by a later pass. Once its render pass ends, the image is left in `DEPTH_STENCIL_READ_ONLY_OPTIMAL`
+pub struct DepthTarget {
+    device: Weak<ash::Device>,
+    // Only held so that the image is freed after the view has been destroyed
+    _image: Image,
+    view: vk::ImageView,
+    format: vk::Format,
+    pub(crate) render_pass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) clear_value: vk::ClearDepthStencilValue,
+}
+
+impl DepthTarget {
+    /// Constructs a new `DepthTarget`.
+    /// Note that the recommended way to create a depth target is through [`Device::create_depth_target()`] rather than using
+    /// `DepthTarget::new()` directly, as it checks that the format can be rendered to and sampled
+    ///
+    /// A pipeline can draw to the depth target if it was created with
+    /// [`PipelineParameters::depth_only_format`](crate::renderer::vulkan::PipelineParameters::depth_only_format) set to the same format
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the depth target on
+    /// * `format`: The format of the depth image, eg. `D32_SFLOAT`
+    /// * `extent`: The size of the depth image
+    /// * `depth_mode`: The depth mode of the pipelines drawing to the target, which decides what the image is cleared to
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::{DepthMode, DepthTarget};
+    ///
+    /// let shadow_map = DepthTarget::new(
+    ///     &device,
+    ///     vk::Format::D32_SFLOAT,
+    ///     vk::Extent2D { width: 2048, height: 2048 },
+    ///     DepthMode::Standard,
+    /// );
+    /// # }
+    /// ```
+    pub fn new(
+        device: &Device,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        depth_mode: DepthMode,
+    ) -> Self {
+        let span = debug_span!("Vulkan/DepthTarget");
+        let _guard = span.enter();
+
+        debug!(
+            "Creating {}x{} depth target with format {:?}",
+            extent.width, extent.height, format
+        );
+        let (image, view) = create_depth_image(
+            device,
+            format,
+            extent,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC,
+            "depth target",
+        );
+        let render_pass = create_depth_only_render_pass(device, format, 1);
+
+        let attachments = [view];
+        let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1)
+            .build();
+        let framebuffer = unsafe {
+            device
+                .logical_device
+                .create_framebuffer(&framebuffer_create_info, None)
+        }
+        .expect("Failed to create depth target framebuffer");
+        track_created(ResourceKind::Framebuffer, 1);
+
+        DepthTarget {
+            device: Rc::downgrade(&device.logical_device),
+            _image: image,
+            view,
+            format,
+            render_pass,
+            framebuffer,
+            extent,
+            clear_value: depth_mode.clear_value(),
+        }
+    }
+
+    /// Gets the view of the depth image, so that it can be bound for sampling
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    /// Gets the format of the depth image
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// Gets the size of the depth image
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+}
+
+impl Drop for DepthTarget {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~DepthTarget");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying depth target");
+        unsafe {
+            device.destroy_framebuffer(self.framebuffer, None);
+            device.destroy_render_pass(self.render_pass, None);
+            device.destroy_image_view(self.view, None);
+        }
+        track_destroyed(ResourceKind::Framebuffer, 1);
+    }
+}
+
+/// Checks whether a format has a depth component, and so can be used for a depth attachment
+///
+/// # Arguments
+///
+/// * `format`: The format to check
+pub(crate) fn is_depth_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D32_SFLOAT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// Allocates a depth image that can be rendered to, and creates a view of its depth aspect
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the image on
+/// * `format`: The format of the image, which must be a depth format
+/// * `extent`: The size of the image
+/// * `extra_usage`: How else the image will be used besides as a depth attachment, eg. sampled
+/// * `name`: A name for the allocation, used when debugging leaks
+pub(crate) fn create_depth_image(
+    device: &Device,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    extra_usage: vk::ImageUsageFlags,
+    name: &str,
+) -> (Image, vk::ImageView) {
+    let image_create_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(
+            vk::Extent3D::builder()
+                .width(extent.width)
+                .height(extent.height)
+                .depth(1)
+                .build(),
+        )
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | extra_usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .build();
+    let image = device.allocate_image(&image_create_info, MemoryLocation::GpuOnly, name);
+
+    // Only the depth aspect can be sampled, even if the format also has stencil
+    let view_create_info = vk::ImageViewCreateInfo::builder()
+        .image(image.image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(format)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::DEPTH)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        )
+        .build();
+    let view = unsafe {
+        device
+            .logical_device
+            .create_image_view(&view_create_info, None)
+    }
+    .expect("Failed to create depth image view");
+
+    (image, view)
+}
+
+/// Constructs a render pass with no colour attachments, which clears a single depth attachment and leaves it ready to be sampled.
+/// Pipelines created with a depth-only format use a render pass built the same way, so that they're compatible with depth targets
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the render pass on
+/// * `format`: The format of the depth attachment
+/// * `view_count`: The number of views to render with multiview, or 1 to not use multiview
+pub(crate) fn create_depth_only_render_pass(
+    device: &Device,
+    format: vk::Format,
+    view_count: u32,
+) -> vk::RenderPass {
+    let attachments = [vk::AttachmentDescription::builder()
+        .format(format)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+        .build()];
+
+    let depth_attachment_reference = vk::AttachmentReference::builder()
+        .attachment(0)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .build();
+
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .depth_stencil_attachment(&depth_attachment_reference)
+        .build()];
+
+    let dependencies = [
+        // Any previous sampling of the image must finish before it's rendered over
+        vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags::SHADER_READ)
+            .dst_stage_mask(
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            )
+            .build(),
+        // Rendering must finish before a later pass samples the image
+        vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .dst_subpass(vk::SUBPASS_EXTERNAL)
+            .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+            .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .build(),
+    ];
+
+    let view_masks = [multiview_mask(view_count)];
+    let mut multiview_create_info = vk::RenderPassMultiviewCreateInfo::builder()
+        .view_masks(&view_masks)
+        .correlation_masks(&view_masks)
+        .build();
+
+    let mut render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+    if view_count > 1 {
+        render_pass_create_info = render_pass_create_info.push_next(&mut multiview_create_info);
+    }
+    let render_pass_create_info = render_pass_create_info.build();
+
+    unsafe {
+        device
+            .logical_device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Failed to create depth-only render pass")
+}
@@ -1,12 +1,48 @@
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::CStr;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use ash::vk;
-use tracing::{debug, debug_span};
+use gpu_allocator::vulkan::{Allocator, AllocatorCreateDesc};
+use gpu_allocator::{AllocationSizes, AllocatorDebugSettings, MemoryLocation};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, info, warn};
 
+use crate::renderer::vulkan::barrier::record_image_layout_transition;
+use crate::renderer::vulkan::depth_target::is_depth_format;
+use crate::renderer::vulkan::fallback_shaders::{FALLBACK_FRAGMENT_SHADER, FALLBACK_VERTEX_SHADER};
+use crate::renderer::vulkan::frame_readback::readback_texel_size;
+use crate::renderer::vulkan::frame_uniforms::FrameUniforms;
+use crate::renderer::vulkan::handle::Slab;
+use crate::renderer::vulkan::image_commands::{record_blit_image, record_clear_color_image};
+use crate::renderer::vulkan::memory::{
+    query_memory_budget, query_resizable_bar_heap, Buffer, Image, MemoryBudget,
+};
+use crate::renderer::vulkan::pipeline::{decode_spirv, read_spirv};
+use crate::renderer::vulkan::pipeline_cache::{load_pipeline_cache, save_pipeline_cache};
+use crate::renderer::vulkan::resource_stats::{
+    live_resources, track_created, track_destroyed, ResourceKind,
+};
 use crate::renderer::vulkan::surface::MAX_FRAMES_IN_FLIGHT;
-use crate::renderer::vulkan::{Context, Pipeline, Surface};
+use crate::renderer::vulkan::texture::{
+    create_texture_image, texture_subresource_range, texture_texel_size, TextureArrayDescriptors,
+};
+use crate::renderer::vulkan::{
+    BufferHandle, CommandRecorder, ComputePipeline, Context, DepthBias, DepthMode, DepthTarget,
+    ImageRegion, MappedBuffer, MultiRenderTarget, Pipeline, PipelineHandle, PipelineParameters,
+    PostProcess, RenderGraph, RenderTarget, RequestedFeatures, ResourceStats, Sampler,
+    SamplerParameters, ShaderStage, ShaderStageSource, SupportedFeatures, Surface, Texture,
+    TextureHandle, UniformData, Vertex,
+};
+use crate::renderer::RendererError;
+
+/// Setting this to the index of a physical device forces that device to be used, rather than guessing which is best
+const GPU_INDEX_ENV_NAME: &str = "CLIENT_GPU_INDEX";
+/// Setting this to a directory makes shaders and other assets resolve relative to it, rather than the executable's directory
+const ASSET_ROOT_ENV_NAME: &str = "CLIENT_ASSET_ROOT";
 
 struct DeviceQueueTriplet<T> {
     graphics: T,
@@ -21,68 +57,434 @@ struct QueueFamilyInfo {
 }
 
 type DeviceQueueFamilyIndices = DeviceQueueTriplet<QueueFamilyInfo>;
+// The indices within its family of the queues each kind of queue uses
+type DeviceQueueIndices = DeviceQueueTriplet<Vec<u32>>;
 
-// TODO - The vk::Queues probably need wrapping in RwLocks because otherwise
-// a queue could be written to in multiple places at the same time if queues are shared
-type DeviceQueues = DeviceQueueTriplet<Vec<vk::Queue>>;
+/// A queue which may be submitted to from several places, so it's locked for each submission as Vulkan requires queues to be
+/// externally synchronised. To avoid deadlocks, never lock more than one queue at a time. Where that can't be avoided, lock them in
+/// the order graphics, compute, transfer, then present, as queues of different kinds may be the same queue
+pub type SharedQueue = Arc<Mutex<vk::Queue>>;
+type DeviceQueues = DeviceQueueTriplet<Vec<SharedQueue>>;
 type DeviceCommandPools = DeviceQueueTriplet<vk::CommandPool>;
 type DeviceCommandBuffers = DeviceQueueTriplet<Vec<vk::CommandBuffer>>;
 
+/// Identifies work submitted to the transfer queue, so that it can be checked for completion before the resources it writes to are used
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TransferHandle(u64);
+
+struct PendingTransfer {
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    // Kept alive until the transfer has finished reading from it
+    staging_buffer: Option<Buffer>,
+}
+
+/// The render pass a frame is recording and the pipeline currently bound in it
+#[derive(Clone, Copy)]
+struct ActivePass {
+    extent: vk::Extent2D,
+    pipeline: PipelineHandle,
+}
+
+/// A resource written by [`Device::submit_transfer()`] which is then used on the graphics queue. If the transfer and graphics queues
+/// are in different families, ownership of exclusive resources is released by the transfer queue and acquired by the graphics queue.
+/// Otherwise only the image layout transition (if any) is recorded
+#[derive(Clone, Copy, Debug)]
+pub enum TransferredResource {
+    Buffer(vk::Buffer),
+    Image {
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        /// The layout the transfer commands leave the image in
+        old_layout: vk::ImageLayout,
+        /// The layout the image should be in once the graphics queue has acquired it
+        new_layout: vk::ImageLayout,
+    },
+}
+
+/// The graphics stages which wait for compute dispatches to finish, as they're where compute results are usually consumed
+/// (as indirect draw arguments, vertices, or shader storage)
+const COMPUTE_CONSUMER_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_raw(
+    vk::PipelineStageFlags::DRAW_INDIRECT.as_raw()
+        | vk::PipelineStageFlags::VERTEX_INPUT.as_raw()
+        | vk::PipelineStageFlags::VERTEX_SHADER.as_raw()
+        | vk::PipelineStageFlags::FRAGMENT_SHADER.as_raw(),
+);
+
+/// Identifies work submitted to the compute queue, so that its results can be read back on the CPU once it's complete
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComputeHandle(u64);
+
+struct PendingCompute {
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    // Destroying the pool frees the dispatch's descriptor set. This is null if the dispatch binds no buffers
+    descriptor_pool: vk::DescriptorPool,
+}
+
+/// How the data given to [`Device::create_buffer_with_data()`] reached the buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferUpload {
+    /// The data was written straight into device-local memory through resizable BAR, and the buffer can be used immediately
+    Direct,
+    /// The data was copied through a staging buffer on the transfer queue, which must complete before the buffer is used
+    Staged(TransferHandle),
+}
+
+impl BufferUpload {
+    /// Gets whether the upload skipped the staging buffer
+    pub fn is_direct(&self) -> bool {
+        matches!(self, BufferUpload::Direct)
+    }
+}
+
+/// Which kind of GPU to prefer when guessing which physical device is best, mirroring WebGPU's power preference
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerPreference {
+    /// Prefer a discrete GPU, for the best performance
+    #[default]
+    HighPerformance,
+    /// Prefer an integrated GPU, to save battery on laptops
+    LowPower,
+}
+
+impl PowerPreference {
+    /// Gets the type of physical device that this preference favours
+    fn preferred_device_type(self) -> vk::PhysicalDeviceType {
+        match self {
+            PowerPreference::HighPerformance => vk::PhysicalDeviceType::DISCRETE_GPU,
+            PowerPreference::LowPower => vk::PhysicalDeviceType::INTEGRATED_GPU,
+        }
+    }
+}
+
+/// The priority of each kind of queue, from 0.0 (lowest) to 1.0 (highest).
+/// Drivers which honour priorities give more time to higher-priority queues, so lowering the transfer and compute priorities stops
+/// background work from starving rendering
+///
+/// Kinds of queue which share a family get separate queues while the family has enough. If they end up sharing a queue, the first of
+/// graphics, transfer, compute, and present sets its priority
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueuePriorities {
+    pub graphics: f32,
+    pub transfer: f32,
+    pub compute: f32,
+    pub present: f32,
+}
+
+impl Default for QueuePriorities {
+    fn default() -> Self {
+        Self {
+            graphics: 1.0,
+            transfer: 1.0,
+            compute: 1.0,
+            present: 1.0,
+        }
+    }
+}
+
+/// The number of queues to create for each kind of work which can be submitted in parallel. The device itself only submits to the
+/// first queue of each kind, so extra queues are for game code which submits from several threads (see
+/// [`Device::graphics_queues()`] and [`Device::compute_queues()`]). If a family has fewer queues than requested, the queues it has are
+/// shared. Transfers and presentation always use a single queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueCounts {
+    pub graphics: u32,
+    pub compute: u32,
+}
+
+impl Default for QueueCounts {
+    fn default() -> Self {
+        Self {
+            graphics: 1,
+            compute: 1,
+        }
+    }
+}
+
+/// Options which control which physical device is chosen and how the logical device is created
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeviceParameters {
+    /// Whether to favour discrete or integrated GPUs. This is ignored if a GPU index is given
+    pub power_preference: PowerPreference,
+    /// The index of the physical device to use (see [`Device::enumerate_adapters()`]), or `None` to guess which is best.
+    /// `CLIENT_GPU_INDEX` takes priority over this
+    pub gpu_index: Option<usize>,
+    /// The PCI vendor ID of the GPU to favour when guessing which is best (eg. `0x10DE` for NVIDIA, `0x1002` for AMD, or `0x8086`
+    /// for Intel), which takes priority over the power preference. This is ignored if a GPU index is given
+    pub preferred_vendor_id: Option<u32>,
+    /// The priorities given to each kind of queue
+    pub queue_priorities: QueuePriorities,
+    /// The number of graphics and compute queues to create
+    pub queue_counts: QueueCounts,
+    /// The optional features to enable. Creating the device fails if any of them aren't supported
+    pub requested_features: RequestedFeatures,
+}
+
+/// A summary of a physical device, so that users with several GPUs can choose which to create the `Device` on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterInfo {
+    /// The index of the physical device, which can be passed to [`Device::new_with_adapter()`] or `CLIENT_GPU_INDEX`
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    /// The PCI vendor ID (eg. `0x10DE` for NVIDIA)
+    pub vendor_id: u32,
+    /// The size of the device-local memory in bytes. Integrated GPUs report shared system memory here
+    pub device_local_memory: u64,
+}
+
 pub struct Device {
+    instance: ash::Instance,
     pub physical_device: vk::PhysicalDevice,
     pub logical_device: Rc<ash::Device>,
-    _queue_family_indices: DeviceQueueFamilyIndices,
+    limits: vk::PhysicalDeviceLimits,
+    enabled_features: SupportedFeatures,
+    // Kept so that using a feature which wasn't enabled can be told apart from using one the device lacks
+    supported_features: SupportedFeatures,
+    memory_budget_enabled: bool,
+    // `None` if multiview isn't enabled
+    max_multiview_view_count: Option<u32>,
+    // `None` if there's no host-visible device-local heap large enough to upload through
+    resizable_bar_heap: Option<u32>,
+    // `None` if VK_KHR_present_wait (and VK_KHR_present_id) aren't supported
+    pub(crate) present_wait: Option<ash::extensions::khr::PresentWait>,
+    // Whether VK_KHR_incremental_present is enabled, so that presents can be limited to the regions that changed
+    pub(crate) incremental_present_enabled: bool,
+    // This is only ever `None` whilst the device is being dropped
+    allocator: Option<Rc<RefCell<Allocator>>>,
+    queue_family_indices: DeviceQueueFamilyIndices,
     queue_families: DeviceQueues,
-    pipelines: HashMap<String, Pipeline>,
+    // Shared by every pipeline, and saved to disk when the device is dropped
+    pipeline_cache: vk::PipelineCache,
+    // This is only ever `None` whilst the device is being constructed or dropped
+    uniforms: Option<FrameUniforms>,
+    pipelines: Slab<Pipeline>,
+    pipeline_names: HashMap<String, PipelineHandle>,
+    buffers: Slab<Buffer>,
+    textures: Slab<Texture>,
     command_pools: DeviceCommandPools,
     command_buffers: DeviceCommandBuffers,
+    pending_transfers: HashMap<TransferHandle, PendingTransfer>,
+    next_transfer_id: u64,
+    asset_root: PathBuf,
+    compute_pipelines: HashMap<String, ComputePipeline>,
+    pending_computes: HashMap<ComputeHandle, PendingCompute>,
+    next_compute_id: u64,
+    // Signalled by compute dispatches which the next graphics submission hasn't waited on yet. If a dispatch finishes before any
+    // submission waits on it, its semaphore is destroyed along with the dispatch, so these can't pile up whilst nothing is drawn
+    compute_semaphores: RefCell<Vec<(ComputeHandle, vk::Semaphore)>>,
+    // Indexed by frame in flight. These have been waited on by the frame's last submission, so can be destroyed once it's finished
+    retired_compute_semaphores: RefCell<Vec<Vec<vk::Semaphore>>>,
+    // Indexed by frame in flight. `None` whilst the frame isn't inside a render pass
+    active_passes: RefCell<Vec<Option<ActivePass>>>,
+    // This is only ever `None` whilst the device is being constructed or dropped
+    texture_array_descriptors: Option<TextureArrayDescriptors>,
+    // Indexed by frame in flight. Incremented each time the frame's previous submission has been waited on
+    frame_serials: RefCell<Vec<u64>>,
+    // The frames in flight whose command buffers have begun recording but haven't been submitted, to catch frames being re-recorded
+    // whilst still in use (eg. when a swapchain image index is passed in place of a frame index)
+    #[cfg(debug_assertions)]
+    recording_frames: RefCell<HashSet<usize>>,
 }
 
 impl Device {
-    /// Constructs a new Device, based on some rough heuristics to guess which is best.
+    /// Constructs a new Device, based on some rough heuristics to guess which is best out of the physical devices which can present
+    /// to the surface. Use [`Device::new_with_adapter()`] to choose the physical device explicitly.
     /// The device will be constructed with separate queues for graphics, transfer, and compute if possible, but otherwise they will be shared
     ///
     /// # Arguments
     ///
     /// * `context`: The `Context` to create the device using
+    /// * `surface`: The `Surface` the device will present to
+    /// * `parameters`: Options for choosing and creating the device
     ///
     /// # Examples
     ///
-    /// ```
-    /// use client::renderer::vulkan::{Context, Device};
+    /// ```no_run
+    /// # use client::renderer::RendererError;
+    /// # fn example() -> Result<(), RendererError> {
+    /// # use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
+    /// # let event_loop = EventLoopBuilder::new().build().unwrap();
+    /// # let window = WindowBuilder::new().build(&event_loop).unwrap();
+    /// use client::renderer::vulkan::{Context, Device, DeviceParameters, PowerPreference, Surface};
     ///
-    /// let context = new Context("my-application", (1.4.2));
-    /// let device = Device::new(&context);
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let surface = Surface::new(&context, &window);
+    /// let parameters = DeviceParameters {
+    ///     power_preference: PowerPreference::LowPower,
+    ///     preferred_vendor_id: Some(0x8086),
+    ///     ..Default::default()
+    /// };
+    /// let device = Device::new(&context, &surface, &parameters)?;
+    /// # Ok(())
+    /// # }
     /// ```
-    pub fn new(context: &Context, surface: &Surface) -> Device {
+    pub fn new(
+        context: &Context,
+        surface: &Surface,
+        parameters: &DeviceParameters,
+    ) -> Result<Device, RendererError> {
         let span = debug_span!("Vulkan/Device");
         let _guard = span.enter();
 
         let physical_devices = unsafe { context.instance.enumerate_physical_devices() }
             .expect("Failed to enumerate physical devices");
 
-        // TODO - Expand this. Some people still have multi-GPU setups and it would be nice to be able to support that
-        // Note that this would require using device groups (and two equivalent GPUs)
+        // Device groups (for splitting work between several equivalent GPUs) aren't supported, so only one GPU is used
+
+        let requested_device_index = get_requested_device_index(physical_devices.len())
+            .or_else(|| {
+                parameters.gpu_index.filter(|index| {
+                    let is_valid = *index < physical_devices.len();
+                    if !is_valid {
+                        warn!(
+                            "Physical device {} was requested, but only {} are available, falling back to automatic selection",
+                            index,
+                            physical_devices.len()
+                        );
+                    }
+                    is_valid
+                })
+            })
+            .filter(|index| {
+                let can_present = supports_presentation(context, &physical_devices[*index], surface);
+                if !can_present {
+                    warn!(
+                        "Physical device {} was requested, but it can't present to the surface, falling back to automatic selection",
+                        index
+                    );
+                }
+                can_present
+            });
+        let physical_device = match requested_device_index {
+            Some(index) => physical_devices[index],
+            None => {
+                let presentable_devices: Vec<vk::PhysicalDevice> = physical_devices
+                    .iter()
+                    .copied()
+                    .filter(|physical_device| {
+                        supports_presentation(context, physical_device, surface)
+                    })
+                    .collect();
+                *select_physical_device(
+                    context,
+                    &presentable_devices,
+                    parameters.power_preference,
+                    parameters.preferred_vendor_id,
+                )
+            }
+        };
+
+        Self::with_physical_device(context, surface, parameters, &physical_device)
+    }
 
-        let physical_device = physical_devices
+    /// Gets a summary of every physical device, in the order used by [`Device::new_with_adapter()`] and `CLIENT_GPU_INDEX`, so that
+    /// one can be chosen explicitly (eg. the discrete GPU in a laptop which also has an integrated one)
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` to enumerate the physical devices of
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::RendererError;
+    /// # use client::renderer::vulkan::Surface;
+    /// # fn example(surface: Surface) -> Result<(), RendererError> {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::{Context, Device, DeviceParameters};
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let adapters = Device::enumerate_adapters(&context);
+    /// let device = match adapters.iter().find(|adapter| adapter.device_type == vk::PhysicalDeviceType::DISCRETE_GPU) {
+    ///     Some(adapter) => Device::new_with_adapter(&context, &surface, adapter.index, &DeviceParameters::default())?,
+    ///     None => Device::new(&context, &surface, &DeviceParameters::default())?,
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enumerate_adapters(context: &Context) -> Vec<AdapterInfo> {
+        let physical_devices =
+            unsafe { context.instance.enumerate_physical_devices() }.unwrap_or_default();
+        physical_devices
             .iter()
-            .reduce(|accum, current| {
-                let device_type =
-                    unsafe { context.instance.get_physical_device_properties(*current) }
-                        .device_type;
-                let current_memory = get_device_local_memory_size(context, current);
-                let accum_memory = get_device_local_memory_size(context, accum);
-
-                if device_type != vk::PhysicalDeviceType::DISCRETE_GPU {
-                    accum
-                } else if current_memory > accum_memory {
-                    current
-                } else {
-                    accum
+            .enumerate()
+            .map(|(index, physical_device)| {
+                let properties = unsafe {
+                    context
+                        .instance
+                        .get_physical_device_properties(*physical_device)
+                };
+                AdapterInfo {
+                    index,
+                    name: unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+                        .to_string_lossy()
+                        .into_owned(),
+                    device_type: properties.device_type,
+                    vendor_id: properties.vendor_id,
+                    device_local_memory: get_device_local_memory_size(context, physical_device),
                 }
             })
-            .expect("Failed to select a physical device");
+            .collect()
+    }
+
+    /// Constructs a new Device on a specific physical device, rather than guessing which is best. Unlike
+    /// [`DeviceParameters::gpu_index`], this isn't overridden by `CLIENT_GPU_INDEX`
+    ///
+    /// If there's no physical device with the index, it can't present to the surface, or it doesn't support the requested features,
+    /// returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` to create the device using
+    /// * `surface`: The `Surface` the device will present to
+    /// * `index`: The index of the physical device, as given by [`Device::enumerate_adapters()`]
+    /// * `parameters`: Options for creating the device. The options for choosing the physical device are ignored
+    pub fn new_with_adapter(
+        context: &Context,
+        surface: &Surface,
+        index: usize,
+        parameters: &DeviceParameters,
+    ) -> Result<Device, RendererError> {
+        let span = debug_span!("Vulkan/Device");
+        let _guard = span.enter();
+
+        let physical_devices =
+            unsafe { context.instance.enumerate_physical_devices() }.map_err(|_| {
+                RendererError::AdapterUnavailable("Failed to enumerate physical devices")
+            })?;
+        let physical_device =
+            physical_devices
+                .get(index)
+                .ok_or(RendererError::AdapterUnavailable(
+                    "No physical device exists with the given index",
+                ))?;
+        if !supports_presentation(context, physical_device, surface) {
+            return Err(RendererError::AdapterUnavailable(
+                "The physical device can't present to the surface",
+            ));
+        }
+
+        Self::with_physical_device(context, surface, parameters, physical_device)
+    }
 
+    /// Constructs a new Device on the chosen physical device
+    ///
+    /// If the physical device doesn't support the requested features, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` to create the device using
+    /// * `surface`: The `Surface` the device will present to
+    /// * `parameters`: Options for creating the device
+    /// * `physical_device`: The physical device to create the device on, which must be able to present to the surface
+    fn with_physical_device(
+        context: &Context,
+        surface: &Surface,
+        parameters: &DeviceParameters,
+        physical_device: &vk::PhysicalDevice,
+    ) -> Result<Device, RendererError> {
         debug!("Selected physical device {:?}", unsafe {
             CStr::from_ptr(
                 context
@@ -93,6 +495,13 @@ impl Device {
             )
         });
 
+        let properties = unsafe {
+            context
+                .instance
+                .get_physical_device_properties(*physical_device)
+        };
+        let limits = properties.limits;
+
         let current_memory = get_device_local_memory_size(context, physical_device);
         debug!(
             "Device has {} GB of dedicated memory",
@@ -108,63 +517,99 @@ impl Device {
             queue_family_indices.compute.index
         );
 
-        let mut queue_create_infos = vec![];
+        let priorities = clamp_queue_priorities(parameters.queue_priorities);
+        let (priorities_by_family, queue_indices) =
+            plan_device_queues(&queue_family_indices, parameters.queue_counts, priorities);
+        // Each family can only appear once, so every queue created in it is described together
+        let queue_create_infos: Vec<vk::DeviceQueueCreateInfo> = priorities_by_family
+            .iter()
+            .map(|(family_index, queue_priorities)| {
+                vk::DeviceQueueCreateInfo::builder()
+                    .queue_family_index(*family_index)
+                    .queue_priorities(queue_priorities)
+                    .build()
+            })
+            .collect();
 
-        let mut indices_used = HashSet::new();
+        // Only the requested features are enabled, so that code can't rely on features it didn't ask for
+        let supported_features = SupportedFeatures::query(&context.instance, *physical_device);
+        let missing_features = supported_features.missing(&parameters.requested_features);
+        if !missing_features.is_empty() {
+            return Err(RendererError::MissingFeatures(missing_features));
+        }
+        let enabled_features = parameters.requested_features;
+        debug!("Enabling features {:?}", enabled_features);
+        let device_feature_info = enabled_features.to_physical_device_features();
 
-        let graphics_queue_priorities: Vec<f32> = (0..queue_family_indices.graphics.count)
-            .map(|_| 1.0)
-            .collect();
-        let graphics_queue_create_info = vk::DeviceQueueCreateInfo::builder()
-            .queue_family_index(queue_family_indices.graphics.index)
-            .queue_priorities(graphics_queue_priorities.as_slice())
+        // Multiview is core in Vulkan 1.1, but still has to be enabled as a feature
+        let max_multiview_view_count = enabled_features
+            .multiview
+            .then(|| get_max_multiview_view_count(context, physical_device));
+        let mut multiview_features = vk::PhysicalDeviceMultiviewFeatures::builder()
+            .multiview(enabled_features.multiview)
             .build();
 
-        queue_create_infos.push(graphics_queue_create_info);
-        indices_used.insert(queue_family_indices.graphics.index);
+        let mut enabled_extensions = vec![ash::extensions::khr::Swapchain::name().as_ptr()];
 
-        let transfer_queue_priorities: Vec<f32> = (0..queue_family_indices.transfer.count)
-            .map(|_| 1.0)
-            .collect();
-        if !indices_used.contains(&queue_family_indices.transfer.index) {
-            let transfer_queue_create_info = vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(queue_family_indices.transfer.index)
-                .queue_priorities(transfer_queue_priorities.as_slice())
-                .build();
-            queue_create_infos.push(transfer_queue_create_info);
-            indices_used.insert(queue_family_indices.transfer.index);
+        let memory_budget_enabled =
+            is_device_extension_supported(context, physical_device, vk::ExtMemoryBudgetFn::name());
+        if memory_budget_enabled {
+            enabled_extensions.push(vk::ExtMemoryBudgetFn::name().as_ptr());
+        } else {
+            debug!("VK_EXT_memory_budget is unavailable, so memory budgets will be estimated");
         }
 
-        let compute_queue_priorities: Vec<f32> = (0..queue_family_indices.compute.count)
-            .map(|_| 1.0)
-            .collect();
-        if !indices_used.contains(&queue_family_indices.compute.index) {
-            let compute_queue_create_info = vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(queue_family_indices.compute.index)
-                .queue_priorities(compute_queue_priorities.as_slice())
-                .build();
-            queue_create_infos.push(compute_queue_create_info);
-            indices_used.insert(queue_family_indices.compute.index);
+        let present_wait_enabled = is_present_wait_supported(context, physical_device);
+        if present_wait_enabled {
+            enabled_extensions.push(vk::KhrPresentIdFn::name().as_ptr());
+            enabled_extensions.push(ash::extensions::khr::PresentWait::name().as_ptr());
+            info!("VK_KHR_present_wait is available, and will be used to limit frame latency");
+        } else {
+            info!(
+                "VK_KHR_present_wait is unavailable, so frame latency will be limited with fences"
+            );
         }
-
-        let present_queue_priority = [1.0];
-        // We do present last, since we only make one queue
-        if !indices_used.contains(&queue_family_indices.present.index) {
-            let present_queue_create_info = vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(queue_family_indices.present.index)
-                .queue_priorities(&present_queue_priority)
-                .build();
-            queue_create_infos.push(present_queue_create_info);
-            indices_used.insert(queue_family_indices.present.index);
+        let incremental_present_enabled = is_device_extension_supported(
+            context,
+            physical_device,
+            vk::KhrIncrementalPresentFn::name(),
+        );
+        if incremental_present_enabled {
+            enabled_extensions.push(vk::KhrIncrementalPresentFn::name().as_ptr());
+            info!("VK_KHR_incremental_present is available, so presents can be limited to the regions that changed");
+        } else {
+            debug!("VK_KHR_incremental_present is unavailable, so every present will update the whole window");
+        }
+        // Portability implementations such as MoltenVK require this to be enabled whenever they expose it, so that the
+        // application acknowledges the parts of Vulkan they don't support
+        if is_device_extension_supported(
+            context,
+            physical_device,
+            vk::KhrPortabilitySubsetFn::name(),
+        ) {
+            enabled_extensions.push(vk::KhrPortabilitySubsetFn::name().as_ptr());
+            info!("The device is a portability implementation, so VK_KHR_portability_subset has been enabled");
         }
 
-        let device_feature_info = vk::PhysicalDeviceFeatures::builder().build();
+        let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR::builder()
+            .present_id(present_wait_enabled)
+            .build();
+        let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR::builder()
+            .present_wait(present_wait_enabled)
+            .build();
 
-        let device_create_info = vk::DeviceCreateInfo::builder()
-            .enabled_extension_names(&[ash::extensions::khr::Swapchain::name().as_ptr()])
+        let mut device_create_info = vk::DeviceCreateInfo::builder()
+            .enabled_extension_names(enabled_extensions.as_slice())
             .enabled_features(&device_feature_info)
             .queue_create_infos(queue_create_infos.as_slice())
-            .build();
+            .push_next(&mut multiview_features);
+        // The feature structs can only be chained when their extensions are enabled
+        if present_wait_enabled {
+            device_create_info = device_create_info
+                .push_next(&mut present_id_features)
+                .push_next(&mut present_wait_features);
+        }
+        let device_create_info = device_create_info.build();
 
         debug!("Creating logical device");
         let logical_device = unsafe {
@@ -175,7 +620,8 @@ impl Device {
         .expect("Failed to create a logical device");
         debug!("Successfully created logical device");
 
-        let queue_families = create_device_queues(&logical_device, &queue_family_indices);
+        let queue_families =
+            create_device_queues(&logical_device, &queue_family_indices, &queue_indices);
         debug!(
             "Created {} queues for graphics, {} queues for present, {} queues for transfer, and {} queues for compute",
             queue_families.graphics.len(),
@@ -184,18 +630,73 @@ impl Device {
             queue_families.compute.len()
         );
 
+        let allocator = Allocator::new(&AllocatorCreateDesc {
+            instance: context.instance.clone(),
+            device: logical_device.clone(),
+            physical_device: *physical_device,
+            debug_settings: AllocatorDebugSettings::default(),
+            buffer_device_address: false,
+            allocation_sizes: AllocationSizes::default(),
+        })
+        .expect("Failed to create GPU memory allocator");
+
         let command_pools = create_command_pools(&logical_device, &queue_family_indices);
         let command_buffers = create_command_buffers(&logical_device, &command_pools);
+        let pipeline_cache = load_pipeline_cache(&logical_device, &properties);
+
+        let present_wait = present_wait_enabled
+            .then(|| ash::extensions::khr::PresentWait::new(&context.instance, &logical_device));
+
+        let resizable_bar_heap = query_resizable_bar_heap(&context.instance, *physical_device);
+        match resizable_bar_heap {
+            Some(heap_index) => info!(
+                "Heap {} is host-visible and device-local, so buffers will be uploaded without staging when possible",
+                heap_index
+            ),
+            None => info!("Resizable BAR is unavailable, so buffers will be uploaded through staging buffers"),
+        }
 
-        Device {
+        let mut device = Device {
+            instance: context.instance.clone(),
             physical_device: *physical_device,
             logical_device: Rc::new(logical_device),
-            _queue_family_indices: queue_family_indices,
+            limits,
+            enabled_features,
+            supported_features,
+            memory_budget_enabled,
+            max_multiview_view_count,
+            resizable_bar_heap,
+            present_wait,
+            incremental_present_enabled,
+            allocator: Some(Rc::new(RefCell::new(allocator))),
+            queue_family_indices,
             queue_families,
-            pipelines: HashMap::new(),
+            pipeline_cache,
+            uniforms: None,
+            pipelines: Slab::new(),
+            pipeline_names: HashMap::new(),
+            buffers: Slab::new(),
+            textures: Slab::new(),
             command_pools,
             command_buffers,
-        }
+            pending_transfers: HashMap::new(),
+            next_transfer_id: 0,
+            asset_root: default_asset_root(),
+            compute_pipelines: HashMap::new(),
+            pending_computes: HashMap::new(),
+            next_compute_id: 0,
+            compute_semaphores: RefCell::new(vec![]),
+            retired_compute_semaphores: RefCell::new(vec![vec![]; MAX_FRAMES_IN_FLIGHT]),
+            active_passes: RefCell::new(vec![None; MAX_FRAMES_IN_FLIGHT]),
+            texture_array_descriptors: None,
+            frame_serials: RefCell::new(vec![0; MAX_FRAMES_IN_FLIGHT]),
+            #[cfg(debug_assertions)]
+            recording_frames: RefCell::new(HashSet::new()),
+        };
+        // The uniform buffers are allocated through the device, so can only be created once it exists
+        device.uniforms = Some(FrameUniforms::new(&device));
+        device.texture_array_descriptors = Some(TextureArrayDescriptors::new(&device));
+        Ok(device)
     }
 
     /// Constructs a new graphics pipeline on the device, referencable by the name provided
@@ -207,32 +708,37 @@ impl Device {
     /// # Arguments
     ///
     /// * `surface`: The `Surface` that the `Pipeline` should render to
-    /// * `vertex_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the application executable
-    /// * `fragment_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the application executable
+    /// * `vertex_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the asset root
+    /// * `fragment_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the asset root
     /// * `name`: The name that the `Pipeline` should be referencable as later
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # use client::renderer::RendererError;
+    /// # fn example() -> Result<(), RendererError> {
     /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
-    /// use client::renderer::vulkan::{Context, Device, Surface};
+    /// use client::renderer::vulkan::{Context, Device, DeviceParameters, PipelineParameters, Surface};
     /// use std::path::Path;
     ///
     /// let event_loop = EventLoopBuilder::new().build().unwrap();
     /// let window = WindowBuilder::new().build(&event_loop).unwrap();
     ///
-    /// let context = Context::new("my-application", (1.4.2));
-    /// let device = Device::new(&context);
-    /// let surface = Surface::new(&context, &device, &window);
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let surface = Surface::new(&context, &window);
+    /// let mut device = Device::new(&context, &surface, &DeviceParameters::default())?;
     ///
-    /// let result = device.create_pipeline(&surface, Path::new("vertex_shader.spv"), Path::new("fragment_shader.spv"), String::from("my_shader"));
+    /// let result = device.create_pipeline(&surface, Path::new("vertex_shader.spv"), Path::new("fragment_shader.spv"), String::from("my_shader"), &PipelineParameters::default());
     /// match result {
-    ///     true => println!("Successfully created and attached pipeline"),
-    ///     false => println("Failed to create shader")
+    ///     Ok(handle) => println!("Successfully created pipeline {:?}", handle),
+    ///     Err(error) => println!("Failed to create pipeline: {}", error)
     /// }
     ///
-    /// let result = device.create_pipeline(&surface, Path::new("vertex_shader_2.spv"), Path::new("fragment_shader_2.spv"), String::from("my_shader"));
-    /// assert_eq!(result, false);
+    /// let result = device.create_pipeline(&surface, Path::new("vertex_shader_2.spv"), Path::new("fragment_shader_2.spv"), String::from("my_shader"), &PipelineParameters::default());
+    /// assert!(result.is_err());
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn create_pipeline(
         &mut self,
@@ -240,168 +746,3058 @@ impl Device {
         vertex_shader_path: &std::path::Path,
         fragment_shader_path: &std::path::Path,
         name: String,
-    ) -> Result<(), &'static str> {
-        let executable_path = std::env::current_exe().unwrap();
-
-        if self.pipelines.contains_key(name.as_str()) {
-            Err("A pipeline already exists with the specified name")
-        } else if !executable_path
-            .parent()
-            .unwrap()
-            .join(fragment_shader_path)
-            .exists()
-            || !executable_path
-                .parent()
-                .unwrap()
-                .join(vertex_shader_path)
-                .exists()
-        {
-            Err("A shader file could not be found at the specified path")
-        } else {
-            let pipeline = Pipeline::new(self, surface, vertex_shader_path, fragment_shader_path);
-            let _res = self.pipelines.insert(name, pipeline);
-            Ok(())
-        }
+        parameters: &PipelineParameters,
+    ) -> Result<PipelineHandle, &'static str> {
+        self.create_pipeline_from_stages(
+            surface,
+            &[
+                ShaderStageSource::main(vertex_shader_path, ShaderStage::Vertex),
+                ShaderStageSource::main(fragment_shader_path, ShaderStage::Fragment),
+            ],
+            name,
+            parameters,
+        )
     }
 
-    /// Get a pipeline by name
+    /// Constructs a new graphics pipeline on the device from an explicit list of stages, referencable by the name provided.
+    /// This allows a single SPIR-V module to provide several stages, and entry points other than `main` to be used
+    ///
+    /// If the device already has a pipeline with the given name, a shader file doesn't exist or isn't valid SPIR-V, there's no vertex
+    /// stage, or a stage is provided more than once, returns `Err`
     ///
     /// # Arguments
     ///
-    /// * `name`: The name of the `Pipeline` to get
+    /// * `surface`: The `Surface` that the `Pipeline` should render to
+    /// * `stages`: The module and entry point to use for each stage of the pipeline
+    /// * `name`: The name that the `Pipeline` should be referencable as later
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
     ///
-    pub fn get_pipeline(&self, name: &str) -> Option<&Pipeline> {
-        self.pipelines.get(name)
-    }
-
-    pub fn begin_graphics_render_pass(
-        &self,
-        current_frame: usize,
-        surface: &mut Surface,
-        pipeline_name: &str,
-    ) -> u32 {
-        let command_buffer = self.command_buffers.graphics.get(current_frame).unwrap();
-        let frame_in_flight = *surface.frame_in_flight.get(current_frame).unwrap();
-
-        unsafe {
-            self.logical_device
-                .wait_for_fences(&[frame_in_flight], true, u64::MAX)
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(device: &mut Device, surface: Surface) -> Result<(), &'static str> {
+    /// use client::renderer::vulkan::{PipelineParameters, ShaderStage, ShaderStageSource};
+    /// use std::path::Path;
+    ///
+    /// let stages = [
+    ///     ShaderStageSource { path: Path::new("shader.spv"), entry_point: "vertex_main", stage: ShaderStage::Vertex },
+    ///     ShaderStageSource { path: Path::new("shader.spv"), entry_point: "fragment_main", stage: ShaderStage::Fragment },
+    /// ];
+    /// device.create_pipeline_from_stages(&surface, &stages, String::from("my_shader"), &PipelineParameters::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_pipeline_from_stages(
+        &mut self,
+        surface: &Surface,
+        stages: &[ShaderStageSource],
+        name: String,
+        parameters: &PipelineParameters,
+    ) -> Result<PipelineHandle, &'static str> {
+        if self.pipeline_names.contains_key(name.as_str()) {
+            return Err("A pipeline already exists with the specified name");
         }
-        .expect("Device was removed or timed out whilst waiting for a fence");
-
-        unsafe { self.logical_device.reset_fences(&[frame_in_flight]) }
-            .expect("Could not reset fence");
 
-        let image_index = surface.acquire_next_image();
-
-        unsafe {
-            self.logical_device
-                .reset_command_buffer(*command_buffer, vk::CommandBufferResetFlags::empty())
+        let mut code_by_path: HashMap<&Path, Vec<u32>> = HashMap::new();
+        for stage in stages {
+            if !code_by_path.contains_key(stage.path) {
+                let code = read_spirv(&self.resolve_asset_path(stage.path))?;
+                code_by_path.insert(stage.path, code);
+            }
         }
-        .expect("Failed to reset graphics command buffer");
 
-        let command_buffer_info = vk::CommandBufferBeginInfo::builder().build();
+        self.create_pipeline_from_code(surface, stages, &code_by_path, name, parameters)
+    }
 
-        unsafe {
-            self.logical_device
-                .begin_command_buffer(*command_buffer, &command_buffer_info)
+    /// Constructs a new graphics pipeline on the device from SPIR-V embedded in the executable (eg. with `include_bytes!`), rather
+    /// than loading it from disk, so that the shaders don't need to be shipped alongside the executable
+    ///
+    /// If the device already has a pipeline with the given name or either shader isn't valid SPIR-V, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `surface`: The `Surface` that the `Pipeline` should render to
+    /// * `vertex_shader`: The compiled SPIR-V vertex shader, which must use `main` as its entry point
+    /// * `fragment_shader`: The compiled SPIR-V fragment shader, which must use `main` as its entry point
+    /// * `name`: The name that the `Pipeline` should be referencable as later
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(
+    /// #     device: &mut Device,
+    /// #     surface: Surface,
+    /// #     vertex_spirv: &[u8],
+    /// #     fragment_spirv: &[u8],
+    /// # ) -> Result<(), &'static str> {
+    /// use client::renderer::vulkan::PipelineParameters;
+    ///
+    /// device.create_pipeline_from_bytes(
+    ///     &surface,
+    ///     vertex_spirv,
+    ///     fragment_spirv,
+    ///     String::from("basic"),
+    ///     &PipelineParameters::default(),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_pipeline_from_bytes(
+        &mut self,
+        surface: &Surface,
+        vertex_shader: &[u8],
+        fragment_shader: &[u8],
+        name: String,
+        parameters: &PipelineParameters,
+    ) -> Result<PipelineHandle, &'static str> {
+        if self.pipeline_names.contains_key(name.as_str()) {
+            return Err("A pipeline already exists with the specified name");
         }
-        .expect("Failed to begin graphics command buffer)");
 
-        let pipeline = self
-            .get_pipeline(pipeline_name)
-            .expect("Failed to get graphics pipeline");
+        // Embedded shaders have no paths, so placeholders identify them (and show up in any warnings about them)
+        let vertex_path = Path::new("<embedded vertex shader>");
+        let fragment_path = Path::new("<embedded fragment shader>");
+        let code_by_path = HashMap::from([
+            (vertex_path, decode_spirv(vertex_shader)?),
+            (fragment_path, decode_spirv(fragment_shader)?),
+        ]);
 
-        let framebuffer = *surface.get_framebuffer(image_index as usize);
-        let clear_values = vk::ClearValue::default();
+        self.create_pipeline_from_code(
+            surface,
+            &[
+                ShaderStageSource::main(vertex_path, ShaderStage::Vertex),
+                ShaderStageSource::main(fragment_path, ShaderStage::Fragment),
+            ],
+            &code_by_path,
+            name,
+            parameters,
+        )
+    }
 
-        let scissor = vk::Rect2D::builder()
-            .extent(surface.swapchain_parameters.as_ref().unwrap().extent)
-            .offset(vk::Offset2D::builder().x(0).y(0).build())
-            .build();
+    /// Constructs a new graphics pipeline on the device from the built-in fallback shaders, which cover the screen in magenta when
+    /// drawn with three vertices. This is used in place of a pipeline whose shaders failed to load, so that the problem is visible
+    /// without stopping the game from launching
+    ///
+    /// If the device already has a pipeline with the given name, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `surface`: The `Surface` that the `Pipeline` should render to
+    /// * `name`: The name that the `Pipeline` should be referencable as later, usually the name of the pipeline it replaces
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, PipelineParameters, Surface};
+    /// # use std::path::Path;
+    /// # use tracing::warn;
+    /// # fn example(
+    /// #     device: &mut Device,
+    /// #     surface: Surface,
+    /// #     vertex_path: &Path,
+    /// #     fragment_path: &Path,
+    /// #     parameters: PipelineParameters,
+    /// # ) -> Result<(), &'static str> {
+    /// if let Err(error) = device.create_pipeline(&surface, vertex_path, fragment_path, String::from("basic"), &parameters) {
+    ///     warn!("Failed to create pipeline ({}), so falling back to the error pipeline", error);
+    ///     device.create_fallback_pipeline(&surface, String::from("basic"))?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_fallback_pipeline(
+        &mut self,
+        surface: &Surface,
+        name: String,
+    ) -> Result<PipelineHandle, &'static str> {
+        let vertex_path = Path::new("<fallback vertex shader>");
+        let fragment_path = Path::new("<fallback fragment shader>");
+        let code_by_path = HashMap::from([
+            (vertex_path, FALLBACK_VERTEX_SHADER.to_vec()),
+            (fragment_path, FALLBACK_FRAGMENT_SHADER.to_vec()),
+        ]);
 
-        let render_pass_info = vk::RenderPassBeginInfo::builder()
-            .render_pass(pipeline.render_pass)
-            .framebuffer(framebuffer)
-            .clear_values(&[clear_values])
-            .render_area(scissor)
-            .build();
+        self.create_pipeline_from_code(
+            surface,
+            &[
+                ShaderStageSource::main(vertex_path, ShaderStage::Vertex),
+                ShaderStageSource::main(fragment_path, ShaderStage::Fragment),
+            ],
+            &code_by_path,
+            name,
+            &PipelineParameters::default(),
+        )
+    }
 
-        unsafe {
-            self.logical_device.cmd_begin_render_pass(
-                *command_buffer,
-                &render_pass_info,
-                vk::SubpassContents::INLINE,
-            )
-        };
+    /// Checks that a pipeline's stages and parameters are supported, then constructs it from SPIR-V code which has already been loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `surface`: The `Surface` that the `Pipeline` should render to
+    /// * `stages`: The module and entry point to use for each stage of the pipeline
+    /// * `code_by_path`: The SPIR-V code of each module used by the stages
+    /// * `name`: The name that the `Pipeline` should be referencable as later
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
+    fn create_pipeline_from_code(
+        &mut self,
+        surface: &Surface,
+        stages: &[ShaderStageSource],
+        code_by_path: &HashMap<&Path, Vec<u32>>,
+        name: String,
+        parameters: &PipelineParameters,
+    ) -> Result<PipelineHandle, &'static str> {
+        let mut stages_used = HashSet::new();
+        let has_duplicate_stage = !stages.iter().all(|stage| stages_used.insert(stage.stage));
 
-        unsafe {
-            self.logical_device.cmd_bind_pipeline(
-                *command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                pipeline.pipeline,
-            )
+        if self.pipeline_names.contains_key(name.as_str()) {
+            Err("A pipeline already exists with the specified name")
+        } else if !stages_used.contains(&ShaderStage::Vertex) {
+            Err("A graphics pipeline requires a vertex stage")
+        } else if has_duplicate_stage {
+            Err("A shader stage was provided more than once")
+        } else if stages_used.contains(&ShaderStage::TessellationControl)
+            != stages_used.contains(&ShaderStage::TessellationEvaluation)
+        {
+            Err("Tessellation requires both a control and an evaluation stage")
+        } else if stages_used.contains(&ShaderStage::Geometry)
+            && !self.enabled_features.geometry_shader
+        {
+            Err(if self.supported_features.geometry_shader {
+                "Geometry shaders are not enabled; add geometry_shader to DeviceParameters::requested_features"
+            } else {
+                "The device does not support geometry shaders"
+            })
+        } else if stages_used.contains(&ShaderStage::TessellationControl)
+            && !self.enabled_features.tessellation_shader
+        {
+            Err(if self.supported_features.tessellation_shader {
+                "Tessellation shaders are not enabled; add tessellation_shader to DeviceParameters::requested_features"
+            } else {
+                "The device does not support tessellation shaders"
+            })
+        } else if stages_used.contains(&ShaderStage::TessellationControl)
+            && (parameters.patch_control_points == 0
+                || parameters.patch_control_points > self.limits.max_tessellation_patch_size)
+        {
+            Err("The number of patch control points is not supported by the device")
+        } else if parameters.view_count == 0 {
+            Err("A pipeline must render at least one view")
+        } else if parameters.view_count > 1 && !self.enabled_features.multiview {
+            Err(if self.supported_features.multiview {
+                "Multiview is not enabled; add multiview to DeviceParameters::requested_features"
+            } else {
+                "The device does not support multiview"
+            })
+        } else if parameters.view_count > 1
+            && self
+                .max_multiview_view_count
+                .is_none_or(|max_view_count| parameters.view_count > max_view_count)
+        {
+            Err("The number of views is not supported by the device")
+        } else if parameters
+            .depth_bias
+            .is_some_and(|depth_bias| depth_bias.clamp != 0.0)
+            && !self.enabled_features.depth_bias_clamp
+        {
+            Err(if self.supported_features.depth_bias_clamp {
+                "Clamping depth bias is not enabled; add depth_bias_clamp to DeviceParameters::requested_features"
+            } else {
+                "The device does not support clamping depth bias"
+            })
+        } else if parameters
+            .depth_only_format
+            .is_some_and(|format| !is_depth_format(format))
+        {
+            Err("The depth-only format is not a depth format")
+        } else if parameters.depth_only_format.is_some()
+            && !parameters.color_attachment_formats.is_empty()
+        {
+            Err("A depth-only pipeline cannot have colour attachments")
+        } else if parameters.color_attachment_formats.len()
+            > self.limits.max_color_attachments as usize
+        {
+            Err("The number of colour attachments is not supported by the device")
+        } else if parameters.push_constant_ranges.iter().any(|range| {
+            range.size == 0 || !range.offset.is_multiple_of(4) || !range.size.is_multiple_of(4)
+        }) {
+            Err("Push constant ranges must be non-empty and aligned to 4 bytes")
+        } else if parameters
+            .push_constant_ranges
+            .iter()
+            .any(|range| range.offset + range.size > self.limits.max_push_constants_size)
+        {
+            Err("A push constant range is larger than the device's maxPushConstantsSize")
+        } else {
+            let pipeline = Pipeline::from_code(self, surface, stages, code_by_path, parameters);
+            let handle = self.pipelines.insert(pipeline);
+            self.pipeline_names.insert(name, handle);
+            Ok(handle)
         }
+    }
 
-        let viewport = vk::Viewport::builder()
-            .x(0.0)
-            .y(0.0)
-            .width(surface.swapchain_parameters.as_ref().unwrap().extent.width as f32)
-            .height(surface.swapchain_parameters.as_ref().unwrap().extent.height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0)
-            .build();
-
-        unsafe {
-            self.logical_device
-                .cmd_set_viewport(*command_buffer, 0, &[viewport])
-        };
-        unsafe {
-            self.logical_device
-                .cmd_set_scissor(*command_buffer, 0, &[scissor])
-        };
-
-        image_index
+    /// Gets the directory that shader and other asset paths are resolved relative to
+    pub fn asset_root(&self) -> &Path {
+        &self.asset_root
     }
 
-    pub fn submit_graphics_queue(
+    /// Sets the directory that shader and other asset paths are resolved relative to. This defaults to `CLIENT_ASSET_ROOT` if it's
+    /// set, or the executable's directory otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `asset_root`: The directory to resolve asset paths relative to. A relative path is resolved against the working directory
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device) {
+    /// use std::path::PathBuf;
+    ///
+    /// // When running through `cargo run`, load assets straight from the crate rather than the target directory
+    /// device.set_asset_root(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+    /// # }
+    /// ```
+    pub fn set_asset_root(&mut self, asset_root: PathBuf) {
+        debug!("Resolving assets relative to {:?}", asset_root);
+        self.asset_root = asset_root;
+    }
+
+    /// Resolves an asset path against the asset root. Absolute paths are returned unchanged
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the asset, relative to the asset root
+    pub fn resolve_asset_path(&self, path: &Path) -> PathBuf {
+        self.asset_root.join(path)
+    }
+
+    /// Get a pipeline by handle
+    ///
+    /// If the pipeline has been destroyed, returns `None`
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle returned when the `Pipeline` was created
+    ///
+    pub fn get_pipeline(&self, handle: PipelineHandle) -> Option<&Pipeline> {
+        self.pipelines.get(handle)
+    }
+
+    /// Finds the handle of a pipeline by the name it was created with
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the `Pipeline` to find
+    pub fn find_pipeline(&self, name: &str) -> Option<PipelineHandle> {
+        self.pipeline_names.get(name).copied()
+    }
+
+    /// Destroys a pipeline, invalidating its handle and freeing its name to be used again.
+    /// The pipeline mustn't be used by any frame that's still in flight
+    ///
+    /// If the pipeline has already been destroyed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the pipeline to destroy
+    pub fn destroy_pipeline(&mut self, handle: PipelineHandle) -> Result<(), &'static str> {
+        self.pipelines
+            .remove(handle)
+            .ok_or("The pipeline has already been destroyed")?;
+        self.pipeline_names.retain(|_name, named| *named != handle);
+        Ok(())
+    }
+
+    /// Takes ownership of a buffer, so that it can be referenced by a handle and lives as long as the device (or until it's destroyed)
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: The buffer to store, created with [`Device::allocate_buffer()`]
+    pub fn insert_buffer(&mut self, buffer: Buffer) -> BufferHandle {
+        self.buffers.insert(buffer)
+    }
+
+    /// Gets a buffer by handle, or `None` if it has been destroyed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the buffer
+    pub fn get_buffer(&self, handle: BufferHandle) -> Option<&Buffer> {
+        self.buffers.get(handle)
+    }
+
+    /// Gets a buffer by handle so that it can be written to, or `None` if it has been destroyed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the buffer
+    pub fn get_buffer_mut(&mut self, handle: BufferHandle) -> Option<&mut Buffer> {
+        self.buffers.get_mut(handle)
+    }
+
+    /// Destroys a buffer, invalidating its handle. The buffer mustn't be used by any frame that's still in flight
+    ///
+    /// If the buffer has already been destroyed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the buffer to destroy
+    pub fn destroy_buffer(&mut self, handle: BufferHandle) -> Result<(), &'static str> {
+        self.buffers
+            .remove(handle)
+            .map(drop)
+            .ok_or("The buffer has already been destroyed")
+    }
+
+    /// Takes ownership of a texture, so that it can be referenced by a handle and lives as long as the device (or until it's destroyed)
+    ///
+    /// # Arguments
+    ///
+    /// * `texture`: The texture to store, created with [`Texture::new()`]
+    pub fn insert_texture(&mut self, texture: Texture) -> TextureHandle {
+        self.textures.insert(texture)
+    }
+
+    /// Gets a texture by handle, or `None` if it has been destroyed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the texture
+    pub fn get_texture(&self, handle: TextureHandle) -> Option<&Texture> {
+        self.textures.get(handle)
+    }
+
+    /// Destroys a texture, invalidating its handle. The texture mustn't be used by any frame that's still in flight
+    ///
+    /// If the texture has already been destroyed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the texture to destroy
+    pub fn destroy_texture(&mut self, handle: TextureHandle) -> Result<(), &'static str> {
+        self.textures
+            .remove(handle)
+            .map(drop)
+            .ok_or("The texture has already been destroyed")
+    }
+
+    /// Constructs a new texture sampler on the device
+    ///
+    /// If the parameters are invalid or the sampler can't be created, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters`: The `SamplerParameters` describing how the `Sampler` should filter
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{DeviceParameters, Surface};
+    /// # fn example(surface: &Surface) -> Result<(), Box<dyn std::error::Error>> {
+    /// use client::renderer::vulkan::{Context, Device, SamplerParameters};
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let device = Device::new(&context, surface, &DeviceParameters::default())?;
+    ///
+    /// let sharp_sampler = device.create_sampler(&SamplerParameters { mip_lod_bias: -1.0, ..Default::default() })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_sampler(&self, parameters: &SamplerParameters) -> Result<Sampler, &'static str> {
+        Sampler::new(self, parameters)
+    }
+
+    /// Creates an offscreen colour target that can be drawn to with [`Device::begin_render_target_pass()`] and then sampled
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: The format of the colour image
+    /// * `extent`: The size of the colour image
+    /// * `clear_color`: The colour the image is cleared to at the start of each pass
+    pub fn create_render_target(
+        &self,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        clear_color: [f32; 4],
+    ) -> RenderTarget {
+        RenderTarget::new(self, format, extent, clear_color)
+    }
+
+    /// Creates an offscreen depth target with no colour attachments, which can be drawn to with [`Device::begin_depth_target_pass()`]
+    /// and then sampled (eg. as a shadow map). Pipelines drawing to it must be created with
+    /// [`PipelineParameters::depth_only_format`] set to the same format
+    ///
+    /// If the format isn't a depth format, or the device can't both render to and sample it, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: The format of the depth image, eg. `D32_SFLOAT`
+    /// * `extent`: The size of the depth image
+    /// * `depth_mode`: The depth mode of the pipelines drawing to the target, which decides what the image is cleared to
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(device: &mut Device, surface: Surface) -> Result<(), &'static str> {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::{DepthMode, PipelineParameters};
+    /// use std::path::Path;
+    ///
+    /// let shadow_map = device.create_depth_target(
+    ///     vk::Format::D32_SFLOAT,
+    ///     vk::Extent2D { width: 2048, height: 2048 },
+    ///     DepthMode::Standard,
+    /// )?;
+    /// let shadow_pipeline = device.create_pipeline(
+    ///     &surface,
+    ///     Path::new("res/shaders/shadow.vert.spv"),
+    ///     Path::new("res/shaders/shadow.frag.spv"),
+    ///     String::from("shadow"),
+    ///     &PipelineParameters { depth_only_format: Some(shadow_map.format()), ..Default::default() },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_depth_target(
+        &self,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        depth_mode: DepthMode,
+    ) -> Result<DepthTarget, &'static str> {
+        if !is_depth_format(format) {
+            return Err("The depth target format is not a depth format");
+        }
+
+        let format_properties = unsafe {
+            self.instance
+                .get_physical_device_format_properties(self.physical_device, format)
+        };
+        let required_features = vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+            | vk::FormatFeatureFlags::SAMPLED_IMAGE;
+        if !format_properties
+            .optimal_tiling_features
+            .contains(required_features)
+        {
+            return Err("The device cannot render to and sample the depth target format");
+        }
+
+        Ok(DepthTarget::new(self, format, extent, depth_mode))
+    }
+
+    /// Chooses the format of the depth buffer that the swapchain is drawn with, preferring the most precise format the device can
+    /// render depth to. Formats with stencil are avoided, as nothing drawn to the swapchain uses it
+    pub(crate) fn find_depth_format(&self) -> vk::Format {
+        // Every device supports at least one of the first two, and D16_UNORM is always supported
+        [
+            vk::Format::D32_SFLOAT,
+            vk::Format::X8_D24_UNORM_PACK32,
+            vk::Format::D16_UNORM,
+        ]
+        .into_iter()
+        .find(|format| {
+            let format_properties = unsafe {
+                self.instance
+                    .get_physical_device_format_properties(self.physical_device, *format)
+            };
+            format_properties
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+        .expect("The device doesn't support any depth formats")
+    }
+
+    /// Creates a set of offscreen colour targets which are all drawn to at once with [`Device::begin_multi_render_target_pass()`]
+    /// and then sampled (eg. a G-buffer for deferred shading). Pipelines drawing to it must be created with
+    /// [`PipelineParameters::color_attachment_formats`] set to the same formats, and write attachment `n` from `location = n`
+    ///
+    /// If there are no formats, more than the device's `maxColorAttachments`, or the device can't both render to and sample one of
+    /// them, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `formats`: The format of each colour image, in attachment order
+    /// * `extent`: The size of the colour images
+    /// * `clear_color`: The colour every image is cleared to at the start of each pass
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(device: &mut Device, surface: Surface) -> Result<(), &'static str> {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::PipelineParameters;
+    /// use std::path::Path;
+    ///
+    /// let formats = [vk::Format::R16G16B16A16_SFLOAT, vk::Format::R16G16B16A16_SFLOAT, vk::Format::R8G8B8A8_UNORM];
+    /// let g_buffer = device.create_multi_render_target(&formats, vk::Extent2D { width: 1280, height: 720 }, [0.0; 4])?;
+    /// let geometry_pipeline = device.create_pipeline(
+    ///     &surface,
+    ///     Path::new("res/shaders/g_buffer.vert.spv"),
+    ///     Path::new("res/shaders/g_buffer.frag.spv"),
+    ///     String::from("g_buffer"),
+    ///     &PipelineParameters { color_attachment_formats: formats.to_vec(), ..Default::default() },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_multi_render_target(
+        &self,
+        formats: &[vk::Format],
+        extent: vk::Extent2D,
+        clear_color: [f32; 4],
+    ) -> Result<MultiRenderTarget, &'static str> {
+        if formats.is_empty() {
+            return Err("A multi-render target needs at least one format");
+        }
+        if formats.len() > self.limits.max_color_attachments as usize {
+            return Err("The number of colour attachments is not supported by the device");
+        }
+
+        let required_features =
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE;
+        let all_supported = formats.iter().all(|format| {
+            unsafe {
+                self.instance
+                    .get_physical_device_format_properties(self.physical_device, *format)
+            }
+            .optimal_tiling_features
+            .contains(required_features)
+        });
+        if !all_supported {
+            return Err("The device cannot render to and sample every multi-render target format");
+        }
+
+        Ok(MultiRenderTarget::new(self, formats, extent, clear_color))
+    }
+
+    /// Creates an offscreen colour target with one array layer per view, for rendering every view in a single multiview pass.
+    /// Pipelines drawing to it must be created with the same [`PipelineParameters::view_count`]
+    ///
+    /// If multiview isn't enabled or supported, or the device doesn't support that many views, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: The format of the colour image
+    /// * `extent`: The size of each layer of the colour image
+    /// * `view_count`: The number of views (eg. 2 for stereo)
+    /// * `clear_color`: The colour the image is cleared to at the start of each pass
+    pub fn create_multiview_render_target(
+        &self,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        view_count: u32,
+        clear_color: [f32; 4],
+    ) -> Result<RenderTarget, &'static str> {
+        match self.max_multiview_view_count {
+            None if self.supported_features.multiview => Err(
+                "Multiview is not enabled; add multiview to DeviceParameters::requested_features",
+            ),
+            None => Err("The device does not support multiview"),
+            Some(max_view_count) if view_count == 0 || view_count > max_view_count => {
+                Err("The number of views is not supported by the device")
+            }
+            Some(_) => Ok(RenderTarget::new_multiview(
+                self,
+                format,
+                extent,
+                view_count,
+                clear_color,
+            )),
+        }
+    }
+
+    /// Reads back a single texel of a render target, eg. to find the object under the cursor after rendering object IDs to an
+    /// `R32_UINT` target. This waits for the device to finish all submitted work and then for the copy, so it stalls the CPU and
+    /// shouldn't be called every frame
+    ///
+    /// The texel's raw bits are returned, so for formats other than `R32_UINT` the caller is responsible for interpreting them.
+    /// If the texel is outside of the target, or the target's format isn't 4 bytes per texel, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The render target to read from, whose render pass has ended
+    /// * `x`: The column of the texel, from the left
+    /// * `y`: The row of the texel, from the top
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, RenderTarget};
+    /// # use winit::dpi::PhysicalPosition;
+    /// # fn example(
+    /// #     device: &Device,
+    /// #     object_id_target: RenderTarget,
+    /// #     cursor_position: PhysicalPosition<f64>,
+    /// # ) -> Result<(), &'static str> {
+    /// let (x, y) = (cursor_position.x as u32, cursor_position.y as u32);
+    /// let object_id = device.read_pixel(&object_id_target, x, y)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_pixel(&self, target: &RenderTarget, x: u32, y: u32) -> Result<u32, &'static str> {
+        let span = debug_span!("Vulkan/Device");
+        let _guard = span.enter();
+
+        let extent = target.extent();
+        if x >= extent.width || y >= extent.height {
+            return Err("The pixel is outside of the render target");
+        }
+        if !matches!(
+            target.format(),
+            vk::Format::R32_UINT
+                | vk::Format::R32_SINT
+                | vk::Format::R32_SFLOAT
+                | vk::Format::R8G8B8A8_UNORM
+                | vk::Format::R8G8B8A8_SRGB
+                | vk::Format::B8G8R8A8_UNORM
+                | vk::Format::B8G8R8A8_SRGB
+        ) {
+            return Err("Only render targets with 4 bytes per texel can be read from");
+        }
+
+        let staging_create_info = vk::BufferCreateInfo::builder()
+            .size(std::mem::size_of::<u32>() as u64)
+            .usage(vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let staging_buffer = self.allocate_buffer(
+            &staging_create_info,
+            MemoryLocation::GpuToCpu,
+            "pixel readback",
+        );
+
+        // The render pass may still be running in a frame in flight
+        unsafe { self.logical_device.device_wait_idle() }
+            .expect("Device was removed whilst waiting to read a pixel");
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let copy_region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build(),
+            )
+            .image_offset(vk::Offset3D {
+                x: x as i32,
+                y: y as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            })
+            .build();
+
+        // Recorded on the graphics queue, as that's the queue family which owns the render target
+        self.submit_graphics_and_wait(|logical_device, command_buffer| {
+            record_image_layout_transition(
+                logical_device,
+                command_buffer,
+                target.image(),
+                subresource_range,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            );
+            unsafe {
+                logical_device.cmd_copy_image_to_buffer(
+                    command_buffer,
+                    target.image(),
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    staging_buffer.buffer,
+                    &[copy_region],
+                )
+            };
+            // Put the image back how its render pass left it, so it can still be sampled
+            record_image_layout_transition(
+                logical_device,
+                command_buffer,
+                target.image(),
+                subresource_range,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        });
+
+        let bytes = staging_buffer
+            .mapped_slice()
+            .expect("Readback buffer should be host-visible");
+        Ok(u32::from_ne_bytes(
+            bytes[..std::mem::size_of::<u32>()].try_into().unwrap(),
+        ))
+    }
+
+    /// Records commands into a new graphics command buffer, submits them, and blocks until they've completed.
+    /// This is for occasional work outside of a frame, such as reading back a render target
+    ///
+    /// # Arguments
+    ///
+    /// * `record`: A function which records the commands into the command buffer it's given
+    fn submit_graphics_and_wait<F>(&self, record: F)
+    where
+        F: FnOnce(&ash::Device, vk::CommandBuffer),
+    {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(1)
+            .command_pool(self.command_pools.graphics)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .build();
+        let command_buffer =
+            *unsafe { self.logical_device.allocate_command_buffers(&allocate_info) }
+                .expect("Failed to allocate graphics command buffer")
+                .first()
+                .unwrap();
+        track_created(ResourceKind::CommandBuffer, 1);
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &begin_info)
+        }
+        .expect("Failed to begin graphics command buffer");
+
+        record(&self.logical_device, command_buffer);
+
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end graphics command buffer");
+
+        let fence = unsafe {
+            self.logical_device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        }
+        .expect("Failed to create fence for graphics submission");
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        unsafe {
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.graphics.first().unwrap()),
+                &[submit_info],
+                fence,
+            )
+        }
+        .expect("Failed to submit graphics queue");
+
+        unsafe {
+            self.logical_device
+                .wait_for_fences(&[fence], true, u64::MAX)
+        }
+        .expect("Device was removed whilst waiting for a graphics submission to complete");
+
+        unsafe {
+            self.logical_device.destroy_fence(fence, None);
+            self.logical_device
+                .free_command_buffers(self.command_pools.graphics, &command_buffers);
+        }
+        track_destroyed(ResourceKind::CommandBuffer, 1);
+    }
+
+    /// Gets the number of pipelines, framebuffers, buffers, images, and command buffers which currently exist, to help track down leaks.
+    /// These are logged when the device is destroyed, at which point they should all be zero
+    pub fn resource_stats(&self) -> ResourceStats {
+        live_resources()
+    }
+
+    /// Gets the optional features that were enabled on the device, which are those requested through
+    /// [`DeviceParameters::requested_features`]
+    pub fn enabled_features(&self) -> SupportedFeatures {
+        self.enabled_features
+    }
+
+    /// Gets the maximum number of views a multiview render pass can have, or `None` if multiview isn't enabled
+    pub fn max_multiview_view_count(&self) -> Option<u32> {
+        self.max_multiview_view_count
+    }
+
+    /// Creates a buffer whose memory is sub-allocated from a larger block, rather than having its own `vk::DeviceMemory`
+    ///
+    /// # Arguments
+    ///
+    /// * `create_info`: The description of the buffer
+    /// * `location`: Where the buffer's memory should live
+    /// * `name`: A name for the allocation, used when debugging leaks
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::MemoryLocation;
+    ///
+    /// let create_info = vk::BufferCreateInfo::builder()
+    ///     .size(1024)
+    ///     .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+    ///     .sharing_mode(vk::SharingMode::EXCLUSIVE)
+    ///     .build();
+    /// let buffer = device.allocate_buffer(&create_info, MemoryLocation::CpuToGpu, "terrain vertices");
+    /// # }
+    /// ```
+    pub fn allocate_buffer(
+        &self,
+        create_info: &vk::BufferCreateInfo,
+        location: MemoryLocation,
+        name: &str,
+    ) -> Buffer {
+        Buffer::new(
+            &self.logical_device,
+            self.allocator.as_ref().unwrap(),
+            create_info,
+            location,
+            name,
+        )
+    }
+
+    /// Creates a buffer like [`Device::allocate_buffer()`], but whose memory starts at a multiple of `min_alignment` within its block
+    ///
+    /// # Arguments
+    ///
+    /// * `create_info`: The description of the buffer
+    /// * `location`: Where the buffer's memory should live
+    /// * `min_alignment`: The alignment of the buffer's memory, in bytes, which must be a power of two
+    /// * `name`: A name for the allocation, used when debugging leaks
+    pub(crate) fn allocate_aligned_buffer(
+        &self,
+        create_info: &vk::BufferCreateInfo,
+        location: MemoryLocation,
+        min_alignment: u64,
+        name: &str,
+    ) -> Buffer {
+        Buffer::new_aligned(
+            &self.logical_device,
+            self.allocator.as_ref().unwrap(),
+            create_info,
+            location,
+            min_alignment,
+            name,
+        )
+    }
+
+    /// Creates a host-visible buffer which can be written with typed slices, flushing the writes when the memory isn't coherent.
+    /// This suits buffers rewritten every frame, such as uniforms and dynamic geometry
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: The size of the buffer in bytes
+    /// * `usage`: How the buffer will be used
+    /// * `name`: A name for the allocation, used when debugging leaks
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # use common::math::Mat4;
+    /// # fn example(device: &Device, view_projection: Mat4) -> Result<(), &'static str> {
+    /// use ash::vk;
+    ///
+    /// let mut camera_uniforms = device.create_mapped_buffer(64, vk::BufferUsageFlags::UNIFORM_BUFFER, "camera uniforms");
+    /// camera_uniforms.write_slice(0, &view_projection.to_cols_array())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_mapped_buffer(
+        &self,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        name: &str,
+    ) -> MappedBuffer {
+        MappedBuffer::new(self, size, usage, name)
+    }
+
+    /// Creates an image whose memory is sub-allocated from a larger block, rather than having its own `vk::DeviceMemory`
+    ///
+    /// # Arguments
+    ///
+    /// * `create_info`: The description of the image
+    /// * `location`: Where the image's memory should live
+    /// * `name`: A name for the allocation, used when debugging leaks
+    pub fn allocate_image(
+        &self,
+        create_info: &vk::ImageCreateInfo,
+        location: MemoryLocation,
+        name: &str,
+    ) -> Image {
+        Image::new(
+            &self.logical_device,
+            self.allocator.as_ref().unwrap(),
+            create_info,
+            location,
+            name,
+        )
+    }
+
+    /// Gets how much memory is in use and how much is available in each memory heap.
+    /// If `VK_EXT_memory_budget` isn't supported then the usage is unknown, and the size of each heap is used as its budget
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device, texture_size: u64) {
+    /// let budget = device.memory_budget();
+    /// if budget.device_local_available() < texture_size {
+    ///     // Defer streaming in the texture
+    /// }
+    /// # }
+    /// ```
+    pub fn memory_budget(&self) -> MemoryBudget {
+        query_memory_budget(
+            &self.instance,
+            self.physical_device,
+            self.memory_budget_enabled,
+        )
+    }
+
+    /// Gets the pipeline cache that pipelines on the device are created with, so that they can reuse the compiled shaders of
+    /// pipelines created before, including those from previous runs
+    pub(crate) fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache
+    }
+
+    /// Gets the layout of the per-frame uniform descriptor set, which pipelines without reflected descriptor sets use as set 0
+    pub(crate) fn uniform_descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.uniforms.as_ref().unwrap().descriptor_set_layout()
+    }
+
+    /// Writes the model, view, and projection matrices that a frame's pipelines read from set 0, binding 0.
+    /// Each frame in flight has its own uniform buffer, so this should be called after [`Device::begin_frame()`] has waited for the
+    /// frame's previous submission, and before the frame is submitted
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight to update
+    /// * `data`: The matrices to write
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, current_frame_index: usize) {
+    /// use client::renderer::vulkan::UniformData;
+    /// use common::math::{look_at, perspective, Vec3};
+    ///
+    /// let data = UniformData {
+    ///     view: look_at(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO, Vec3::Y),
+    ///     proj: perspective(std::f32::consts::FRAC_PI_2, 16.0 / 9.0, 0.1, 100.0),
+    ///     ..Default::default()
+    /// };
+    /// device.update_uniform(current_frame_index, &data);
+    /// # }
+    /// ```
+    pub fn update_uniform(&mut self, frame_index: usize, data: &UniformData) {
+        self.uniforms.as_mut().unwrap().update(frame_index, data);
+    }
+
+    /// Binds the frame's uniform descriptor set to set 0 if the pipeline uses it, which must happen after the pipeline is bound
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `command_buffer`: The command buffer being recorded
+    /// * `pipeline`: The graphics pipeline that was just bound
+    fn bind_frame_uniforms(
+        &self,
+        current_frame: usize,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &Pipeline,
+    ) {
+        if !pipeline.binds_frame_uniforms {
+            return;
+        }
+
+        let descriptor_set = self
+            .uniforms
+            .as_ref()
+            .unwrap()
+            .descriptor_set(current_frame);
+        unsafe {
+            self.logical_device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.layout,
+                0,
+                &[descriptor_set],
+                &[],
+            )
+        };
+    }
+
+    /// Gets the limits of the physical device, such as the maximum sampler LOD bias
+    pub fn limits(&self) -> &vk::PhysicalDeviceLimits {
+        &self.limits
+    }
+
+    /// Records commands into a new transfer command buffer and submits them to the transfer queue without waiting for them to complete
+    ///
+    /// Returns a `TransferHandle` which can be passed to [`Device::is_resource_ready()`] or [`Device::wait_for_resource()`]
+    /// before using anything the commands write to
+    ///
+    /// # Arguments
+    ///
+    /// * `record`: A function which records the transfer commands into the command buffer it's given
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use ash::vk;
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(
+    /// #     device: &mut Device,
+    /// #     staging_buffer: vk::Buffer,
+    /// #     vertex_buffer: vk::Buffer,
+    /// #     copy_region: vk::BufferCopy,
+    /// # ) {
+    /// let upload = device.submit_transfer_async(|logical_device, command_buffer| unsafe {
+    ///     logical_device.cmd_copy_buffer(command_buffer, staging_buffer, vertex_buffer, &[copy_region]);
+    /// });
+    ///
+    /// if device.is_resource_ready(upload) {
+    ///     // Safe to draw using vertex_buffer
+    /// }
+    /// # }
+    /// ```
+    pub fn submit_transfer_async<F>(&mut self, record: F) -> TransferHandle
+    where
+        F: FnOnce(&ash::Device, vk::CommandBuffer),
+    {
+        self.collect_finished_transfers();
+
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(1)
+            .command_pool(self.command_pools.transfer)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .build();
+        let command_buffer =
+            *unsafe { self.logical_device.allocate_command_buffers(&allocate_info) }
+                .expect("Failed to allocate transfer command buffer")
+                .first()
+                .unwrap();
+        track_created(ResourceKind::CommandBuffer, 1);
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &begin_info)
+        }
+        .expect("Failed to begin transfer command buffer");
+
+        record(&self.logical_device, command_buffer);
+
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end transfer command buffer");
+
+        let fence = unsafe {
+            self.logical_device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        }
+        .expect("Failed to create fence for transfer");
+
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&[command_buffer])
+            .build();
+        unsafe {
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.transfer.first().unwrap()),
+                &[submit_info],
+                fence,
+            )
+        }
+        .expect("Failed to submit transfer queue");
+
+        let handle = TransferHandle(self.next_transfer_id);
+        self.next_transfer_id += 1;
+        self.pending_transfers.insert(
+            handle,
+            PendingTransfer {
+                command_buffer,
+                fence,
+                staging_buffer: None,
+            },
+        );
+
+        handle
+    }
+
+    /// Records commands into the transfer command buffer, submits them to the transfer queue, and waits for them to complete.
+    /// Unlike [`Device::submit_transfer_async()`], the resources the commands write to can be used on the graphics queue as soon as
+    /// this returns, as their queue family ownership is moved to the graphics family when the families differ
+    ///
+    /// # Arguments
+    ///
+    /// * `transferred`: The exclusive resources the commands write to, which will be used on the graphics queue afterwards. Resources
+    ///   which are shared between the families don't need to be listed, unless an image needs its layout changing
+    /// * `record`: A function which records the transfer commands into the command buffer it's given
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use ash::vk;
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(
+    /// #     device: &Device,
+    /// #     staging_buffer: vk::Buffer,
+    /// #     vertex_buffer: vk::Buffer,
+    /// #     copy_region: vk::BufferCopy,
+    /// # ) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::TransferredResource;
+    ///
+    /// device.submit_transfer(&[TransferredResource::Buffer(vertex_buffer)], |command_buffer| unsafe {
+    ///     device.logical_device.cmd_copy_buffer(command_buffer, staging_buffer, vertex_buffer, &[copy_region]);
+    /// });
+    /// // vertex_buffer can now be drawn from
+    /// # }
+    /// ```
+    pub fn submit_transfer<F>(&self, transferred: &[TransferredResource], record: F)
+    where
+        F: FnOnce(vk::CommandBuffer),
+    {
+        let transfer_family = self.queue_family_indices.transfer.index;
+        let graphics_family = self.queue_family_indices.graphics.index;
+        let transfers_ownership = transfer_family != graphics_family;
+
+        let command_buffer = *self.command_buffers.transfer.first().unwrap();
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &begin_info)
+        }
+        .expect("Failed to begin transfer command buffer");
+
+        record(command_buffer);
+
+        // The release half of the ownership transfer. Its destination stage is ignored, as the acquire barrier is what waits. When the
+        // families are the same this only changes image layouts, and the host waiting on the fence makes the writes visible
+        record_ownership_barriers(
+            &self.logical_device,
+            command_buffer,
+            transferred,
+            (transfer_family, graphics_family),
+            transfers_ownership,
+            (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+            (
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::AccessFlags::empty(),
+            ),
+        );
+
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end transfer command buffer");
+
+        let fence = unsafe {
+            self.logical_device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        }
+        .expect("Failed to create fence for transfer");
+
+        if !transfers_ownership || transferred.is_empty() {
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(&[command_buffer])
+                .build();
+            unsafe {
+                self.logical_device.queue_submit(
+                    *lock_queue(self.queue_families.transfer.first().unwrap()),
+                    &[submit_info],
+                    fence,
+                )
+            }
+            .expect("Failed to submit transfer queue");
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[fence], true, u64::MAX)
+            }
+            .expect("Device was removed whilst waiting for a transfer to complete");
+        } else {
+            self.submit_ownership_acquire(command_buffer, transferred, fence);
+        }
+        unsafe { self.logical_device.destroy_fence(fence, None) };
+    }
+
+    /// Submits a transfer command buffer which releases ownership of resources, followed by a graphics command buffer which acquires
+    /// them once the transfer has finished, and waits for the fence to be signalled once every resource has been acquired
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer_command_buffer`: The recorded transfer commands, ending with the release barriers
+    /// * `transferred`: The resources being moved to the graphics queue family
+    /// * `fence`: The fence to signal once the resources have been acquired
+    fn submit_ownership_acquire(
+        &self,
+        transfer_command_buffer: vk::CommandBuffer,
+        transferred: &[TransferredResource],
+        fence: vk::Fence,
+    ) {
+        let transferred_semaphore = unsafe {
+            self.logical_device
+                .create_semaphore(&vk::SemaphoreCreateInfo::default(), None)
+        }
+        .expect("Failed to create semaphore for transfer");
+
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(1)
+            .command_pool(self.command_pools.graphics)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .build();
+        let acquire_command_buffer =
+            *unsafe { self.logical_device.allocate_command_buffers(&allocate_info) }
+                .expect("Failed to allocate ownership acquire command buffer")
+                .first()
+                .unwrap();
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(acquire_command_buffer, &begin_info)
+        }
+        .expect("Failed to begin ownership acquire command buffer");
+        // The source stage is ignored, as the semaphore already waits for the release
+        record_ownership_barriers(
+            &self.logical_device,
+            acquire_command_buffer,
+            transferred,
+            (
+                self.queue_family_indices.transfer.index,
+                self.queue_family_indices.graphics.index,
+            ),
+            true,
+            (
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::empty(),
+            ),
+            (
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+            ),
+        );
+        unsafe {
+            self.logical_device
+                .end_command_buffer(acquire_command_buffer)
+        }
+        .expect("Failed to end ownership acquire command buffer");
+
+        let release_submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&[transfer_command_buffer])
+            .signal_semaphores(&[transferred_semaphore])
+            .build();
+        unsafe {
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.transfer.first().unwrap()),
+                &[release_submit_info],
+                vk::Fence::null(),
+            )
+        }
+        .expect("Failed to submit transfer queue");
+
+        let acquire_submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&[acquire_command_buffer])
+            .wait_semaphores(&[transferred_semaphore])
+            .wait_dst_stage_mask(&[vk::PipelineStageFlags::ALL_COMMANDS])
+            .build();
+        unsafe {
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.graphics.first().unwrap()),
+                &[acquire_submit_info],
+                fence,
+            )
+        }
+        .expect("Failed to submit graphics queue");
+
+        unsafe {
+            self.logical_device
+                .wait_for_fences(&[fence], true, u64::MAX)
+        }
+        .expect("Device was removed whilst waiting for a transfer to complete");
+        unsafe {
+            self.logical_device
+                .free_command_buffers(self.command_pools.graphics, &[acquire_command_buffer]);
+            self.logical_device
+                .destroy_semaphore(transferred_semaphore, None);
+        }
+    }
+
+    /// Creates a device-local buffer filled with the given data (eg. vertices or uniforms).
+    ///
+    /// If the device has a large host-visible device-local heap (ie. resizable BAR is enabled) with room for the data, it's written
+    /// straight into the buffer. Otherwise it's copied through a staging buffer on the transfer queue, and the returned
+    /// `BufferUpload` holds the `TransferHandle` to wait on before using the buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The bytes to fill the buffer with
+    /// * `usage`: How the buffer will be used. `TRANSFER_DST` is added if the buffer has to be staged
+    /// * `name`: A name for the allocation, used when debugging leaks
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{BufferUpload, Device};
+    /// # use tracing::debug;
+    /// # fn example(device: &mut Device, vertex_bytes: Vec<u8>) {
+    /// use ash::vk;
+    ///
+    /// let (vertex_buffer, upload) = device.create_buffer_with_data(
+    ///     &vertex_bytes,
+    ///     vk::BufferUsageFlags::VERTEX_BUFFER,
+    ///     "terrain vertices",
+    /// );
+    /// debug!("Uploaded terrain directly: {}", upload.is_direct());
+    /// if let BufferUpload::Staged(transfer) = upload {
+    ///     device.wait_for_resource(transfer);
+    /// }
+    /// # }
+    /// ```
+    pub fn create_buffer_with_data(
+        &mut self,
+        data: &[u8],
+        usage: vk::BufferUsageFlags,
+        name: &str,
+    ) -> (Buffer, BufferUpload) {
+        let size = data.len() as u64;
+
+        let has_room_in_bar = self.resizable_bar_heap.is_some_and(|heap_index| {
+            self.memory_budget().heaps[heap_index as usize].available() >= size
+        });
+        if has_room_in_bar {
+            let mut buffer =
+                self.allocate_shared_buffer(size, usage, MemoryLocation::CpuToGpu, name);
+            // The allocator falls back to system memory if the heap is full, in which case it's better to stage
+            if buffer.is_device_local() {
+                buffer.mapped_slice_mut().unwrap()[..data.len()].copy_from_slice(data);
+                debug!("Uploaded {} bytes to {} without staging", size, name);
+                return (buffer, BufferUpload::Direct);
+            }
+        }
+
+        let staging_create_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let mut staging_buffer =
+            self.allocate_buffer(&staging_create_info, MemoryLocation::CpuToGpu, "staging");
+        staging_buffer.mapped_slice_mut().unwrap()[..data.len()].copy_from_slice(data);
+
+        let buffer = self.allocate_shared_buffer(
+            size,
+            usage | vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::GpuOnly,
+            name,
+        );
+
+        let copy_region = vk::BufferCopy::builder().size(size).build();
+        let (source, destination) = (staging_buffer.buffer, buffer.buffer);
+        let transfer = self.submit_transfer_async(|logical_device, command_buffer| unsafe {
+            logical_device.cmd_copy_buffer(command_buffer, source, destination, &[copy_region]);
+        });
+        self.pending_transfers
+            .get_mut(&transfer)
+            .unwrap()
+            .staging_buffer = Some(staging_buffer);
+
+        debug!(
+            "Uploaded {} bytes to {} through a staging buffer",
+            size, name
+        );
+        (buffer, BufferUpload::Staged(transfer))
+    }
+
+    /// Creates a vertex buffer holding the given vertices, and returns a handle to it. The vertices are uploaded through
+    /// [`Device::create_buffer_with_data()`], and this waits for any staged upload to finish so that the buffer can be drawn from
+    /// straight away
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The vertices to upload, which mustn't be empty
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, current_frame_index: usize) {
+    /// use client::renderer::vulkan::Vertex;
+    ///
+    /// let triangle = device.create_vertex_buffer(&[
+    ///     Vertex { position: [0.0, -0.5], color: [1.0, 0.0, 0.0] },
+    ///     Vertex { position: [0.5, 0.5], color: [0.0, 1.0, 0.0] },
+    ///     Vertex { position: [-0.5, 0.5], color: [0.0, 0.0, 1.0] },
+    /// ]);
+    /// device.bind_vertex_buffer(current_frame_index, device.get_buffer(triangle).unwrap());
+    /// device.draw_vertices(current_frame_index, 3);
+    /// # }
+    /// ```
+    pub fn create_vertex_buffer(&mut self, data: &[Vertex]) -> BufferHandle {
+        assert!(
+            !data.is_empty(),
+            "A vertex buffer needs at least one vertex"
+        );
+
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
+        };
+        self.create_ready_buffer(bytes, vk::BufferUsageFlags::VERTEX_BUFFER, "vertices")
+    }
+
+    /// Creates an index buffer holding the given `u32` indices, and returns a handle to it. Like [`Device::create_vertex_buffer()`],
+    /// this waits for any staged upload to finish so that the buffer can be drawn from straight away
+    ///
+    /// # Arguments
+    ///
+    /// * `indices`: The indices to upload, which mustn't be empty
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, current_frame_index: usize) {
+    /// use client::renderer::vulkan::Vertex;
+    ///
+    /// // A quad only needs 4 vertices when its two triangles share a diagonal
+    /// let quad_vertices = device.create_vertex_buffer(&[
+    ///     Vertex { position: [-0.5, -0.5], color: [1.0, 0.0, 0.0] },
+    ///     Vertex { position: [0.5, -0.5], color: [0.0, 1.0, 0.0] },
+    ///     Vertex { position: [0.5, 0.5], color: [0.0, 0.0, 1.0] },
+    ///     Vertex { position: [-0.5, 0.5], color: [1.0, 1.0, 1.0] },
+    /// ]);
+    /// let quad_indices = device.create_index_buffer(&[0, 1, 2, 2, 3, 0]);
+    ///
+    /// device.bind_vertex_buffer(current_frame_index, device.get_buffer(quad_vertices).unwrap());
+    /// device.bind_index_buffer(current_frame_index, device.get_buffer(quad_indices).unwrap());
+    /// device.draw_indexed(current_frame_index, 6);
+    /// # }
+    /// ```
+    pub fn create_index_buffer(&mut self, indices: &[u32]) -> BufferHandle {
+        assert!(
+            !indices.is_empty(),
+            "An index buffer needs at least one index"
+        );
+
+        self.create_ready_buffer(
+            index_buffer_bytes(indices),
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            "indices",
+        )
+    }
+
+    /// Creates a sampled texture holding the given texels, and returns a handle to it. The texels are copied through a staging buffer
+    /// on the transfer queue, and this waits for the upload to finish so that the texture can be drawn with straight away
+    ///
+    /// If the format isn't supported, either dimension is zero, or the number of bytes doesn't match the size, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels`: The texels, tightly packed in rows from the top. [`load_texture_pixels()`](crate::renderer::vulkan::load_texture_pixels)
+    ///   loads an image file in this layout
+    /// * `width`: The width of the texture in texels
+    /// * `height`: The height of the texture in texels
+    /// * `format`: The format of the texels. 8-bit formats with one, two, or four channels are supported
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, current_frame_index: usize) -> Result<(), &'static str> {
+    /// use ash::vk;
+    ///
+    /// // A 2x2 checkerboard
+    /// let checkerboard = device.create_texture(
+    ///     &[
+    ///         255, 255, 255, 255, 0, 0, 0, 255,
+    ///         0, 0, 0, 255, 255, 255, 255, 255,
+    ///     ],
+    ///     2,
+    ///     2,
+    ///     vk::Format::R8G8B8A8_SRGB,
+    /// )?;
+    /// device.bind_texture(current_frame_index, checkerboard)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_texture(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: vk::Format,
+    ) -> Result<TextureHandle, &'static str> {
+        let image = self.upload_texture_image(pixels, width, height, 1, format)?;
+        let texture = Texture::new(self, image, format, vk::Extent2D { width, height });
+        Ok(self.insert_texture(texture))
+    }
+
+    /// Creates a sampled array texture holding the given layers, and returns a handle to it. Shaders sample it as a `sampler2DArray`,
+    /// and it's bound with [`Device::bind_texture_array()`]. As with [`Device::create_texture()`], this waits for the upload to finish
+    ///
+    /// If the format isn't supported, any dimension is zero, there are more layers than the device supports, or the number of bytes
+    /// doesn't match the size, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels`: The texels of each layer in turn, each tightly packed in rows from the top
+    /// * `width`: The width of each layer in texels
+    /// * `height`: The height of each layer in texels
+    /// * `layer_count`: The number of layers
+    /// * `format`: The format of the texels. 8-bit formats with one, two, or four channels are supported
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device) -> Result<(), &'static str> {
+    /// use ash::vk;
+    ///
+    /// // A white layer and a red layer, each 1x1
+    /// let sprites = device.create_texture_array(
+    ///     &[255, 255, 255, 255, 255, 0, 0, 255],
+    ///     1,
+    ///     1,
+    ///     2,
+    ///     vk::Format::R8G8B8A8_SRGB,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_texture_array(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        format: vk::Format,
+    ) -> Result<TextureHandle, &'static str> {
+        if layer_count > self.limits.max_image_array_layers {
+            return Err("The texture has more layers than the device's maxImageArrayLayers");
+        }
+        let image = self.upload_texture_image(pixels, width, height, layer_count, format)?;
+        let texture = Texture::new_array(
+            self,
+            image,
+            format,
+            vk::Extent2D { width, height },
+            layer_count,
+        );
+        Ok(self.insert_texture(texture))
+    }
+
+    /// Creates a texture image and copies texels into it through a staging buffer, waiting for the upload to finish
+    ///
+    /// If the format isn't supported, any dimension is zero, or the number of bytes doesn't match the size, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels`: The texels of each layer in turn
+    /// * `width`: The width of each layer in texels
+    /// * `height`: The height of each layer in texels
+    /// * `layer_count`: The number of layers
+    /// * `format`: The format of the texels
+    fn upload_texture_image(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        format: vk::Format,
+    ) -> Result<Image, &'static str> {
+        let texel_size = texture_texel_size(format).ok_or("The texture format isn't supported")?;
+        if width == 0 || height == 0 || layer_count == 0 {
+            return Err(
+                "A texture must be at least one texel wide and high, with at least one layer",
+            );
+        }
+        if pixels.len() as u64 != width as u64 * height as u64 * layer_count as u64 * texel_size {
+            return Err("The number of bytes doesn't match the size and format of the texture");
+        }
+
+        let staging_create_info = vk::BufferCreateInfo::builder()
+            .size(pixels.len() as u64)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let mut staging_buffer =
+            self.allocate_buffer(&staging_create_info, MemoryLocation::CpuToGpu, "staging");
+        staging_buffer.mapped_slice_mut().unwrap()[..pixels.len()].copy_from_slice(pixels);
+
+        let extent = vk::Extent2D { width, height };
+        let image = create_texture_image(self, format, extent, layer_count);
+
+        let copy_region = vk::BufferImageCopy::builder()
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(layer_count)
+                    .build(),
+            )
+            .image_extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .build();
+        let (source, destination) = (staging_buffer.buffer, image.image);
+        let transferred = [TransferredResource::Image {
+            image: destination,
+            subresource_range: texture_subresource_range(layer_count),
+            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }];
+        let logical_device = &self.logical_device;
+        self.submit_transfer(&transferred, |command_buffer| {
+            record_image_layout_transition(
+                logical_device,
+                command_buffer,
+                destination,
+                texture_subresource_range(layer_count),
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            );
+            unsafe {
+                logical_device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    source,
+                    destination,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[copy_region],
+                )
+            };
+        });
+        // The transfer has finished, so the staging buffer can be freed
+        drop(staging_buffer);
+
+        debug!(
+            "Uploaded {}x{}x{} texture with format {:?}",
+            width, height, layer_count, format
+        );
+        Ok(image)
+    }
+
+    /// Binds a texture to binding 1 of the frame's uniform descriptor set (set 0), so that it's sampled by the frame's draws.
+    /// As with [`Device::update_uniform()`], this should be called after [`Device::begin_frame()`], and must also be called before the
+    /// frame's render pass begins, since the descriptor set is bound then. Every draw in the frame samples the same texture
+    ///
+    /// If the texture has been destroyed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight being recorded
+    /// * `texture`: The handle of the texture to sample
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, PipelineHandle, Surface, TextureHandle};
+    /// # fn example(
+    /// #     device: &mut Device,
+    /// #     mut surface: Surface,
+    /// #     current_frame_index: usize,
+    /// #     grass: TextureHandle,
+    /// #     terrain_pipeline: PipelineHandle,
+    /// # ) -> Result<(), &'static str> {
+    /// let Some(image_index) = device.begin_frame(current_frame_index, &mut surface) else {
+    ///     return Ok(());
+    /// };
+    /// device.bind_texture(current_frame_index, grass)?;
+    /// device.begin_surface_render_pass(current_frame_index, &mut surface, image_index, terrain_pipeline);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_texture(
+        &mut self,
+        frame_index: usize,
+        texture: TextureHandle,
+    ) -> Result<(), &'static str> {
+        let texture = self
+            .textures
+            .get(texture)
+            .ok_or("The texture has been destroyed")?;
+        if texture.is_array() {
+            return Err("Array textures must be bound with Device::bind_texture_array()");
+        }
+        self.uniforms.as_ref().unwrap().set_texture(
+            frame_index,
+            texture.view(),
+            texture.sampler().sampler,
+        );
+        Ok(())
+    }
+
+    /// Gets the layout of the descriptor sets that [`Device::bind_texture_array()`] binds, which holds a single combined image
+    /// sampler at binding 0 for the fragment shader. Pipelines which sample array textures list it in
+    /// [`PipelineParameters::descriptor_set_layouts`]
+    pub fn texture_array_descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.texture_array_descriptors
+            .as_ref()
+            .unwrap()
+            .descriptor_set_layout()
+    }
+
+    /// Binds an array texture for the draws that follow, as a descriptor set of the pipeline currently bound in the render pass.
+    /// Unlike [`Device::bind_texture()`], this can be called between draws, and each draw samples whichever texture was bound last
+    ///
+    /// If the frame isn't inside a render pass, the texture has been destroyed or isn't an array, or too many textures have been
+    /// bound in this frame, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `set`: The set number to bind to, whose layout must be [`Device::texture_array_descriptor_set_layout()`]
+    /// * `texture`: The handle of a texture created with [`Device::create_texture_array()`]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, TextureHandle};
+    /// # fn example(device: &mut Device, current_frame_index: usize, sprites: TextureHandle) -> Result<(), &'static str> {
+    /// // Whilst a pipeline created with `descriptor_set_layouts: vec![device.texture_array_descriptor_set_layout()]` is bound
+    /// device.bind_texture_array(current_frame_index, 1, sprites)?;
+    /// device.draw_vertices(current_frame_index, 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_texture_array(
+        &self,
+        current_frame_index: usize,
+        set: u32,
+        texture: TextureHandle,
+    ) -> Result<(), &'static str> {
+        let pipeline = self
+            .bound_pipeline(current_frame_index)
+            .ok_or("A render pass must be begun before binding a texture array")?;
+        let pipeline = self
+            .get_pipeline(pipeline)
+            .ok_or("The bound pipeline has been destroyed")?;
+        let texture = self
+            .textures
+            .get(texture)
+            .ok_or("The texture has been destroyed")?;
+        if !texture.is_array() {
+            return Err("The texture wasn't created with Device::create_texture_array()");
+        }
+
+        let descriptor_set = self.texture_array_descriptors.as_ref().unwrap().allocate(
+            current_frame_index,
+            texture.view(),
+            texture.sampler().sampler,
+        )?;
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.layout,
+                set,
+                &[descriptor_set],
+                &[],
+            )
+        };
+        Ok(())
+    }
+
+    /// Creates a buffer holding the given data and returns a handle to it, waiting for any staged upload to finish first
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The bytes to upload
+    /// * `usage`: How the buffer will be used
+    /// * `name`: A name for the allocation, used when debugging leaks
+    fn create_ready_buffer(
+        &mut self,
+        data: &[u8],
+        usage: vk::BufferUsageFlags,
+        name: &str,
+    ) -> BufferHandle {
+        let (buffer, upload) = self.create_buffer_with_data(data, usage, name);
+        if let BufferUpload::Staged(transfer) = upload {
+            self.wait_for_resource(transfer);
+        }
+        self.insert_buffer(buffer)
+    }
+
+    /// Creates a buffer with the given usage and memory location, and returns a handle to it. This is the general form of the typed
+    /// buffer helpers, for when they don't fit (eg. a storage buffer for compute). If the memory is host-visible, it can be written
+    /// through [`Device::write_buffer()`] or [`Buffer::mapped_slice_mut()`]
+    ///
+    /// The buffer is shared between the graphics, transfer, and compute queues, so it can be used on any of them
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: The size of the buffer in bytes
+    /// * `usage`: How the buffer will be used
+    /// * `memory`: Where the buffer's memory should live
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, particle_bytes: Vec<u8>) -> Result<(), &'static str> {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::MemoryLocation;
+    ///
+    /// let particles = device.create_buffer(
+    ///     particle_bytes.len() as u64,
+    ///     vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+    ///     MemoryLocation::CpuToGpu,
+    /// );
+    /// device.write_buffer(particles, 0, &particle_bytes)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_buffer(
+        &mut self,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory: MemoryLocation,
+    ) -> BufferHandle {
+        let name = format!("{:?} buffer", usage);
+        let buffer = self.allocate_shared_buffer(size, usage, memory, &name);
+        self.insert_buffer(buffer)
+    }
+
+    /// Copies data into a host-visible buffer. The buffer mustn't be in use by any frame that's still in flight
+    ///
+    /// If the buffer has been destroyed, isn't host-visible, or is too small for the data at the given offset, returns `Err` and
+    /// nothing is written
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the buffer to write to
+    /// * `offset`: The offset in bytes to start writing at
+    /// * `data`: The bytes to write
+    pub fn write_buffer(
+        &mut self,
+        handle: BufferHandle,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), &'static str> {
+        let mapped = self
+            .buffers
+            .get_mut(handle)
+            .ok_or("The buffer has already been destroyed")?
+            .mapped_slice_mut()
+            .ok_or("The buffer isn't host-visible")?;
+        let destination = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| mapped.get_mut(offset..offset.checked_add(data.len())?))
+            .ok_or("The data doesn't fit in the buffer")?;
+        destination.copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Allocates a buffer shared between every queue family that buffers are used on, as it may be written on the transfer queue and
+    /// then used on either the graphics or the compute queue
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: The size of the buffer in bytes
+    /// * `usage`: How the buffer will be used
+    /// * `location`: Where the buffer's memory should live
+    /// * `name`: A name for the allocation, used when debugging leaks
+    fn allocate_shared_buffer(
+        &self,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        location: MemoryLocation,
+        name: &str,
+    ) -> Buffer {
+        let queue_family_indices = self.buffer_queue_family_indices();
+        let create_info = share_between_queue_families(
+            vk::BufferCreateInfo::builder().size(size).usage(usage),
+            &queue_family_indices,
+        )
+        .build();
+        self.allocate_buffer(&create_info, location, name)
+    }
+
+    /// Gets the distinct queue families that buffers are used on, so that they can be shared between them without ownership transfers
+    fn buffer_queue_family_indices(&self) -> Vec<u32> {
+        let mut queue_family_indices = vec![];
+        for index in [
+            self.queue_family_indices.graphics.index,
+            self.queue_family_indices.transfer.index,
+            self.queue_family_indices.compute.index,
+        ] {
+            if !queue_family_indices.contains(&index) {
+                queue_family_indices.push(index);
+            }
+        }
+        queue_family_indices
+    }
+
+    /// Gets the distinct queue families that swapchain images are used on. Images are drawn to on the graphics queue and presented on
+    /// the present queue, and when those are different families the images are shared between them rather than having their
+    /// ownership transferred every frame
+    pub(crate) fn swapchain_queue_family_indices(&self) -> Vec<u32> {
+        let graphics = self.queue_family_indices.graphics.index;
+        let present = self.queue_family_indices.present.index;
+        if graphics == present {
+            vec![graphics]
+        } else {
+            vec![graphics, present]
+        }
+    }
+
+    /// Checks whether the transfer that writes a resource has completed, without blocking
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The `TransferHandle` returned when the transfer was submitted
+    pub fn is_resource_ready(&self, handle: TransferHandle) -> bool {
+        match self.pending_transfers.get(&handle) {
+            // Transfers are only forgotten once they've completed
+            None => true,
+            Some(transfer) => unsafe { self.logical_device.get_fence_status(transfer.fence) }
+                .expect("Device was removed whilst checking the status of a transfer"),
+        }
+    }
+
+    /// Blocks until the transfer that writes a resource has completed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The `TransferHandle` returned when the transfer was submitted
+    pub fn wait_for_resource(&self, handle: TransferHandle) {
+        if let Some(transfer) = self.pending_transfers.get(&handle) {
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[transfer.fence], true, u64::MAX)
+            }
+            .expect("Device was removed whilst waiting for a transfer to complete");
+        }
+    }
+
+    /// Frees the command buffers and fences of any transfers which have completed
+    fn collect_finished_transfers(&mut self) {
+        let finished: Vec<TransferHandle> = self
+            .pending_transfers
+            .keys()
+            .filter(|handle| self.is_resource_ready(**handle))
+            .copied()
+            .collect();
+
+        for handle in finished {
+            let transfer = self.pending_transfers.remove(&handle).unwrap();
+            destroy_pending_transfer(&self.logical_device, self.command_pools.transfer, &transfer);
+        }
+    }
+
+    /// Begins recording a frame and begins the render pass which draws to the swapchain, binding the given pipeline.
+    /// This is equivalent to calling [`Device::begin_frame()`] followed by [`Device::begin_surface_render_pass()`]
+    ///
+    /// If no swapchain image could be acquired, returns `None` and the frame should be skipped
+    pub fn begin_graphics_render_pass(
+        &self,
+        current_frame: usize,
+        surface: &mut Surface,
+        pipeline: PipelineHandle,
+    ) -> Option<u32> {
+        let image_index = self.begin_frame(current_frame, surface)?;
+        self.begin_surface_render_pass(current_frame, surface, image_index, pipeline);
+        Some(image_index)
+    }
+
+    /// Waits for the frame's previous submission to finish, acquires the next swapchain image, and begins the frame's command buffer.
+    /// Anything recorded before [`Device::begin_surface_render_pass()`] (such as a [`RenderGraph`]) runs before the swapchain is drawn to
+    ///
+    /// If no swapchain image could be acquired, returns `None` and the frame should be skipped
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `surface`: The `Surface` being rendered to
+    pub fn begin_frame(&self, current_frame: usize, surface: &mut Surface) -> Option<u32> {
+        let frame_in_flight = *surface.frame_in_flight.get(current_frame).unwrap();
+
+        unsafe {
+            self.logical_device
+                .wait_for_fences(&[frame_in_flight], true, u64::MAX)
+        }
+        .expect("Device was removed or timed out whilst waiting for a fence");
+
+        // Acquiring only signals the frame's semaphore, so the fence is left for the submission to signal.
+        // It must only be reset once we know the frame will be submitted, otherwise nothing would ever signal it
+        let image_index = surface.acquire_next_image()?;
+
+        self.reset_frame(current_frame, frame_in_flight);
+        Some(image_index)
+    }
+
+    /// Waits for the frame's previous submission to finish and begins the frame's command buffer, without acquiring a swapchain image.
+    /// This is for frames which only draw to render targets (eg. accumulating several frames before presenting one), and must be
+    /// finished with [`Device::end_offscreen_frame()`] and [`Surface::submit_offscreen()`] rather than presenting
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `surface`: The `Surface` whose frames in flight are being used
+    pub fn begin_offscreen_frame(&self, current_frame: usize, surface: &Surface) {
+        let frame_in_flight = *surface.frame_in_flight.get(current_frame).unwrap();
+
+        unsafe {
+            self.logical_device
+                .wait_for_fences(&[frame_in_flight], true, u64::MAX)
+        }
+        .expect("Device was removed or timed out whilst waiting for a fence");
+
+        self.reset_frame(current_frame, frame_in_flight);
+    }
+
+    /// Ends the command buffer of a frame started with [`Device::begin_offscreen_frame()`], once all its render passes have ended
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    pub fn end_offscreen_frame(&self, current_frame: usize) {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end graphics command buffer");
+    }
+
+    /// Resets the frame's fence and command buffer, and begins recording into the command buffer.
+    /// The frame's previous submission must have finished, and the frame must be submitted afterwards so that the fence is signalled again
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `frame_in_flight`: The frame's fence
+    fn reset_frame(&self, current_frame: usize, frame_in_flight: vk::Fence) {
+        let command_buffer = self.command_buffers.graphics.get(current_frame).unwrap();
+
+        #[cfg(debug_assertions)]
+        {
+            assert!(
+                self.recording_frames.borrow_mut().insert(current_frame),
+                "Frame {} was recorded again before it was submitted",
+                current_frame
+            );
+            assert_eq!(
+                unsafe { self.logical_device.get_fence_status(frame_in_flight) },
+                Ok(true),
+                "Frame {} was recorded again before its previous submission finished",
+                current_frame
+            );
+        }
+
+        // The frame's previous submission has finished waiting on these
+        for semaphore in self.retired_compute_semaphores.borrow_mut()[current_frame].drain(..) {
+            unsafe { self.logical_device.destroy_semaphore(semaphore, None) };
+        }
+        self.texture_array_descriptors
+            .as_ref()
+            .unwrap()
+            .reset(current_frame);
+        self.frame_serials.borrow_mut()[current_frame] += 1;
+
+        unsafe { self.logical_device.reset_fences(&[frame_in_flight]) }
+            .expect("Could not reset fence");
+
+        unsafe {
+            self.logical_device
+                .reset_command_buffer(*command_buffer, vk::CommandBufferResetFlags::empty())
+        }
+        .expect("Failed to reset graphics command buffer");
+
+        let command_buffer_info = vk::CommandBufferBeginInfo::builder().build();
+
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(*command_buffer, &command_buffer_info)
+        }
+        .expect("Failed to begin graphics command buffer)");
+    }
+
+    /// Records every pass of a render graph into the frame's command buffer.
+    /// This must be called after [`Device::begin_frame()`] and before [`Device::begin_surface_render_pass()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `graph`: The `RenderGraph` to record
+    /// * `record`: Called once per pass with the pass's name and the command buffer, to record its draw commands
+    pub fn record_render_graph<F>(&self, current_frame: usize, graph: &RenderGraph, record: F)
+    where
+        F: FnMut(&str, vk::CommandBuffer),
+    {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+        graph.record(command_buffer, record);
+    }
+
+    /// Records a layout transition for an image into the frame's command buffer, with access and stage masks derived from the layouts.
+    /// This must be called outside of a render pass, after [`Device::begin_frame()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `image`: The image to transition
+    /// * `subresource_range`: The mip levels and array layers to transition
+    /// * `old_layout`: The layout the image is currently in, or `UNDEFINED` if its contents can be discarded
+    /// * `new_layout`: The layout to move the image to
+    pub fn transition_image_layout(
+        &self,
+        current_frame: usize,
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+        record_image_layout_transition(
+            &self.logical_device,
+            command_buffer,
+            image,
+            subresource_range,
+            old_layout,
+            new_layout,
+        );
+    }
+
+    /// Records a clear of specific mip levels and array layers of a colour image into the frame's command buffer.
+    /// This must be called outside of a render pass, after [`Device::begin_frame()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `image`: The image to clear, which must have been created with `TRANSFER_DST` usage
+    /// * `layout`: The layout the image is in, which must be `TRANSFER_DST_OPTIMAL` or `GENERAL`
+    /// * `subresource_range`: The mip levels and array layers to clear
+    /// * `color`: The colour to clear to
+    pub fn clear_color_image(
+        &self,
+        current_frame: usize,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        subresource_range: vk::ImageSubresourceRange,
+        color: [f32; 4],
+    ) {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+        record_clear_color_image(
+            &self.logical_device,
+            command_buffer,
+            image,
+            layout,
+            subresource_range,
+            color,
+        );
+    }
+
+    /// Records a blit between regions of images into the frame's command buffer, eg. between the mip levels of a texture or into a
+    /// cubemap face. This must be called outside of a render pass, after [`Device::begin_frame()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `source`: The region to read from, in `TRANSFER_SRC_OPTIMAL` layout
+    /// * `destination`: The region to write to, in `TRANSFER_DST_OPTIMAL` layout
+    /// * `filter`: How to filter the source when it's scaled
+    pub fn blit_image(
+        &self,
+        current_frame: usize,
+        source: ImageRegion,
+        destination: ImageRegion,
+        filter: vk::Filter,
+    ) {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+        record_blit_image(
+            &self.logical_device,
+            command_buffer,
+            source,
+            destination,
+            filter,
+        );
+    }
+
+    /// Begins a render pass which draws to an offscreen render target rather than the swapchain, binding the given pipeline.
+    /// This must be called after [`Device::begin_frame()`] and before [`Device::begin_surface_render_pass()`], and be paired with [`Device::end_render_target_pass()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `target`: The `RenderTarget` to draw to
+    /// * `pipeline`: The pipeline to bind, which must be compatible with the target's format
+    pub fn begin_render_target_pass(
+        &self,
+        current_frame: usize,
+        target: &RenderTarget,
+        pipeline: PipelineHandle,
+    ) {
+        let clear_value = vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: target.clear_color,
+            },
+        };
+        self.begin_offscreen_render_pass(
+            current_frame,
+            target.render_pass,
+            target.framebuffer,
+            target.extent,
+            &[clear_value],
+            pipeline,
+        );
+    }
+
+    /// Ends a render pass begun with [`Device::begin_render_target_pass()`], leaving the target ready to be sampled
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    pub fn end_render_target_pass(&self, current_frame: usize) {
+        self.end_render_pass(current_frame);
+    }
+
+    /// Begins a render pass which draws only depth to an offscreen depth target (eg. a shadow map), binding the given pipeline.
+    /// This must be called after [`Device::begin_frame()`] and before [`Device::begin_surface_render_pass()`], and be paired with
+    /// [`Device::end_depth_target_pass()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `target`: The `DepthTarget` to draw to
+    /// * `pipeline`: The pipeline to bind, which must have been created with the target's format as its depth-only format
+    pub fn begin_depth_target_pass(
+        &self,
+        current_frame: usize,
+        target: &DepthTarget,
+        pipeline: PipelineHandle,
+    ) {
+        let clear_value = vk::ClearValue {
+            depth_stencil: target.clear_value,
+        };
+        self.begin_offscreen_render_pass(
+            current_frame,
+            target.render_pass,
+            target.framebuffer,
+            target.extent,
+            &[clear_value],
+            pipeline,
+        );
+    }
+
+    /// Ends a render pass begun with [`Device::begin_depth_target_pass()`], leaving the target in `DEPTH_STENCIL_READ_ONLY_OPTIMAL`
+    /// so that it's ready to be sampled
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    pub fn end_depth_target_pass(&self, current_frame: usize) {
+        self.end_render_pass(current_frame);
+    }
+
+    /// Begins a render pass which draws to every image of an offscreen multi-render target at once, binding the given pipeline.
+    /// This must be called after [`Device::begin_frame()`] and before [`Device::begin_surface_render_pass()`], and be paired with
+    /// [`Device::end_multi_render_target_pass()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `target`: The `MultiRenderTarget` to draw to
+    /// * `pipeline`: The pipeline to bind, which must have been created with the target's formats as its colour attachment formats
+    pub fn begin_multi_render_target_pass(
+        &self,
+        current_frame: usize,
+        target: &MultiRenderTarget,
+        pipeline: PipelineHandle,
+    ) {
+        let clear_value = vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: target.clear_color,
+            },
+        };
+        let clear_values = vec![clear_value; target.formats().len()];
+        self.begin_offscreen_render_pass(
+            current_frame,
+            target.render_pass,
+            target.framebuffer,
+            target.extent,
+            &clear_values,
+            pipeline,
+        );
+    }
+
+    /// Ends a render pass begun with [`Device::begin_multi_render_target_pass()`], leaving every image ready to be sampled
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    pub fn end_multi_render_target_pass(&self, current_frame: usize) {
+        self.end_render_pass(current_frame);
+    }
+
+    /// Begins a render pass which draws to an offscreen target, binding the given pipeline and setting
+    /// the viewport and scissor to cover the whole target
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `render_pass`: The target's render pass
+    /// * `framebuffer`: The target's framebuffer
+    /// * `extent`: The size of the target
+    /// * `clear_values`: The value each attachment is cleared to, in attachment order
+    /// * `pipeline`: The pipeline to bind
+    fn begin_offscreen_render_pass(
+        &self,
+        current_frame: usize,
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        extent: vk::Extent2D,
+        clear_values: &[vk::ClearValue],
+        pipeline: PipelineHandle,
+    ) {
+        let command_buffer = *self.command_buffers.graphics.get(current_frame).unwrap();
+
+        self.active_passes.borrow_mut()[current_frame] = Some(ActivePass { extent, pipeline });
+        let pipeline = self
+            .get_pipeline(pipeline)
+            .expect("Failed to get graphics pipeline");
+
+        let render_area = vk::Rect2D::builder()
+            .extent(extent)
+            .offset(vk::Offset2D::builder().x(0).y(0).build())
+            .build();
+
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(render_pass)
+            .framebuffer(framebuffer)
+            .clear_values(clear_values)
+            .render_area(render_area)
+            .build();
+
+        let viewport = pipeline.viewport(extent);
+
+        unsafe {
+            self.logical_device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            );
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline,
+            );
+        }
+        self.bind_frame_uniforms(current_frame, command_buffer, pipeline);
+        unsafe {
+            self.logical_device
+                .cmd_set_viewport(command_buffer, 0, &[viewport]);
+            self.logical_device
+                .cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+    }
+
+    /// Begins the render pass which draws to the swapchain image, binding the given pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame`: The index of the frame in flight being recorded
+    /// * `surface`: The `Surface` being rendered to
+    /// * `image_index`: The swapchain image returned by [`Device::begin_frame()`]
+    /// * `pipeline`: The pipeline to bind
+    pub fn begin_surface_render_pass(
+        &self,
+        current_frame: usize,
+        surface: &mut Surface,
+        image_index: u32,
+        pipeline: PipelineHandle,
+    ) {
+        let command_buffer = self.command_buffers.graphics.get(current_frame).unwrap();
+
+        self.active_passes.borrow_mut()[current_frame] = Some(ActivePass {
+            extent: surface.swapchain_parameters.as_ref().unwrap().extent,
+            pipeline,
+        });
+        let pipeline = self
+            .get_pipeline(pipeline)
+            .expect("Failed to get graphics pipeline");
+
+        let framebuffer = *surface.get_framebuffer(image_index as usize);
+        let clear_values = [
+            vk::ClearValue {
+                color: surface.clear_color_value(),
+            },
+            vk::ClearValue {
+                depth_stencil: pipeline.depth_mode.clear_value(),
+            },
+        ];
+
+        let scissor = vk::Rect2D::builder()
+            .extent(surface.swapchain_parameters.as_ref().unwrap().extent)
+            .offset(vk::Offset2D::builder().x(0).y(0).build())
+            .build();
+
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(pipeline.render_pass)
+            .framebuffer(framebuffer)
+            .clear_values(&clear_values)
+            .render_area(scissor)
+            .build();
+
+        unsafe {
+            self.logical_device.cmd_begin_render_pass(
+                *command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            )
+        };
+
+        unsafe {
+            self.logical_device.cmd_bind_pipeline(
+                *command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline,
+            )
+        }
+        self.bind_frame_uniforms(current_frame, *command_buffer, pipeline);
+
+        let viewport = pipeline.viewport(surface.swapchain_parameters.as_ref().unwrap().extent);
+
+        unsafe {
+            self.logical_device
+                .cmd_set_viewport(*command_buffer, 0, &[viewport])
+        };
+        unsafe {
+            self.logical_device
+                .cmd_set_scissor(*command_buffer, 0, &[scissor])
+        };
+    }
+
+    /// Submits the frame's graphics command buffer. The submission also waits for any compute dispatches submitted since the last one
+    /// before the stages which usually consume their results
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight being submitted
+    /// * `signal_semaphores`: Semaphores to signal once the commands have finished
+    /// * `wait_semaphores`: Semaphores to wait on before the commands reach the matching stage in `stage_flags`
+    /// * `stage_flags`: The stage at which each semaphore in `wait_semaphores` is waited on
+    /// * `frame_fence`: The frame's fence, which must have been reset by [`Device::begin_frame()`] and is signalled once the commands have finished
+    pub fn submit_graphics_queue(
         &self,
         frame_index: usize,
         signal_semaphores: &[vk::Semaphore],
         wait_semaphores: &[vk::Semaphore],
         stage_flags: &[vk::PipelineStageFlags],
-        wait_fence: &vk::Fence,
+        frame_fence: &vk::Fence,
     ) {
+        let command_buffers = [*self.command_buffers.graphics.get(frame_index).unwrap()];
+
+        #[cfg(debug_assertions)]
+        {
+            assert!(
+                self.recording_frames.borrow_mut().remove(&frame_index),
+                "Frame {} was submitted without being recorded",
+                frame_index
+            );
+            assert_eq!(
+                unsafe { self.logical_device.get_fence_status(*frame_fence) },
+                Ok(false),
+                "Frame {} was submitted with a fence that's still in use",
+                frame_index
+            );
+        }
+
+        let compute_semaphores: Vec<vk::Semaphore> = self
+            .compute_semaphores
+            .take()
+            .into_iter()
+            .map(|(_, semaphore)| semaphore)
+            .collect();
+        let wait_semaphores: Vec<vk::Semaphore> = wait_semaphores
+            .iter()
+            .chain(compute_semaphores.iter())
+            .copied()
+            .collect();
+        let stage_flags: Vec<vk::PipelineStageFlags> = stage_flags
+            .iter()
+            .copied()
+            .chain(compute_semaphores.iter().map(|_| COMPUTE_CONSUMER_STAGES))
+            .collect();
+        self.retired_compute_semaphores.borrow_mut()[frame_index].extend(compute_semaphores);
+
         let submit_info = vk::SubmitInfo::builder()
-            .command_buffers(&[*self.command_buffers.graphics.get(frame_index).unwrap()])
+            .command_buffers(&command_buffers)
             .signal_semaphores(signal_semaphores)
-            .wait_semaphores(wait_semaphores)
-            .wait_dst_stage_mask(stage_flags)
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&stage_flags)
             .build();
 
-        // FIXME - Validation error `VUID-vkQueueSubmit-fence-00064` (fence is already in use by another submission)
         unsafe {
-            self.logical_device.queue_submit(
-                *self.queue_families.graphics.first().unwrap(),
-                &[submit_info],
-                *wait_fence,
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.graphics.first().unwrap()),
+                &[submit_info],
+                *frame_fence,
+            )
+        }
+        .expect("Failed to submit graphics queue");
+    }
+
+    /// Presents a swapchain image on the present queue. This may be a different queue family to the graphics queue, so the present
+    /// must wait on a semaphore signalled by the frame's graphics submission rather than relying on submission order
+    ///
+    /// Returns whether the swapchain is suboptimal, or `Err` if presenting failed (including if the swapchain is out of date)
+    pub fn present_queue(
+        &self,
+        swapchain_ext: &ash::extensions::khr::Swapchain,
+        present_info: &vk::PresentInfoKHR,
+    ) -> ash::prelude::VkResult<bool> {
+        unsafe {
+            swapchain_ext.queue_present(
+                *lock_queue(self.queue_families.present.first().unwrap()),
+                present_info,
+            )
+        }
+    }
+
+    /// Gets the graphics queues created with [`QueueCounts::graphics`], for submitting from several threads. The device submits
+    /// frames to the first, and queues may be shared if the family didn't have enough, so each must be locked whilst it's submitted to
+    pub fn graphics_queues(&self) -> &[SharedQueue] {
+        &self.queue_families.graphics
+    }
+
+    /// Gets the compute queues created with [`QueueCounts::compute`], for submitting from several threads. The device dispatches to
+    /// the first, and queues may be shared if the family didn't have enough, so each must be locked whilst it's submitted to
+    pub fn compute_queues(&self) -> &[SharedQueue] {
+        &self.queue_families.compute
+    }
+
+    /// Blocks until every presentation request submitted to the present queue has been processed
+    pub fn wait_for_present_queue_idle(&self) {
+        unsafe {
+            self.logical_device
+                .queue_wait_idle(*lock_queue(self.queue_families.present.first().unwrap()))
+        }
+        .expect("Device was removed whilst waiting for the present queue");
+    }
+
+    /// Binds a vertex buffer for the draws that follow, for pipelines which read their vertices from binding 0
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `buffer`: A buffer created with `VERTEX_BUFFER` usage
+    pub fn bind_vertex_buffer(&self, current_frame_index: usize, buffer: &Buffer) {
+        self.bind_vertex_buffer_at(current_frame_index, buffer, 0);
+    }
+
+    /// Binds part of a vertex buffer for the draws that follow, so that their first vertex is read from an offset into it. This
+    /// lets several draws in a frame read from different parts of the same buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `buffer`: A buffer created with `VERTEX_BUFFER` usage
+    /// * `offset`: The offset of the first vertex in bytes
+    pub fn bind_vertex_buffer_at(&self, current_frame_index: usize, buffer: &Buffer, offset: u64) {
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[buffer.buffer],
+                &[offset],
+            )
+        };
+    }
+
+    /// Binds an index buffer of `u32` indices for the indexed draws that follow
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `buffer`: A buffer created with `INDEX_BUFFER` usage, eg. by [`Device::create_index_buffer()`]
+    pub fn bind_index_buffer(&self, current_frame_index: usize, buffer: &Buffer) {
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device.cmd_bind_index_buffer(
+                command_buffer,
+                buffer.buffer,
+                0,
+                vk::IndexType::UINT32,
+            )
+        };
+    }
+
+    /// Records arbitrary commands into the frame's graphics command buffer, for custom passes that need more than
+    /// [`Device::draw_vertices()`]. This must be called whilst a render pass is active, eg. between
+    /// [`Device::begin_render_target_pass()`] and [`Device::end_render_target_pass()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `record`: A function which records commands with the `CommandRecorder` it's given
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Buffer, Device, PipelineHandle};
+    /// # fn example(
+    /// #     device: &Device,
+    /// #     current_frame_index: usize,
+    /// #     terrain_pipeline: PipelineHandle,
+    /// #     terrain_vertices: Buffer,
+    /// #     terrain_vertex_count: u32,
+    /// #     water_pipeline: PipelineHandle,
+    /// # ) -> Result<(), &'static str> {
+    /// device.record_graphics(current_frame_index, |recorder| {
+    ///     recorder.bind_pipeline(terrain_pipeline)?;
+    ///     recorder.bind_vertex_buffer(&terrain_vertices);
+    ///     recorder.draw(terrain_vertex_count, 1, 0, 0);
+    ///     recorder.bind_pipeline(water_pipeline)?;
+    ///     recorder.draw(6, 1, 0, 0);
+    ///     Ok::<(), &'static str>(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_graphics<F, R>(&self, current_frame_index: usize, record: F) -> R
+    where
+        F: FnOnce(&mut CommandRecorder) -> R,
+    {
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        let mut recorder = CommandRecorder::new(
+            &self.logical_device,
+            command_buffer,
+            &self.pipelines,
+            self.enabled_features,
+            self.limits.line_width_range,
+        );
+        record(&mut recorder)
+    }
+
+    /// Clears a region of the current render pass's colour attachment, without ending the pass.
+    /// This is separate from the clear at the start of the pass, and is useful for split-screen viewports or partial redraws
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `rect`: The region of the attachment to clear
+    /// * `color`: The colour to clear the region to
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device, current_frame_index: usize) {
+    /// use ash::vk;
+    ///
+    /// // Clear the right half of a 1280x720 framebuffer to black
+    /// let rect = vk::Rect2D {
+    ///     offset: vk::Offset2D { x: 640, y: 0 },
+    ///     extent: vk::Extent2D { width: 640, height: 720 },
+    /// };
+    /// device.clear_attachment(current_frame_index, rect, [0.0, 0.0, 0.0, 1.0]);
+    /// # }
+    /// ```
+    pub fn clear_attachment(&self, current_frame_index: usize, rect: vk::Rect2D, color: [f32; 4]) {
+        self.record_graphics(current_frame_index, |recorder| {
+            recorder.clear_attachment(rect, color)
+        });
+    }
+
+    /// Sets the depth bias for the draws that follow, for pipelines created with the `DepthBias` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `depth_bias`: The depth bias to apply. A clamp other than 0 is ignored unless `depth_bias_clamp` is enabled
+    pub fn set_depth_bias(&self, current_frame_index: usize, depth_bias: DepthBias) {
+        self.record_graphics(current_frame_index, |recorder| {
+            recorder.set_depth_bias(depth_bias)
+        });
+    }
+
+    /// Sets the width of lines for the draws that follow, for pipelines created with the `LineWidth` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `line_width`: The width in pixels, which is clamped to the range the device supports. Widths other than 1 are ignored
+    ///   unless `wide_lines` was requested when creating the device
+    pub fn set_line_width(&self, current_frame_index: usize, line_width: f32) {
+        self.record_graphics(current_frame_index, |recorder| {
+            recorder.set_line_width(line_width)
+        });
+    }
+
+    /// Sets the constant colour used by blend factors such as `CONSTANT_COLOR` for the draws that follow, for pipelines created with
+    /// the `BlendConstants` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `blend_constants`: The red, green, blue, and alpha components of the constant colour
+    pub fn set_blend_constants(&self, current_frame_index: usize, blend_constants: [f32; 4]) {
+        self.record_graphics(current_frame_index, |recorder| {
+            recorder.set_blend_constants(blend_constants)
+        });
+    }
+
+    /// Sets the value that stencil tests compare against for the draws that follow, for pipelines created with the
+    /// `StencilReference` dynamic state
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `face_mask`: Whether to set the reference for front faces, back faces, or both
+    /// * `reference`: The stencil reference value
+    pub fn set_stencil_reference(
+        &self,
+        current_frame_index: usize,
+        face_mask: vk::StencilFaceFlags,
+        reference: u32,
+    ) {
+        self.record_graphics(current_frame_index, |recorder| {
+            recorder.set_stencil_reference(face_mask, reference)
+        });
+    }
+
+    /// Binds a different graphics pipeline within the render pass being recorded, so that the draws which follow use its shaders
+    /// (eg. to draw UI over world geometry in the same pass). The viewport is reset to cover the pass, and the frame's uniforms are
+    /// bound if the pipeline uses them
+    ///
+    /// If no pipeline has the given name, the frame isn't inside a render pass, or the pipeline's render pass isn't compatible with
+    /// the one being recorded, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `name`: The name the pipeline was created with
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, PipelineHandle, Surface};
+    /// # fn example(
+    /// #     device: &mut Device,
+    /// #     mut surface: Surface,
+    /// #     current_frame_index: usize,
+    /// #     image_index: u32,
+    /// #     world_pipeline: PipelineHandle,
+    /// #     terrain_index_count: u32,
+    /// #     ui_vertex_count: u32,
+    /// # ) -> Result<(), &'static str> {
+    /// device.begin_surface_render_pass(current_frame_index, &mut surface, image_index, world_pipeline);
+    /// device.draw_indexed(current_frame_index, terrain_index_count);
+    /// device.bind_pipeline(current_frame_index, "ui")?;
+    /// device.draw_vertices(current_frame_index, ui_vertex_count);
+    /// device.end_surface_render_pass(current_frame_index);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_pipeline(
+        &self,
+        current_frame_index: usize,
+        name: &str,
+    ) -> Result<(), &'static str> {
+        let handle = self
+            .find_pipeline(name)
+            .ok_or("No pipeline exists with the given name")?;
+        let active_pass = self.active_passes.borrow()[current_frame_index]
+            .ok_or("A render pass must be begun before binding a pipeline")?;
+
+        let pipeline = self.get_pipeline(handle).unwrap();
+        let bound_pipeline = self
+            .get_pipeline(active_pass.pipeline)
+            .ok_or("The pipeline the render pass was begun with has been destroyed")?;
+        if !pipeline.is_render_pass_compatible(bound_pipeline) {
+            return Err(
+                "The pipeline's render pass isn't compatible with the render pass being recorded",
+            );
+        }
+
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline,
+            );
+            self.logical_device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[pipeline.viewport(active_pass.extent)],
+            );
+        }
+        self.bind_frame_uniforms(current_frame_index, command_buffer, pipeline);
+
+        self.active_passes.borrow_mut()[current_frame_index] = Some(ActivePass {
+            pipeline: handle,
+            ..active_pass
+        });
+        Ok(())
+    }
+
+    /// Writes push constants for the draws that follow, using the ranges of the pipeline currently bound in the render pass. The bytes
+    /// are written from offset 0, and are given to every stage whose range they overlap
+    ///
+    /// If the frame isn't inside a render pass, the bytes aren't a multiple of 4, there are more bytes than `maxPushConstantsSize`, or
+    /// the bytes go beyond the pipeline's push constant ranges, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `bytes`: The push constant data
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # struct Model {
+    /// #     transform: common::math::Mat4,
+    /// #     index_count: u32,
+    /// # }
+    /// # fn example(device: &mut Device, current_frame_index: usize, models: Vec<Model>) -> Result<(), &'static str> {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::PipelineParameters;
+    /// use common::math::Mat4;
+    ///
+    /// let parameters = PipelineParameters {
+    ///     push_constant_ranges: vec![vk::PushConstantRange { stage_flags: vk::ShaderStageFlags::VERTEX, offset: 0, size: 64 }],
+    ///     ..Default::default()
+    /// };
+    /// // ...
+    /// for model in &models {
+    ///     let matrix = model.transform.to_cols_array();
+    ///     let bytes = unsafe { std::slice::from_raw_parts(matrix.as_ptr() as *const u8, std::mem::size_of_val(&matrix)) };
+    ///     device.push_constants(current_frame_index, bytes)?;
+    ///     device.draw_indexed(current_frame_index, model.index_count);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push_constants(
+        &self,
+        current_frame_index: usize,
+        bytes: &[u8],
+    ) -> Result<(), &'static str> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err("Push constants must be a multiple of 4 bytes");
+        }
+        if bytes.len() as u64 > self.limits.max_push_constants_size as u64 {
+            return Err("The push constants are larger than the device's maxPushConstantsSize");
+        }
+
+        let pipeline = self
+            .bound_pipeline(current_frame_index)
+            .ok_or("A render pass must be begun before pushing constants")?;
+        let pipeline = self
+            .get_pipeline(pipeline)
+            .ok_or("The bound pipeline has been destroyed")?;
+
+        let size = bytes.len() as u32;
+        let overlapping_ranges = pipeline
+            .push_constant_ranges()
+            .iter()
+            .filter(|range| range.offset < size);
+        let stage_flags = overlapping_ranges
+            .clone()
+            .fold(vk::ShaderStageFlags::empty(), |stage_flags, range| {
+                stage_flags | range.stage_flags
+            });
+        let covered_size = overlapping_ranges
+            .map(|range| range.offset + range.size)
+            .max()
+            .unwrap_or(0);
+        if covered_size < size {
+            return Err(
+                "The push constants are larger than the bound pipeline's push constant ranges",
+            );
+        }
+
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device.cmd_push_constants(
+                command_buffer,
+                pipeline.layout,
+                stage_flags,
+                0,
+                bytes,
             )
-        }
-        .expect("Failed to submit graphics queue");
+        };
+        Ok(())
     }
 
-    pub fn present_queue(
-        &self,
-        swapchain_ext: &ash::extensions::khr::Swapchain,
-        present_info: &vk::PresentInfoKHR,
-    ) {
-        unsafe {
-            swapchain_ext.queue_present(*self.queue_families.present.first().unwrap(), present_info)
-        }
-        .expect("Failed to present graphics queue");
+    /// Gets the pipeline bound in the render pass being recorded, or `None` if the frame isn't inside a render pass
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    pub fn bound_pipeline(&self, current_frame_index: usize) -> Option<PipelineHandle> {
+        self.active_passes.borrow()[current_frame_index].map(|active_pass| active_pass.pipeline)
+    }
+
+    /// Gets a number which changes each time a frame in flight begins again, after its previous submission has finished. Anything
+    /// written for the frame's previous submission can be reused once this changes, eg. to append to a per-frame buffer over several
+    /// draws and start again from the beginning in the next frame
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    pub fn frame_serial(&self, current_frame_index: usize) -> u64 {
+        self.frame_serials.borrow()[current_frame_index]
     }
 
+    /// Draws from the bound vertex buffer using the pipeline currently bound in the render pass
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `vertex_count`: The number of vertices to draw
     pub fn draw_vertices(&mut self, current_frame_index: usize, vertex_count: u32) {
+        assert!(
+            self.bound_pipeline(current_frame_index).is_some(),
+            "A render pass must be begun before drawing"
+        );
         let command_buffer = *self
             .command_buffers
             .graphics
@@ -413,15 +3809,597 @@ impl Device {
         };
     }
 
-    pub fn end_graphics_render_pass(&mut self, current_frame_index: usize) {
-        let command_buffer = *self
-            .command_buffers
-            .graphics
-            .get(current_frame_index)
-            .unwrap();
-        unsafe { self.logical_device.cmd_end_render_pass(command_buffer) };
-        unsafe { self.logical_device.end_command_buffer(command_buffer) }
-            .expect("Failed to end graphics command buffer")
+    /// Draws from the bound vertex buffer using the bound index buffer, so that vertices shared between triangles only need to be
+    /// stored once. An index buffer must have been bound with [`Device::bind_index_buffer()`], and the draw uses the pipeline currently
+    /// bound in the render pass
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `index_count`: The number of indices to draw
+    pub fn draw_indexed(&mut self, current_frame_index: usize, index_count: u32) {
+        assert!(
+            self.bound_pipeline(current_frame_index).is_some(),
+            "A render pass must be begun before drawing"
+        );
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe {
+            self.logical_device
+                .cmd_draw_indexed(command_buffer, index_count, 1, 0, 0, 0)
+        };
+    }
+
+    /// Ends the swapchain render pass and the frame's command buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    pub fn end_graphics_render_pass(&mut self, current_frame_index: usize) {
+        self.end_surface_render_pass(current_frame_index);
+        self.end_frame(current_frame_index);
+    }
+
+    /// Ends the swapchain render pass, leaving the frame's command buffer open so that more work (such as a post-process) can be
+    /// recorded before [`Device::end_frame()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    pub fn end_surface_render_pass(&self, current_frame_index: usize) {
+        self.end_render_pass(current_frame_index);
+    }
+
+    /// Ends the render pass being recorded, whichever kind of target it draws to
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    fn end_render_pass(&self, current_frame_index: usize) {
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe { self.logical_device.cmd_end_render_pass(command_buffer) };
+        self.active_passes.borrow_mut()[current_frame_index] = None;
+    }
+
+    /// Ends the frame's command buffer, once all its render passes have ended, so that it's ready to be submitted
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    pub fn end_frame(&self, current_frame_index: usize) {
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end graphics command buffer")
+    }
+
+    /// Creates a post-process which runs a compute shader over the swapchain image before it's presented
+    ///
+    /// If the shader file doesn't exist or isn't valid SPIR-V, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `shader_path`: A `Path` which references a compiled SPIR-V compute shader, relative to the asset root
+    pub fn create_post_process(
+        &self,
+        shader_path: &std::path::Path,
+    ) -> Result<PostProcess, &'static str> {
+        PostProcess::new(self, shader_path)
+    }
+
+    /// Records a post-process over the swapchain image. This must be called after [`Device::end_surface_render_pass()`] and before
+    /// [`Device::end_frame()`]
+    ///
+    /// If the swapchain images can't be used as storage images, returns `Err` and nothing is recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `surface`: The `Surface` being rendered to
+    /// * `image_index`: The index of the swapchain image that was drawn to
+    /// * `post_process`: The post-process to run
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(device: &Device, mut surface: Surface, current_frame_index: usize, image_index: u32) -> Result<(), &'static str> {
+    /// use std::path::Path;
+    ///
+    /// surface.set_storage_usage(true);
+    /// let grayscale = device.create_post_process(Path::new("res/shaders/grayscale.comp.spv"))?;
+    ///
+    /// // Whilst recording a frame
+    /// device.end_surface_render_pass(current_frame_index);
+    /// device.dispatch_post_process(current_frame_index, &surface, image_index, &grayscale)?;
+    /// device.end_frame(current_frame_index);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dispatch_post_process(
+        &self,
+        current_frame_index: usize,
+        surface: &Surface,
+        image_index: u32,
+        post_process: &PostProcess,
+    ) -> Result<(), &'static str> {
+        if !surface.is_storage_usage_enabled() {
+            return Err("The swapchain images can't be used as storage images");
+        }
+
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        let (image, view) = surface.swapchain_image(image_index);
+        post_process.record(
+            &self.logical_device,
+            command_buffer,
+            current_frame_index,
+            image,
+            view,
+            surface.swapchain_parameters.as_ref().unwrap().extent,
+        );
+        Ok(())
+    }
+
+    /// Records a copy of a swapchain image into a new host-visible buffer, so that the frame can be read back once it's finished
+    /// (eg. for visual regression tests). This must be called after the frame has been drawn and post-processed, and before
+    /// [`Device::end_frame()`]. The buffer holds the texels tightly packed in rows from the top, in the swapchain's format, and must
+    /// be kept alive until the frame has finished
+    ///
+    /// If the swapchain images can't be copied from or their format can't be read back, returns `Err` and nothing is recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `current_frame_index`: The index of the frame being recorded
+    /// * `surface`: The `Surface` being rendered to
+    /// * `image_index`: The index of the swapchain image that was drawn to
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, Surface};
+    /// # fn example(device: &Device, mut surface: Surface, current_frame_index: usize, image_index: u32) -> Result<(), &'static str> {
+    /// // Whilst recording a frame
+    /// device.end_surface_render_pass(current_frame_index);
+    /// let readback = device.record_frame_readback(current_frame_index, &surface, image_index)?;
+    /// device.end_frame(current_frame_index);
+    /// surface.flip_buffers(image_index, None);
+    ///
+    /// // Once the frame has finished
+    /// let texels = readback.mapped_slice().unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_frame_readback(
+        &self,
+        current_frame_index: usize,
+        surface: &Surface,
+        image_index: u32,
+    ) -> Result<Buffer, &'static str> {
+        if !surface.is_transfer_source_enabled() {
+            return Err("The swapchain images can't be copied from");
+        }
+        let swapchain_parameters = surface.swapchain_parameters.as_ref().unwrap();
+        let extent = swapchain_parameters.extent;
+        let texel_size = readback_texel_size(swapchain_parameters.surface_format.format)
+            .ok_or("Frames can't be read back in the swapchain's format")?;
+
+        let readback_create_info = vk::BufferCreateInfo::builder()
+            .size(extent.width as u64 * extent.height as u64 * texel_size)
+            .usage(vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let readback_buffer = self.allocate_buffer(
+            &readback_create_info,
+            MemoryLocation::GpuToCpu,
+            "frame readback",
+        );
+
+        let command_buffer = *self
+            .command_buffers
+            .graphics
+            .get(current_frame_index)
+            .unwrap();
+        let (image, _view) = surface.swapchain_image(image_index);
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+
+        // The image is already in `PRESENT_SRC_KHR`, but the render pass and post-process writes still have to be made visible to
+        // the copy, which a transition from that layout alone wouldn't do
+        let to_transfer_source = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_WRITE,
+            )
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .build();
+        let copy_region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build(),
+            )
+            .image_extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .build();
+
+        unsafe {
+            self.logical_device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_source],
+            );
+            self.logical_device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback_buffer.buffer,
+                &[copy_region],
+            );
+        }
+        record_image_layout_transition(
+            &self.logical_device,
+            command_buffer,
+            image,
+            subresource_range,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        );
+
+        Ok(readback_buffer)
+    }
+
+    /// Constructs a new compute pipeline on the device, referencable by the name provided
+    ///
+    /// If the device already has a compute pipeline with the given name or the shader file doesn't exist, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name that the pipeline should be referencable as later
+    /// * `shader_path`: A `Path` which references a compiled SPIR-V compute shader, relative to the asset root
+    /// * `storage_buffer_count`: The number of storage buffers the shader binds at set 0, starting from binding 0
+    pub fn create_compute_pipeline(
+        &mut self,
+        name: String,
+        shader_path: &std::path::Path,
+        storage_buffer_count: u32,
+    ) -> Result<(), &'static str> {
+        if self.compute_pipelines.contains_key(&name) {
+            return Err("A compute pipeline with the specified name already exists");
+        }
+
+        let pipeline = ComputePipeline::new(self, shader_path, storage_buffer_count)?;
+        self.compute_pipelines.insert(name, pipeline);
+        Ok(())
+    }
+
+    /// Submits a compute dispatch to the compute queue, binding the given buffers as the shader's storage buffers in order.
+    ///
+    /// The next frame submitted waits for the dispatch to finish before reading its results, and dispatches run in the order they're
+    /// submitted, so no further synchronisation is needed to use the results on the GPU. To read them on the CPU, wait on the
+    /// returned `ComputeHandle` with [`Device::wait_for_compute()`]. Buffers which frames still in flight read from shouldn't be
+    /// written by a dispatch
+    ///
+    /// If no compute pipeline has the given name, a buffer has been destroyed, or the number of buffers doesn't match the pipeline,
+    /// returns `Err` and nothing is submitted
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline_name`: The name the compute pipeline was created with
+    /// * `groups`: The number of workgroups to dispatch in each dimension
+    /// * `buffers`: The storage buffers to bind, which must be shared with the compute queue (as those from
+    ///   [`Device::create_buffer_with_data()`] are)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use ash::vk;
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &mut Device, particle_bytes: Vec<u8>, particle_count: u32) -> Result<(), &'static str> {
+    /// use std::path::Path;
+    ///
+    /// device.create_compute_pipeline(String::from("particles"), Path::new("res/shaders/particles.comp.spv"), 1)?;
+    /// let (particles, _upload) = device.create_buffer_with_data(
+    ///     &particle_bytes,
+    ///     vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+    ///     "particles",
+    /// );
+    /// let particles = device.insert_buffer(particles);
+    ///
+    /// // Once per frame, before drawing the particles
+    /// device.dispatch_compute("particles", [particle_count.div_ceil(64), 1, 1], &[particles])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dispatch_compute(
+        &mut self,
+        pipeline_name: &str,
+        groups: [u32; 3],
+        buffers: &[BufferHandle],
+    ) -> Result<ComputeHandle, &'static str> {
+        self.collect_finished_computes();
+
+        let pipeline = self
+            .compute_pipelines
+            .get(pipeline_name)
+            .ok_or("No compute pipeline exists with the specified name")?;
+        if buffers.len() != pipeline.storage_buffer_count() as usize {
+            return Err("The number of buffers doesn't match the compute pipeline's bindings");
+        }
+        let buffer_infos = buffers
+            .iter()
+            .map(|handle| {
+                self.buffers.get(*handle).map(|buffer| {
+                    vk::DescriptorBufferInfo::builder()
+                        .buffer(buffer.buffer)
+                        .offset(0)
+                        .range(vk::WHOLE_SIZE)
+                        .build()
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("A buffer has already been destroyed")?;
+
+        // Each dispatch gets its own pool, so that its set lives exactly as long as the dispatch
+        let (descriptor_pool, descriptor_set) = if buffer_infos.is_empty() {
+            (vk::DescriptorPool::null(), None)
+        } else {
+            let pool_sizes = [vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(buffer_infos.len() as u32)
+                .build()];
+            let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+                .max_sets(1)
+                .pool_sizes(&pool_sizes)
+                .build();
+            let descriptor_pool = unsafe {
+                self.logical_device
+                    .create_descriptor_pool(&descriptor_pool_create_info, None)
+            }
+            .expect("Failed to create compute descriptor pool");
+
+            let set_layouts = [pipeline.descriptor_set_layout];
+            let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&set_layouts)
+                .build();
+            let descriptor_set = *unsafe {
+                self.logical_device
+                    .allocate_descriptor_sets(&descriptor_set_allocate_info)
+            }
+            .expect("Failed to allocate compute descriptor set")
+            .first()
+            .unwrap();
+
+            let descriptor_writes: Vec<vk::WriteDescriptorSet> = buffer_infos
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer_info)| {
+                    vk::WriteDescriptorSet::builder()
+                        .dst_set(descriptor_set)
+                        .dst_binding(binding as u32)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .buffer_info(std::slice::from_ref(buffer_info))
+                        .build()
+                })
+                .collect();
+            unsafe {
+                self.logical_device
+                    .update_descriptor_sets(&descriptor_writes, &[])
+            };
+            (descriptor_pool, Some(descriptor_set))
+        };
+
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(1)
+            .command_pool(self.command_pools.compute)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .build();
+        let command_buffer =
+            *unsafe { self.logical_device.allocate_command_buffers(&allocate_info) }
+                .expect("Failed to allocate compute command buffer")
+                .first()
+                .unwrap();
+        track_created(ResourceKind::CommandBuffer, 1);
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &begin_info)
+        }
+        .expect("Failed to begin compute command buffer");
+
+        // Earlier dispatches' writes must land before this one reads or overwrites them
+        let memory_barrier = vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+            .build();
+        unsafe {
+            self.logical_device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.pipeline,
+            );
+            if let Some(descriptor_set) = descriptor_set {
+                self.logical_device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    pipeline.pipeline_layout,
+                    0,
+                    &[descriptor_set],
+                    &[],
+                );
+            }
+            self.logical_device
+                .cmd_dispatch(command_buffer, groups[0], groups[1], groups[2]);
+        }
+
+        unsafe { self.logical_device.end_command_buffer(command_buffer) }
+            .expect("Failed to end compute command buffer");
+
+        let fence = unsafe {
+            self.logical_device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        }
+        .expect("Failed to create fence for compute dispatch");
+        let semaphore = unsafe {
+            self.logical_device
+                .create_semaphore(&vk::SemaphoreCreateInfo::default(), None)
+        }
+        .expect("Failed to create semaphore for compute dispatch");
+
+        let command_buffers = [command_buffer];
+        let signal_semaphores = [semaphore];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores)
+            .build();
+        unsafe {
+            self.logical_device.queue_submit(
+                *lock_queue(self.queue_families.compute.first().unwrap()),
+                &[submit_info],
+                fence,
+            )
+        }
+        .expect("Failed to submit compute queue");
+
+        debug!(
+            "Dispatched {:?} workgroups of compute pipeline {}",
+            groups, pipeline_name
+        );
+
+        let handle = ComputeHandle(self.next_compute_id);
+        self.next_compute_id += 1;
+        self.compute_semaphores
+            .borrow_mut()
+            .push((handle, semaphore));
+        self.pending_computes.insert(
+            handle,
+            PendingCompute {
+                command_buffer,
+                fence,
+                descriptor_pool,
+            },
+        );
+
+        Ok(handle)
+    }
+
+    /// Checks whether a compute dispatch has completed, without blocking
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The `ComputeHandle` returned when the dispatch was submitted
+    pub fn is_compute_finished(&self, handle: ComputeHandle) -> bool {
+        match self.pending_computes.get(&handle) {
+            // Dispatches are only forgotten once they've completed
+            None => true,
+            Some(compute) => unsafe { self.logical_device.get_fence_status(compute.fence) }
+                .expect("Device was removed whilst checking the status of a compute dispatch"),
+        }
+    }
+
+    /// Blocks until a compute dispatch has completed, eg. before reading its results on the CPU
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The `ComputeHandle` returned when the dispatch was submitted
+    pub fn wait_for_compute(&self, handle: ComputeHandle) {
+        if let Some(compute) = self.pending_computes.get(&handle) {
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[compute.fence], true, u64::MAX)
+            }
+            .expect("Device was removed whilst waiting for a compute dispatch to complete");
+        }
+    }
+
+    /// Frees the command buffers, fences, and descriptor pools of any compute dispatches which have completed, along with the
+    /// semaphores of those which no graphics submission has waited on
+    fn collect_finished_computes(&mut self) {
+        let finished: Vec<ComputeHandle> = self
+            .pending_computes
+            .keys()
+            .filter(|handle| self.is_compute_finished(**handle))
+            .copied()
+            .collect();
+
+        for handle in finished {
+            let compute = self.pending_computes.remove(&handle).unwrap();
+            destroy_pending_compute(&self.logical_device, self.command_pools.compute, &compute);
+        }
+
+        // The dispatch has already finished, so there's nothing left for the next frame to wait on
+        self.compute_semaphores
+            .borrow_mut()
+            .retain(|(handle, semaphore)| {
+                let is_finished = !self.pending_computes.contains_key(handle);
+                if is_finished {
+                    unsafe { self.logical_device.destroy_semaphore(*semaphore, None) };
+                }
+                !is_finished
+            });
+    }
+
+    /// Checks whether images with the given format can be used as storage images
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: The image format to check
+    pub(crate) fn supports_storage_image(&self, format: vk::Format) -> bool {
+        let format_properties = unsafe {
+            self.instance
+                .get_physical_device_format_properties(self.physical_device, format)
+        };
+        format_properties
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
     }
 }
 
@@ -430,6 +4408,35 @@ impl Drop for Device {
         let span = debug_span!("Vulkan/~Device");
         let _guard = span.enter();
 
+        for transfer in self.pending_transfers.values() {
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[transfer.fence], true, u64::MAX)
+            }
+            .expect("Device was removed whilst waiting for a transfer to complete");
+            destroy_pending_transfer(&self.logical_device, self.command_pools.transfer, transfer);
+        }
+        self.pending_transfers.clear();
+
+        for compute in self.pending_computes.values() {
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[compute.fence], true, u64::MAX)
+            }
+            .expect("Device was removed whilst waiting for a compute dispatch to complete");
+            destroy_pending_compute(&self.logical_device, self.command_pools.compute, compute);
+        }
+        self.pending_computes.clear();
+        let compute_semaphores = self
+            .compute_semaphores
+            .take()
+            .into_iter()
+            .map(|(_, semaphore)| semaphore)
+            .chain(self.retired_compute_semaphores.take().into_iter().flatten());
+        for semaphore in compute_semaphores {
+            unsafe { self.logical_device.destroy_semaphore(semaphore, None) };
+        }
+
         unsafe {
             self.logical_device.free_command_buffers(
                 self.command_pools.graphics,
@@ -454,25 +4461,55 @@ impl Drop for Device {
                 self.command_buffers.compute.as_slice(),
             )
         };
+        track_destroyed(
+            ResourceKind::CommandBuffer,
+            self.command_buffers.graphics.len()
+                + self.command_buffers.present.len()
+                + self.command_buffers.transfer.len()
+                + self.command_buffers.compute.len(),
+        );
 
+        // Kinds of queue in the same family share a pool, which must only be destroyed once
+        let command_pools: HashSet<vk::CommandPool> = [
+            self.command_pools.graphics,
+            self.command_pools.present,
+            self.command_pools.transfer,
+            self.command_pools.compute,
+        ]
+        .into_iter()
+        .collect();
+        for command_pool in command_pools {
+            unsafe { self.logical_device.destroy_command_pool(command_pool, None) };
+        }
+
+        self.pipelines.clear();
+        self.pipeline_names.clear();
+        self.compute_pipelines.clear();
+        self.uniforms = None;
+        self.texture_array_descriptors = None;
+        save_pipeline_cache(&self.logical_device, self.pipeline_cache);
         unsafe {
             self.logical_device
-                .destroy_command_pool(self.command_pools.graphics, None)
-        };
-        unsafe {
-            self.logical_device
-                .destroy_command_pool(self.command_pools.present, None)
-        };
-        unsafe {
-            self.logical_device
-                .destroy_command_pool(self.command_pools.transfer, None)
-        };
-        unsafe {
-            self.logical_device
-                .destroy_command_pool(self.command_pools.compute, None)
+                .destroy_pipeline_cache(self.pipeline_cache, None)
         };
+        self.buffers.clear();
+        self.textures.clear();
 
-        self.pipelines.clear();
+        // The allocator frees its memory blocks when dropped, so it must go before the device does
+        match self.allocator.take().map(Rc::try_unwrap) {
+            Some(Ok(allocator)) => drop(allocator),
+            _ => warn!("GPU memory allocator is still referenced, so some memory will leak"),
+        }
+
+        let resource_stats = live_resources();
+        if resource_stats.total() == 0 {
+            debug!("No Vulkan objects were leaked");
+        } else {
+            warn!(
+                "Vulkan objects still exist whilst destroying the device, so they have leaked ({:?})",
+                resource_stats
+            );
+        }
 
         debug!("Destroying logical device");
         unsafe {
@@ -482,6 +4519,164 @@ impl Drop for Device {
     }
 }
 
+/// Records the barriers for one half of a queue family ownership transfer, which also move images into their new layouts.
+/// If ownership isn't being transferred, only the barriers for images which change layout are recorded
+///
+/// # Arguments
+///
+/// * `device`: The logical device that the command buffer belongs to
+/// * `command_buffer`: A command buffer in the recording state
+/// * `transferred`: The resources being transferred
+/// * `(source_family, destination_family)`: The queue families ownership moves from and to
+/// * `transfers_ownership`: Whether the families differ, so that ownership has to be transferred
+/// * `(source_stage, source_access)`: The stages and accesses which must finish before the barrier
+/// * `(destination_stage, destination_access)`: The stages and accesses which wait for the barrier
+fn record_ownership_barriers(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    transferred: &[TransferredResource],
+    (source_family, destination_family): (u32, u32),
+    transfers_ownership: bool,
+    (source_stage, source_access): (vk::PipelineStageFlags, vk::AccessFlags),
+    (destination_stage, destination_access): (vk::PipelineStageFlags, vk::AccessFlags),
+) {
+    let (source_family, destination_family) = if transfers_ownership {
+        (source_family, destination_family)
+    } else {
+        (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+    };
+
+    let mut buffer_barriers = vec![];
+    let mut image_barriers = vec![];
+    for resource in transferred {
+        match *resource {
+            TransferredResource::Buffer(buffer) if transfers_ownership => buffer_barriers.push(
+                vk::BufferMemoryBarrier::builder()
+                    .src_queue_family_index(source_family)
+                    .dst_queue_family_index(destination_family)
+                    .buffer(buffer)
+                    .offset(0)
+                    .size(vk::WHOLE_SIZE)
+                    .src_access_mask(source_access)
+                    .dst_access_mask(destination_access)
+                    .build(),
+            ),
+            TransferredResource::Image {
+                image,
+                subresource_range,
+                old_layout,
+                new_layout,
+            } if transfers_ownership || old_layout != new_layout => image_barriers.push(
+                vk::ImageMemoryBarrier::builder()
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_queue_family_index(source_family)
+                    .dst_queue_family_index(destination_family)
+                    .image(image)
+                    .subresource_range(subresource_range)
+                    .src_access_mask(source_access)
+                    .dst_access_mask(destination_access)
+                    .build(),
+            ),
+            _ => {}
+        }
+    }
+    if buffer_barriers.is_empty() && image_barriers.is_empty() {
+        return;
+    }
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            source_stage,
+            destination_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &buffer_barriers,
+            &image_barriers,
+        )
+    };
+}
+
+/// Destroys the fence and frees the command buffer belonging to a completed transfer
+///
+/// # Arguments
+///
+/// * `device`: The logical device the transfer was submitted on
+/// * `command_pool`: The transfer command pool the command buffer was allocated from
+/// * `transfer`: The completed transfer
+fn destroy_pending_transfer(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    transfer: &PendingTransfer,
+) {
+    unsafe { device.destroy_fence(transfer.fence, None) };
+    unsafe { device.free_command_buffers(command_pool, &[transfer.command_buffer]) };
+    track_destroyed(ResourceKind::CommandBuffer, 1);
+}
+
+/// Destroys the fence and descriptor pool and frees the command buffer belonging to a completed compute dispatch
+///
+/// # Arguments
+///
+/// * `device`: The logical device the dispatch was submitted on
+/// * `command_pool`: The compute command pool the command buffer was allocated from
+/// * `compute`: The completed dispatch
+fn destroy_pending_compute(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    compute: &PendingCompute,
+) {
+    unsafe { device.destroy_fence(compute.fence, None) };
+    unsafe { device.destroy_descriptor_pool(compute.descriptor_pool, None) };
+    unsafe { device.free_command_buffers(command_pool, &[compute.command_buffer]) };
+    track_destroyed(ResourceKind::CommandBuffer, 1);
+}
+
+/// Gets the bytes an index buffer holds for the given indices, which [`Device::bind_index_buffer()`] binds as `UINT32`
+///
+/// # Arguments
+///
+/// * `indices`: The indices to upload
+fn index_buffer_bytes(indices: &[u32]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(
+            indices.as_ptr() as *const u8,
+            std::mem::size_of_val(indices),
+        )
+    }
+}
+
+/// Locks a queue so that it can be submitted to
+///
+/// # Arguments
+///
+/// * `queue`: The queue to lock
+fn lock_queue(queue: &SharedQueue) -> MutexGuard<'_, vk::Queue> {
+    queue
+        .lock()
+        .expect("A thread panicked whilst submitting to a queue")
+}
+
+/// Sets a buffer to be shared between the given queue families, using exclusive sharing if there's only one
+///
+/// # Arguments
+///
+/// * `create_info`: The description of the buffer
+/// * `queue_family_indices`: The distinct queue families the buffer is used on
+fn share_between_queue_families<'a>(
+    create_info: vk::BufferCreateInfoBuilder<'a>,
+    queue_family_indices: &'a [u32],
+) -> vk::BufferCreateInfoBuilder<'a> {
+    if queue_family_indices.len() > 1 {
+        create_info
+            .sharing_mode(vk::SharingMode::CONCURRENT)
+            .queue_family_indices(queue_family_indices)
+    } else {
+        create_info.sharing_mode(vk::SharingMode::EXCLUSIVE)
+    }
+}
+
 fn create_command_buffers(
     device: &ash::Device,
     command_pools: &DeviceCommandPools,
@@ -518,6 +4713,10 @@ fn create_command_buffers(
     let compute = unsafe { device.allocate_command_buffers(&compute_buffer_allocate_info) }
         .expect("Failed to allocate primary compute command buffer");
 
+    track_created(
+        ResourceKind::CommandBuffer,
+        graphics.len() + present.len() + transfer.len() + compute.len(),
+    );
     DeviceCommandBuffers {
         graphics,
         present,
@@ -526,47 +4725,38 @@ fn create_command_buffers(
     }
 }
 
+/// Creates a command pool for each distinct queue family, so kinds of queue which share a family also share a pool
+///
+/// # Arguments
+///
+/// * `device`: The logical device to create the pools on
+/// * `queue_family_indices`: The family each kind of queue uses
 fn create_command_pools(
     device: &ash::Device,
     queue_family_indices: &DeviceQueueFamilyIndices,
 ) -> DeviceCommandPools {
-    let graphics_queue_pool_create_info = vk::CommandPoolCreateInfo::builder()
-        .queue_family_index(queue_family_indices.graphics.index)
-        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-        .build();
-    let graphics = unsafe { device.create_command_pool(&graphics_queue_pool_create_info, None) }
-        .expect("Failed to create graphics command pool");
-
-    let present_queue_pool_create_info = vk::CommandPoolCreateInfo::builder()
-        .queue_family_index(queue_family_indices.present.index)
-        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-        .build();
-    let present = unsafe { device.create_command_pool(&present_queue_pool_create_info, None) }
-        .expect("Failed to create present command pool");
-
-    let transfer_queue_pool_create_info = vk::CommandPoolCreateInfo::builder()
-        .queue_family_index(queue_family_indices.transfer.index)
-        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-        .build();
-    let transfer = unsafe { device.create_command_pool(&transfer_queue_pool_create_info, None) }
-        .expect("Failed to create transfer command pool");
-
-    let compute_queue_pool_create_info = vk::CommandPoolCreateInfo::builder()
-        .queue_family_index(queue_family_indices.compute.index)
-        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-        .build();
-    let compute = unsafe { device.create_command_pool(&compute_queue_pool_create_info, None) }
-        .expect("Failed to create graphics command pool");
+    let mut command_pools: HashMap<u32, vk::CommandPool> = HashMap::new();
+    let mut get_command_pool = |family: &QueueFamilyInfo| -> vk::CommandPool {
+        *command_pools.entry(family.index).or_insert_with(|| {
+            let command_pool_create_info = vk::CommandPoolCreateInfo::builder()
+                .queue_family_index(family.index)
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                .build();
+            unsafe { device.create_command_pool(&command_pool_create_info, None) }
+                .expect("Failed to create command pool")
+        })
+    };
 
     DeviceCommandPools {
-        graphics,
-        present,
-        transfer,
-        compute,
+        graphics: get_command_pool(&queue_family_indices.graphics),
+        present: get_command_pool(&queue_family_indices.present),
+        transfer: get_command_pool(&queue_family_indices.transfer),
+        compute: get_command_pool(&queue_family_indices.compute),
     }
 }
 
-/// Gets the queues from a logical device, given a list of queue indices
+/// Gets the queues from a logical device, given a list of queue indices. Kinds of queue which use the same queue in the same family
+/// share one `SharedQueue`, so that submissions to it are serialised by a single lock
 ///
 /// # Arguments
 ///
@@ -575,7 +4765,7 @@ fn create_command_pools(
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// use client::renderer::vulkan::Context;
 /// use ash::vk;
 ///
@@ -619,27 +4809,102 @@ fn create_command_pools(
 /// let logical_device = unsafe { context.instance.create_device(*physical_device, &device_create_info, None) }
 ///     .expect("Failed to create a logical device");
 /// ```
-fn create_device_queues(device: &ash::Device, indices: &DeviceQueueFamilyIndices) -> DeviceQueues {
-    // TODO - It's possible that this will retrieve the same queues multiple times.
-    // Whilst I don't think it's necessarily harmful, I can't imagine that calling
-    // free on the same n queues multiple times is something that the API likes
+fn create_device_queues(
+    device: &ash::Device,
+    indices: &DeviceQueueFamilyIndices,
+    queue_indices: &DeviceQueueIndices,
+) -> DeviceQueues {
+    let mut queues: HashMap<(u32, u32), SharedQueue> = HashMap::new();
+    let mut get_queues = |family: &QueueFamilyInfo, queue_indices: &[u32]| -> Vec<SharedQueue> {
+        queue_indices
+            .iter()
+            .map(|queue_index| {
+                queues
+                    .entry((family.index, *queue_index))
+                    .or_insert_with(|| {
+                        Arc::new(Mutex::new(unsafe {
+                            device.get_device_queue(family.index, *queue_index)
+                        }))
+                    })
+                    .clone()
+            })
+            .collect()
+    };
 
     DeviceQueues {
-        graphics: (0..indices.graphics.count)
-            .map(|i| unsafe { device.get_device_queue(indices.graphics.index, i) })
-            .collect(),
-        present: (0..indices.present.count)
-            .map(|i| unsafe { device.get_device_queue(indices.present.index, i) })
-            .collect(),
-        transfer: (0..indices.transfer.count)
-            .map(|i| unsafe { device.get_device_queue(indices.transfer.index, i) })
-            .collect(),
-        compute: (0..indices.compute.count)
-            .map(|i| unsafe { device.get_device_queue(indices.compute.index, i) })
-            .collect(),
+        graphics: get_queues(&indices.graphics, &queue_indices.graphics),
+        present: get_queues(&indices.present, &queue_indices.present),
+        transfer: get_queues(&indices.transfer, &queue_indices.transfer),
+        compute: get_queues(&indices.compute, &queue_indices.compute),
     }
 }
 
+/// Decides how many queues to create in each family and which of them each kind of queue uses, so that only the queues which are
+/// used get created. Each kind of queue gets queues of its own while its family has enough, and otherwise shares the family's
+/// queues from the first. Presentation always shares a queue if its family already has one, as it never needs its own
+///
+/// Returns the priority of each queue to create keyed by family index, and the queue indices each kind of queue uses
+///
+/// # Arguments
+///
+/// * `indices`: The family each kind of queue uses
+/// * `counts`: The number of graphics and compute queues requested
+/// * `priorities`: The priority of each kind of queue, which must be between 0.0 and 1.0
+fn plan_device_queues(
+    indices: &DeviceQueueFamilyIndices,
+    counts: QueueCounts,
+    priorities: QueuePriorities,
+) -> (BTreeMap<u32, Vec<f32>>, DeviceQueueIndices) {
+    let mut priorities_by_family: BTreeMap<u32, Vec<f32>> = BTreeMap::new();
+
+    let graphics = assign_queues(
+        priorities_by_family
+            .entry(indices.graphics.index)
+            .or_default(),
+        indices.graphics.count,
+        counts.graphics,
+        priorities.graphics,
+    );
+    let transfer = assign_queues(
+        priorities_by_family
+            .entry(indices.transfer.index)
+            .or_default(),
+        indices.transfer.count,
+        1,
+        priorities.transfer,
+    );
+    let compute = assign_queues(
+        priorities_by_family
+            .entry(indices.compute.index)
+            .or_default(),
+        indices.compute.count,
+        counts.compute,
+        priorities.compute,
+    );
+    let present = if priorities_by_family.contains_key(&indices.present.index) {
+        vec![0]
+    } else {
+        assign_queues(
+            priorities_by_family
+                .entry(indices.present.index)
+                .or_default(),
+            indices.present.count,
+            1,
+            priorities.present,
+        )
+    };
+
+    (
+        priorities_by_family,
+        DeviceQueueIndices {
+            graphics,
+            present,
+            transfer,
+            compute,
+        },
+    )
+}
+
 /// Gets the indices required to create graphics, transfer, and compute queues.
 /// The function will attempt to get unique queue family indices if possible
 /// (ie. 3 <type>-only queues) but will otherwise fallback to whichever queue family
@@ -651,7 +4916,7 @@ fn create_device_queues(device: &ash::Device, indices: &DeviceQueueFamilyIndices
 /// * `device`: The physical device to create the queues on
 ///
 /// # Examples
-/// ```
+/// ```ignore
 /// use client::renderer::vulkan::Context;
 /// use ash::vk;
 ///
@@ -739,31 +5004,8 @@ fn find_device_queues_indices(
         }
     };
 
-    let transfer_queue = queue_properties
-        .iter()
-        .enumerate()
-        .reduce(|accum, current| {
-            if !current.1.queue_flags.contains(vk::QueueFlags::TRANSFER) {
-                // If current doesn't support transfer, fallback to accum
-                accum
-            } else {
-                let accum_is_mixed = accum.1.queue_flags.contains(vk::QueueFlags::COMPUTE)
-                    || accum.1.queue_flags.contains(vk::QueueFlags::GRAPHICS);
-                let current_is_mixed = current.1.queue_flags.contains(vk::QueueFlags::COMPUTE)
-                    || current.1.queue_flags.contains(vk::QueueFlags::GRAPHICS);
-                let current_has_more_queues = current.1.queue_count >= accum.1.queue_count;
-
-                if !accum_is_mixed && current_is_mixed {
-                    // Accum is a dedicated queue where current is not
-                    accum
-                } else if (accum_is_mixed && !current_is_mixed) || current_has_more_queues {
-                    // Current is a dedicated queue where accum is not
-                    current
-                } else {
-                    accum
-                }
-            }
-        })
+    let transfer_queue = select_transfer_queue_family(&queue_properties)
+        .map(|index| (index, &queue_properties[index]))
         .expect("Failed to find a valid transfer queue");
 
     let compute_queue = queue_properties
@@ -809,6 +5051,324 @@ fn find_device_queues_indices(
     }
 }
 
+/// Assigns queues in a family to one kind of queue, creating new queues whilst the family has any left and then sharing the
+/// family's queues from the first
+///
+/// Returns the indices of the assigned queues within the family
+///
+/// # Arguments
+///
+/// * `created`: The priority of each queue already created in the family, which new queues are added to
+/// * `available`: The number of queues the family has
+/// * `count`: The number of queues wanted, of which at least one is always assigned
+/// * `priority`: The priority of any queues created
+fn assign_queues(created: &mut Vec<f32>, available: u32, count: u32, priority: f32) -> Vec<u32> {
+    if count > available {
+        warn!(
+            "{} queues were requested from a family with only {}, so some will be shared",
+            count, available
+        );
+    }
+
+    (0..count.max(1))
+        .map(|i| {
+            if (created.len() as u32) < available {
+                created.push(priority);
+                created.len() as u32 - 1
+            } else {
+                i % created.len() as u32
+            }
+        })
+        .collect()
+}
+
+/// Picks the best queue family for transfers, or `None` if no family supports them. Families without any queues are skipped
+///
+/// Families are ranked by, in order:
+/// - Being dedicated to transfers (no graphics or compute support), as these usually map to the GPU's copy engines
+/// - Supporting the fewest other capabilities
+/// - Having the most queues
+///
+/// Any remaining tie goes to the family with the lowest index, so the result doesn't depend on how the driver orders equivalent families
+///
+/// # Arguments
+///
+/// * `queue_properties`: The properties of each of the device's queue families, in index order
+fn select_transfer_queue_family(queue_properties: &[vk::QueueFamilyProperties]) -> Option<usize> {
+    queue_properties
+        .iter()
+        .enumerate()
+        .filter(|(_, properties)| {
+            // Graphics and compute families always support transfers, even if they don't report it
+            properties.queue_count > 0
+                && properties.queue_flags.intersects(
+                    vk::QueueFlags::TRANSFER | vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+                )
+        })
+        .max_by_key(|(index, properties)| {
+            let is_dedicated = !properties
+                .queue_flags
+                .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE);
+            let other_capabilities = (properties.queue_flags & !vk::QueueFlags::TRANSFER)
+                .as_raw()
+                .count_ones();
+
+            (
+                is_dedicated,
+                std::cmp::Reverse(other_capabilities),
+                properties.queue_count,
+                std::cmp::Reverse(*index),
+            )
+        })
+        .map(|(index, _)| index)
+}
+
+/// Gets the maximum number of views a multiview render pass can have on a physical device which supports multiview
+///
+/// # Arguments
+///
+/// * `context`: The `Context` the physical device was queried from
+/// * `device`: The physical device to check
+fn get_max_multiview_view_count(context: &Context, device: &vk::PhysicalDevice) -> u32 {
+    let mut multiview_properties = vk::PhysicalDeviceMultiviewProperties::default();
+    let mut properties = vk::PhysicalDeviceProperties2::builder()
+        .push_next(&mut multiview_properties)
+        .build();
+    unsafe {
+        context
+            .instance
+            .get_physical_device_properties2(*device, &mut properties)
+    };
+
+    debug!(
+        "Multiview supports up to {} views",
+        multiview_properties.max_multiview_view_count
+    );
+    multiview_properties.max_multiview_view_count
+}
+
+/// Checks whether a physical device supports waiting for presentation with VK_KHR_present_wait, which requires both the extension and
+/// VK_KHR_present_id (along with their features) to be supported
+///
+/// # Arguments
+///
+/// * `context`: The `Context` the physical device was queried from
+/// * `device`: The physical device to check
+fn is_present_wait_supported(context: &Context, device: &vk::PhysicalDevice) -> bool {
+    if !is_device_extension_supported(context, device, vk::KhrPresentIdFn::name())
+        || !is_device_extension_supported(
+            context,
+            device,
+            ash::extensions::khr::PresentWait::name(),
+        )
+    {
+        return false;
+    }
+
+    let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR::default();
+    let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+    let mut features = vk::PhysicalDeviceFeatures2::builder()
+        .push_next(&mut present_id_features)
+        .push_next(&mut present_wait_features)
+        .build();
+    unsafe {
+        context
+            .instance
+            .get_physical_device_features2(*device, &mut features)
+    };
+
+    present_id_features.present_id == vk::TRUE && present_wait_features.present_wait == vk::TRUE
+}
+
+/// Checks whether a physical device supports a device extension
+///
+/// # Arguments
+///
+/// * `context`: The `Context` the physical device was queried from
+/// * `device`: The physical device to check
+/// * `extension_name`: The name of the extension to look for
+fn is_device_extension_supported(
+    context: &Context,
+    device: &vk::PhysicalDevice,
+    extension_name: &CStr,
+) -> bool {
+    let extensions = unsafe {
+        context
+            .instance
+            .enumerate_device_extension_properties(*device)
+    }
+    .expect("Failed to enumerate device extensions");
+
+    extensions.iter().any(
+        |extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == extension_name,
+    )
+}
+
+/// Clamps queue priorities into the range Vulkan accepts, warning about any that were out of range
+///
+/// # Arguments
+///
+/// * `priorities`: The requested priorities
+fn clamp_queue_priorities(priorities: QueuePriorities) -> QueuePriorities {
+    let clamp = |name: &str, priority: f32| {
+        if (0.0..=1.0).contains(&priority) {
+            priority
+        } else {
+            warn!(
+                "{} queue priority {} is outside of 0.0 to 1.0, so it will be clamped",
+                name, priority
+            );
+            if priority.is_nan() {
+                1.0
+            } else {
+                priority.clamp(0.0, 1.0)
+            }
+        }
+    };
+
+    QueuePriorities {
+        graphics: clamp("Graphics", priorities.graphics),
+        transfer: clamp("Transfer", priorities.transfer),
+        compute: clamp("Compute", priorities.compute),
+        present: clamp("Present", priorities.present),
+    }
+}
+
+/// Gets the index of the physical device requested through the `CLIENT_GPU_INDEX` environment variable, if any.
+/// An index which is invalid or out of range is logged and ignored
+///
+/// # Arguments
+///
+/// * `device_count`: The number of physical devices available
+fn get_requested_device_index(device_count: usize) -> Option<usize> {
+    let value = std::env::var(GPU_INDEX_ENV_NAME).ok()?;
+    match value.parse::<usize>() {
+        Ok(index) if index < device_count => {
+            info!(
+                "Using physical device {} as requested by {}",
+                index, GPU_INDEX_ENV_NAME
+            );
+            Some(index)
+        }
+        Ok(index) => {
+            warn!(
+                "{} requested physical device {}, but only {} are available, falling back to automatic selection",
+                GPU_INDEX_ENV_NAME, index, device_count
+            );
+            None
+        }
+        Err(_) => {
+            warn!(
+                "Ignoring {} as {:?} is not a valid device index",
+                GPU_INDEX_ENV_NAME, value
+            );
+            None
+        }
+    }
+}
+
+/// Gets the directory assets are resolved relative to by default, which is `CLIENT_ASSET_ROOT` if it's set, or the executable's
+/// directory otherwise
+fn default_asset_root() -> PathBuf {
+    if let Some(asset_root) = std::env::var_os(ASSET_ROOT_ENV_NAME) {
+        let asset_root = PathBuf::from(asset_root);
+        info!(
+            "{} is set, so assets will be resolved relative to {:?}",
+            ASSET_ROOT_ENV_NAME, asset_root
+        );
+        return asset_root;
+    }
+
+    std::env::current_exe()
+        .expect("Failed to get the path of the executable")
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// Gets whether any of a physical device's queue families can present to the surface
+///
+/// # Arguments
+///
+/// * `context`: The `Context` the physical device was enumerated from
+/// * `device`: The physical device to check
+/// * `surface`: The `Surface` to present to
+fn supports_presentation(
+    context: &Context,
+    device: &vk::PhysicalDevice,
+    surface: &Surface,
+) -> bool {
+    let queue_family_count = unsafe {
+        context
+            .instance
+            .get_physical_device_queue_family_properties(*device)
+    }
+    .len() as u32;
+    (0..queue_family_count).any(|queue_family_index| {
+        unsafe {
+            surface
+                .surface_extension
+                .get_physical_device_surface_support(*device, queue_family_index, surface.surface)
+        }
+        .unwrap_or(false)
+    })
+}
+
+/// Guesses which physical device is best, favouring a GPU from the preferred vendor, then of the preferred type, then with the
+/// most dedicated memory
+///
+/// # Arguments
+///
+/// * `context`: The `Context` the devices were enumerated from
+/// * `physical_devices`: The physical devices to choose between
+/// * `power_preference`: Whether to favour discrete or integrated GPUs
+/// * `preferred_vendor_id`: The PCI vendor ID of the GPU to favour, if any
+fn select_physical_device<'a>(
+    context: &Context,
+    physical_devices: &'a [vk::PhysicalDevice],
+    power_preference: PowerPreference,
+    preferred_vendor_id: Option<u32>,
+) -> &'a vk::PhysicalDevice {
+    let preferred_device_type = power_preference.preferred_device_type();
+    let is_preferred = |device: &vk::PhysicalDevice| {
+        let properties = unsafe { context.instance.get_physical_device_properties(*device) };
+        (
+            preferred_vendor_id.is_some_and(|vendor_id| properties.vendor_id == vendor_id),
+            properties.device_type == preferred_device_type,
+        )
+    };
+
+    let physical_device = physical_devices
+        .iter()
+        .reduce(|accum, current| {
+            let current_rank = (
+                is_preferred(current),
+                get_device_local_memory_size(context, current),
+            );
+            let accum_rank = (
+                is_preferred(accum),
+                get_device_local_memory_size(context, accum),
+            );
+
+            if current_rank > accum_rank {
+                current
+            } else {
+                accum
+            }
+        })
+        .expect("Failed to select a physical device");
+
+    if let Some(vendor_id) = preferred_vendor_id {
+        if !is_preferred(physical_device).0 {
+            warn!(
+                "No physical device from vendor {:#06X} is available, falling back to automatic selection",
+                vendor_id
+            );
+        }
+    }
+    physical_device
+}
+
 /// Gets the size of the device-local memory on a physical device (ie. the dedicated GDDRX / HBM memory)
 ///
 /// # Arguments
@@ -818,7 +5378,7 @@ fn find_device_queues_indices(
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// use client::renderer::vulkan::Context;
 /// use ash::vk;
 ///
@@ -855,3 +5415,65 @@ fn get_device_local_memory_size(context: &Context, device: &vk::PhysicalDevice)
         .iter()
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_family(queue_flags: vk::QueueFlags, queue_count: u32) -> vk::QueueFamilyProperties {
+        vk::QueueFamilyProperties::builder()
+            .queue_flags(queue_flags)
+            .queue_count(queue_count)
+            .build()
+    }
+
+    const ALL: vk::QueueFlags = vk::QueueFlags::from_raw(
+        vk::QueueFlags::GRAPHICS.as_raw()
+            | vk::QueueFlags::COMPUTE.as_raw()
+            | vk::QueueFlags::TRANSFER.as_raw(),
+    );
+
+    #[test]
+    fn transfer_family_prefers_dedicated_family() {
+        let families = [
+            queue_family(ALL, 16),
+            queue_family(vk::QueueFlags::TRANSFER, 1),
+        ];
+        assert_eq!(select_transfer_queue_family(&families), Some(1));
+    }
+
+    #[test]
+    fn transfer_family_prefers_fewest_capabilities() {
+        let families = [
+            queue_family(ALL, 4),
+            queue_family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER, 4),
+        ];
+        assert_eq!(select_transfer_queue_family(&families), Some(1));
+    }
+
+    #[test]
+    fn transfer_family_skips_families_without_queues() {
+        let families = [
+            queue_family(ALL, 1),
+            queue_family(vk::QueueFlags::TRANSFER, 0),
+        ];
+        assert_eq!(select_transfer_queue_family(&families), Some(0));
+    }
+
+    #[test]
+    fn transfer_family_ties_go_to_lowest_index() {
+        let families = [
+            queue_family(ALL, 1),
+            queue_family(vk::QueueFlags::TRANSFER, 2),
+            queue_family(vk::QueueFlags::TRANSFER, 2),
+        ];
+        assert_eq!(select_transfer_queue_family(&families), Some(1));
+    }
+
+    #[test]
+    fn transfer_family_falls_back_to_graphics() {
+        let families = [queue_family(vk::QueueFlags::GRAPHICS, 1)];
+        assert_eq!(select_transfer_queue_family(&families), Some(0));
+        assert_eq!(select_transfer_queue_family(&[]), None);
+    }
+}
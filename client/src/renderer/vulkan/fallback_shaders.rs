@@ -0,0 +1,151 @@
+// Hand-assembled SPIR-V for the "missing shader" pipeline, which is built into the executable so that it can't fail to load.
+// It fills the screen with magenta, which makes a shader problem obvious without stopping the game from launching.
+// Each instruction is preceded by its disassembly, and IDs are numbered in the order they first appear
+
+/// Covers the screen with a single triangle from three vertices, without any vertex input. Equivalent to:
+///
+/// ```glsl
+/// #version 450
+///
+/// vec4 positions[3] = {
+///     vec4(-1.0, -1.0, 0.0, 1.0),
+///     vec4(3.0, -1.0, 0.0, 1.0),
+///     vec4(-1.0, 3.0, 0.0, 1.0)
+/// };
+///
+/// void main() {
+///     gl_Position = positions[gl_VertexIndex];
+/// }
+/// ```
+#[rustfmt::skip]
+pub(crate) const FALLBACK_VERTEX_SHADER: [u32; 150] = [
+    // Header: magic number, version 1.0, generator, ID bound, schema
+    0x0723_0203, 0x0001_0000, 0, 29, 0,
+    // OpCapability Shader
+    0x0002_0011, 1,
+    // OpMemoryModel Logical GLSL450
+    0x0003_000E, 0, 1,
+    // OpEntryPoint Vertex %main "main" %gl_Position %gl_VertexIndex
+    0x0007_000F, 0, 1, 0x6E69616D, 0, 2, 3,
+    // OpDecorate %gl_Position BuiltIn Position
+    0x0004_0047, 2, 11, 0,
+    // OpDecorate %gl_VertexIndex BuiltIn VertexIndex
+    0x0004_0047, 3, 11, 42,
+    // %void = OpTypeVoid
+    0x0002_0013, 4,
+    // %main_type = OpTypeFunction %void
+    0x0003_0021, 5, 4,
+    // %float = OpTypeFloat 32
+    0x0003_0016, 6, 32,
+    // %vec4 = OpTypeVector %float 4
+    0x0004_0017, 7, 6, 4,
+    // %int = OpTypeInt 32 1
+    0x0004_0015, 8, 32, 1,
+    // %uint = OpTypeInt 32 0
+    0x0004_0015, 9, 32, 0,
+    // %uint_3 = OpConstant %uint 3
+    0x0004_002B, 9, 10, 3,
+    // %vec4_array = OpTypeArray %vec4 %uint_3
+    0x0004_001C, 11, 7, 10,
+    // %output_vec4 = OpTypePointer Output %vec4
+    0x0004_0020, 12, 3, 7,
+    // %input_int = OpTypePointer Input %int
+    0x0004_0020, 13, 1, 8,
+    // %function_vec4_array = OpTypePointer Function %vec4_array
+    0x0004_0020, 14, 7, 11,
+    // %function_vec4 = OpTypePointer Function %vec4
+    0x0004_0020, 15, 7, 7,
+    // %float_minus_1 = OpConstant %float -1
+    0x0004_002B, 6, 16, 0xBF800000,
+    // %float_3 = OpConstant %float 3
+    0x0004_002B, 6, 17, 0x40400000,
+    // %float_0 = OpConstant %float 0
+    0x0004_002B, 6, 18, 0,
+    // %float_1 = OpConstant %float 1
+    0x0004_002B, 6, 19, 0x3F800000,
+    // %top_left = OpConstantComposite %vec4 %float_minus_1 %float_minus_1 %float_0 %float_1
+    0x0007_002C, 7, 20, 16, 16, 18, 19,
+    // %beyond_top_right = OpConstantComposite %vec4 %float_3 %float_minus_1 %float_0 %float_1
+    0x0007_002C, 7, 21, 17, 16, 18, 19,
+    // %beyond_bottom_left = OpConstantComposite %vec4 %float_minus_1 %float_3 %float_0 %float_1
+    0x0007_002C, 7, 22, 16, 17, 18, 19,
+    // %positions = OpConstantComposite %vec4_array %top_left %beyond_top_right %beyond_bottom_left
+    0x0006_002C, 11, 23, 20, 21, 22,
+    // %gl_Position = OpVariable %output_vec4 Output
+    0x0004_003B, 12, 2, 3,
+    // %gl_VertexIndex = OpVariable %input_int Input
+    0x0004_003B, 13, 3, 1,
+    // %main = OpFunction %void None %main_type
+    0x0005_0036, 4, 1, 0, 5,
+    // %entry = OpLabel
+    0x0002_00F8, 24,
+    // %local_positions = OpVariable %function_vec4_array Function %positions
+    0x0005_003B, 14, 25, 7, 23,
+    // %vertex_index = OpLoad %int %gl_VertexIndex
+    0x0004_003D, 8, 26, 3,
+    // %position_pointer = OpAccessChain %function_vec4 %local_positions %vertex_index
+    0x0005_0041, 15, 27, 25, 26,
+    // %position = OpLoad %vec4 %position_pointer
+    0x0004_003D, 7, 28, 27,
+    // OpStore %gl_Position %position
+    0x0003_003E, 2, 28,
+    // OpReturn
+    0x0001_00FD,
+    // OpFunctionEnd
+    0x0001_0038,
+];
+
+/// Writes magenta to the first colour attachment. Equivalent to:
+///
+/// ```glsl
+/// #version 450
+///
+/// layout (location = 0) out vec4 outColour;
+///
+/// void main() {
+///     outColour = vec4(1.0, 0.0, 1.0, 1.0);
+/// }
+/// ```
+#[rustfmt::skip]
+pub(crate) const FALLBACK_FRAGMENT_SHADER: [u32; 70] = [
+    // Header: magic number, version 1.0, generator, ID bound, schema
+    0x0723_0203, 0x0001_0000, 0, 12, 0,
+    // OpCapability Shader
+    0x0002_0011, 1,
+    // OpMemoryModel Logical GLSL450
+    0x0003_000E, 0, 1,
+    // OpEntryPoint Fragment %main "main" %out_colour
+    0x0006_000F, 4, 1, 0x6E69616D, 0, 2,
+    // OpExecutionMode %main OriginUpperLeft
+    0x0003_0010, 1, 7,
+    // OpDecorate %out_colour Location 0
+    0x0004_0047, 2, 30, 0,
+    // %void = OpTypeVoid
+    0x0002_0013, 3,
+    // %main_type = OpTypeFunction %void
+    0x0003_0021, 4, 3,
+    // %float = OpTypeFloat 32
+    0x0003_0016, 5, 32,
+    // %vec4 = OpTypeVector %float 4
+    0x0004_0017, 6, 5, 4,
+    // %output_vec4 = OpTypePointer Output %vec4
+    0x0004_0020, 7, 3, 6,
+    // %float_0 = OpConstant %float 0
+    0x0004_002B, 5, 8, 0,
+    // %float_1 = OpConstant %float 1
+    0x0004_002B, 5, 9, 0x3F800000,
+    // %magenta = OpConstantComposite %vec4 %float_1 %float_0 %float_1 %float_1
+    0x0007_002C, 6, 10, 9, 8, 9, 9,
+    // %out_colour = OpVariable %output_vec4 Output
+    0x0004_003B, 7, 2, 3,
+    // %main = OpFunction %void None %main_type
+    0x0005_0036, 3, 1, 0, 4,
+    // %entry = OpLabel
+    0x0002_00F8, 11,
+    // OpStore %out_colour %magenta
+    0x0003_003E, 2, 10,
+    // OpReturn
+    0x0001_00FD,
+    // OpFunctionEnd
+    0x0001_0038,
+];
@@ -0,0 +1,147 @@
+use ash::vk;
+use serde::{Deserialize, Serialize};
+
+/// The optional features a physical device supports.
+/// These can be queried through [`Context::device_features()`](crate::renderer::vulkan::Context::device_features) before a `Device`
+/// is created, for example so that a settings menu can grey out options the GPU can't use
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupportedFeatures {
+    /// Geometry shader stages in pipelines
+    pub geometry_shader: bool,
+    /// Tessellation control and evaluation stages in pipelines
+    pub tessellation_shader: bool,
+    /// Anisotropic filtering in samplers
+    pub sampler_anisotropy: bool,
+    /// Wireframe and point polygon modes
+    pub fill_mode_non_solid: bool,
+    /// Lines wider than 1 pixel
+    pub wide_lines: bool,
+    /// Rendering several views in a single pass, eg. for stereo
+    pub multiview: bool,
+    /// Clamping the depth bias applied to polygons
+    pub depth_bias_clamp: bool,
+}
+
+/// The optional features to enable when creating a `Device`, through
+/// [`DeviceParameters::requested_features`](crate::renderer::vulkan::DeviceParameters::requested_features).
+/// Features which aren't requested are left disabled, even if they're supported
+pub type RequestedFeatures = SupportedFeatures;
+
+impl SupportedFeatures {
+    /// Queries which of the optional features a physical device supports
+    ///
+    /// # Arguments
+    ///
+    /// * `instance`: The instance the physical device was enumerated from
+    /// * `physical_device`: The physical device to query
+    pub(crate) fn query(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> Self {
+        let mut multiview_features = vk::PhysicalDeviceMultiviewFeatures::default();
+        let mut features = vk::PhysicalDeviceFeatures2::builder()
+            .push_next(&mut multiview_features)
+            .build();
+        unsafe { instance.get_physical_device_features2(physical_device, &mut features) };
+
+        let core_features = features.features;
+        SupportedFeatures {
+            geometry_shader: core_features.geometry_shader == vk::TRUE,
+            tessellation_shader: core_features.tessellation_shader == vk::TRUE,
+            sampler_anisotropy: core_features.sampler_anisotropy == vk::TRUE,
+            fill_mode_non_solid: core_features.fill_mode_non_solid == vk::TRUE,
+            wide_lines: core_features.wide_lines == vk::TRUE,
+            multiview: multiview_features.multiview == vk::TRUE,
+            depth_bias_clamp: core_features.depth_bias_clamp == vk::TRUE,
+        }
+    }
+
+    /// Gets the names of the features in `required` which aren't supported, so that they can be reported to the user
+    ///
+    /// # Arguments
+    ///
+    /// * `required`: The features that are needed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use client::renderer::vulkan::SupportedFeatures;
+    ///
+    /// let supported = SupportedFeatures {
+    ///     geometry_shader: true,
+    ///     ..Default::default()
+    /// };
+    /// let required = SupportedFeatures {
+    ///     geometry_shader: true,
+    ///     sampler_anisotropy: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(supported.missing(&required), vec!["sampler_anisotropy"]);
+    /// ```
+    pub fn missing(&self, required: &SupportedFeatures) -> Vec<&'static str> {
+        [
+            (
+                "geometry_shader",
+                required.geometry_shader,
+                self.geometry_shader,
+            ),
+            (
+                "tessellation_shader",
+                required.tessellation_shader,
+                self.tessellation_shader,
+            ),
+            (
+                "sampler_anisotropy",
+                required.sampler_anisotropy,
+                self.sampler_anisotropy,
+            ),
+            (
+                "fill_mode_non_solid",
+                required.fill_mode_non_solid,
+                self.fill_mode_non_solid,
+            ),
+            ("wide_lines", required.wide_lines, self.wide_lines),
+            ("multiview", required.multiview, self.multiview),
+            (
+                "depth_bias_clamp",
+                required.depth_bias_clamp,
+                self.depth_bias_clamp,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, is_required, is_supported)| *is_required && !*is_supported)
+        .map(|(name, _, _)| name)
+        .collect()
+    }
+
+    /// Gets a copy of these features with the named ones disabled, eg. to drop the features a device reported as missing
+    ///
+    /// # Arguments
+    ///
+    /// * `names`: The names of the features to disable, as returned by [`SupportedFeatures::missing()`]
+    pub(crate) fn without(mut self, names: &[&str]) -> Self {
+        for name in names {
+            match *name {
+                "geometry_shader" => self.geometry_shader = false,
+                "tessellation_shader" => self.tessellation_shader = false,
+                "sampler_anisotropy" => self.sampler_anisotropy = false,
+                "fill_mode_non_solid" => self.fill_mode_non_solid = false,
+                "wide_lines" => self.wide_lines = false,
+                "multiview" => self.multiview = false,
+                "depth_bias_clamp" => self.depth_bias_clamp = false,
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Gets the core features to enable when creating a logical device
+    pub(crate) fn to_physical_device_features(self) -> vk::PhysicalDeviceFeatures {
+        vk::PhysicalDeviceFeatures::builder()
+            .geometry_shader(self.geometry_shader)
+            .tessellation_shader(self.tessellation_shader)
+            .sampler_anisotropy(self.sampler_anisotropy)
+            .fill_mode_non_solid(self.fill_mode_non_solid)
+            .wide_lines(self.wide_lines)
+            .depth_bias_clamp(self.depth_bias_clamp)
+            .build()
+    }
+}
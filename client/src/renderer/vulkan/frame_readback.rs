@@ -0,0 +1,137 @@
+use ash::vk;
+use image::RgbaImage;
+
+/// Gets the number of bytes per texel of a swapchain format that frames can be read back from, or `None` if it isn't supported
+///
+/// # Arguments
+///
+/// * `format`: The format of the swapchain images
+pub(crate) fn readback_texel_size(format: vk::Format) -> Option<u64> {
+    match format {
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::A2R10G10B10_UNORM_PACK32 => Some(4),
+        _ => None,
+    }
+}
+
+/// Decodes a frame read back from a swapchain image into 8-bit RGBA, as the frame would be shown on screen.
+/// The values are copied without any colour space conversion, since both sRGB formats and UNORM formats presented in the sRGB
+/// colour space hold sRGB-encoded values. Alpha is set to opaque, as the swapchain is presented without blending
+///
+/// If the format isn't supported or there are too few bytes for the extent, returns `Err`
+///
+/// # Arguments
+///
+/// * `format`: The format of the swapchain image
+/// * `extent`: The size of the swapchain image
+/// * `bytes`: The texels of the image, tightly packed in rows from the top
+pub(crate) fn decode_frame(
+    format: vk::Format,
+    extent: vk::Extent2D,
+    bytes: &[u8],
+) -> Result<RgbaImage, &'static str> {
+    let texel_size =
+        readback_texel_size(format).ok_or("Frames can't be read back in this format")?;
+    let texel_count = extent.width as usize * extent.height as usize;
+    let bytes = bytes
+        .get(..texel_count * texel_size as usize)
+        .ok_or("The frame is smaller than its extent")?;
+
+    let pixels: Vec<u8> = bytes
+        .chunks_exact(4)
+        .flat_map(|texel| {
+            let [red, green, blue] = match format {
+                vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+                    [texel[2], texel[1], texel[0]]
+                }
+                vk::Format::A2B10G10R10_UNORM_PACK32 => {
+                    let packed = u32::from_le_bytes(texel.try_into().unwrap());
+                    [packed, packed >> 10, packed >> 20].map(unorm10_to_unorm8)
+                }
+                vk::Format::A2R10G10B10_UNORM_PACK32 => {
+                    let packed = u32::from_le_bytes(texel.try_into().unwrap());
+                    [packed >> 20, packed >> 10, packed].map(unorm10_to_unorm8)
+                }
+                _ => [texel[0], texel[1], texel[2]],
+            };
+            [red, green, blue, u8::MAX]
+        })
+        .collect();
+
+    Ok(RgbaImage::from_raw(extent.width, extent.height, pixels).unwrap())
+}
+
+/// Converts the lowest 10 bits of a packed value from a 10-bit normalised channel to an 8-bit one, rounding to the nearest value
+///
+/// # Arguments
+///
+/// * `packed`: The value whose lowest 10 bits hold the channel
+fn unorm10_to_unorm8(packed: u32) -> u8 {
+    let channel = packed & 0x3FF;
+    ((channel * 255 + 511) / 1023) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D {
+        width: 2,
+        height: 1,
+    };
+
+    /// Gets the pixels of a decoded frame, in order
+    fn decoded_pixels(format: vk::Format, bytes: &[u8]) -> Vec<[u8; 4]> {
+        decode_frame(format, EXTENT, bytes)
+            .unwrap()
+            .pixels()
+            .map(|pixel| pixel.0)
+            .collect()
+    }
+
+    #[test]
+    fn bgra8_swaps_red_and_blue() {
+        let bytes = [10, 20, 30, 40, 1, 2, 3, 4];
+
+        assert_eq!(
+            decoded_pixels(vk::Format::B8G8R8A8_UNORM, &bytes),
+            vec![[30, 20, 10, 255], [3, 2, 1, 255]]
+        );
+    }
+
+    #[test]
+    fn rgba8_is_copied_with_opaque_alpha() {
+        let bytes = [10, 20, 30, 40, 1, 2, 3, 4];
+
+        assert_eq!(
+            decoded_pixels(vk::Format::R8G8B8A8_SRGB, &bytes),
+            vec![[10, 20, 30, 255], [1, 2, 3, 255]]
+        );
+    }
+
+    #[test]
+    fn a2b10g10r10_is_unpacked_and_rounded() {
+        // Red is in the lowest bits, then green, then blue, then 2 bits of alpha
+        let white: u32 = 1023 | (1023 << 10) | (1023 << 20) | (3 << 30);
+        let mixed: u32 = 1023 | (341 << 20);
+        let bytes: Vec<u8> = [white, mixed]
+            .iter()
+            .flat_map(|texel| texel.to_le_bytes())
+            .collect();
+
+        assert_eq!(
+            decoded_pixels(vk::Format::A2B10G10R10_UNORM_PACK32, &bytes),
+            vec![[255, 255, 255, 255], [255, 0, 85, 255]]
+        );
+    }
+
+    #[test]
+    fn unsupported_formats_and_short_frames_are_rejected() {
+        assert!(decode_frame(vk::Format::R16G16B16A16_SFLOAT, EXTENT, &[0; 16]).is_err());
+        assert!(decode_frame(vk::Format::R8G8B8A8_UNORM, EXTENT, &[0; 7]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use common::math::Mat4;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::surface::MAX_FRAMES_IN_FLIGHT;
+use crate::renderer::vulkan::{Device, MappedBuffer};
+
+/// The transforms that vertex shaders read from the uniform buffer at set 0, binding 0, laid out to match a GLSL block such as:
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) uniform FrameUniforms {
+///     mat4 model;
+///     mat4 view;
+///     mat4 proj;
+/// };
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UniformData {
+    /// Transforms from model space to world space
+    pub model: Mat4,
+    /// Transforms from world space to view space, usually built with `common::math::look_at`
+    pub view: Mat4,
+    /// Transforms from view space to clip space, usually built with `common::math::perspective`
+    pub proj: Mat4,
+}
+
+impl Default for UniformData {
+    fn default() -> Self {
+        Self {
+            model: Mat4::IDENTITY,
+            view: Mat4::IDENTITY,
+            proj: Mat4::IDENTITY,
+        }
+    }
+}
+
+/// A uniform buffer and descriptor set for each frame in flight, holding the [`UniformData`] that graphics pipelines are bound with.
+/// Each frame has its own buffer so that it can be rewritten whilst the previous frame is still being drawn.
+/// Binding 1 of each set is a combined image sampler, which is written by [`Device::bind_texture()`]
+pub(crate) struct FrameUniforms {
+    device: Weak<ash::Device>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    // Indexed by frame in flight
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    buffers: Vec<MappedBuffer>,
+}
+
+impl FrameUniforms {
+    /// Constructs a new `FrameUniforms`, with every frame's buffer holding the default (identity) transforms
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the buffers and descriptor sets on
+    pub(crate) fn new(device: &Device) -> Self {
+        let span = debug_span!("Vulkan/FrameUniforms");
+        let _guard = span.enter();
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
+                .build(),
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(1)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                .build(),
+        ];
+        let descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+        let descriptor_set_layout = unsafe {
+            device
+                .logical_device
+                .create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+        }
+        .expect("Failed to create uniform descriptor set layout");
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                .build(),
+            vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                .build(),
+        ];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(MAX_FRAMES_IN_FLIGHT as u32)
+            .pool_sizes(&pool_sizes)
+            .build();
+        let descriptor_pool = unsafe {
+            device
+                .logical_device
+                .create_descriptor_pool(&descriptor_pool_create_info, None)
+        }
+        .expect("Failed to create uniform descriptor pool");
+
+        let set_layouts = [descriptor_set_layout; MAX_FRAMES_IN_FLIGHT];
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_sets = unsafe {
+            device
+                .logical_device
+                .allocate_descriptor_sets(&descriptor_set_allocate_info)
+        }
+        .expect("Failed to allocate uniform descriptor sets");
+
+        debug!(
+            "Creating {} uniform buffers of {} bytes",
+            MAX_FRAMES_IN_FLIGHT,
+            std::mem::size_of::<UniformData>()
+        );
+        let buffers: Vec<MappedBuffer> = descriptor_sets
+            .iter()
+            .map(|descriptor_set| {
+                let mut buffer = MappedBuffer::new(
+                    device,
+                    std::mem::size_of::<UniformData>() as u64,
+                    vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    "frame uniforms",
+                );
+                buffer
+                    .write_slice(0, &[UniformData::default()])
+                    .expect("The uniform buffer fits the uniform data");
+
+                // The buffers never change, so the sets only need writing once
+                let buffer_infos = [vk::DescriptorBufferInfo::builder()
+                    .buffer(buffer.buffer().buffer)
+                    .offset(0)
+                    .range(vk::WHOLE_SIZE)
+                    .build()];
+                let descriptor_writes = [vk::WriteDescriptorSet::builder()
+                    .dst_set(*descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&buffer_infos)
+                    .build()];
+                unsafe {
+                    device
+                        .logical_device
+                        .update_descriptor_sets(&descriptor_writes, &[])
+                };
+
+                buffer
+            })
+            .collect();
+
+        FrameUniforms {
+            device: Rc::downgrade(&device.logical_device),
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            buffers,
+        }
+    }
+
+    /// Gets the layout of the descriptor sets, which pipelines use as set 0
+    pub(crate) fn descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.descriptor_set_layout
+    }
+
+    /// Gets the descriptor set for a frame in flight
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight
+    pub(crate) fn descriptor_set(&self, frame_index: usize) -> vk::DescriptorSet {
+        *self.descriptor_sets.get(frame_index).unwrap()
+    }
+
+    /// Copies new transforms into a frame's uniform buffer. The frame's previous submission must have finished
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight
+    /// * `data`: The transforms to write
+    pub(crate) fn update(&mut self, frame_index: usize, data: &UniformData) {
+        self.buffers
+            .get_mut(frame_index)
+            .unwrap()
+            .write_slice(0, std::slice::from_ref(data))
+            .expect("The uniform buffer fits the uniform data");
+    }
+
+    /// Points a frame's combined image sampler at a texture. The frame's previous submission must have finished, and the frame's
+    /// descriptor set mustn't have been bound by the commands being recorded yet
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight
+    /// * `view`: The view of the texture, which must be in `SHADER_READ_ONLY_OPTIMAL`
+    /// * `sampler`: The sampler to sample the texture with
+    pub(crate) fn set_texture(
+        &self,
+        frame_index: usize,
+        view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) {
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        let image_infos = [vk::DescriptorImageInfo::builder()
+            .image_view(view)
+            .sampler(sampler)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build()];
+        let descriptor_writes = [vk::WriteDescriptorSet::builder()
+            .dst_set(self.descriptor_set(frame_index))
+            .dst_binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos)
+            .build()];
+        unsafe { device.update_descriptor_sets(&descriptor_writes, &[]) };
+    }
+}
+
+impl Drop for FrameUniforms {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~FrameUniforms");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying uniform descriptor sets");
+        // Freeing the pool frees the sets allocated from it
+        unsafe {
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::renderer::vulkan::{Buffer, Pipeline, Texture};
+
+/// A reference to a buffer owned by a `Device`
+pub type BufferHandle = Handle<Buffer>;
+/// A reference to a texture owned by a `Device`
+pub type TextureHandle = Handle<Texture>;
+/// A reference to a graphics pipeline owned by a `Device`
+pub type PipelineHandle = Handle<Pipeline>;
+
+/// A typed reference to a resource owned by a `Device`.
+///
+/// Each handle pairs a slot index with the generation of the resource that was in the slot when the handle was created. When a
+/// resource is destroyed its slot's generation is bumped, so a stale handle is detected (and lookups return `None`) rather than
+/// referring to whichever resource reuses the slot. Handles are plain data, so they can be freely copied and sent between threads
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    // `fn() -> T` keeps the handle `Send` and `Sync` regardless of whether `T` is
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: u32, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// These are implemented by hand, as deriving them would require `T` to implement them too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Storage for resources which are referenced by `Handle`s, reusing the slots of destroyed resources
+pub(crate) struct Slab<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Self {
+            slots: vec![],
+            free_slots: vec![],
+        }
+    }
+}
+
+impl<T> Slab<T> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a resource, and returns a handle to it
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The resource to store
+    pub(crate) fn insert(&mut self, value: T) -> Handle<T> {
+        match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                Handle::new(index, slot.generation)
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("Too many resources");
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                Handle::new(index, 0)
+            }
+        }
+    }
+
+    /// Gets the resource a handle refers to, or `None` if it's been removed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle to look up
+    pub(crate) fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    /// Gets the resource a handle refers to mutably, or `None` if it's been removed
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle to look up
+    pub(crate) fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    /// Removes the resource a handle refers to and returns it, invalidating every handle to it.
+    /// If it's already been removed, returns `None`
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`: The handle of the resource to remove
+    pub(crate) fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+        Some(value)
+    }
+
+    /// Removes every resource, invalidating all handles
+    pub(crate) fn clear(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.value.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_slots.push(index as u32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserted_values_can_be_looked_up() {
+        let mut slab = Slab::new();
+        let first = slab.insert("first");
+        let second = slab.insert("second");
+
+        assert_ne!(first, second);
+        assert_eq!(slab.get(first), Some(&"first"));
+        assert_eq!(slab.get(second), Some(&"second"));
+
+        *slab.get_mut(second).unwrap() = "changed";
+        assert_eq!(slab.get(second), Some(&"changed"));
+    }
+
+    #[test]
+    fn removed_values_are_gone() {
+        let mut slab = Slab::new();
+        let handle = slab.insert(1);
+
+        assert_eq!(slab.remove(handle), Some(1));
+        assert_eq!(slab.get(handle), None);
+        assert_eq!(slab.get_mut(handle), None);
+        assert_eq!(slab.remove(handle), None);
+    }
+
+    #[test]
+    fn stale_handles_miss_a_reused_slot() {
+        let mut slab = Slab::new();
+        let old = slab.insert(1);
+        slab.remove(old);
+
+        let new = slab.insert(2);
+        assert_eq!(new.index, old.index, "the slot should be reused");
+        assert_ne!(new, old);
+        assert_eq!(slab.get(old), None);
+        assert_eq!(slab.remove(old), None);
+        assert_eq!(slab.get(new), Some(&2));
+    }
+
+    #[test]
+    fn clear_invalidates_every_handle() {
+        let mut slab = Slab::new();
+        let handles = [slab.insert(1), slab.insert(2)];
+
+        slab.clear();
+        assert!(handles.iter().all(|handle| slab.get(*handle).is_none()));
+
+        let reused = slab.insert(3);
+        assert!(!handles.contains(&reused));
+        assert_eq!(slab.get(reused), Some(&3));
+    }
+}
@@ -0,0 +1,137 @@
+use ash::vk;
+
+/// Records a clear of specific mip levels and array layers of a colour image, eg. a single cubemap face or texture array layer.
+/// The image must have been created with `TRANSFER_DST` usage
+///
+/// Like [`record_image_layout_transition()`](crate::renderer::vulkan::record_image_layout_transition), this is a free function so that
+/// it can be used whilst recording transfers with [`Device::submit_transfer_async()`](crate::renderer::vulkan::Device::submit_transfer_async)
+///
+/// # Arguments
+///
+/// * `device`: The logical device that the command buffer belongs to
+/// * `command_buffer`: A command buffer in the recording state, outside of any render pass
+/// * `image`: The image to clear
+/// * `layout`: The layout the image is in, which must be `TRANSFER_DST_OPTIMAL` or `GENERAL`
+/// * `subresource_range`: The mip levels and array layers to clear
+/// * `color`: The colour to clear to
+///
+/// # Examples
+///
+/// ```no_run
+/// # use client::renderer::vulkan::Device;
+/// # use ash::vk;
+/// # fn example(device: &mut Device, cubemap: vk::Image) {
+/// use ash::vk;
+/// use client::renderer::vulkan::record_clear_color_image;
+///
+/// // Clear the third face of a cubemap to black
+/// let face = vk::ImageSubresourceRange::builder()
+///     .aspect_mask(vk::ImageAspectFlags::COLOR)
+///     .base_mip_level(0)
+///     .level_count(vk::REMAINING_MIP_LEVELS)
+///     .base_array_layer(2)
+///     .layer_count(1)
+///     .build();
+/// device.submit_transfer_async(|logical_device, command_buffer| {
+///     record_clear_color_image(
+///         logical_device,
+///         command_buffer,
+///         cubemap,
+///         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+///         face,
+///         [0.0, 0.0, 0.0, 1.0],
+///     );
+/// });
+/// # }
+/// ```
+pub fn record_clear_color_image(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    layout: vk::ImageLayout,
+    subresource_range: vk::ImageSubresourceRange,
+    color: [f32; 4],
+) {
+    let clear_color = vk::ClearColorValue { float32: color };
+    unsafe {
+        device.cmd_clear_color_image(
+            command_buffer,
+            image,
+            layout,
+            &clear_color,
+            &[subresource_range],
+        )
+    };
+}
+
+/// A region of one mip level of an image, across one or more array layers
+#[derive(Clone, Copy, Debug)]
+pub struct ImageRegion {
+    pub image: vk::Image,
+    /// The mip level and array layers the region covers
+    pub subresource: vk::ImageSubresourceLayers,
+    /// Opposite corners of the region
+    pub bounds: [vk::Offset3D; 2],
+}
+
+/// Records a blit from one region of an image to another, scaling and converting the format if needed. The source and destination
+/// can be different mip levels or array layers of the same image (eg. when generating mipmaps), as long as they don't overlap
+///
+/// The source must be in `TRANSFER_SRC_OPTIMAL` layout and created with `TRANSFER_SRC` usage, and the destination must be in
+/// `TRANSFER_DST_OPTIMAL` layout and created with `TRANSFER_DST` usage
+///
+/// # Arguments
+///
+/// * `device`: The logical device that the command buffer belongs to
+/// * `command_buffer`: A command buffer in the recording state, outside of any render pass
+/// * `source`: The region to read from
+/// * `destination`: The region to write to, which must cover as many array layers as the source
+/// * `filter`: How to filter the source when it's scaled
+///
+/// # Examples
+///
+/// ```no_run
+/// # use ash::vk;
+/// # fn example(logical_device: &ash::Device, command_buffer: vk::CommandBuffer, texture: vk::Image) {
+/// use ash::vk;
+/// use client::renderer::vulkan::{record_blit_image, ImageRegion};
+///
+/// // Downsample mip level 0 of a 256x256 texture into level 1
+/// let level = |mip_level, size| ImageRegion {
+///     image: texture,
+///     subresource: vk::ImageSubresourceLayers::builder()
+///         .aspect_mask(vk::ImageAspectFlags::COLOR)
+///         .mip_level(mip_level)
+///         .base_array_layer(0)
+///         .layer_count(1)
+///         .build(),
+///     bounds: [vk::Offset3D::default(), vk::Offset3D { x: size, y: size, z: 1 }],
+/// };
+/// record_blit_image(logical_device, command_buffer, level(0, 256), level(1, 128), vk::Filter::LINEAR);
+/// # }
+/// ```
+pub fn record_blit_image(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    source: ImageRegion,
+    destination: ImageRegion,
+    filter: vk::Filter,
+) {
+    let region = vk::ImageBlit::builder()
+        .src_subresource(source.subresource)
+        .src_offsets(source.bounds)
+        .dst_subresource(destination.subresource)
+        .dst_offsets(destination.bounds)
+        .build();
+    unsafe {
+        device.cmd_blit_image(
+            command_buffer,
+            source.image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            destination.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+            filter,
+        )
+    };
+}
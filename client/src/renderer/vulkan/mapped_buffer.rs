@@ -0,0 +1,153 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+
+use crate::renderer::vulkan::{Buffer, Device};
+
+/// A host-visible buffer which is written through typed slices rather than raw pointers, for data that changes often (eg. uniforms
+/// or dynamic geometry). The allocator keeps host-visible memory mapped for as long as it's allocated, so the buffer is mapped from
+/// the moment it's created until it's dropped. Writes to memory which isn't host-coherent are flushed straight away, so they're
+/// visible to any commands submitted afterwards
+pub struct MappedBuffer {
+    device: Weak<ash::Device>,
+    buffer: Buffer,
+    size: u64,
+    non_coherent_atom_size: u64,
+}
+
+impl MappedBuffer {
+    /// Constructs a new `MappedBuffer`.
+    /// Note that the recommended way to create a mapped buffer is through [`Device::create_mapped_buffer()`] rather than using
+    /// `MappedBuffer::new()` directly
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the buffer on
+    /// * `size`: The size of the buffer in bytes
+    /// * `usage`: How the buffer will be used
+    /// * `name`: A name for the allocation, used when debugging leaks
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::MappedBuffer;
+    ///
+    /// let particles = MappedBuffer::new(&device, 4096, vk::BufferUsageFlags::VERTEX_BUFFER, "particles");
+    /// # }
+    /// ```
+    pub fn new(device: &Device, size: u64, usage: vk::BufferUsageFlags, name: &str) -> Self {
+        let non_coherent_atom_size = device.limits().non_coherent_atom_size.max(1);
+
+        // Flushed ranges are rounded out to whole atoms, so the buffer starts and ends on atom boundaries to keep them inside it
+        let create_info = vk::BufferCreateInfo::builder()
+            .size(size.next_multiple_of(non_coherent_atom_size))
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let buffer = device.allocate_aligned_buffer(
+            &create_info,
+            MemoryLocation::CpuToGpu,
+            non_coherent_atom_size,
+            name,
+        );
+
+        MappedBuffer {
+            device: Rc::downgrade(&device.logical_device),
+            buffer,
+            size,
+            non_coherent_atom_size,
+        }
+    }
+
+    /// Gets the underlying buffer, eg. so that it can be bound
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Gets the size of the buffer in bytes, as requested when it was created
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Copies a slice of values into the buffer, then flushes the bytes written if the memory isn't host-coherent.
+    /// The buffer mustn't be in use by any frame that's still in flight
+    ///
+    /// `T` should be `#[repr(C)]` (or a primitive) with no padding, so that its layout matches what the shaders read
+    ///
+    /// If the data doesn't fit in the buffer at the given offset, returns `Err` and nothing is written
+    ///
+    /// # Arguments
+    ///
+    /// * `offset`: The offset in bytes to start writing at
+    /// * `data`: The values to write
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{Device, MappedBuffer, SpriteVertex};
+    /// # struct Particle;
+    /// # impl Particle {
+    /// #     fn vertices(&self) -> Vec<SpriteVertex> {
+    /// #         Vec::new()
+    /// #     }
+    /// # }
+    /// # fn example(
+    /// #     device: &Device,
+    /// #     current_frame_index: usize,
+    /// #     particles: &[Particle],
+    /// #     dynamic_vertices: &mut MappedBuffer,
+    /// # ) -> Result<(), &'static str> {
+    /// let particle_vertices: Vec<SpriteVertex> = particles.iter().flat_map(Particle::vertices).collect();
+    /// dynamic_vertices.write_slice(0, &particle_vertices)?;
+    /// device.bind_vertex_buffer(current_frame_index, dynamic_vertices.buffer());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_slice<T: Copy>(&mut self, offset: u64, data: &[T]) -> Result<(), &'static str> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
+        };
+        let end = offset
+            .checked_add(bytes.len() as u64)
+            .filter(|end| *end <= self.size)
+            .ok_or("The data doesn't fit in the buffer")?;
+
+        let mapped = self
+            .buffer
+            .mapped_slice_mut()
+            .expect("Mapped buffer memory should be host-visible");
+        mapped[offset as usize..end as usize].copy_from_slice(bytes);
+
+        if !bytes.is_empty() && !self.buffer.is_host_coherent() {
+            self.flush(offset, end);
+        }
+        Ok(())
+    }
+
+    /// Flushes a range of the buffer, rounded out to whole `nonCoherentAtomSize` atoms as flushes require
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The offset in bytes of the first byte written
+    /// * `end`: The offset in bytes just past the last byte written
+    fn flush(&self, start: u64, end: u64) {
+        let device = self.device.upgrade().expect("Device should still exist");
+        let (memory, memory_offset) = self.buffer.memory_range();
+
+        let atom_size = self.non_coherent_atom_size;
+        let range_start = (memory_offset + start) / atom_size * atom_size;
+        let range_end = (memory_offset + end).next_multiple_of(atom_size);
+        let memory_range = vk::MappedMemoryRange::builder()
+            .memory(memory)
+            .offset(range_start)
+            .size(range_end - range_start)
+            .build();
+
+        unsafe { device.flush_mapped_memory_ranges(&[memory_range]) }
+            .expect("Failed to flush mapped buffer memory");
+    }
+}
@@ -0,0 +1,362 @@
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, AllocationScheme, Allocator};
+use gpu_allocator::MemoryLocation;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+
+/// Without resizable BAR, only a 256 MiB window of VRAM is host-visible, which is too small (and too contended) to upload through
+const BAR_WINDOW_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The memory usage and budget of a single memory heap, in bytes
+#[derive(Clone, Copy, Debug)]
+pub struct HeapBudget {
+    /// The total size of the heap
+    pub size: u64,
+    /// How much of the heap is currently in use by this process
+    pub usage: u64,
+    /// How much of the heap this process can use before allocations are likely to fail or be slow
+    pub budget: u64,
+    /// Whether the heap is local to the device (ie. VRAM on a discrete GPU)
+    pub device_local: bool,
+}
+
+impl HeapBudget {
+    /// Gets how much more of the heap can be used before reaching the budget
+    pub fn available(&self) -> u64 {
+        self.budget.saturating_sub(self.usage)
+    }
+}
+
+/// The memory usage and budget of each memory heap on a device
+#[derive(Clone, Debug)]
+pub struct MemoryBudget {
+    pub heaps: Vec<HeapBudget>,
+    /// Whether the figures come from `VK_EXT_memory_budget`. If not, the usage is unknown (and reported as zero) and the budget is the size of the heap
+    pub is_exact: bool,
+}
+
+impl MemoryBudget {
+    /// Gets how much more device-local memory can be used before reaching the budget, across all device-local heaps
+    pub fn device_local_available(&self) -> u64 {
+        self.heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.available())
+            .sum()
+    }
+}
+
+/// Finds the heap that the CPU can write to directly despite being in VRAM, which is only large enough to upload through when
+/// resizable BAR (or Smart Access Memory) is enabled, or when the device is integrated and all of its memory is shared
+///
+/// Returns the index of the heap, or `None` if there isn't one larger than the default BAR window
+///
+/// # Arguments
+///
+/// * `instance`: The instance the physical device was queried from
+/// * `physical_device`: The physical device to search the memory of
+pub(crate) fn query_resizable_bar_heap(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> Option<u32> {
+    let memory_properties =
+        unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+    let host_visible_device_local =
+        vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::HOST_VISIBLE;
+    memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+        .iter()
+        .filter(|memory_type| {
+            memory_type
+                .property_flags
+                .contains(host_visible_device_local)
+        })
+        .map(|memory_type| memory_type.heap_index)
+        .filter(|heap_index| {
+            memory_properties.memory_heaps[*heap_index as usize].size > BAR_WINDOW_SIZE
+        })
+        .max_by_key(|heap_index| memory_properties.memory_heaps[*heap_index as usize].size)
+}
+
+/// Queries the memory usage and budget of each heap on a physical device
+///
+/// # Arguments
+///
+/// * `instance`: The instance the physical device was queried from
+/// * `physical_device`: The physical device to get the memory budget of
+/// * `memory_budget_enabled`: Whether `VK_EXT_memory_budget` was enabled on the device. If not, the heap sizes are used as the budget
+pub(crate) fn query_memory_budget(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    memory_budget_enabled: bool,
+) -> MemoryBudget {
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut memory_properties = vk::PhysicalDeviceMemoryProperties2::builder();
+    if memory_budget_enabled {
+        memory_properties = memory_properties.push_next(&mut budget_properties);
+    }
+    let mut memory_properties = memory_properties.build();
+
+    unsafe {
+        instance.get_physical_device_memory_properties2(physical_device, &mut memory_properties)
+    };
+
+    let memory_properties = memory_properties.memory_properties;
+    let heaps = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+        .iter()
+        .enumerate()
+        .map(|(index, heap)| HeapBudget {
+            size: heap.size,
+            usage: if memory_budget_enabled {
+                budget_properties.heap_usage[index]
+            } else {
+                0
+            },
+            budget: if memory_budget_enabled {
+                budget_properties.heap_budget[index]
+            } else {
+                heap.size
+            },
+            device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+        })
+        .collect();
+
+    MemoryBudget {
+        heaps,
+        is_exact: memory_budget_enabled,
+    }
+}
+
+/// A `vk::Buffer` whose memory has been sub-allocated from one of the device's memory blocks.
+/// The buffer is destroyed and its memory returned to the allocator when it's dropped
+pub struct Buffer {
+    device: Weak<ash::Device>,
+    allocator: Weak<RefCell<Allocator>>,
+    pub(crate) buffer: vk::Buffer,
+    allocation: Option<Allocation>,
+}
+
+impl Buffer {
+    /// Constructs a new `Buffer` and binds memory to it.
+    /// Note that the recommended way to create a buffer is through [`Device::allocate_buffer()`] rather than using `Buffer::new()` directly
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The logical device to create the buffer on
+    /// * `allocator`: The allocator to take the buffer's memory from
+    /// * `create_info`: The description of the buffer
+    /// * `location`: Where the buffer's memory should live
+    /// * `name`: A name for the allocation, used when debugging leaks
+    pub(crate) fn new(
+        device: &Rc<ash::Device>,
+        allocator: &Rc<RefCell<Allocator>>,
+        create_info: &vk::BufferCreateInfo,
+        location: MemoryLocation,
+        name: &str,
+    ) -> Self {
+        Self::new_aligned(device, allocator, create_info, location, 1, name)
+    }
+
+    /// Constructs a new `Buffer` whose memory starts at a multiple of `min_alignment` within its memory block, as well as meeting
+    /// the buffer's own alignment requirement
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The logical device to create the buffer on
+    /// * `allocator`: The allocator to take the buffer's memory from
+    /// * `create_info`: The description of the buffer
+    /// * `location`: Where the buffer's memory should live
+    /// * `min_alignment`: The alignment of the buffer's memory, in bytes, which must be a power of two
+    /// * `name`: A name for the allocation, used when debugging leaks
+    pub(crate) fn new_aligned(
+        device: &Rc<ash::Device>,
+        allocator: &Rc<RefCell<Allocator>>,
+        create_info: &vk::BufferCreateInfo,
+        location: MemoryLocation,
+        min_alignment: u64,
+        name: &str,
+    ) -> Self {
+        let span = debug_span!("Vulkan/Buffer");
+        let _guard = span.enter();
+
+        debug!("Creating buffer {}", name);
+        let buffer = unsafe { device.create_buffer(create_info, None) }
+            .expect("Failed to create Vulkan buffer");
+        let mut requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        requirements.alignment = requirements.alignment.max(min_alignment);
+
+        let allocation = allocator
+            .borrow_mut()
+            .allocate(&AllocationCreateDesc {
+                name,
+                requirements,
+                location,
+                linear: true,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate memory for buffer");
+
+        unsafe { device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()) }
+            .expect("Failed to bind buffer memory");
+
+        track_created(ResourceKind::Buffer, 1);
+        Buffer {
+            device: Rc::downgrade(device),
+            allocator: Rc::downgrade(allocator),
+            buffer,
+            allocation: Some(allocation),
+        }
+    }
+
+    /// Gets the size of the memory backing the buffer, which may be larger than the size requested
+    pub fn size(&self) -> u64 {
+        self.allocation.as_ref().unwrap().size()
+    }
+
+    /// Gets whether the buffer's memory is local to the device (ie. in VRAM on a discrete GPU)
+    pub fn is_device_local(&self) -> bool {
+        self.allocation
+            .as_ref()
+            .unwrap()
+            .memory_properties()
+            .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+    }
+
+    /// Gets the buffer's memory as a read-only byte slice, if it's host-visible
+    pub fn mapped_slice(&self) -> Option<&[u8]> {
+        self.allocation.as_ref().unwrap().mapped_slice()
+    }
+
+    /// Gets the buffer's memory as a byte slice, if it's host-visible
+    pub fn mapped_slice_mut(&mut self) -> Option<&mut [u8]> {
+        self.allocation.as_mut().unwrap().mapped_slice_mut()
+    }
+
+    /// Gets a pointer to the start of the buffer's memory, if it's host-visible. The memory stays mapped for as long as the buffer
+    /// exists, so the pointer can be held onto (eg. to write uniforms every frame), but mustn't be used after the buffer is dropped
+    pub fn mapped_ptr(&self) -> Option<NonNull<c_void>> {
+        self.allocation.as_ref().unwrap().mapped_ptr()
+    }
+
+    /// Gets whether writes to the buffer's memory are visible to the device without being flushed
+    pub(crate) fn is_host_coherent(&self) -> bool {
+        self.allocation
+            .as_ref()
+            .unwrap()
+            .memory_properties()
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    /// Gets the memory block the buffer was sub-allocated from, and the offset of the buffer's memory within it
+    pub(crate) fn memory_range(&self) -> (vk::DeviceMemory, u64) {
+        let allocation = self.allocation.as_ref().unwrap();
+        (unsafe { allocation.memory() }, allocation.offset())
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~Buffer");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+        let allocator = self
+            .allocator
+            .upgrade()
+            .expect("Allocator should still exist");
+
+        debug!("Destroying buffer");
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        track_destroyed(ResourceKind::Buffer, 1);
+        allocator
+            .borrow_mut()
+            .free(self.allocation.take().unwrap())
+            .expect("Failed to free buffer memory");
+    }
+}
+
+/// A `vk::Image` whose memory has been sub-allocated from one of the device's memory blocks.
+/// The image is destroyed and its memory returned to the allocator when it's dropped
+pub struct Image {
+    device: Weak<ash::Device>,
+    allocator: Weak<RefCell<Allocator>>,
+    pub(crate) image: vk::Image,
+    allocation: Option<Allocation>,
+}
+
+impl Image {
+    /// Constructs a new `Image` and binds memory to it.
+    /// Note that the recommended way to create an image is through [`Device::allocate_image()`] rather than using `Image::new()` directly
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The logical device to create the image on
+    /// * `allocator`: The allocator to take the image's memory from
+    /// * `create_info`: The description of the image
+    /// * `location`: Where the image's memory should live
+    /// * `name`: A name for the allocation, used when debugging leaks
+    pub(crate) fn new(
+        device: &Rc<ash::Device>,
+        allocator: &Rc<RefCell<Allocator>>,
+        create_info: &vk::ImageCreateInfo,
+        location: MemoryLocation,
+        name: &str,
+    ) -> Self {
+        let span = debug_span!("Vulkan/Image");
+        let _guard = span.enter();
+
+        debug!("Creating image {}", name);
+        let image = unsafe { device.create_image(create_info, None) }
+            .expect("Failed to create Vulkan image");
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let allocation = allocator
+            .borrow_mut()
+            .allocate(&AllocationCreateDesc {
+                name,
+                requirements,
+                location,
+                linear: create_info.tiling == vk::ImageTiling::LINEAR,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate memory for image");
+
+        unsafe { device.bind_image_memory(image, allocation.memory(), allocation.offset()) }
+            .expect("Failed to bind image memory");
+
+        track_created(ResourceKind::Image, 1);
+        Image {
+            device: Rc::downgrade(device),
+            allocator: Rc::downgrade(allocator),
+            image,
+            allocation: Some(allocation),
+        }
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~Image");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+        let allocator = self
+            .allocator
+            .upgrade()
+            .expect("Allocator should still exist");
+
+        debug!("Destroying image");
+        unsafe { device.destroy_image(self.image, None) };
+        track_destroyed(ResourceKind::Image, 1);
+        allocator
+            .borrow_mut()
+            .free(self.allocation.take().unwrap())
+            .expect("Failed to free image memory");
+    }
+}
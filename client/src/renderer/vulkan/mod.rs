@@ -1,9 +1,64 @@
+mod barrier;
+mod bitmap_font;
+mod command_recorder;
+mod compute;
 mod context;
+mod depth_target;
 mod device;
+mod fallback_shaders;
+mod features;
+mod frame_readback;
+mod frame_uniforms;
+mod handle;
+mod image_commands;
+mod mapped_buffer;
+mod memory;
+mod multi_render_target;
 mod pipeline;
+mod pipeline_cache;
+mod post_process;
+mod reflection;
+mod render_graph;
+mod render_target;
+mod resource_stats;
+mod sampler;
+mod sprite_batch;
 mod surface;
+mod texture;
+mod vertex;
 
-pub use context::Context;
-pub use device::Device;
-pub use pipeline::Pipeline;
-pub use surface::Surface;
+pub use barrier::record_image_layout_transition;
+pub use bitmap_font::BitmapFont;
+pub use command_recorder::CommandRecorder;
+pub use compute::ComputePipeline;
+pub use context::{Context, ContextParameters, DebugMessageSeverity};
+pub use depth_target::DepthTarget;
+pub use device::{
+    AdapterInfo, BufferUpload, ComputeHandle, Device, DeviceParameters, PowerPreference,
+    QueueCounts, QueuePriorities, SharedQueue, TransferHandle, TransferredResource,
+};
+pub use features::{RequestedFeatures, SupportedFeatures};
+pub(crate) use frame_readback::{decode_frame, readback_texel_size};
+pub use frame_uniforms::UniformData;
+pub use gpu_allocator::MemoryLocation;
+pub use handle::{BufferHandle, Handle, PipelineHandle, TextureHandle};
+pub use image_commands::{record_blit_image, record_clear_color_image, ImageRegion};
+pub use mapped_buffer::MappedBuffer;
+pub use memory::{Buffer, HeapBudget, Image, MemoryBudget};
+pub use multi_render_target::MultiRenderTarget;
+pub use pipeline::{
+    DepthBias, DepthMode, DynamicState, Pipeline, PipelineParameters, ShaderStage,
+    ShaderStageSource, SpecializationConstants, SpecializationValue,
+};
+pub use post_process::PostProcess;
+pub use reflection::ShaderReflection;
+pub use render_graph::{AttachmentDescription, AttachmentHandle, RenderGraph, RenderGraphBuilder};
+pub use render_target::RenderTarget;
+pub use resource_stats::ResourceStats;
+pub use sampler::{Sampler, SamplerParameters};
+pub use sprite_batch::{Sprite, SpriteBatch, SpriteVertex};
+pub use surface::{
+    BufferingMode, ExternalAcquire, PresentMode, Surface, SurfaceCapabilities, SurfaceFormat,
+};
+pub use texture::{load_texture_pixels, Texture};
+pub use vertex::Vertex;
@@ -0,0 +1,140 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::render_target::{
+    create_sampled_colour_image, create_sampled_colour_render_pass,
+};
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{Device, Image};
+
+/// A set of offscreen colour images that are all rendered to in a single pass, each from a different fragment shader output
+/// `location` (eg. the position, normal, and albedo of a G-buffer for deferred shading). Once its render pass ends, every image is
+/// left in `SHADER_READ_ONLY_OPTIMAL` so that a later pass can sample them
+pub struct MultiRenderTarget {
+    device: Weak<ash::Device>,
+    // Dropped after the views have been destroyed
+    _images: Vec<Image>,
+    views: Vec<vk::ImageView>,
+    formats: Vec<vk::Format>,
+    pub(crate) render_pass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) clear_color: [f32; 4],
+}
+
+impl MultiRenderTarget {
+    /// Constructs a new `MultiRenderTarget`.
+    /// Note that the recommended way to create a multi-render target is through [`Device::create_multi_render_target()`] rather
+    /// than using `MultiRenderTarget::new()` directly, as it checks that the device supports the number of attachments and their formats
+    ///
+    /// A pipeline can draw to the target if it was created with
+    /// [`PipelineParameters::color_attachment_formats`](crate::renderer::vulkan::PipelineParameters::color_attachment_formats) set to
+    /// the same formats in the same order
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the target on
+    /// * `formats`: The format of each colour image, in the order of the fragment shader output locations they're written from
+    /// * `extent`: The size of the colour images
+    /// * `clear_color`: The colour every image is cleared to at the start of each pass
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::MultiRenderTarget;
+    ///
+    /// let g_buffer = MultiRenderTarget::new(
+    ///     &device,
+    ///     &[vk::Format::R16G16B16A16_SFLOAT, vk::Format::R16G16B16A16_SFLOAT, vk::Format::R8G8B8A8_UNORM],
+    ///     vk::Extent2D { width: 1280, height: 720 },
+    ///     [0.0, 0.0, 0.0, 0.0],
+    /// );
+    /// # }
+    /// ```
+    pub fn new(
+        device: &Device,
+        formats: &[vk::Format],
+        extent: vk::Extent2D,
+        clear_color: [f32; 4],
+    ) -> Self {
+        let span = debug_span!("Vulkan/MultiRenderTarget");
+        let _guard = span.enter();
+
+        debug!(
+            "Creating {}x{} render target with formats {:?}",
+            extent.width, extent.height, formats
+        );
+        let (images, views): (Vec<Image>, Vec<vk::ImageView>) = formats
+            .iter()
+            .map(|format| {
+                create_sampled_colour_image(device, *format, extent, 1, "multi-render target")
+            })
+            .unzip();
+        let render_pass = create_sampled_colour_render_pass(device, formats, 1);
+
+        let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(&views)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1)
+            .build();
+        let framebuffer = unsafe {
+            device
+                .logical_device
+                .create_framebuffer(&framebuffer_create_info, None)
+        }
+        .expect("Failed to create multi-render target framebuffer");
+        track_created(ResourceKind::Framebuffer, 1);
+
+        MultiRenderTarget {
+            device: Rc::downgrade(&device.logical_device),
+            _images: images,
+            views,
+            formats: formats.to_vec(),
+            render_pass,
+            framebuffer,
+            extent,
+            clear_color,
+        }
+    }
+
+    /// Gets the view of each colour image, in attachment order, so that they can be bound for sampling
+    pub fn views(&self) -> &[vk::ImageView] {
+        &self.views
+    }
+
+    /// Gets the format of each colour image, in attachment order
+    pub fn formats(&self) -> &[vk::Format] {
+        &self.formats
+    }
+
+    /// Gets the size of the colour images
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+}
+
+impl Drop for MultiRenderTarget {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~MultiRenderTarget");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying multi-render target");
+        unsafe {
+            device.destroy_framebuffer(self.framebuffer, None);
+            device.destroy_render_pass(self.render_pass, None);
+            for view in &self.views {
+                device.destroy_image_view(*view, None);
+            }
+        }
+        track_destroyed(ResourceKind::Framebuffer, 1);
+    }
+}
@@ -1,96 +1,504 @@
-use crate::renderer::vulkan::{Device, Surface};
+use crate::renderer::vulkan::depth_target::create_depth_only_render_pass;
+use crate::renderer::vulkan::render_target::create_sampled_colour_render_pass;
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{Device, ShaderReflection, Surface};
 use ash::vk;
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::CString;
+use std::path::Path;
 use std::rc::{Rc, Weak};
 use tracing::{debug, debug_span, warn};
 
+/// The first word of every SPIR-V module
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+
+/// A value for a specialization constant, which is baked into a shader when the pipeline is created
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecializationValue {
+    Bool(bool),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+}
+
+impl SpecializationValue {
+    fn to_ne_bytes(self) -> [u8; 4] {
+        match self {
+            // Booleans are 32-bit in SPIR-V
+            SpecializationValue::Bool(value) => vk::Bool32::from(value).to_ne_bytes(),
+            SpecializationValue::Int(value) => value.to_ne_bytes(),
+            SpecializationValue::UInt(value) => value.to_ne_bytes(),
+            SpecializationValue::Float(value) => value.to_ne_bytes(),
+        }
+    }
+}
+
+/// Specialization constant values, keyed by their `constant_id`.
+/// These are kept in order, so that the same constants always pack into the same specialization data
+pub type SpecializationConstants = BTreeMap<u32, SpecializationValue>;
+
+/// How depth values are mapped and compared.
+/// The projection matrix, the pipeline's depth test, and the depth clear value must all agree on the mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Near is 0 and far is 1, so nearer fragments have smaller depths
+    #[default]
+    Standard,
+    /// Near is 1 and far is 0, so nearer fragments have larger depths.
+    /// This gives much better precision at a distance, and should be paired with `common::math::perspective_reverse_z`
+    ReverseZ,
+}
+
+impl DepthMode {
+    /// Gets the comparison that passes for fragments nearer than what's already in the depth buffer
+    pub fn compare_op(&self) -> vk::CompareOp {
+        match self {
+            DepthMode::Standard => vk::CompareOp::LESS,
+            DepthMode::ReverseZ => vk::CompareOp::GREATER,
+        }
+    }
+
+    /// Gets the value the depth buffer should be cleared to, which is the furthest possible depth
+    pub fn clear_value(&self) -> vk::ClearDepthStencilValue {
+        vk::ClearDepthStencilValue {
+            depth: match self {
+                DepthMode::Standard => 1.0,
+                DepthMode::ReverseZ => 0.0,
+            },
+            stencil: 0,
+        }
+    }
+}
+
+/// An offset added to the depth of each polygon, which stops surfaces from shadowing themselves ("shadow acne") when rendering shadow maps.
+/// The offset is `constant_factor * r + slope_factor * m`, where `r` is the smallest resolvable depth difference and `m` is the
+/// polygon's depth slope
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    /// A constant offset, in units of the smallest resolvable depth difference
+    pub constant_factor: f32,
+    /// The largest (or, if negative, smallest) offset that's applied. A clamp other than 0 requires the `depthBiasClamp` device feature
+    pub clamp: f32,
+    /// How much the offset grows with the polygon's depth slope, so that surfaces at glancing angles are offset further
+    pub slope_factor: f32,
+}
+
+/// Pipeline state which can be changed whilst recording, rather than being fixed when the pipeline is built.
+/// The viewport and scissor are always dynamic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DynamicState {
+    /// The width of lines, set with [`Device::set_line_width()`]
+    LineWidth,
+    /// The depth bias, set with [`Device::set_depth_bias()`]
+    DepthBias,
+    /// The constant colour used by blend factors such as `CONSTANT_COLOR`, set with [`Device::set_blend_constants()`]
+    BlendConstants,
+    /// The value stencil tests compare against, set with [`Device::set_stencil_reference()`]
+    StencilReference,
+}
+
+impl From<DynamicState> for vk::DynamicState {
+    fn from(dynamic_state: DynamicState) -> Self {
+        match dynamic_state {
+            DynamicState::LineWidth => vk::DynamicState::LINE_WIDTH,
+            DynamicState::DepthBias => vk::DynamicState::DEPTH_BIAS,
+            DynamicState::BlendConstants => vk::DynamicState::BLEND_CONSTANTS,
+            DynamicState::StencilReference => vk::DynamicState::STENCIL_REFERENCE,
+        }
+    }
+}
+
+/// Options which control how a graphics pipeline is built, beyond which shaders it uses
+#[derive(Clone, Debug)]
+pub struct PipelineParameters {
+    /// Values for the specialization constants used by the shaders, keyed by `constant_id`.
+    /// The same constants are given to every stage, as entries with IDs a shader doesn't use are ignored
+    pub specialization_constants: SpecializationConstants,
+    /// The number of control points in each patch, if the pipeline has tessellation stages
+    pub patch_control_points: u32,
+    /// How the depth test compares fragments, which must match the projection and the depth clear value
+    pub depth_mode: DepthMode,
+    /// The number of views rendered at once with multiview (eg. 2 for stereo), which shaders can tell apart with `gl_ViewIndex`.
+    /// Values above 1 require multiview support, and the pipeline can then only draw to render targets with the same number of views
+    pub view_count: u32,
+    /// The vertex buffers the pipeline reads from, or none if the vertex shader generates its own vertices
+    pub vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    /// The vertex shader inputs, and where in the vertex buffers they're read from
+    pub vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    /// The depth bias applied to every polygon, or `None` to leave depths as they are
+    pub depth_bias: Option<DepthBias>,
+    /// The state which is set whilst recording rather than fixed when the pipeline is built, so that it can be changed without
+    /// rebuilding the pipeline (eg. tuning the depth bias per shadow cascade). Dynamic state must be set before drawing
+    pub dynamic_states: Vec<DynamicState>,
+    /// Whether the viewport is flipped with a negative height, so that +Y points up in clip space as it does in OpenGL.
+    /// This also reverses the winding order, so front faces are counter-clockwise as they are by default in OpenGL
+    pub flip_viewport_y: bool,
+    /// The format of the depth attachment when the pipeline draws only depth into a [`DepthTarget`](crate::renderer::vulkan::DepthTarget)
+    /// with the same format (eg. for shadow maps), or `None` to draw colour. Depth-only pipelines have no colour blend state, so their
+    /// fragment shader (if any) shouldn't write any colour outputs
+    pub depth_only_format: Option<vk::Format>,
+    /// The formats of the colour attachments when the pipeline draws into a
+    /// [`MultiRenderTarget`](crate::renderer::vulkan::MultiRenderTarget) with the same formats, or empty to draw to the surface (a
+    /// single attachment in the surface's format, along with its depth buffer). The fragment shader writes attachment `n` from the output at `location = n`. These attachments usually
+    /// hold data rather than colours (eg. G-buffer normals), so they're written without blending
+    pub color_attachment_formats: Vec<vk::Format>,
+    /// The push constant ranges the shaders read small per-draw data (eg. a model matrix) from, which is written with
+    /// [`Device::push_constants()`](crate::renderer::vulkan::Device::push_constants). If this is empty, the ranges are discovered by
+    /// reflection when the `reflection` feature is enabled
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    /// The layouts of descriptor sets the caller binds itself (eg. the layout from
+    /// [`Device::texture_array_descriptor_set_layout()`](crate::renderer::vulkan::Device::texture_array_descriptor_set_layout)),
+    /// which follow the frame uniforms as set 1 onwards. If this isn't empty, descriptor sets found by reflection are ignored.
+    /// The layouts aren't destroyed along with the pipeline, so they must outlive it
+    pub descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+}
+
+impl Default for PipelineParameters {
+    fn default() -> Self {
+        Self {
+            specialization_constants: SpecializationConstants::new(),
+            patch_control_points: 3,
+            depth_mode: DepthMode::default(),
+            view_count: 1,
+            vertex_bindings: vec![],
+            vertex_attributes: vec![],
+            depth_bias: None,
+            dynamic_states: vec![],
+            flip_viewport_y: false,
+            depth_only_format: None,
+            color_attachment_formats: vec![],
+            push_constant_ranges: vec![],
+            descriptor_set_layouts: vec![],
+        }
+    }
+}
+
+/// The programmable stages of a graphics pipeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    /// Requires the `geometryShader` device feature
+    Geometry,
+    /// Requires the `tessellationShader` device feature, and a `TessellationEvaluation` stage
+    TessellationControl,
+    /// Requires the `tessellationShader` device feature, and a `TessellationControl` stage
+    TessellationEvaluation,
+    Fragment,
+}
+
+impl From<ShaderStage> for vk::ShaderStageFlags {
+    fn from(stage: ShaderStage) -> Self {
+        match stage {
+            ShaderStage::Vertex => vk::ShaderStageFlags::VERTEX,
+            ShaderStage::Geometry => vk::ShaderStageFlags::GEOMETRY,
+            ShaderStage::TessellationControl => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+            ShaderStage::TessellationEvaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            ShaderStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+        }
+    }
+}
+
+/// Where to find the code for a single pipeline stage.
+/// Several stages can share the same `path` if the SPIR-V module has multiple entry points
+#[derive(Clone, Copy, Debug)]
+pub struct ShaderStageSource<'a> {
+    /// A `Path` which references a compiled SPIR-V module, relative to the asset root
+    pub path: &'a Path,
+    /// The name of the entry point within the module
+    pub entry_point: &'a str,
+    pub stage: ShaderStage,
+}
+
+impl<'a> ShaderStageSource<'a> {
+    /// Describes a stage that uses the conventional `main` entry point
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: A `Path` which references a compiled SPIR-V module, relative to the asset root
+    /// * `stage`: The stage the module should be used for
+    pub fn main(path: &'a Path, stage: ShaderStage) -> Self {
+        Self {
+            path,
+            entry_point: "main",
+            stage,
+        }
+    }
+}
+
 pub struct Pipeline {
     device: Weak<ash::Device>,
-    layout: vk::PipelineLayout,
-    cache: vk::PipelineCache,
+    descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
+    pub(crate) layout: vk::PipelineLayout,
     pub render_pass: vk::RenderPass,
     pub(crate) pipeline: vk::Pipeline,
-    vertex_shader: vk::ShaderModule,
-    fragment_shader: vk::ShaderModule,
+    shader_modules: Vec<vk::ShaderModule>,
+    flip_viewport_y: bool,
+    pub(crate) depth_mode: DepthMode,
+    // Whether set 0 is the device's per-frame uniform buffer, which happens when no descriptor sets were discovered by reflection
+    pub(crate) binds_frame_uniforms: bool,
+    // The formats of the render pass's attachments, in order, which decide which render passes the pipeline can be bound in
+    attachment_formats: Vec<vk::Format>,
+    view_count: u32,
 }
 
 impl Pipeline {
     /// Constructs a new graphics `Pipeline` using the provided shaders.
     /// Note that the recommended way to create a pipeline is through [`Device::create_pipeline()`]) rather than using `Pipeline::new()` directly
     ///
+    /// Each SPIR-V module is only loaded once, even if it provides several stages
+    ///
     /// # Arguments
     ///
     /// * `device`: The `Device` to construct the `Pipeline` on
     /// * `surface`: The `Surface` that the `Pipeline` should render to
-    /// * `vertex_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the application executable
-    /// * `fragment_shader_path`: A `Path` which references a compiled SPIR-V vertex shader, relative to the application executable
+    /// * `stages`: The module and entry point to use for each stage of the pipeline
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # use client::renderer::RendererError;
+    /// # use client::renderer::vulkan::{DeviceParameters, Pipeline};
+    /// # fn example() -> Result<(), RendererError> {
     /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
-    /// use client::renderer::vulkan::{Context, Device, Surface};
+    /// use client::renderer::vulkan::{Context, Device, PipelineParameters, Surface, ShaderStage, ShaderStageSource};
     /// use std::path::Path;
     ///
     /// let event_loop = EventLoopBuilder::new().build().unwrap();
     /// let window = WindowBuilder::new().build(&event_loop).unwrap();
     ///
-    /// let context = Context::new("my-application", (1.4.2));
-    /// let device = Device::new(&context);
-    /// let surface = Surface::new(&context, &device, &window);
-    /// let pipeline = Pipeline::new(&surface, Path::new("vertex_shader.spv"), Path::new("fragment_shader.spv"), String::from("my_shader"));
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let surface = Surface::new(&context, &window);
+    /// let device = Device::new(&context, &surface, &DeviceParameters::default())?;
+    ///
+    /// let stages = [
+    ///     ShaderStageSource { path: Path::new("shader.spv"), entry_point: "vertex_main", stage: ShaderStage::Vertex },
+    ///     ShaderStageSource { path: Path::new("shader.spv"), entry_point: "fragment_main", stage: ShaderStage::Fragment },
+    /// ];
+    /// let pipeline = Pipeline::new(&device, &surface, &stages, &PipelineParameters::default());
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn new(
         device: &Device,
         surface: &Surface,
-        vertex_shader_path: &std::path::Path,
-        fragment_shader_path: &std::path::Path,
+        stages: &[ShaderStageSource],
+        parameters: &PipelineParameters,
     ) -> Self {
-        let vertex_shader_module = load_shader(device, vertex_shader_path)
-            .expect("The vertex shader either wasn't found, or was invalid");
-        let fragment_shader_module = load_shader(device, fragment_shader_path)
-            .expect("The vertex shader either wasn't found, or was invalid");
+        let mut code_by_path: HashMap<&Path, Vec<u32>> = HashMap::new();
+        for stage in stages {
+            if !code_by_path.contains_key(stage.path) {
+                let code = read_spirv(&device.resolve_asset_path(stage.path))
+                    .expect("A shader wasn't found");
+                code_by_path.insert(stage.path, code);
+            }
+        }
 
-        let shader_entry_point: CString = CString::new("main").unwrap();
+        Self::from_code(device, surface, stages, &code_by_path, parameters)
+    }
 
-        let vertex_shader_state_create_info = vk::PipelineShaderStageCreateInfo::builder()
-            .name(shader_entry_point.as_c_str())
-            .module(vertex_shader_module)
-            .stage(vk::ShaderStageFlags::VERTEX)
-            .build();
+    /// Constructs a new graphics `Pipeline` from SPIR-V code which has already been loaded, eg. from shaders embedded in the executable
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to construct the `Pipeline` on
+    /// * `surface`: The `Surface` that the `Pipeline` should render to
+    /// * `stages`: The module and entry point to use for each stage of the pipeline. The paths only identify modules within
+    ///   `code_by_path`, and aren't read from
+    /// * `code_by_path`: The SPIR-V code of each module used by the stages
+    /// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
+    pub(crate) fn from_code(
+        device: &Device,
+        surface: &Surface,
+        stages: &[ShaderStageSource],
+        code_by_path: &HashMap<&Path, Vec<u32>>,
+        parameters: &PipelineParameters,
+    ) -> Self {
+        let modules_by_path: HashMap<&Path, vk::ShaderModule> = code_by_path
+            .iter()
+            .map(|(path, code)| (*path, create_shader_module(device, code)))
+            .collect();
+
+        let reflection = reflect_stages(stages, code_by_path);
+        // Vertex inputs declared in the parameters take precedence over reflected ones
+        let reflected_parameters;
+        let parameters = if parameters.vertex_bindings.is_empty()
+            && parameters.vertex_attributes.is_empty()
+            && !reflection.vertex_bindings.is_empty()
+        {
+            reflected_parameters = PipelineParameters {
+                vertex_bindings: reflection.vertex_bindings.clone(),
+                vertex_attributes: reflection.vertex_attributes.clone(),
+                ..parameters.clone()
+            };
+            &reflected_parameters
+        } else {
+            parameters
+        };
 
-        let fragment_shader_state_create_info = vk::PipelineShaderStageCreateInfo::builder()
-            .name(shader_entry_point.as_c_str())
-            .module(fragment_shader_module)
-            .stage(vk::ShaderStageFlags::FRAGMENT)
+        let entry_points: Vec<CString> = stages
+            .iter()
+            .map(|stage| {
+                CString::new(stage.entry_point)
+                    .expect("Shader entry point names cannot contain null characters")
+            })
+            .collect();
+
+        let (specialization_map_entries, specialization_data) =
+            create_specialization_data(&parameters.specialization_constants);
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(specialization_map_entries.as_slice())
+            .data(specialization_data.as_slice())
             .build();
 
-        let pipeline_layout = create_pipeline_layout(device);
-        let pipeline_cache = create_pipeline_cache(device);
-        let render_pass = create_render_pass(device, surface);
+        let shader_stage_create_infos: Vec<vk::PipelineShaderStageCreateInfo> = stages
+            .iter()
+            .zip(entry_points.iter())
+            .map(|(stage, entry_point)| {
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .name(entry_point.as_c_str())
+                    .module(*modules_by_path.get(stage.path).unwrap())
+                    .stage(stage.stage.into())
+                    .specialization_info(&specialization_info)
+                    .build()
+            })
+            .collect();
+
+        // Push constant ranges declared in the parameters take precedence over reflected ones
+        let push_constant_ranges = if parameters.push_constant_ranges.is_empty() {
+            reflection.push_constant_ranges
+        } else {
+            parameters.push_constant_ranges.clone()
+        };
+        // Likewise for descriptor set layouts, which are then owned by the caller rather than the pipeline
+        let descriptor_set_layouts = if parameters.descriptor_set_layouts.is_empty() {
+            create_descriptor_set_layouts(device, &reflection.descriptor_sets)
+        } else {
+            vec![]
+        };
+        // The frame uniform layout belongs to the device, so it's kept out of `descriptor_set_layouts` to avoid destroying it
+        let binds_frame_uniforms = descriptor_set_layouts.is_empty();
+        let pipeline_layout = if binds_frame_uniforms {
+            let set_layouts: Vec<vk::DescriptorSetLayout> =
+                std::iter::once(device.uniform_descriptor_set_layout())
+                    .chain(parameters.descriptor_set_layouts.iter().copied())
+                    .collect();
+            create_pipeline_layout(device, &set_layouts, &push_constant_ranges)
+        } else {
+            create_pipeline_layout(device, &descriptor_set_layouts, &push_constant_ranges)
+        };
+        let attachment_formats = match parameters.depth_only_format {
+            Some(format) => vec![format],
+            None if !parameters.color_attachment_formats.is_empty() => {
+                parameters.color_attachment_formats.clone()
+            }
+            None => vec![
+                surface
+                    .swapchain_parameters
+                    .as_ref()
+                    .unwrap()
+                    .surface_format
+                    .format,
+                surface.depth_format(),
+            ],
+        };
+        let render_pass = match parameters.depth_only_format {
+            Some(format) => create_depth_only_render_pass(device, format, parameters.view_count),
+            None if !parameters.color_attachment_formats.is_empty() => {
+                create_sampled_colour_render_pass(
+                    device,
+                    &parameters.color_attachment_formats,
+                    parameters.view_count,
+                )
+            }
+            None => create_render_pass(device, surface, parameters.view_count),
+        };
         let graphics_pipeline = create_graphics_pipeline(
             device,
             surface,
             &pipeline_layout,
             &render_pass,
-            &pipeline_cache,
-            vertex_shader_state_create_info,
-            fragment_shader_state_create_info,
+            &device.pipeline_cache(),
+            shader_stage_create_infos.as_slice(),
+            parameters,
         );
 
+        track_created(ResourceKind::Pipeline, 1);
         Pipeline {
             device: Rc::downgrade(&device.logical_device),
+            descriptor_set_layouts,
+            push_constant_ranges,
             layout: pipeline_layout,
-            cache: pipeline_cache,
             render_pass,
             pipeline: graphics_pipeline,
-            vertex_shader: vertex_shader_module,
-            fragment_shader: fragment_shader_module,
+            shader_modules: modules_by_path.into_values().collect(),
+            flip_viewport_y: parameters.flip_viewport_y,
+            depth_mode: parameters.depth_mode,
+            binds_frame_uniforms,
+            attachment_formats,
+            view_count: parameters.view_count,
         }
     }
 }
 
+impl Pipeline {
+    /// Gets the layout of each descriptor set the pipeline uses, indexed by set number.
+    /// These are only discovered with the `reflection` feature, and are empty otherwise
+    pub fn descriptor_set_layouts(&self) -> &[vk::DescriptorSetLayout] {
+        &self.descriptor_set_layouts
+    }
+
+    /// Gets the push constant ranges the pipeline uses.
+    /// These are only discovered with the `reflection` feature, and are empty otherwise
+    pub fn push_constant_ranges(&self) -> &[vk::PushConstantRange] {
+        &self.push_constant_ranges
+    }
+
+    /// Gets the viewport covering the whole of a framebuffer, flipped if the pipeline was created with `flip_viewport_y`
+    ///
+    /// # Arguments
+    ///
+    /// * `extent`: The size of the framebuffer being drawn to
+    pub fn viewport(&self, extent: vk::Extent2D) -> vk::Viewport {
+        full_viewport(extent, self.flip_viewport_y)
+    }
+
+    /// Gets whether this pipeline can be bound in a render pass begun with another pipeline, which is the case when their render
+    /// passes have the same attachment formats and view count
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The pipeline the render pass was begun with
+    pub fn is_render_pass_compatible(&self, other: &Pipeline) -> bool {
+        self.attachment_formats == other.attachment_formats && self.view_count == other.view_count
+    }
+}
+
+/// Creates a viewport covering the whole of a framebuffer.
+/// When flipped, the viewport starts at the bottom of the framebuffer and has a negative height, which is valid since Vulkan 1.1
+///
+/// # Arguments
+///
+/// * `extent`: The size of the framebuffer being drawn to
+/// * `flip_y`: Whether +Y should point up in clip space
+fn full_viewport(extent: vk::Extent2D, flip_y: bool) -> vk::Viewport {
+    let (y, height) = if flip_y {
+        (extent.height as f32, -(extent.height as f32))
+    } else {
+        (0.0, extent.height as f32)
+    };
+    vk::Viewport::builder()
+        .x(0.0)
+        .y(y)
+        .width(extent.width as f32)
+        .height(height)
+        .min_depth(0.0)
+        .max_depth(1.0)
+        .build()
+}
+
 impl Drop for Pipeline {
     fn drop(&mut self) {
         let span = debug_span!("Vulkan/~Pipeline");
@@ -100,51 +508,72 @@ impl Drop for Pipeline {
 
         debug!("Destroying pipeline");
         unsafe { device.destroy_pipeline(self.pipeline, None) };
+        track_destroyed(ResourceKind::Pipeline, 1);
         debug!("Destroying render pass");
         unsafe { device.destroy_render_pass(self.render_pass, None) };
-        debug!("Destroying pipeline cache");
-        unsafe { device.destroy_pipeline_cache(self.cache, None) };
         debug!("Destroying pipeline layout");
         unsafe { device.destroy_pipeline_layout(self.layout, None) };
-        debug!("Destroying vertex shader module");
-        unsafe { device.destroy_shader_module(self.vertex_shader, None) };
-        debug!("Destroying fragment shader module");
-        unsafe { device.destroy_shader_module(self.fragment_shader, None) };
+        for descriptor_set_layout in &self.descriptor_set_layouts {
+            unsafe { device.destroy_descriptor_set_layout(*descriptor_set_layout, None) };
+        }
+        for shader_module in &self.shader_modules {
+            debug!("Destroying shader module {:?}", shader_module);
+            unsafe { device.destroy_shader_module(*shader_module, None) };
+        }
     }
 }
 
-/// Constructs an `ash::vk::PipelineLayout` with default parameters
+/// Packs specialization constant values into the map entries and data blob used by `vk::SpecializationInfo`
 ///
 /// # Arguments
 ///
-/// * `device`: The `Device` to create the pipeline layout for
+/// * `specialization_constants`: The values of the constants, keyed by `constant_id`
 ///
-fn create_pipeline_layout(device: &Device) -> vk::PipelineLayout {
-    let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo::builder().build();
+fn create_specialization_data(
+    specialization_constants: &SpecializationConstants,
+) -> (Vec<vk::SpecializationMapEntry>, Vec<u8>) {
+    let mut map_entries = Vec::with_capacity(specialization_constants.len());
+    let mut data = Vec::with_capacity(specialization_constants.len() * 4);
 
-    unsafe {
-        device
-            .logical_device
-            .create_pipeline_layout(&pipeline_layout_create_info, None)
+    for (constant_id, value) in specialization_constants {
+        let bytes = value.to_ne_bytes();
+        map_entries.push(
+            vk::SpecializationMapEntry::builder()
+                .constant_id(*constant_id)
+                .offset(data.len() as u32)
+                .size(bytes.len())
+                .build(),
+        );
+        data.extend_from_slice(&bytes);
     }
-    .expect("Failed to create Vulkan pipeline")
+
+    (map_entries, data)
 }
 
-/// Constructs an `ash::vk::PipelineCache` with default parameters
+/// Constructs an `ash::vk::PipelineLayout`
 ///
 /// # Arguments
 ///
 /// * `device`: The `Device` to create the pipeline layout for
+/// * `descriptor_set_layouts`: The layout of each descriptor set, indexed by set number
+/// * `push_constant_ranges`: The push constant ranges used by the shaders
 ///
-fn create_pipeline_cache(device: &Device) -> vk::PipelineCache {
-    let pipeline_cache_create_info = vk::PipelineCacheCreateInfo::builder().build();
+fn create_pipeline_layout(
+    device: &Device,
+    descriptor_set_layouts: &[vk::DescriptorSetLayout],
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> vk::PipelineLayout {
+    let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(descriptor_set_layouts)
+        .push_constant_ranges(push_constant_ranges)
+        .build();
 
     unsafe {
         device
             .logical_device
-            .create_pipeline_cache(&pipeline_cache_create_info, None)
+            .create_pipeline_layout(&pipeline_layout_create_info, None)
     }
-    .expect("Failed to create Vulkan pipeline cache")
+    .expect("Failed to create Vulkan pipeline")
 }
 
 /// Constructs an `ash::vk::RenderPass` with default parameters
@@ -153,8 +582,9 @@ fn create_pipeline_cache(device: &Device) -> vk::PipelineCache {
 ///
 /// * `device`: The `Device` to create the pipeline layout for
 /// * `surface`: The `Surface` that the render pass should render to
+/// * `view_count`: The number of views to render with multiview, or 1 to not use multiview
 ///
-fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
+fn create_render_pass(device: &Device, surface: &Surface, view_count: u32) -> vk::RenderPass {
     let colour_attachment = vk::AttachmentDescription::builder()
         .format(
             surface
@@ -173,29 +603,69 @@ fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
         .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
         .build();
 
+    // The depth buffer is only needed whilst the frame is drawn, so it isn't stored
+    let depth_attachment = vk::AttachmentDescription::builder()
+        .format(surface.depth_format())
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .build();
+
     let colour_attachment_reference = vk::AttachmentReference::builder()
         .attachment(0)
         .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
         .build();
+    let depth_attachment_reference = vk::AttachmentReference::builder()
+        .attachment(1)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .build();
 
     let subpass = vk::SubpassDescription::builder()
         .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
         .color_attachments(&[colour_attachment_reference])
+        .depth_stencil_attachment(&depth_attachment_reference)
         .build();
 
+    // The depth buffer is shared by every swapchain image, so the previous frame's depth tests must finish before it's cleared again
     let subpass_dependency = vk::SubpassDependency::builder()
         .src_subpass(vk::SUBPASS_EXTERNAL)
-        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-        .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-        .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .src_stage_mask(
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        )
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_stage_mask(
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        )
+        .dst_access_mask(
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        )
         .build();
 
-    let render_pass_create_info = vk::RenderPassCreateInfo::builder()
-        .attachments(&[colour_attachment])
-        .subpasses(&[subpass])
-        .dependencies(&[subpass_dependency])
+    let attachments = [colour_attachment, depth_attachment];
+    let subpasses = [subpass];
+    let dependencies = [subpass_dependency];
+    let view_masks = [multiview_mask(view_count)];
+    let mut multiview_create_info = vk::RenderPassMultiviewCreateInfo::builder()
+        .view_masks(&view_masks)
+        .correlation_masks(&view_masks)
         .build();
 
+    let mut render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+    if view_count > 1 {
+        render_pass_create_info = render_pass_create_info.push_next(&mut multiview_create_info);
+    }
+    let render_pass_create_info = render_pass_create_info.build();
+
     unsafe {
         device
             .logical_device
@@ -204,6 +674,20 @@ fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
     .expect("Failed to create Vulkan render pass")
 }
 
+/// Gets the mask which selects the first `view_count` views of a multiview render pass.
+/// The same mask is used as the correlation mask, since views are expected to be spatially close (eg. a pair of eyes)
+///
+/// # Arguments
+///
+/// * `view_count`: The number of views
+pub(crate) fn multiview_mask(view_count: u32) -> u32 {
+    if view_count >= 32 {
+        u32::MAX
+    } else {
+        (1 << view_count) - 1
+    }
+}
+
 /// Constructs an `ash::vk::Pipeline` with default parameters, using the provided shaders
 ///
 /// # Arguments
@@ -213,12 +697,12 @@ fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
 /// * `pipeline_layout`: The pipeline layout to make the pipeline according to
 /// * `render_pass`: The render pass the pipeline should use
 /// * `pipeline_cache`: The pipeline cache that the pipeline should use
-/// * `vertex_shader`: The `PipelineShaderStageCreateInfo` for the vertex shader that the pipeline should use
-/// * `fragment_shader`: The `PipelineShaderStageCreateInfo` for the fragment shader that the pipeline should use
+/// * `shader_stages`: The `PipelineShaderStageCreateInfo`s for each shader stage that the pipeline should use
+/// * `parameters`: The `PipelineParameters` controlling how the pipeline is built
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// use ash::vk;
 /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
 /// use client::renderer::vulkan::{Context, Device, Surface};
@@ -232,8 +716,8 @@ fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
 /// let surface = Surface::new(&context, &device, &window);
 ///
 ///
-/// let vertex_shader_module = load_shader(device, vertex_shader_path).unwrap();
-/// let fragment_shader_module = load_shader(device, fragment_shader_path).unwrap();
+/// let vertex_shader_module = create_shader_module(&device, &read_spirv(vertex_shader_path).unwrap());
+/// let fragment_shader_module = create_shader_module(&device, &read_spirv(fragment_shader_path).unwrap());
 ///
 /// let shader_entry_point: CString = CString::new("main").unwrap();
 ///
@@ -249,17 +733,16 @@ fn create_render_pass(device: &Device, surface: &Surface) -> vk::RenderPass {
 ///     .stage(vk::ShaderStageFlags::FRAGMENT)
 ///     .build();
 ///
-/// let pipeline_layout = create_pipeline_layout(&device);
-/// let pipeline_cache = create_pipeline_cache(&device);
+/// let pipeline_layout = create_pipeline_layout(&device, &[], &[]);
 /// let render_pass = create_render_pass(&device, &surface);
 /// let graphics_pipeline = create_graphics_pipeline(
 ///     &device,
 ///     &surface,
 ///     &pipeline_layout,
 ///     &render_pass,
-///     &pipeline_cache,
-///     vertex_shader_state_create_info,
-///     fragment_shader_state_create_info
+///     &device.pipeline_cache(),
+///     &[vertex_shader_state_create_info, fragment_shader_state_create_info],
+///     None
 /// );
 /// ```
 fn create_graphics_pipeline(
@@ -268,36 +751,38 @@ fn create_graphics_pipeline(
     pipeline_layout: &vk::PipelineLayout,
     render_pass: &vk::RenderPass,
     pipeline_cache: &vk::PipelineCache,
-    vertex_shader: vk::PipelineShaderStageCreateInfo,
-    fragment_shader: vk::PipelineShaderStageCreateInfo,
+    shader_stages: &[vk::PipelineShaderStageCreateInfo],
+    parameters: &PipelineParameters,
 ) -> vk::Pipeline {
-    // let vertex_input_attribute_description = vk::VertexInputAttributeDescription::builder()
-    //     .format(surface.swapchain_parameters.surface_format.format)
-    //     .location(0)
-    //     .build();
-
-    // let vertex_input_binding_description = vk::VertexInputBindingDescription::builder()
-    //     .binding(0)
-    //     .build();
+    // The patch control point count is only used if the pipeline has tessellation stages
+    let patch_control_points = shader_stages
+        .iter()
+        .any(|stage| stage.stage == vk::ShaderStageFlags::TESSELLATION_CONTROL)
+        .then_some(parameters.patch_control_points);
 
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_attribute_descriptions(&[])
-        .vertex_binding_descriptions(&[])
+        .vertex_attribute_descriptions(&parameters.vertex_attributes)
+        .vertex_binding_descriptions(&parameters.vertex_bindings)
         .build();
 
+    // Tessellation stages consume patches rather than triangles
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .topology(if patch_control_points.is_some() {
+            vk::PrimitiveTopology::PATCH_LIST
+        } else {
+            vk::PrimitiveTopology::TRIANGLE_LIST
+        })
         .primitive_restart_enable(false);
 
-    let viewport = vk::Viewport::builder()
-        .x(0.0)
-        .y(0.0)
-        .width(surface.swapchain_parameters.as_ref().unwrap().extent.width as f32)
-        .height(surface.swapchain_parameters.as_ref().unwrap().extent.height as f32)
-        .min_depth(0.0)
-        .max_depth(1.0)
+    let tessellation_state = vk::PipelineTessellationStateCreateInfo::builder()
+        .patch_control_points(patch_control_points.unwrap_or(0))
         .build();
 
+    let viewport = full_viewport(
+        surface.swapchain_parameters.as_ref().unwrap().extent,
+        parameters.flip_viewport_y,
+    );
+
     let scissor = vk::Rect2D::builder()
         .extent(surface.swapchain_parameters.as_ref().unwrap().extent)
         .offset(vk::Offset2D::builder().x(0).y(0).build())
@@ -308,12 +793,24 @@ fn create_graphics_pipeline(
         .viewports(&[viewport])
         .build();
 
+    let depth_bias = parameters.depth_bias.unwrap_or_default();
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
         .cull_mode(vk::CullModeFlags::BACK)
-        .front_face(vk::FrontFace::CLOCKWISE)
+        // Flipping the viewport mirrors every triangle, so the winding order is reversed to match
+        .front_face(if parameters.flip_viewport_y {
+            vk::FrontFace::COUNTER_CLOCKWISE
+        } else {
+            vk::FrontFace::CLOCKWISE
+        })
         .polygon_mode(vk::PolygonMode::FILL)
         .line_width(1.0)
-        .depth_bias_enable(false)
+        .depth_bias_enable(
+            parameters.depth_bias.is_some()
+                || parameters.dynamic_states.contains(&DynamicState::DepthBias),
+        )
+        .depth_bias_constant_factor(depth_bias.constant_factor)
+        .depth_bias_clamp(depth_bias.clamp)
+        .depth_bias_slope_factor(depth_bias.slope_factor)
         .build();
 
     let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
@@ -321,7 +818,14 @@ fn create_graphics_pipeline(
         .sample_shading_enable(false)
         .build();
 
-    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder().build();
+    // This is ignored unless the render pass has a depth attachment
+    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(parameters.depth_mode.compare_op())
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false)
+        .build();
 
     let color_blend_attachment_state = vk::PipelineColorBlendAttachmentState::builder()
         .blend_enable(true)
@@ -333,31 +837,56 @@ fn create_graphics_pipeline(
         .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
         .build();
 
+    // Each attachment needs its own blend state, and data such as G-buffer normals would be corrupted by blending
+    let color_blend_attachment_states = if parameters.color_attachment_formats.is_empty() {
+        vec![color_blend_attachment_state]
+    } else {
+        let opaque_attachment_state = vk::PipelineColorBlendAttachmentState::builder()
+            .blend_enable(false)
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .build();
+        vec![opaque_attachment_state; parameters.color_attachment_formats.len()]
+    };
+
     let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
         .logic_op_enable(false)
         .logic_op(vk::LogicOp::COPY)
-        .attachments(&[color_blend_attachment_state])
+        .attachments(&color_blend_attachment_states)
         .build();
 
+    let mut dynamic_states = vec![vk::DynamicState::SCISSOR, vk::DynamicState::VIEWPORT];
+    for dynamic_state in &parameters.dynamic_states {
+        // Listing a state twice is invalid
+        let dynamic_state = vk::DynamicState::from(*dynamic_state);
+        if !dynamic_states.contains(&dynamic_state) {
+            dynamic_states.push(dynamic_state);
+        }
+    }
     let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
-        .dynamic_states(&[vk::DynamicState::SCISSOR, vk::DynamicState::VIEWPORT])
+        .dynamic_states(&dynamic_states)
         .build();
 
-    let pipeline_create_info = vk::GraphicsPipelineCreateInfo::builder()
-        .stages(&[fragment_shader, vertex_shader])
+    let mut pipeline_create_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(shader_stages)
         .vertex_input_state(&vertex_input_state)
         .input_assembly_state(&input_assembly_state)
         .viewport_state(&viewport_state)
         .rasterization_state(&rasterization_state)
         .multisample_state(&multisample_state)
         .depth_stencil_state(&depth_stencil_state)
-        .color_blend_state(&color_blend_state)
         .dynamic_state(&dynamic_state)
         .layout(*pipeline_layout)
         .render_pass(*render_pass)
         .subpass(0)
-        .base_pipeline_handle(vk::Pipeline::null())
-        .build();
+        .base_pipeline_handle(vk::Pipeline::null());
+    if patch_control_points.is_some() {
+        pipeline_create_info = pipeline_create_info.tessellation_state(&tessellation_state);
+    }
+    // A depth-only render pass has no colour attachments to blend
+    if parameters.depth_only_format.is_none() {
+        pipeline_create_info = pipeline_create_info.color_blend_state(&color_blend_state);
+    }
+    let pipeline_create_info = pipeline_create_info.build();
 
     *unsafe {
         device.logical_device.create_graphics_pipelines(
@@ -371,63 +900,156 @@ fn create_graphics_pipeline(
     .expect("Pipeline creation was successful, but returned no pipeline object")
 }
 
-/// Attempts to load a shader file from the `Path` provided, and creates a shader module using it.
-///
-///
-/// If the file existed, contained valid bytecode, and was compiled successfully, returns `Some<ash::vk::ShaderModule>`
+/// Attempts to read a compiled SPIR-V shader file from the `Path` provided
 ///
-/// If the file doesn't exist, the bytecode is invalid, or compilation fails, returns `None`
+/// If the file doesn't exist or isn't valid SPIR-V, returns `Err`
 ///
 /// # Arguments
 ///
-/// * `device`: The `Device` to create the shader module on
-/// * `relative_file_path`: A `Path` referencing a compiled SPIR-V shader file, relative to the application executable
+/// * `file_path`: A `Path` referencing a compiled SPIR-V shader file, already resolved with [`Device::resolve_asset_path()`]
 ///
 /// # Examples
 ///
+/// ```ignore
+/// let vertex_shader_code = read_spirv(&device.resolve_asset_path(Path::new("vertex_shader.spv")))
+///     .expect("Something went wrong whilst trying to read the shader");
 /// ```
-/// use client::renderer::vulkan::{Context, Device};
-///
-/// let context = new Context("my-application", (1.4.2));
-/// let device = Device::new(&context);
-///
-/// let vertex_shader_module = load_shader(&device, Path::new("vertex_shader.spv"))
-///     .expect("Something went wrong whilst trying to load the shader");
-///
-/// ```
-fn load_shader(device: &Device, relative_file_path: &std::path::Path) -> Option<vk::ShaderModule> {
-    let current_exe = std::env::current_exe();
-    let joined_file_path = current_exe
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join(relative_file_path);
-    let absolute_file_path = joined_file_path.as_path();
-
-    if !absolute_file_path.exists() {
+pub(crate) fn read_spirv(file_path: &Path) -> Result<Vec<u32>, &'static str> {
+    if !file_path.exists() {
         warn!(
             "Tried to load a shader at {:?} but it does not exist",
-            absolute_file_path
+            file_path
         );
-        None
+        Err("A shader file could not be found at the specified path")
     } else {
-        let code_as_bytes = std::fs::read(absolute_file_path).expect("Failed to read file");
+        let code_as_bytes = std::fs::read(file_path).expect("Failed to read file");
+        decode_spirv(&code_as_bytes)
+            .inspect_err(|error| warn!("Failed to load the shader at {:?} ({})", file_path, error))
+    }
+}
 
-        let mut cursor = std::io::Cursor::new(&code_as_bytes);
-        let mut code = vec![0u32; code_as_bytes.len() / 4];
-        let _res = cursor.read_u32_into::<LittleEndian>(code.as_mut_slice());
+/// Decodes compiled SPIR-V into words. The bytes don't need to be aligned, so shaders embedded with `include_bytes!` can be used
+///
+/// If the bytes aren't a whole number of words or don't start with the SPIR-V magic number, returns `Err`
+///
+/// # Arguments
+///
+/// * `code_as_bytes`: The SPIR-V code, in little-endian byte order
+pub(crate) fn decode_spirv(code_as_bytes: &[u8]) -> Result<Vec<u32>, &'static str> {
+    if !code_as_bytes.len().is_multiple_of(4) {
+        return Err("The shader code is not a whole number of 32-bit words");
+    }
 
-        let shader_module_create_info = vk::ShaderModuleCreateInfo::builder()
-            .code(code.as_slice())
-            .build();
+    let mut cursor = std::io::Cursor::new(code_as_bytes);
+    let mut code = vec![0u32; code_as_bytes.len() / 4];
+    cursor
+        .read_u32_into::<LittleEndian>(code.as_mut_slice())
+        .map_err(|_| "Failed to read the shader code")?;
+
+    if code.first() != Some(&SPIRV_MAGIC_NUMBER) {
+        return Err("The shader code is not SPIR-V");
+    }
+    Ok(code)
+}
 
-        let shader_module = unsafe {
-            device
-                .logical_device
-                .create_shader_module(&shader_module_create_info, None)
+/// Creates a shader module from SPIR-V code
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the shader module on
+/// * `code`: The SPIR-V words of the shader
+pub(crate) fn create_shader_module(device: &Device, code: &[u32]) -> vk::ShaderModule {
+    let shader_module_create_info = vk::ShaderModuleCreateInfo::builder().code(code).build();
+
+    unsafe {
+        device
+            .logical_device
+            .create_shader_module(&shader_module_create_info, None)
+    }
+    .expect("Failed to create shader module")
+}
+
+/// Reflects the SPIR-V of every stage, and combines what they use
+///
+/// # Arguments
+///
+/// * `stages`: The stages of the pipeline
+/// * `code_by_path`: The SPIR-V code of each module used by the stages
+#[cfg(feature = "reflection")]
+fn reflect_stages(
+    stages: &[ShaderStageSource],
+    code_by_path: &HashMap<&Path, Vec<u32>>,
+) -> ShaderReflection {
+    let mut reflection = ShaderReflection::default();
+    for stage in stages {
+        let code = code_by_path.get(stage.path).unwrap();
+        match ShaderReflection::from_spirv(code, stage.stage.into()) {
+            Ok(stage_reflection) => reflection.merge(stage_reflection),
+            Err(error) => warn!("Failed to reflect {:?} ({})", stage.path, error),
         }
-        .expect("Failed to create shader module");
+    }
+    reflection
+}
+
+/// Without the `reflection` feature nothing is discovered, so pipelines only use what their parameters declare
+#[cfg(not(feature = "reflection"))]
+fn reflect_stages(
+    _stages: &[ShaderStageSource],
+    _code_by_path: &HashMap<&Path, Vec<u32>>,
+) -> ShaderReflection {
+    ShaderReflection::default()
+}
+
+/// Creates a descriptor set layout for each set that's used, with empty layouts filling any gaps between set indices
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the layouts on
+/// * `descriptor_sets`: The bindings of each set, keyed by set index
+fn create_descriptor_set_layouts(
+    device: &Device,
+    descriptor_sets: &BTreeMap<u32, Vec<vk::DescriptorSetLayoutBinding>>,
+) -> Vec<vk::DescriptorSetLayout> {
+    let set_count = descriptor_sets
+        .keys()
+        .next_back()
+        .map_or(0, |last_set| last_set + 1);
+
+    (0..set_count)
+        .map(|set| {
+            let bindings = descriptor_sets.get(&set).map_or(&[][..], Vec::as_slice);
+            let create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+                .bindings(bindings)
+                .build();
+            unsafe {
+                device
+                    .logical_device
+                    .create_descriptor_set_layout(&create_info, None)
+            }
+            .expect("Failed to create descriptor set layout")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specialization_data_is_packed_in_constant_id_order() {
+        let mut specialization_constants = SpecializationConstants::new();
+        specialization_constants.insert(2, SpecializationValue::Float(1.5));
+        specialization_constants.insert(0, SpecializationValue::Bool(true));
+        specialization_constants.insert(1, SpecializationValue::UInt(7));
+
+        let (map_entries, data) = create_specialization_data(&specialization_constants);
 
-        Some(shader_module)
+        let ids_and_offsets: Vec<(u32, u32)> = map_entries
+            .iter()
+            .map(|entry| (entry.constant_id, entry.offset))
+            .collect();
+        assert_eq!(ids_and_offsets, vec![(0, 0), (1, 4), (2, 8)]);
+        assert_eq!(&data[4..8], &7u32.to_ne_bytes());
+        assert_eq!(&data[8..12], &1.5f32.to_ne_bytes());
     }
 }
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use ash::vk;
+use tracing::{debug, info, warn};
+
+/// Where the pipeline cache is kept, relative to the directory the executable is in
+const PIPELINE_CACHE_PATH: &str = "cache/pipeline.bin";
+/// The size of a `VkPipelineCacheHeaderVersionOne`, which every cache starts with
+const PIPELINE_CACHE_HEADER_SIZE: usize = 32;
+
+/// Creates the pipeline cache that every pipeline on the device is built with, seeded from the cache saved by the last run if there
+/// is one. A cache which is unreadable, corrupt, or was saved by a different GPU or driver is ignored, and an empty cache is
+/// created instead, as the driver would otherwise be free to reject it
+///
+/// # Arguments
+///
+/// * `device`: The logical device to create the cache on
+/// * `properties`: The properties of the physical device, which the saved cache must have been created on
+pub(crate) fn load_pipeline_cache(
+    device: &ash::Device,
+    properties: &vk::PhysicalDeviceProperties,
+) -> vk::PipelineCache {
+    let initial_data = pipeline_cache_path()
+        .and_then(|path| match std::fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(error) => {
+                debug!("No pipeline cache was loaded from {:?} ({})", path, error);
+                None
+            }
+        })
+        .filter(|data| {
+            let is_compatible = is_pipeline_cache_compatible(data, properties);
+            if !is_compatible {
+                warn!("The saved pipeline cache is corrupt or from another device, so it's being ignored");
+            }
+            is_compatible
+        })
+        .unwrap_or_default();
+
+    let pipeline_cache_create_info = vk::PipelineCacheCreateInfo::builder()
+        .initial_data(&initial_data)
+        .build();
+    let pipeline_cache = unsafe { device.create_pipeline_cache(&pipeline_cache_create_info, None) }
+        .or_else(|error| {
+            // The header check can't catch everything, so fall back to an empty cache if the driver still rejects it
+            warn!(
+                "Failed to create pipeline cache from saved data ({})",
+                error
+            );
+            let empty_create_info = vk::PipelineCacheCreateInfo::default();
+            unsafe { device.create_pipeline_cache(&empty_create_info, None) }
+        })
+        .expect("Failed to create Vulkan pipeline cache");
+
+    if !initial_data.is_empty() {
+        info!("Loaded {} bytes of pipeline cache", initial_data.len());
+    }
+    pipeline_cache
+}
+
+/// Writes the contents of the pipeline cache to disk so that the next run can skip compiling the same pipelines again.
+/// Failing to save the cache only slows down the next launch, so errors are logged rather than returned
+///
+/// # Arguments
+///
+/// * `device`: The logical device the cache was created on
+/// * `pipeline_cache`: The cache to save
+pub(crate) fn save_pipeline_cache(device: &ash::Device, pipeline_cache: vk::PipelineCache) {
+    let Some(path) = pipeline_cache_path() else {
+        return;
+    };
+    let data = match unsafe { device.get_pipeline_cache_data(pipeline_cache) } {
+        Ok(data) => data,
+        Err(error) => {
+            warn!("Failed to get the pipeline cache data ({})", error);
+            return;
+        }
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, &data));
+    match result {
+        Ok(()) => info!("Saved {} bytes of pipeline cache to {:?}", data.len(), path),
+        Err(error) => warn!(
+            "Failed to save the pipeline cache to {:?} ({})",
+            path, error
+        ),
+    }
+}
+
+/// Gets the path of the pipeline cache file, next to the executable, or `None` if the executable's path is unknown
+fn pipeline_cache_path() -> Option<PathBuf> {
+    let executable = std::env::current_exe()
+        .map_err(|error| warn!("Failed to get the path of the executable ({})", error))
+        .ok()?;
+    Some(executable.parent()?.join(PIPELINE_CACHE_PATH))
+}
+
+/// Checks whether saved pipeline cache data has a valid header and was created by the same GPU and driver
+///
+/// # Arguments
+///
+/// * `data`: The saved pipeline cache data
+/// * `properties`: The properties of the physical device the cache would be used on
+fn is_pipeline_cache_compatible(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < PIPELINE_CACHE_HEADER_SIZE {
+        return false;
+    }
+
+    let read_u32 = |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+    read_u32(0) as usize == PIPELINE_CACHE_HEADER_SIZE
+        && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..PIPELINE_CACHE_HEADER_SIZE] == properties.pipeline_cache_uuid
+}
@@ -0,0 +1,271 @@
+use std::ffi::CString;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::pipeline::{create_shader_module, read_spirv};
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::surface::MAX_FRAMES_IN_FLIGHT;
+use crate::renderer::vulkan::Device;
+
+/// The width and height of each compute shader workgroup, which must match the shader's `local_size_x` and `local_size_y`
+const WORKGROUP_SIZE: u32 = 8;
+
+/// A compute shader which is run over the swapchain image after it's been drawn to and before it's presented (eg. for tonemapping
+/// or a vignette). The shader reads and writes the image in place through a storage image at set 0, binding 0, and should use
+/// 8x8 workgroups
+///
+/// The swapchain must have been created with storage usage, which can be requested with [`Surface::set_storage_usage()`](crate::renderer::vulkan::Surface::set_storage_usage)
+pub struct PostProcess {
+    device: Weak<ash::Device>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    // Indexed by frame in flight, as a set can only be updated once the frame's previous submission has finished with it
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl PostProcess {
+    /// Constructs a new `PostProcess` from a compute shader.
+    /// Note that the recommended way to create a post-process is through [`Device::create_post_process()`] rather than using `PostProcess::new()` directly
+    ///
+    /// If the shader file doesn't exist or isn't valid SPIR-V, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the post-process on
+    /// * `shader_path`: A `Path` which references a compiled SPIR-V compute shader, relative to the asset root
+    pub fn new(device: &Device, shader_path: &Path) -> Result<Self, &'static str> {
+        let span = debug_span!("Vulkan/PostProcess");
+        let _guard = span.enter();
+
+        let code = read_spirv(&device.resolve_asset_path(shader_path))?;
+        let shader_module = create_shader_module(device, &code);
+
+        let bindings = [vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            .build()];
+        let descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+        let descriptor_set_layout = unsafe {
+            device
+                .logical_device
+                .create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+        }
+        .expect("Failed to create post-process descriptor set layout");
+
+        let pool_sizes = [vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::STORAGE_IMAGE)
+            .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+            .build()];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(MAX_FRAMES_IN_FLIGHT as u32)
+            .pool_sizes(&pool_sizes)
+            .build();
+        let descriptor_pool = unsafe {
+            device
+                .logical_device
+                .create_descriptor_pool(&descriptor_pool_create_info, None)
+        }
+        .expect("Failed to create post-process descriptor pool");
+
+        let set_layouts = [descriptor_set_layout; MAX_FRAMES_IN_FLIGHT];
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_sets = unsafe {
+            device
+                .logical_device
+                .allocate_descriptor_sets(&descriptor_set_allocate_info)
+        }
+        .expect("Failed to allocate post-process descriptor sets");
+
+        let pipeline_layout_set_layouts = [descriptor_set_layout];
+        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&pipeline_layout_set_layouts)
+            .build();
+        let pipeline_layout = unsafe {
+            device
+                .logical_device
+                .create_pipeline_layout(&pipeline_layout_create_info, None)
+        }
+        .expect("Failed to create post-process pipeline layout");
+
+        let entry_point = CString::new("main").unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(shader_module)
+            .name(entry_point.as_c_str())
+            .build();
+        let pipeline_create_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage)
+            .layout(pipeline_layout)
+            .build();
+
+        debug!("Creating post-process pipeline from {:?}", shader_path);
+        let pipeline = *unsafe {
+            device.logical_device.create_compute_pipelines(
+                device.pipeline_cache(),
+                &[pipeline_create_info],
+                None,
+            )
+        }
+        .expect("Failed to create post-process pipeline")
+        .first()
+        .unwrap();
+        track_created(ResourceKind::Pipeline, 1);
+
+        unsafe {
+            device
+                .logical_device
+                .destroy_shader_module(shader_module, None)
+        };
+
+        Ok(PostProcess {
+            device: Rc::downgrade(&device.logical_device),
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+
+    /// Records the post-process over a swapchain image whose render pass has ended, leaving it ready to present
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The logical device that the command buffer belongs to
+    /// * `command_buffer`: The frame's graphics command buffer, outside of any render pass
+    /// * `current_frame_index`: The index of the frame being recorded, whose previous submission has finished
+    /// * `image`: The swapchain image, in `PRESENT_SRC_KHR` layout
+    /// * `view`: A view of the swapchain image
+    /// * `extent`: The size of the swapchain image
+    pub(crate) fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        current_frame_index: usize,
+        image: vk::Image,
+        view: vk::ImageView,
+        extent: vk::Extent2D,
+    ) {
+        let descriptor_set = *self.descriptor_sets.get(current_frame_index).unwrap();
+
+        // The swapchain image can change every frame, so the set is rewritten each time
+        let image_infos = [vk::DescriptorImageInfo::builder()
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL)
+            .build()];
+        let descriptor_writes = [vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+            .image_info(&image_infos)
+            .build()];
+        unsafe { device.update_descriptor_sets(&descriptor_writes, &[]) };
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+
+        // The render pass's colour writes must land before the shader reads them
+        let to_general = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+            .build();
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_general],
+            )
+        };
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline_layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                extent.width.div_ceil(WORKGROUP_SIZE),
+                extent.height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        // The presentation engine synchronises with the semaphores rather than the barrier
+        let to_present = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .build();
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_present],
+            )
+        };
+    }
+}
+
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~PostProcess");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying post-process");
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            // Destroying the pool frees its descriptor sets
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+        track_destroyed(ResourceKind::Pipeline, 1);
+    }
+}
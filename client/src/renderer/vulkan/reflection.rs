@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "reflection")]
+use std::collections::HashMap;
+
+use ash::vk;
+#[cfg(feature = "reflection")]
+use rspirv::dr::{Instruction, Module, Operand};
+#[cfg(feature = "reflection")]
+use rspirv::spirv::{Decoration, Dim, Op, StorageClass};
+
+/// The resources a set of shader stages use, as discovered from their SPIR-V.
+/// Reflecting shaders requires the `reflection` feature, and without it pipelines are created as if nothing was discovered
+#[derive(Clone, Debug, Default)]
+pub struct ShaderReflection {
+    /// The bindings of each descriptor set, keyed by set index
+    pub descriptor_sets: BTreeMap<u32, Vec<vk::DescriptorSetLayoutBinding>>,
+    /// The push constant ranges, with at most one range covering every stage that uses push constants
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    /// The vertex inputs, packed tightly into binding 0 in location order
+    pub vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    /// The vertex buffer binding the attributes are read from, if there are any
+    pub vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+}
+
+impl ShaderReflection {
+    /// Reflects a single SPIR-V module
+    ///
+    /// If the module can't be parsed, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `code`: The SPIR-V words of the module
+    /// * `stage`: The stage the module is used for, which is recorded against its bindings and push constants
+    #[cfg(feature = "reflection")]
+    pub fn from_spirv(code: &[u32], stage: vk::ShaderStageFlags) -> Result<Self, &'static str> {
+        let module = rspirv::dr::load_words(code).map_err(|_| "The shader is not valid SPIR-V")?;
+        let reflector = Reflector::new(&module);
+
+        let mut reflection = ShaderReflection::default();
+        let mut vertex_inputs = BTreeMap::new();
+
+        for variable in module
+            .types_global_values
+            .iter()
+            .filter(|instruction| instruction.class.opcode == Op::Variable)
+        {
+            let (Some(id), Some(pointer_type)) = (variable.result_id, variable.result_type) else {
+                continue;
+            };
+            let storage_class = variable.operands[0].unwrap_storage_class();
+            let Some(pointee) = reflector.pointee_type(pointer_type) else {
+                continue;
+            };
+
+            match storage_class {
+                StorageClass::Uniform
+                | StorageClass::UniformConstant
+                | StorageClass::StorageBuffer => {
+                    let (Some(set), Some(binding)) = (
+                        reflector.decoration(id, Decoration::DescriptorSet),
+                        reflector.decoration(id, Decoration::Binding),
+                    ) else {
+                        continue;
+                    };
+                    let Some((descriptor_type, descriptor_count)) =
+                        reflector.descriptor_type(pointee, storage_class)
+                    else {
+                        continue;
+                    };
+                    reflection.descriptor_sets.entry(set).or_default().push(
+                        vk::DescriptorSetLayoutBinding::builder()
+                            .binding(binding)
+                            .descriptor_type(descriptor_type)
+                            .descriptor_count(descriptor_count)
+                            .stage_flags(stage)
+                            .build(),
+                    );
+                }
+                StorageClass::PushConstant => {
+                    let size = reflector.type_size(pointee);
+                    if size > 0 {
+                        reflection.push_constant_ranges = vec![vk::PushConstantRange {
+                            stage_flags: stage,
+                            offset: 0,
+                            size,
+                        }];
+                    }
+                }
+                StorageClass::Input if stage == vk::ShaderStageFlags::VERTEX => {
+                    // Built-ins such as gl_VertexIndex aren't read from vertex buffers
+                    if let Some(location) = reflector.decoration(id, Decoration::Location) {
+                        if let Some(format) = reflector.vertex_format(pointee) {
+                            vertex_inputs.insert(location, format);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut offset = 0;
+        for (location, (format, size)) in vertex_inputs {
+            reflection.vertex_attributes.push(
+                vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(location)
+                    .format(format)
+                    .offset(offset)
+                    .build(),
+            );
+            offset += size;
+        }
+        if offset > 0 {
+            reflection.vertex_bindings.push(
+                vk::VertexInputBindingDescription::builder()
+                    .binding(0)
+                    .stride(offset)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+                    .build(),
+            );
+        }
+
+        Ok(reflection)
+    }
+
+    /// Combines the reflection of another stage into this one, so that bindings used by several stages are visible to all of them
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The reflection of another stage of the same pipeline
+    pub fn merge(&mut self, other: ShaderReflection) {
+        for (set, bindings) in other.descriptor_sets {
+            let existing_bindings = self.descriptor_sets.entry(set).or_default();
+            for binding in bindings {
+                match existing_bindings
+                    .iter_mut()
+                    .find(|existing| existing.binding == binding.binding)
+                {
+                    Some(existing) => existing.stage_flags |= binding.stage_flags,
+                    None => existing_bindings.push(binding),
+                }
+            }
+        }
+
+        for range in other.push_constant_ranges {
+            match self.push_constant_ranges.first_mut() {
+                Some(existing) => {
+                    existing.stage_flags |= range.stage_flags;
+                    existing.size = existing.size.max(range.size);
+                }
+                None => self.push_constant_ranges.push(range),
+            }
+        }
+
+        if self.vertex_attributes.is_empty() {
+            self.vertex_attributes = other.vertex_attributes;
+            self.vertex_bindings = other.vertex_bindings;
+        }
+    }
+}
+
+/// Looks up the types, constants, and decorations of a module
+#[cfg(feature = "reflection")]
+struct Reflector<'a> {
+    definitions: HashMap<u32, &'a Instruction>,
+    decorations: HashMap<(u32, Decoration), u32>,
+    member_offsets: HashMap<(u32, u32), u32>,
+    array_strides: HashMap<u32, u32>,
+}
+
+#[cfg(feature = "reflection")]
+impl<'a> Reflector<'a> {
+    fn new(module: &'a Module) -> Self {
+        let definitions = module
+            .types_global_values
+            .iter()
+            .filter_map(|instruction| instruction.result_id.map(|id| (id, instruction)))
+            .collect();
+
+        let mut decorations = HashMap::new();
+        let mut member_offsets = HashMap::new();
+        let mut array_strides = HashMap::new();
+        for annotation in &module.annotations {
+            match annotation.class.opcode {
+                Op::Decorate => {
+                    let target = annotation.operands[0].unwrap_id_ref();
+                    let decoration = annotation.operands[1].unwrap_decoration();
+                    let value = literal(annotation.operands.get(2)).unwrap_or(0);
+                    if decoration == Decoration::ArrayStride {
+                        array_strides.insert(target, value);
+                    }
+                    decorations.insert((target, decoration), value);
+                }
+                Op::MemberDecorate
+                    if annotation.operands[2].unwrap_decoration() == Decoration::Offset =>
+                {
+                    let target = annotation.operands[0].unwrap_id_ref();
+                    let member = annotation.operands[1].unwrap_literal_int32();
+                    let offset = literal(annotation.operands.get(3)).unwrap_or(0);
+                    member_offsets.insert((target, member), offset);
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            definitions,
+            decorations,
+            member_offsets,
+            array_strides,
+        }
+    }
+
+    fn decoration(&self, id: u32, decoration: Decoration) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    fn pointee_type(&self, pointer_type: u32) -> Option<u32> {
+        let pointer = self.definitions.get(&pointer_type)?;
+        (pointer.class.opcode == Op::TypePointer).then(|| pointer.operands[1].unwrap_id_ref())
+    }
+
+    fn constant_value(&self, id: u32) -> Option<u32> {
+        let constant = self.definitions.get(&id)?;
+        (constant.class.opcode == Op::Constant)
+            .then(|| literal(constant.operands.first()))
+            .flatten()
+    }
+
+    /// Gets the descriptor type and count for a resource variable's type, looking through arrays
+    fn descriptor_type(
+        &self,
+        type_id: u32,
+        storage_class: StorageClass,
+    ) -> Option<(vk::DescriptorType, u32)> {
+        let definition = self.definitions.get(&type_id)?;
+        match definition.class.opcode {
+            Op::TypeArray => {
+                let element = definition.operands[0].unwrap_id_ref();
+                let length = self.constant_value(definition.operands[1].unwrap_id_ref())?;
+                let (descriptor_type, count) = self.descriptor_type(element, storage_class)?;
+                Some((descriptor_type, count * length))
+            }
+            Op::TypeRuntimeArray => {
+                self.descriptor_type(definition.operands[0].unwrap_id_ref(), storage_class)
+            }
+            Op::TypeStruct => {
+                if storage_class == StorageClass::StorageBuffer
+                    || self.decoration(type_id, Decoration::BufferBlock).is_some()
+                {
+                    Some((vk::DescriptorType::STORAGE_BUFFER, 1))
+                } else {
+                    Some((vk::DescriptorType::UNIFORM_BUFFER, 1))
+                }
+            }
+            Op::TypeSampledImage => Some((vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 1)),
+            Op::TypeSampler => Some((vk::DescriptorType::SAMPLER, 1)),
+            Op::TypeImage => {
+                let is_buffer = matches!(definition.operands[1], Operand::Dim(Dim::DimBuffer));
+                let is_subpass_input =
+                    matches!(definition.operands[1], Operand::Dim(Dim::DimSubpassData));
+                // 1 means the image is sampled, and 2 means it's read and written as storage
+                let is_storage = definition.operands[5].unwrap_literal_int32() == 2;
+                Some((
+                    match (is_subpass_input, is_buffer, is_storage) {
+                        (true, _, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                        (false, true, true) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                        (false, true, false) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                        (false, false, true) => vk::DescriptorType::STORAGE_IMAGE,
+                        (false, false, false) => vk::DescriptorType::SAMPLED_IMAGE,
+                    },
+                    1,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the size in bytes of a type, as laid out in a buffer
+    fn type_size(&self, type_id: u32) -> u32 {
+        let Some(definition) = self.definitions.get(&type_id) else {
+            return 0;
+        };
+        match definition.class.opcode {
+            Op::TypeInt | Op::TypeFloat => definition.operands[0].unwrap_literal_int32() / 8,
+            Op::TypeBool => 4,
+            Op::TypeVector | Op::TypeMatrix => {
+                self.type_size(definition.operands[0].unwrap_id_ref())
+                    * definition.operands[1].unwrap_literal_int32()
+            }
+            Op::TypeArray => {
+                let element = definition.operands[0].unwrap_id_ref();
+                let length = self
+                    .constant_value(definition.operands[1].unwrap_id_ref())
+                    .unwrap_or(0);
+                let stride = self
+                    .array_strides
+                    .get(&type_id)
+                    .copied()
+                    .unwrap_or_else(|| self.type_size(element));
+                stride * length
+            }
+            Op::TypeStruct => definition
+                .operands
+                .iter()
+                .enumerate()
+                .map(|(member, operand)| {
+                    let offset = self
+                        .member_offsets
+                        .get(&(type_id, member as u32))
+                        .copied()
+                        .unwrap_or(0);
+                    offset + self.type_size(operand.unwrap_id_ref())
+                })
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Gets the format and size of a vertex input, for 32-bit scalars and vectors
+    fn vertex_format(&self, type_id: u32) -> Option<(vk::Format, u32)> {
+        let definition = self.definitions.get(&type_id)?;
+        let (component_type, count) = match definition.class.opcode {
+            Op::TypeVector => (
+                *self
+                    .definitions
+                    .get(&definition.operands[0].unwrap_id_ref())?,
+                definition.operands[1].unwrap_literal_int32(),
+            ),
+            _ => (*definition, 1),
+        };
+
+        let is_32_bit = component_type
+            .operands
+            .first()
+            .and_then(|width| literal(Some(width)))
+            == Some(32);
+        if !is_32_bit {
+            return None;
+        }
+        let formats = match component_type.class.opcode {
+            Op::TypeFloat => [
+                vk::Format::R32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            Op::TypeInt if literal(component_type.operands.get(1)) == Some(1) => [
+                vk::Format::R32_SINT,
+                vk::Format::R32G32_SINT,
+                vk::Format::R32G32B32_SINT,
+                vk::Format::R32G32B32A32_SINT,
+            ],
+            Op::TypeInt => [
+                vk::Format::R32_UINT,
+                vk::Format::R32G32_UINT,
+                vk::Format::R32G32B32_UINT,
+                vk::Format::R32G32B32A32_UINT,
+            ],
+            _ => return None,
+        };
+
+        let format = *formats.get(count.checked_sub(1)? as usize)?;
+        Some((format, count * 4))
+    }
+}
+
+/// Reads a 32-bit literal operand, if it is one
+#[cfg(feature = "reflection")]
+fn literal(operand: Option<&Operand>) -> Option<u32> {
+    match operand? {
+        Operand::LiteralInt32(value) => Some(*value),
+        _ => None,
+    }
+}
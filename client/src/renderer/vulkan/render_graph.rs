@@ -0,0 +1,531 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::render_target::create_sampled_colour_image;
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{Device, Image};
+
+/// Identifies an attachment declared on a [`RenderGraphBuilder`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AttachmentHandle(usize);
+
+/// Describes an offscreen colour image that passes can render to and sample from
+pub struct AttachmentDescription {
+    /// A name for the attachment, used when debugging
+    pub name: String,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    /// The colour the attachment is cleared to by the first pass that writes to it, or `None` if its previous contents don't matter
+    pub clear_color: Option<[f32; 4]>,
+}
+
+struct PassDescription {
+    name: String,
+    inputs: Vec<AttachmentHandle>,
+    outputs: Vec<AttachmentHandle>,
+}
+
+/// Declares the attachments and passes of a [`RenderGraph`].
+/// Passes are executed in the order they're added, and can only read attachments written by an earlier pass
+///
+/// # Examples
+///
+/// ```no_run
+/// # use client::renderer::vulkan::Device;
+/// # fn example(device: &Device) {
+/// use ash::vk;
+/// use client::renderer::vulkan::{AttachmentDescription, RenderGraphBuilder};
+///
+/// let mut builder = RenderGraphBuilder::new();
+/// let scene = builder.add_attachment(AttachmentDescription {
+///     name: String::from("scene"),
+///     format: vk::Format::B8G8R8A8_UNORM,
+///     extent: vk::Extent2D { width: 1280, height: 720 },
+///     clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+/// });
+/// let bloom = builder.add_attachment(AttachmentDescription {
+///     name: String::from("bloom"),
+///     format: vk::Format::B8G8R8A8_UNORM,
+///     extent: vk::Extent2D { width: 640, height: 360 },
+///     clear_color: None,
+/// });
+///
+/// builder.add_pass("scene", &[], &[scene]).unwrap();
+/// builder.add_pass("bloom", &[scene], &[bloom]).unwrap();
+/// let graph = builder.build(&device).unwrap();
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RenderGraphBuilder {
+    attachments: Vec<AttachmentDescription>,
+    passes: Vec<PassDescription>,
+}
+
+impl RenderGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a new attachment, returning the handle that passes should use to refer to it
+    ///
+    /// # Arguments
+    ///
+    /// * `description`: The format, size, and clear colour of the attachment
+    pub fn add_attachment(&mut self, description: AttachmentDescription) -> AttachmentHandle {
+        self.attachments.push(description);
+        AttachmentHandle(self.attachments.len() - 1)
+    }
+
+    /// Declares a new pass, which runs after every pass that has already been added
+    ///
+    /// Returns `Err` if the name is already in use, the pass has no outputs, an attachment is written more than once or is both
+    /// read and written, an input hasn't been written by an earlier pass, or the outputs don't all have the same extent
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name that the pass is referred to by when recording and when fetching its render pass
+    /// * `inputs`: The attachments that the pass samples in its shaders
+    /// * `outputs`: The attachments that the pass renders to, in the order of the fragment shader's output locations
+    pub fn add_pass(
+        &mut self,
+        name: &str,
+        inputs: &[AttachmentHandle],
+        outputs: &[AttachmentHandle],
+    ) -> Result<(), &'static str> {
+        if self.passes.iter().any(|pass| pass.name == name) {
+            return Err("A pass already exists with the specified name");
+        }
+        if outputs.is_empty() {
+            return Err("A pass must render to at least one attachment");
+        }
+        if inputs
+            .iter()
+            .chain(outputs.iter())
+            .any(|handle| handle.0 >= self.attachments.len())
+        {
+            return Err("An attachment handle does not belong to this graph");
+        }
+        if outputs
+            .iter()
+            .enumerate()
+            .any(|(index, output)| outputs[..index].contains(output))
+        {
+            return Err("A pass cannot render to the same attachment more than once");
+        }
+        if inputs.iter().any(|input| outputs.contains(input)) {
+            return Err("A pass cannot read from an attachment that it renders to");
+        }
+        if inputs
+            .iter()
+            .any(|input| !self.passes.iter().any(|pass| pass.outputs.contains(input)))
+        {
+            return Err(
+                "A pass cannot read from an attachment before an earlier pass has rendered to it",
+            );
+        }
+
+        let extent = self.attachments[outputs[0].0].extent;
+        if outputs
+            .iter()
+            .any(|output| self.attachments[output.0].extent != extent)
+        {
+            return Err("Every attachment a pass renders to must be the same size");
+        }
+
+        self.passes.push(PassDescription {
+            name: name.to_string(),
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Creates the images, render passes, and framebuffers needed to execute the graph.
+    /// The load operation and layouts of each attachment are derived from which passes write and read it
+    ///
+    /// Returns `Err` if the graph has no passes
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` that the graph will be executed on
+    pub fn build(self, device: &Device) -> Result<RenderGraph, &'static str> {
+        let span = debug_span!("Vulkan/RenderGraph");
+        let _guard = span.enter();
+
+        if self.passes.is_empty() {
+            return Err("A render graph must have at least one pass");
+        }
+
+        let attachments: Vec<GraphAttachment> = self
+            .attachments
+            .iter()
+            .map(|description| create_attachment(device, description))
+            .collect();
+
+        let passes = self
+            .passes
+            .iter()
+            .enumerate()
+            .map(|(index, pass)| {
+                debug!("Creating render pass for {}", pass.name);
+                let render_pass = create_render_pass(device, &self, index);
+
+                let views: Vec<vk::ImageView> = pass
+                    .outputs
+                    .iter()
+                    .map(|output| attachments[output.0].view)
+                    .collect();
+                let extent = self.attachments[pass.outputs[0].0].extent;
+                let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .attachments(views.as_slice())
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1)
+                    .build();
+                let framebuffer = unsafe {
+                    device
+                        .logical_device
+                        .create_framebuffer(&framebuffer_create_info, None)
+                }
+                .expect("Failed to create render graph framebuffer");
+                track_created(ResourceKind::Framebuffer, 1);
+
+                let clear_values = pass
+                    .outputs
+                    .iter()
+                    .map(|output| vk::ClearValue {
+                        color: vk::ClearColorValue {
+                            float32: self.attachments[output.0].clear_color.unwrap_or_default(),
+                        },
+                    })
+                    .collect();
+
+                CompiledPass {
+                    name: pass.name.clone(),
+                    render_pass,
+                    framebuffer,
+                    extent,
+                    clear_values,
+                }
+            })
+            .collect();
+
+        Ok(RenderGraph {
+            device: Rc::downgrade(&device.logical_device),
+            attachments,
+            passes,
+        })
+    }
+}
+
+struct GraphAttachment {
+    // Dropped after the view has been destroyed
+    _image: Image,
+    view: vk::ImageView,
+}
+
+struct CompiledPass {
+    name: String,
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+    clear_values: Vec<vk::ClearValue>,
+}
+
+/// A set of passes rendering to offscreen attachments, built by a [`RenderGraphBuilder`]
+pub struct RenderGraph {
+    device: Weak<ash::Device>,
+    attachments: Vec<GraphAttachment>,
+    passes: Vec<CompiledPass>,
+}
+
+impl RenderGraph {
+    /// Gets the render pass created for a pass, so that pipelines can be checked for compatibility with it.
+    /// A pipeline created through [`Device::create_pipeline()`] is compatible with any pass that renders to a single attachment of the swapchain's format
+    ///
+    /// # Arguments
+    ///
+    /// * `pass_name`: The name the pass was added with
+    pub fn render_pass(&self, pass_name: &str) -> Option<vk::RenderPass> {
+        self.passes
+            .iter()
+            .find(|pass| pass.name == pass_name)
+            .map(|pass| pass.render_pass)
+    }
+
+    /// Gets the view of an attachment, so that it can be bound for sampling
+    ///
+    /// # Arguments
+    ///
+    /// * `attachment`: The handle returned when the attachment was declared
+    pub fn attachment_view(&self, attachment: AttachmentHandle) -> vk::ImageView {
+        self.attachments[attachment.0].view
+    }
+
+    /// Records every pass into `command_buffer`, in the order they were added.
+    /// Each pass's render pass is begun and its viewport and scissor set to cover its attachments before `record` is called
+    ///
+    /// # Arguments
+    ///
+    /// * `command_buffer`: A command buffer in the recording state, outside of any render pass
+    /// * `record`: Called once per pass with the pass's name, to record its draw commands
+    pub(crate) fn record<F>(&self, command_buffer: vk::CommandBuffer, mut record: F)
+    where
+        F: FnMut(&str, vk::CommandBuffer),
+    {
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        for pass in &self.passes {
+            let render_area = vk::Rect2D::builder()
+                .offset(vk::Offset2D::builder().x(0).y(0).build())
+                .extent(pass.extent)
+                .build();
+
+            let render_pass_info = vk::RenderPassBeginInfo::builder()
+                .render_pass(pass.render_pass)
+                .framebuffer(pass.framebuffer)
+                .clear_values(pass.clear_values.as_slice())
+                .render_area(render_area)
+                .build();
+
+            let viewport = vk::Viewport::builder()
+                .x(0.0)
+                .y(0.0)
+                .width(pass.extent.width as f32)
+                .height(pass.extent.height as f32)
+                .min_depth(0.0)
+                .max_depth(1.0)
+                .build();
+
+            unsafe {
+                device.cmd_begin_render_pass(
+                    command_buffer,
+                    &render_pass_info,
+                    vk::SubpassContents::INLINE,
+                );
+                device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+            }
+
+            record(pass.name.as_str(), command_buffer);
+
+            unsafe { device.cmd_end_render_pass(command_buffer) };
+        }
+    }
+}
+
+impl Drop for RenderGraph {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~RenderGraph");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        for pass in &self.passes {
+            debug!("Destroying framebuffer and render pass for {}", pass.name);
+            unsafe {
+                device.destroy_framebuffer(pass.framebuffer, None);
+                device.destroy_render_pass(pass.render_pass, None);
+            }
+            track_destroyed(ResourceKind::Framebuffer, 1);
+        }
+        for attachment in &self.attachments {
+            unsafe { device.destroy_image_view(attachment.view, None) };
+        }
+    }
+}
+
+/// Allocates the image backing an attachment and creates a view of it
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the attachment on
+/// * `description`: The description of the attachment
+fn create_attachment(device: &Device, description: &AttachmentDescription) -> GraphAttachment {
+    let (image, view) = create_sampled_colour_image(
+        device,
+        description.format,
+        description.extent,
+        1,
+        description.name.as_str(),
+    );
+
+    GraphAttachment {
+        _image: image,
+        view,
+    }
+}
+
+/// Creates the render pass for one pass of the graph.
+/// An attachment is cleared (or discarded) the first time it's written and loaded otherwise,
+/// and is left ready for sampling if a later pass reads it
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the render pass on
+/// * `graph`: The graph the pass belongs to
+/// * `pass_index`: The position of the pass in the graph
+fn create_render_pass(
+    device: &Device,
+    graph: &RenderGraphBuilder,
+    pass_index: usize,
+) -> vk::RenderPass {
+    let pass = &graph.passes[pass_index];
+
+    let attachments: Vec<vk::AttachmentDescription> = pass
+        .outputs
+        .iter()
+        .map(|output| {
+            let description = &graph.attachments[output.0];
+            let last_write = graph.passes[..pass_index]
+                .iter()
+                .rposition(|earlier| earlier.outputs.contains(output));
+
+            let (load_op, initial_layout) = match last_write {
+                Some(last_write) => (
+                    vk::AttachmentLoadOp::LOAD,
+                    final_layout(&graph.passes, last_write, *output),
+                ),
+                None if description.clear_color.is_some() => {
+                    (vk::AttachmentLoadOp::CLEAR, vk::ImageLayout::UNDEFINED)
+                }
+                None => (vk::AttachmentLoadOp::DONT_CARE, vk::ImageLayout::UNDEFINED),
+            };
+
+            vk::AttachmentDescription::builder()
+                .format(description.format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(load_op)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(initial_layout)
+                .final_layout(final_layout(&graph.passes, pass_index, *output))
+                .build()
+        })
+        .collect();
+
+    let colour_attachment_references: Vec<vk::AttachmentReference> = (0..attachments.len())
+        .map(|index| {
+            vk::AttachmentReference::builder()
+                .attachment(index as u32)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .build()
+        })
+        .collect();
+
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(colour_attachment_references.as_slice())
+        .build()];
+
+    let dependencies = [
+        // Earlier passes must have finished writing anything this pass samples or renders over
+        vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(
+                vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            )
+            .dst_access_mask(
+                vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )
+            .build(),
+        // Later passes may sample what this pass renders
+        vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .dst_subpass(vk::SUBPASS_EXTERNAL)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .build(),
+    ];
+
+    let render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(attachments.as_slice())
+        .subpasses(&subpasses)
+        .dependencies(&dependencies)
+        .build();
+
+    unsafe {
+        device
+            .logical_device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Failed to create render graph render pass")
+}
+
+/// Gets the layout a pass leaves one of its outputs in, which is ready for sampling if any later pass reads it
+///
+/// # Arguments
+///
+/// * `passes`: Every pass in the graph, in order
+/// * `pass_index`: The position of the pass that renders to the attachment
+/// * `attachment`: The attachment rendered to
+fn final_layout(
+    passes: &[PassDescription],
+    pass_index: usize,
+    attachment: AttachmentHandle,
+) -> vk::ImageLayout {
+    if passes[pass_index + 1..]
+        .iter()
+        .any(|pass| pass.inputs.contains(&attachment))
+    {
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+    } else {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(name: &str) -> AttachmentDescription {
+        AttachmentDescription {
+            name: String::from(name),
+            format: vk::Format::R8G8B8A8_UNORM,
+            extent: vk::Extent2D {
+                width: 64,
+                height: 64,
+            },
+            clear_color: None,
+        }
+    }
+
+    #[test]
+    fn duplicate_outputs_are_rejected() {
+        let mut builder = RenderGraphBuilder::new();
+        let albedo = builder.add_attachment(attachment("albedo"));
+        let normals = builder.add_attachment(attachment("normals"));
+
+        assert_eq!(
+            builder.add_pass("g_buffer", &[], &[albedo, normals, albedo]),
+            Err("A pass cannot render to the same attachment more than once")
+        );
+        assert_eq!(
+            builder.add_pass("g_buffer", &[], &[albedo, normals]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn inputs_must_be_written_by_an_earlier_pass() {
+        let mut builder = RenderGraphBuilder::new();
+        let scene = builder.add_attachment(attachment("scene"));
+        let bloom = builder.add_attachment(attachment("bloom"));
+
+        assert!(builder.add_pass("bloom", &[scene], &[bloom]).is_err());
+        assert_eq!(builder.add_pass("scene", &[], &[scene]), Ok(()));
+        assert_eq!(builder.add_pass("bloom", &[scene], &[bloom]), Ok(()));
+    }
+}
@@ -0,0 +1,392 @@
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::pipeline::multiview_mask;
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{Device, Image};
+
+/// An offscreen colour image that can be rendered to instead of the swapchain, then sampled by a later pass.
+/// Once its render pass ends, the image is left in `SHADER_READ_ONLY_OPTIMAL` so it's ready to be sampled
+pub struct RenderTarget {
+    device: Weak<ash::Device>,
+    // Dropped after the view has been destroyed
+    image: Image,
+    view: vk::ImageView,
+    format: vk::Format,
+    pub(crate) render_pass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) clear_color: [f32; 4],
+    view_count: u32,
+}
+
+impl RenderTarget {
+    /// Constructs a new `RenderTarget`.
+    /// Note that the recommended way to create a render target is through [`Device::create_render_target()`] rather than using `RenderTarget::new()` directly
+    ///
+    /// A pipeline can draw to the render target if it was created for a surface with the same format
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the render target on
+    /// * `format`: The format of the colour image
+    /// * `extent`: The size of the colour image
+    /// * `clear_color`: The colour the image is cleared to at the start of each pass
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Device;
+    /// # fn example(device: &Device) {
+    /// use ash::vk;
+    /// use client::renderer::vulkan::RenderTarget;
+    ///
+    /// let target = RenderTarget::new(
+    ///     &device,
+    ///     vk::Format::B8G8R8A8_UNORM,
+    ///     vk::Extent2D { width: 1280, height: 720 },
+    ///     [0.0, 0.0, 0.0, 1.0],
+    /// );
+    /// # }
+    /// ```
+    pub fn new(
+        device: &Device,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        clear_color: [f32; 4],
+    ) -> Self {
+        Self::new_multiview(device, format, extent, 1, clear_color)
+    }
+
+    /// Constructs a new `RenderTarget` with one array layer per view, which is rendered to with multiview so that every view is drawn
+    /// in a single pass. The colour image is viewed as a 2D array, and shaders can tell which layer they're drawing with `gl_ViewIndex`.
+    /// Note that the recommended way to create a multiview render target is through [`Device::create_multiview_render_target()`],
+    /// which checks that the device supports the number of views
+    ///
+    /// A pipeline can draw to the render target if it was created for a surface with the same format and with the same
+    /// [`PipelineParameters::view_count`](crate::renderer::vulkan::PipelineParameters::view_count)
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the render target on
+    /// * `format`: The format of the colour image
+    /// * `extent`: The size of each layer of the colour image
+    /// * `view_count`: The number of views, or 1 to not use multiview
+    /// * `clear_color`: The colour the image is cleared to at the start of each pass
+    pub fn new_multiview(
+        device: &Device,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        view_count: u32,
+        clear_color: [f32; 4],
+    ) -> Self {
+        let span = debug_span!("Vulkan/RenderTarget");
+        let _guard = span.enter();
+
+        debug!(
+            "Creating {}x{} render target with format {:?} and {} view(s)",
+            extent.width, extent.height, format, view_count
+        );
+        let (image, view) =
+            create_sampled_colour_image(device, format, extent, view_count, "render target");
+        let render_pass = create_sampled_colour_render_pass(device, &[format], view_count);
+
+        // With multiview, the views are selected by the render pass rather than the framebuffer's layers
+        let attachments = [view];
+        let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1)
+            .build();
+        let framebuffer = unsafe {
+            device
+                .logical_device
+                .create_framebuffer(&framebuffer_create_info, None)
+        }
+        .expect("Failed to create render target framebuffer");
+        track_created(ResourceKind::Framebuffer, 1);
+
+        RenderTarget {
+            device: Rc::downgrade(&device.logical_device),
+            image,
+            view,
+            format,
+            render_pass,
+            framebuffer,
+            extent,
+            clear_color,
+            view_count,
+        }
+    }
+
+    /// Gets the view of the colour image, so that it can be bound for sampling
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    /// Gets the colour image, eg. so that it can be copied from
+    pub(crate) fn image(&self) -> vk::Image {
+        self.image.image
+    }
+
+    /// Gets the format of the colour image
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// Gets the size of the colour image
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Gets the number of views, which is also the number of array layers in the colour image
+    pub fn view_count(&self) -> u32 {
+        self.view_count
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~RenderTarget");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying render target");
+        unsafe {
+            device.destroy_framebuffer(self.framebuffer, None);
+            device.destroy_render_pass(self.render_pass, None);
+            device.destroy_image_view(self.view, None);
+        }
+        track_destroyed(ResourceKind::Framebuffer, 1);
+    }
+}
+
+/// Allocates a colour image that can be rendered to, sampled, and copied from, and creates a view of it.
+/// If the image has more than one layer, it's viewed as a 2D array
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the image on
+/// * `format`: The format of the image
+/// * `extent`: The size of the image
+/// * `layer_count`: The number of array layers in the image
+/// * `name`: A name for the allocation, used when debugging leaks
+pub(crate) fn create_sampled_colour_image(
+    device: &Device,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layer_count: u32,
+    name: &str,
+) -> (Image, vk::ImageView) {
+    let image_create_info = sampled_colour_image_create_info(format, extent, layer_count);
+    let image = device.allocate_image(&image_create_info, MemoryLocation::GpuOnly, name);
+
+    let view_create_info = sampled_colour_view_create_info(image.image, format, layer_count);
+    let view = unsafe {
+        device
+            .logical_device
+            .create_image_view(&view_create_info, None)
+    }
+    .expect("Failed to create colour image view");
+
+    (image, view)
+}
+
+/// Describes a colour image that can be rendered to, sampled, and copied from
+///
+/// # Arguments
+///
+/// * `format`: The format of the image
+/// * `extent`: The size of the image
+/// * `layer_count`: The number of array layers in the image
+fn sampled_colour_image_create_info(
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layer_count: u32,
+) -> vk::ImageCreateInfo {
+    vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(
+            vk::Extent3D::builder()
+                .width(extent.width)
+                .height(extent.height)
+                .depth(1)
+                .build(),
+        )
+        .mip_levels(1)
+        .array_layers(layer_count)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+        )
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .build()
+}
+
+/// Describes a view of every layer of a colour image, which is a 2D array if there's more than one layer
+///
+/// # Arguments
+///
+/// * `image`: The image to view
+/// * `format`: The format of the image
+/// * `layer_count`: The number of array layers in the image
+fn sampled_colour_view_create_info(
+    image: vk::Image,
+    format: vk::Format,
+    layer_count: u32,
+) -> vk::ImageViewCreateInfo {
+    let view_type = if layer_count > 1 {
+        vk::ImageViewType::TYPE_2D_ARRAY
+    } else {
+        vk::ImageViewType::TYPE_2D
+    };
+
+    vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(view_type)
+        .format(format)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(layer_count)
+                .build(),
+        )
+        .build()
+}
+
+/// Constructs a render pass which clears one or more colour attachments and leaves them ready to be sampled.
+/// Attachment `n` is written by the fragment shader output at `location = n`. Pipelines with colour attachment formats use a render
+/// pass built the same way, so that they're compatible with multi-render targets
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the render pass on
+/// * `formats`: The format of each colour attachment
+/// * `view_count`: The number of views to render with multiview, or 1 to not use multiview
+pub(crate) fn create_sampled_colour_render_pass(
+    device: &Device,
+    formats: &[vk::Format],
+    view_count: u32,
+) -> vk::RenderPass {
+    let attachments: Vec<vk::AttachmentDescription> = formats
+        .iter()
+        .map(|format| {
+            vk::AttachmentDescription::builder()
+                .format(*format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .build()
+        })
+        .collect();
+
+    let colour_attachment_references: Vec<vk::AttachmentReference> = (0..formats.len() as u32)
+        .map(|attachment| {
+            vk::AttachmentReference::builder()
+                .attachment(attachment)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .build()
+        })
+        .collect();
+
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&colour_attachment_references)
+        .build()];
+
+    let dependencies = [
+        // Any previous sampling of the image must finish before it's rendered over
+        vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .src_access_mask(vk::AccessFlags::SHADER_READ)
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .build(),
+        // Rendering must finish before a later pass samples the image
+        vk::SubpassDependency::builder()
+            .src_subpass(0)
+            .dst_subpass(vk::SUBPASS_EXTERNAL)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .build(),
+    ];
+
+    let view_masks = [multiview_mask(view_count)];
+    let mut multiview_create_info = vk::RenderPassMultiviewCreateInfo::builder()
+        .view_masks(&view_masks)
+        .correlation_masks(&view_masks)
+        .build();
+
+    let mut render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses)
+        .dependencies(&dependencies);
+    if view_count > 1 {
+        render_pass_create_info = render_pass_create_info.push_next(&mut multiview_create_info);
+    }
+    let render_pass_create_info = render_pass_create_info.build();
+
+    unsafe {
+        device
+            .logical_device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Failed to create render target render pass")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_view_target_renders_both_layers() {
+        let format = vk::Format::R8G8B8A8_UNORM;
+        let extent = vk::Extent2D {
+            width: 64,
+            height: 32,
+        };
+
+        // The same mask is used for the view mask and the correlation mask
+        assert_eq!(multiview_mask(2), 0b11);
+
+        let image_create_info = sampled_colour_image_create_info(format, extent, 2);
+        assert_eq!(image_create_info.array_layers, 2);
+
+        let view_create_info = sampled_colour_view_create_info(vk::Image::null(), format, 2);
+        assert_eq!(view_create_info.view_type, vk::ImageViewType::TYPE_2D_ARRAY);
+        assert_eq!(view_create_info.subresource_range.base_array_layer, 0);
+        assert_eq!(view_create_info.subresource_range.layer_count, 2);
+    }
+
+    #[test]
+    fn single_view_target_is_not_an_array() {
+        assert_eq!(multiview_mask(1), 0b1);
+
+        let view_create_info =
+            sampled_colour_view_create_info(vk::Image::null(), vk::Format::R8G8B8A8_UNORM, 1);
+        assert_eq!(view_create_info.view_type, vk::ImageViewType::TYPE_2D);
+        assert_eq!(view_create_info.subresource_range.layer_count, 1);
+    }
+}
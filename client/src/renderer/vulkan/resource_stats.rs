@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LIVE_PIPELINES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_FRAMEBUFFERS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BUFFERS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_IMAGES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_COMMAND_BUFFERS: AtomicUsize = AtomicUsize::new(0);
+
+/// The kinds of Vulkan object whose creation and destruction is counted, to help catch leaks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ResourceKind {
+    Pipeline,
+    Framebuffer,
+    Buffer,
+    Image,
+    CommandBuffer,
+}
+
+impl ResourceKind {
+    fn counter(self) -> &'static AtomicUsize {
+        match self {
+            ResourceKind::Pipeline => &LIVE_PIPELINES,
+            ResourceKind::Framebuffer => &LIVE_FRAMEBUFFERS,
+            ResourceKind::Buffer => &LIVE_BUFFERS,
+            ResourceKind::Image => &LIVE_IMAGES,
+            ResourceKind::CommandBuffer => &LIVE_COMMAND_BUFFERS,
+        }
+    }
+}
+
+/// The number of each kind of Vulkan object which currently exist.
+/// Any which are non-zero once everything has been dropped have leaked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    pub pipelines: usize,
+    pub framebuffers: usize,
+    pub buffers: usize,
+    pub images: usize,
+    pub command_buffers: usize,
+}
+
+impl ResourceStats {
+    /// Gets the total number of objects which currently exist
+    pub fn total(&self) -> usize {
+        self.pipelines + self.framebuffers + self.buffers + self.images + self.command_buffers
+    }
+}
+
+/// Records that objects have been created
+///
+/// # Arguments
+///
+/// * `kind`: The kind of object
+/// * `count`: The number of objects created
+pub(crate) fn track_created(kind: ResourceKind, count: usize) {
+    kind.counter().fetch_add(count, Ordering::Relaxed);
+}
+
+/// Records that objects have been destroyed
+///
+/// # Arguments
+///
+/// * `kind`: The kind of object
+/// * `count`: The number of objects destroyed
+pub(crate) fn track_destroyed(kind: ResourceKind, count: usize) {
+    kind.counter().fetch_sub(count, Ordering::Relaxed);
+}
+
+/// Gets the number of each kind of object which currently exist
+pub(crate) fn live_resources() -> ResourceStats {
+    ResourceStats {
+        pipelines: LIVE_PIPELINES.load(Ordering::Relaxed),
+        framebuffers: LIVE_FRAMEBUFFERS.load(Ordering::Relaxed),
+        buffers: LIVE_BUFFERS.load(Ordering::Relaxed),
+        images: LIVE_IMAGES.load(Ordering::Relaxed),
+        command_buffers: LIVE_COMMAND_BUFFERS.load(Ordering::Relaxed),
+    }
+}
@@ -0,0 +1,119 @@
+use crate::renderer::vulkan::Device;
+use ash::vk;
+use std::rc::{Rc, Weak};
+use tracing::{debug, debug_span, warn};
+
+/// The parameters used to create a `Sampler`
+///
+/// By default samplers use trilinear filtering with no level of detail bias and the full mip range
+pub struct SamplerParameters {
+    /// The bias added to the level of detail calculated by the sampler. Negative values bias towards sharper mips, positive values towards blurrier ones
+    pub mip_lod_bias: f32,
+    /// The lowest (most detailed) mip level that can be sampled
+    pub min_lod: f32,
+    /// The highest (least detailed) mip level that can be sampled. `vk::LOD_CLAMP_NONE` allows the full mip chain to be used
+    pub max_lod: f32,
+}
+
+impl Default for SamplerParameters {
+    fn default() -> Self {
+        Self {
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+        }
+    }
+}
+
+pub struct Sampler {
+    device: Weak<ash::Device>,
+    pub(crate) sampler: vk::Sampler,
+}
+
+impl Sampler {
+    /// Constructs a new `Sampler` using the provided parameters.
+    /// Note that the recommended way to create a sampler is through [`Device::create_sampler()`] rather than using `Sampler::new()` directly
+    ///
+    /// If `min_lod` is greater than `max_lod`, or the sampler can't be created (eg. the device is out of memory), returns `Err`
+    ///
+    /// The LOD bias is clamped to the range supported by the device
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to construct the `Sampler` on
+    /// * `parameters`: The `SamplerParameters` describing how the `Sampler` should filter
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::{DeviceParameters, Surface};
+    /// # fn example(surface: &Surface) -> Result<(), Box<dyn std::error::Error>> {
+    /// use client::renderer::vulkan::{Context, Device, Sampler, SamplerParameters};
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let device = Device::new(&context, &surface, &DeviceParameters::default())?;
+    ///
+    /// let sampler = Sampler::new(&device, &SamplerParameters { mip_lod_bias: -0.5, ..Default::default() })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(device: &Device, parameters: &SamplerParameters) -> Result<Self, &'static str> {
+        let span = debug_span!("Vulkan/Sampler");
+        let _guard = span.enter();
+
+        if parameters.min_lod > parameters.max_lod {
+            return Err("The minimum LOD of a sampler cannot be greater than its maximum LOD");
+        }
+
+        let max_lod_bias = device.limits().max_sampler_lod_bias;
+        let mip_lod_bias = num::clamp(parameters.mip_lod_bias, -max_lod_bias, max_lod_bias);
+        if mip_lod_bias != parameters.mip_lod_bias {
+            warn!(
+                "Sampler LOD bias {} is outside of the supported range, clamping to {}",
+                parameters.mip_lod_bias, mip_lod_bias
+            );
+        }
+
+        let sampler_create_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .mip_lod_bias(mip_lod_bias)
+            .min_lod(parameters.min_lod)
+            .max_lod(parameters.max_lod)
+            .anisotropy_enable(false)
+            .compare_enable(false)
+            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .build();
+
+        debug!("Creating sampler");
+        let sampler = unsafe {
+            device
+                .logical_device
+                .create_sampler(&sampler_create_info, None)
+        }
+        .map_err(|_| "Failed to create sampler")?;
+        debug!("Successfully created sampler");
+
+        Ok(Sampler {
+            device: Rc::downgrade(&device.logical_device),
+            sampler,
+        })
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~Sampler");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying sampler");
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
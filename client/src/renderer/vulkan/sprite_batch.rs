@@ -0,0 +1,408 @@
+use ash::vk;
+use common::color::Color;
+use gpu_allocator::MemoryLocation;
+use tracing::{debug, debug_span};
+
+use crate::renderer::vulkan::surface::MAX_FRAMES_IN_FLIGHT;
+use crate::renderer::vulkan::{Buffer, Device, PipelineParameters, TextureHandle};
+
+/// The number of sprites the vertex buffers have room for before they first need to grow
+const INITIAL_SPRITE_CAPACITY: usize = 256;
+/// Each sprite is drawn as two triangles, without an index buffer
+const VERTICES_PER_SPRITE: usize = 6;
+/// The set the sprite texture array is bound to, after the frame uniforms at set 0
+const TEXTURE_SET: u32 = 1;
+
+/// A textured quad, positioned in pixels from the top-left corner of the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    /// The position of the top-left corner, in pixels
+    pub position: [f32; 2],
+    /// The width and height, in pixels
+    pub size: [f32; 2],
+    /// The texture coordinates of the top-left corner
+    pub uv_min: [f32; 2],
+    /// The texture coordinates of the bottom-right corner
+    pub uv_max: [f32; 2],
+    /// The colour the texture is multiplied by
+    pub color: Color,
+    /// The layer of the texture array (or the page of the atlas) to sample from
+    pub texture_id: u32,
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            size: [0.0, 0.0],
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+            color: Color::WHITE,
+            texture_id: 0,
+        }
+    }
+}
+
+/// A single corner of a sprite, as it's laid out in the vertex buffer
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteVertex {
+    /// The position in normalised device coordinates
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    /// The colour in linear space, with alpha
+    pub color: [f32; 4],
+    pub texture_id: u32,
+}
+
+impl SpriteVertex {
+    /// Gets the description of the vertex buffer binding that sprite pipelines read from
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<SpriteVertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    /// Gets the descriptions of each field of the vertex, which match the inputs of `sprite.vert`
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 4] {
+        [
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(std::mem::offset_of!(SpriteVertex, position) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(std::mem::offset_of!(SpriteVertex, uv) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(2)
+                .format(vk::Format::R32G32B32A32_SFLOAT)
+                .offset(std::mem::offset_of!(SpriteVertex, color) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(3)
+                .format(vk::Format::R32_UINT)
+                .offset(std::mem::offset_of!(SpriteVertex, texture_id) as u32)
+                .build(),
+        ]
+    }
+}
+
+/// The vertex buffer a frame in flight's sprites are written to
+#[derive(Default)]
+struct FrameVertices {
+    buffer: Option<Buffer>,
+    // Buffers which were replaced by a larger one during the frame. Earlier draws in the frame still read from them, so they're kept
+    // until the frame's submission has finished
+    retired_buffers: Vec<Buffer>,
+    // The number of vertices already written to `buffer` in this frame, which later flushes append after
+    vertex_count: usize,
+    // The `Device::frame_serial()` of the frame the vertices were written in
+    frame_serial: u64,
+}
+
+impl FrameVertices {
+    /// Starts writing from the beginning of the buffer again if the frame has begun again since it was last written, as its
+    /// previous submission has finished reading the vertices
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_serial`: The `Device::frame_serial()` of the frame being recorded
+    fn begin(&mut self, frame_serial: u64) {
+        if self.frame_serial != frame_serial {
+            self.frame_serial = frame_serial;
+            self.vertex_count = 0;
+            self.retired_buffers.clear();
+        }
+    }
+
+    /// Gets whether the buffer has room for more vertices after those already written in this frame
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_count`: The number of vertices to add
+    fn has_room_for(&self, vertex_count: usize) -> bool {
+        self.buffer.as_ref().is_some_and(|buffer| {
+            buffer.size()
+                >= ((self.vertex_count + vertex_count) * std::mem::size_of::<SpriteVertex>()) as u64
+        })
+    }
+}
+
+/// Collects sprites over a frame and draws them, for UI and other 2D elements.
+///
+/// Sprites are written into a host-visible vertex buffer, with one buffer per frame in flight so that a frame's sprites aren't
+/// overwritten whilst the GPU is still reading them. Each flush appends to the frame's buffer, so a batch can be flushed several
+/// times in a frame (eg. with a different texture each time). The texture is an array, and each sprite picks its layer, so sprites
+/// sharing a texture array are drawn with a single draw call, in the order they were added
+///
+/// # Examples
+///
+/// ```no_run
+/// # use client::renderer::vulkan::{Device, Surface, TextureHandle};
+/// # fn example(mut device: Device, surface: &Surface, sprites: TextureHandle, current_frame_index: usize) -> Result<(), &'static str> {
+/// use std::path::Path;
+/// use client::renderer::vulkan::{Sprite, SpriteBatch};
+///
+/// let sprite_pipeline = device.create_pipeline(
+///     &surface,
+///     Path::new("res/shaders/sprite.vert.spv"),
+///     Path::new("res/shaders/sprite.frag.spv"),
+///     String::from("sprites"),
+///     &SpriteBatch::pipeline_parameters(&device),
+/// )?;
+///
+/// let mut batch = SpriteBatch::new();
+/// batch.begin([1280.0, 720.0]);
+/// batch.draw(Sprite {
+///     position: [16.0, 16.0],
+///     size: [64.0, 64.0],
+///     texture_id: 2,
+///     ..Default::default()
+/// });
+/// // Whilst `sprite_pipeline` is bound, where `sprites` was created with `Device::create_texture_array()`
+/// batch.flush(&mut device, current_frame_index, sprites)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SpriteBatch {
+    vertices: Vec<SpriteVertex>,
+    screen_size: [f32; 2],
+    frames: [FrameVertices; MAX_FRAMES_IN_FLIGHT],
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::with_capacity(INITIAL_SPRITE_CAPACITY * VERTICES_PER_SPRITE),
+            screen_size: [1.0, 1.0],
+            frames: Default::default(),
+        }
+    }
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the pipeline parameters a sprite pipeline needs, so that it reads `SpriteVertex`es from the batch's vertex buffer and
+    /// samples the texture array bound to set 1, as `sprite.frag` does
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` the pipeline will be created on
+    pub fn pipeline_parameters(device: &Device) -> PipelineParameters {
+        PipelineParameters {
+            vertex_bindings: vec![SpriteVertex::binding_description()],
+            vertex_attributes: SpriteVertex::attribute_descriptions().to_vec(),
+            descriptor_set_layouts: vec![device.texture_array_descriptor_set_layout()],
+            ..Default::default()
+        }
+    }
+
+    /// Starts a new batch, discarding any sprites which weren't flushed
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_size`: The width and height of the area being drawn to, in pixels
+    pub fn begin(&mut self, screen_size: [f32; 2]) {
+        self.vertices.clear();
+        self.screen_size = screen_size;
+    }
+
+    /// Adds a sprite to the batch. Sprites added later are drawn on top of those added earlier
+    ///
+    /// # Arguments
+    ///
+    /// * `sprite`: The sprite to draw
+    pub fn draw(&mut self, sprite: Sprite) {
+        let to_ndc = |x: f32, y: f32| {
+            [
+                x / self.screen_size[0] * 2.0 - 1.0,
+                y / self.screen_size[1] * 2.0 - 1.0,
+            ]
+        };
+        let [left, top] = sprite.position;
+        let right = left + sprite.size[0];
+        let bottom = top + sprite.size[1];
+        let color = sprite.color.to_linear();
+
+        let vertex = |x: f32, y: f32, u: f32, v: f32| SpriteVertex {
+            position: to_ndc(x, y),
+            uv: [u, v],
+            color,
+            texture_id: sprite.texture_id,
+        };
+        let top_left = vertex(left, top, sprite.uv_min[0], sprite.uv_min[1]);
+        let top_right = vertex(right, top, sprite.uv_max[0], sprite.uv_min[1]);
+        let bottom_left = vertex(left, bottom, sprite.uv_min[0], sprite.uv_max[1]);
+        let bottom_right = vertex(right, bottom, sprite.uv_max[0], sprite.uv_max[1]);
+
+        // Pipelines cull counter-clockwise faces, and Vulkan's Y axis points down
+        self.vertices.extend_from_slice(&[
+            top_left,
+            top_right,
+            bottom_right,
+            top_left,
+            bottom_right,
+            bottom_left,
+        ]);
+    }
+
+    /// Gets the number of sprites waiting to be flushed
+    pub fn len(&self) -> usize {
+        self.vertices.len() / VERTICES_PER_SPRITE
+    }
+
+    /// Gets whether there are no sprites waiting to be flushed
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Gets the vertices of the sprites waiting to be flushed, as they'll be written to the vertex buffer
+    pub fn vertices(&self) -> &[SpriteVertex] {
+        &self.vertices
+    }
+
+    /// Uploads the batched sprites and records a draw for them, then empties the batch. The vertices are appended after any flushed
+    /// earlier in the frame, so those draws are left as they were.
+    /// This must be called between beginning and ending a render pass, whilst a pipeline created with
+    /// [`SpriteBatch::pipeline_parameters()`] is bound
+    ///
+    /// If the texture can't be bound (see [`Device::bind_texture_array()`]), returns `Err` and leaves the sprites in the batch
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to draw with
+    /// * `current_frame_index`: The index of the frame being recorded, which selects the vertex buffer to write to
+    /// * `texture`: The texture array the sprites sample, created with [`Device::create_texture_array()`]
+    pub fn flush(
+        &mut self,
+        device: &mut Device,
+        current_frame_index: usize,
+        texture: TextureHandle,
+    ) -> Result<(), &'static str> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let span = debug_span!("Vulkan/SpriteBatch");
+        let _guard = span.enter();
+
+        device.bind_texture_array(current_frame_index, TEXTURE_SET, texture)?;
+
+        let vertex_bytes = unsafe {
+            std::slice::from_raw_parts(
+                self.vertices.as_ptr() as *const u8,
+                std::mem::size_of_val(self.vertices.as_slice()),
+            )
+        };
+
+        let frame = &mut self.frames[current_frame_index];
+        frame.begin(device.frame_serial(current_frame_index));
+        if !frame.has_room_for(self.vertices.len()) {
+            let capacity = ((frame.vertex_count + self.vertices.len()) / VERTICES_PER_SPRITE)
+                .next_power_of_two()
+                .max(INITIAL_SPRITE_CAPACITY);
+            debug!(
+                "Growing sprite vertex buffer {} to {} sprites",
+                current_frame_index, capacity
+            );
+            let create_info = vk::BufferCreateInfo::builder()
+                .size((capacity * VERTICES_PER_SPRITE * std::mem::size_of::<SpriteVertex>()) as u64)
+                .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .build();
+            let buffer =
+                device.allocate_buffer(&create_info, MemoryLocation::CpuToGpu, "sprite vertices");
+            if let Some(retired_buffer) = frame.buffer.replace(buffer) {
+                frame.retired_buffers.push(retired_buffer);
+            }
+            // Earlier draws in the frame read from the retired buffer, so the new one is written from the beginning
+            frame.vertex_count = 0;
+        }
+
+        let offset = frame.vertex_count * std::mem::size_of::<SpriteVertex>();
+        let buffer = frame.buffer.as_mut().unwrap();
+        buffer
+            .mapped_slice_mut()
+            .expect("Sprite vertex buffer should be host-visible")
+            [offset..offset + vertex_bytes.len()]
+            .copy_from_slice(vertex_bytes);
+        frame.vertex_count += self.vertices.len();
+
+        device.bind_vertex_buffer_at(current_frame_index, buffer, offset as u64);
+        device.draw_vertices(current_frame_index, self.vertices.len() as u32);
+        self.vertices.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_vertices_are_appended_until_the_frame_begins_again() {
+        let mut frame = FrameVertices::default();
+        frame.begin(1);
+        frame.vertex_count = 12;
+
+        // Flushing again in the same frame appends after the vertices already written
+        frame.begin(1);
+        assert_eq!(frame.vertex_count, 12);
+
+        // Once the frame begins again, its previous submission has finished reading them
+        frame.begin(2);
+        assert_eq!(frame.vertex_count, 0);
+    }
+
+    #[test]
+    fn frame_vertices_without_a_buffer_have_no_room() {
+        let frame = FrameVertices::default();
+        assert!(!frame.has_room_for(VERTICES_PER_SPRITE));
+    }
+
+    #[test]
+    fn glyph_uvs_and_layers_reach_the_sprite_shader_inputs() {
+        let shader = include_str!("../../../res/shaders/sprite.vert");
+        assert!(shader.contains("layout (location = 1) in vec2 inUv;"));
+        assert!(shader.contains("layout (location = 3) in uint inTextureId;"));
+
+        let attributes = SpriteVertex::attribute_descriptions();
+        let uv = attributes
+            .iter()
+            .find(|attribute| attribute.location == 1)
+            .unwrap();
+        assert_eq!(uv.format, vk::Format::R32G32_SFLOAT);
+        assert_eq!(uv.offset as usize, std::mem::offset_of!(SpriteVertex, uv));
+        let texture_id = attributes
+            .iter()
+            .find(|attribute| attribute.location == 3)
+            .unwrap();
+        assert_eq!(texture_id.format, vk::Format::R32_UINT);
+        assert_eq!(
+            texture_id.offset as usize,
+            std::mem::offset_of!(SpriteVertex, texture_id)
+        );
+    }
+
+    #[test]
+    fn sprite_shader_samples_the_texture_array_at_the_sprite_uvs() {
+        let shader = include_str!("../../../res/shaders/sprite.frag");
+        assert!(shader.contains(&format!(
+            "layout (set = {}, binding = 0) uniform sampler2DArray spriteTextures;",
+            TEXTURE_SET
+        )));
+        assert!(shader.contains("texture(spriteTextures, vec3(fragUv, float(fragTextureId)))"));
+    }
+}
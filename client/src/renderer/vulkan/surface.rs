@@ -1,15 +1,25 @@
+use std::fmt;
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use ash::{extensions, vk};
+use common::color::Color;
 use num;
-use tracing::{debug, debug_span};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, warn};
 use winit::window::raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
-use crate::renderer::vulkan::{Context, Device, Pipeline};
+use crate::renderer::vulkan::depth_target::create_depth_image;
+use crate::renderer::vulkan::resource_stats::{track_created, track_destroyed, ResourceKind};
+use crate::renderer::vulkan::{Context, Device, Image, Pipeline};
+use crate::renderer::RendererError;
 
 pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
+/// How long to wait for the presentation engine to hand over an image before skipping the frame
+const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_millis(100);
+
 struct SwapChainInfo {
     capabilities: vk::SurfaceCapabilitiesKHR,
     formats: Vec<vk::SurfaceFormatKHR>,
@@ -22,6 +32,161 @@ pub struct SwapChainParameters {
     pub extent: vk::Extent2D,
 }
 
+/// The ways in which rendered images can be handed to the display
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Images are displayed immediately, which may cause tearing
+    Immediate,
+    /// Images are queued for the next vertical blank, replacing any image already waiting (triple buffering)
+    Mailbox,
+    /// Images are queued for the next vertical blank (vsync)
+    Fifo,
+    /// As `Fifo`, but late images are displayed immediately, which may cause tearing
+    FifoRelaxed,
+}
+
+impl From<PresentMode> for vk::PresentModeKHR {
+    fn from(present_mode: PresentMode) -> Self {
+        match present_mode {
+            PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+            PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+            PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+            PresentMode::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED,
+        }
+    }
+}
+
+impl TryFrom<vk::PresentModeKHR> for PresentMode {
+    type Error = &'static str;
+
+    fn try_from(present_mode: vk::PresentModeKHR) -> Result<Self, Self::Error> {
+        match present_mode {
+            vk::PresentModeKHR::IMMEDIATE => Ok(PresentMode::Immediate),
+            vk::PresentModeKHR::MAILBOX => Ok(PresentMode::Mailbox),
+            vk::PresentModeKHR::FIFO => Ok(PresentMode::Fifo),
+            vk::PresentModeKHR::FIFO_RELAXED => Ok(PresentMode::FifoRelaxed),
+            _ => Err("The present mode is not supported on desktop platforms"),
+        }
+    }
+}
+
+impl fmt::Display for PresentMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PresentMode::Immediate => "Immediate",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Fifo => "FIFO",
+            PresentMode::FifoRelaxed => "FIFO (relaxed)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A pixel format and colour space pair that a surface can present
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceFormat {
+    format: vk::Format,
+    color_space: vk::ColorSpaceKHR,
+}
+
+impl From<vk::SurfaceFormatKHR> for SurfaceFormat {
+    fn from(surface_format: vk::SurfaceFormatKHR) -> Self {
+        Self {
+            format: surface_format.format,
+            color_space: surface_format.color_space,
+        }
+    }
+}
+
+impl fmt::Display for SurfaceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} ({:?})", self.format, self.color_space)
+    }
+}
+
+/// Everything a surface supports on the current device, for populating graphics settings
+pub struct SurfaceCapabilities {
+    pub formats: Vec<SurfaceFormat>,
+    pub present_modes: Vec<PresentMode>,
+    pub min_image_count: u32,
+    /// The maximum number of swapchain images, or `None` if there's no limit
+    pub max_image_count: Option<u32>,
+    /// The current size of the surface, or `None` if the size is determined by the swapchain
+    pub current_extent: Option<winit::dpi::PhysicalSize<u32>>,
+}
+
+impl From<&SwapChainInfo> for SurfaceCapabilities {
+    fn from(swapchain_info: &SwapChainInfo) -> Self {
+        let capabilities = &swapchain_info.capabilities;
+
+        Self {
+            formats: swapchain_info
+                .formats
+                .iter()
+                .map(|format| SurfaceFormat::from(*format))
+                .collect(),
+            present_modes: swapchain_info
+                .present_modes
+                .iter()
+                .filter_map(|mode| PresentMode::try_from(*mode).ok())
+                .collect(),
+            min_image_count: capabilities.min_image_count,
+            max_image_count: if capabilities.max_image_count == 0 {
+                None
+            } else {
+                Some(capabilities.max_image_count)
+            },
+            current_extent: if capabilities.current_extent.width == u32::MAX {
+                None
+            } else {
+                Some(winit::dpi::PhysicalSize::new(
+                    capabilities.current_extent.width,
+                    capabilities.current_extent.height,
+                ))
+            },
+        }
+    }
+}
+
+/// How many images the swapchain should cycle through, trading latency for smoothness
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BufferingMode {
+    /// Two swapchain images, presented with vsync
+    #[default]
+    Double,
+    /// Three swapchain images, presented with `Mailbox` so that the newest frame is always displayed
+    Triple,
+}
+
+impl BufferingMode {
+    fn image_count(&self) -> u32 {
+        match self {
+            BufferingMode::Double => 2,
+            BufferingMode::Triple => 3,
+        }
+    }
+
+    fn present_mode(&self) -> Option<PresentMode> {
+        match self {
+            // `None` keeps the default FIFO-based selection
+            BufferingMode::Double => None,
+            BufferingMode::Triple => Some(PresentMode::Mailbox),
+        }
+    }
+}
+
+/// A swapchain image that was acquired outside of the `Surface` (eg. by a video decoder or an XR runtime), to be drawn to and
+/// presented by the next frame instead of one the `Surface` acquires itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExternalAcquire {
+    /// The index of the swapchain image that was acquired
+    pub image_index: u32,
+    /// A semaphore which is signalled once the image is ready to be drawn to
+    pub wait_semaphore: vk::Semaphore,
+    /// A semaphore to signal once the frame has finished rendering, or `None` if nothing outside the `Surface` needs to know
+    pub signal_semaphore: Option<vk::Semaphore>,
+}
+
 pub struct Surface {
     device: Option<Arc<RwLock<Device>>>,
     pub(super) surface_extension: extensions::khr::Surface,
@@ -29,109 +194,885 @@ pub struct Surface {
     swapchain_extension: Option<extensions::khr::Swapchain>,
     swapchain: Option<vk::SwapchainKHR>,
     pub(super) swapchain_parameters: Option<SwapChainParameters>,
-    _swapchain_images: Vec<vk::Image>,
+    swapchain_images: Vec<vk::Image>,
     image_views: Vec<vk::ImageView>,
+    // Shared by every swapchain image, as only one frame is drawn at a time
+    depth_image: Option<(Image, vk::ImageView)>,
+    depth_format: vk::Format,
     framebuffers: Option<Vec<vk::Framebuffer>>,
+    // Kept so that the framebuffers can be rebuilt when the swapchain is recreated
+    framebuffer_render_pass: Option<vk::RenderPass>,
     current_framebuffer_index: usize,
+    frame_number: u64,
+    needs_recreate: bool,
+    acquire_timeout: Duration,
+    // Used instead of acquiring an image for the next frame, and cleared once that frame is presented
+    external_acquire: Option<ExternalAcquire>,
+    deterministic_present: bool,
+    max_frame_latency: Option<u32>,
+    storage_usage_requested: bool,
+    // Whether the current swapchain's images can be used as storage images, which may be false even if requested
+    storage_usage_enabled: bool,
+    // Whether the current swapchain's images can be copied from, so that frames can be read back
+    transfer_source_enabled: bool,
+    preferred_surface_format: Option<SurfaceFormat>,
+    preferred_present_mode: Option<PresentMode>,
+    buffering_mode: BufferingMode,
+    clear_color: Color,
+    // Indexed by frame in flight, as acquiring only signals a semaphore and the frame's fence guards its reuse
     image_available: Vec<vk::Semaphore>,
+    // Indexed by swapchain image, as presentation may still be waiting on it when the frame in flight comes round again
     render_finished: Vec<vk::Semaphore>,
+    // Indexed by frame in flight, and only ever signalled by the frame's graphics queue submission
     pub(super) frame_in_flight: Vec<vk::Fence>,
 }
 
-impl Surface {
-    /// Constructs a new `Surface`
+impl Surface {
+    /// Constructs a new `Surface`
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` which will render to the `Surface`
+    /// * `device`: The `Device` which will render to the `Surface`
+    /// * `window`: The `Window` that the surface should be created on
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::RendererError;
+    /// # use client::renderer::vulkan::DeviceParameters;
+    /// # fn example() -> Result<(), RendererError> {
+    /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
+    /// use client::renderer::vulkan::{Context, Device, Surface};
+    ///
+    /// let event_loop = EventLoopBuilder::new().build().unwrap();
+    /// let window = WindowBuilder::new().build(&event_loop).unwrap();
+    ///
+    /// let context = Context::new("my-application", (1, 4, 2));
+    /// let surface = Surface::new(&context, &window);
+    /// let device = Device::new(&context, &surface, &DeviceParameters::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(context: &Context, window: &winit::window::Window) -> Self {
+        let span = debug_span!("Vulkan/Surface");
+        let _guard = span.enter();
+
+        let extension = extensions::khr::Surface::new(&context.entry_point, &context.instance);
+        debug!("Creating SurfaceKHR");
+        let surface = unsafe {
+            ash_window::create_surface(
+                &context.entry_point,
+                &context.instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )
+        }
+        .expect("Failed to create Vulkan surface");
+        debug!("Successfully created surface");
+
+        Surface {
+            device: None,
+            surface_extension: extension,
+            surface,
+            swapchain_extension: None,
+            swapchain: None,
+            swapchain_parameters: None,
+            swapchain_images: vec![],
+            image_views: vec![],
+            depth_image: None,
+            depth_format: vk::Format::UNDEFINED,
+            framebuffers: None,
+            framebuffer_render_pass: None,
+            current_framebuffer_index: 0,
+            frame_number: 0,
+            needs_recreate: false,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            external_acquire: None,
+            deterministic_present: false,
+            max_frame_latency: None,
+            storage_usage_requested: false,
+            storage_usage_enabled: false,
+            transfer_source_enabled: false,
+            preferred_surface_format: None,
+            preferred_present_mode: None,
+            buffering_mode: BufferingMode::default(),
+            clear_color: Color::BLACK,
+            image_available: vec![],
+            render_finished: vec![],
+            frame_in_flight: vec![],
+        }
+    }
+
+    /// Creates the swapchain, along with the synchronisation objects used to render each frame in flight
+    ///
+    /// If the surface doesn't support any formats or present modes, or the swapchain has no images, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` which will render to the `Surface`
+    /// * `device`: The `Device` which will render to the `Surface`
+    /// * `window`: The `Window` that the surface was created on
+    pub fn create_swapchain(
+        &mut self,
+        context: &Context,
+        device: &Arc<RwLock<Device>>,
+        window: &winit::window::Window,
+    ) -> Result<(), RendererError> {
+        self.device = Some(device.clone());
+
+        let device_guard = device.read();
+        let device_lock = device_guard.unwrap();
+        let device = device_lock.deref();
+
+        self.swapchain_extension = Some(extensions::khr::Swapchain::new(
+            &context.instance,
+            &device.logical_device,
+        ));
+
+        let swapchain_parameters =
+            self.create_swapchain_images(device, window.inner_size(), None)?;
+
+        let semaphore_create_info = vk::SemaphoreCreateInfo::builder().build();
+
+        self.image_available = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                unsafe {
+                    device
+                        .logical_device
+                        .create_semaphore(&semaphore_create_info, None)
+                }
+                .expect("Failed to create semaphore for checking if framebuffer is available")
+            })
+            .collect();
+
+        let fence_create_info = vk::FenceCreateInfo::builder()
+            .flags(vk::FenceCreateFlags::SIGNALED)
+            .build();
+
+        self.frame_in_flight = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                unsafe { device.logical_device.create_fence(&fence_create_info, None) }
+                    .expect("Failed to create fence for checking if frame is in flight")
+            })
+            .collect();
+
+        self.swapchain_parameters = Some(swapchain_parameters);
+        Ok(())
+    }
+
+    /// Recreates the swapchain at the window's current size, along with its image views and framebuffers.
+    /// This should be called whenever the physical size of the window changes, such as when it's moved to a display with a different scale factor
+    ///
+    /// If the window has no area (eg. it's minimised), the swapchain is left as it is
+    ///
+    /// If the surface no longer supports any formats or present modes, or the new swapchain has no images, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: The `Context` the swapchain was created with
+    /// * `window`: The `Window` that the surface was created on
+    pub fn recreate_swapchain(
+        &mut self,
+        context: &Context,
+        window: &winit::window::Window,
+    ) -> Result<(), RendererError> {
+        let span = debug_span!("Vulkan/Surface");
+        let _guard = span.enter();
+
+        let window_size = window.inner_size();
+        if window_size.width == 0 || window_size.height == 0 {
+            debug!("Window has no area, not recreating swapchain");
+            return Ok(());
+        }
+
+        let device_arc = self
+            .device
+            .clone()
+            .expect("The swapchain can only be recreated once it has been created");
+        let device_guard = device_arc.read();
+        let device_lock = device_guard.unwrap();
+        let device = device_lock.deref();
+
+        // Nothing can still be using the old images when they're destroyed
+        unsafe { device.logical_device.device_wait_idle() }
+            .expect("Device was removed whilst recreating the swapchain");
+
+        debug!(
+            "Recreating swapchain at {}x{}",
+            window_size.width, window_size.height
+        );
+        if self.external_acquire.take().is_some() {
+            warn!("Discarding an externally acquired image, as it belonged to the old swapchain");
+        }
+        self.destroy_swapchain_image_views(device);
+        self.swapchain_extension = Some(extensions::khr::Swapchain::new(
+            &context.instance,
+            &device.logical_device,
+        ));
+
+        // Handing the old swapchain over lets the driver reuse its resources, but it's retired either way so must be destroyed
+        let old_swapchain = self.swapchain.take();
+        let swapchain_parameters = self.create_swapchain_images(device, window_size, old_swapchain);
+        if let Some(old_swapchain) = old_swapchain {
+            self.destroy_swapchain(old_swapchain);
+        }
+        self.swapchain_parameters = Some(swapchain_parameters?);
+        self.needs_recreate = false;
+
+        if let Some(render_pass) = self.framebuffer_render_pass {
+            self.framebuffers = Some(self.create_framebuffers(device, render_pass));
+        }
+        Ok(())
+    }
+
+    pub fn create_framebuffers_for_pipeline(&mut self, device: &Device, pipeline: &Pipeline) {
+        // Framebuffers made for an earlier pipeline would otherwise leak
+        self.destroy_framebuffers(device);
+        self.framebuffer_render_pass = Some(pipeline.render_pass);
+        self.framebuffers = Some(self.create_framebuffers(device, pipeline.render_pass));
+    }
+
+    fn create_framebuffers(
+        &self,
+        device: &Device,
+        render_pass: vk::RenderPass,
+    ) -> Vec<vk::Framebuffer> {
+        track_created(ResourceKind::Framebuffer, self.image_views.len());
+        (0..self.image_views.len())
+            .map(|index| {
+                let attachments = [self.image_views[index], self.depth_view()];
+                let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .width(self.swapchain_parameters.as_ref().unwrap().extent.width)
+                    .height(self.swapchain_parameters.as_ref().unwrap().extent.height)
+                    .attachments(&attachments)
+                    .layers(1)
+                    .build();
+
+                unsafe {
+                    device
+                        .logical_device
+                        .create_framebuffer(&framebuffer_create_info, None)
+                }
+                .expect("Failed to create framebuffer")
+            })
+            .collect::<Vec<vk::Framebuffer>>()
+    }
+
+    /// Gets the format of the depth buffer that the swapchain images are drawn with, which is chosen when the swapchain is created
+    pub fn depth_format(&self) -> vk::Format {
+        self.depth_format
+    }
+
+    /// Gets the view of the depth buffer, which must have been created along with the swapchain
+    fn depth_view(&self) -> vk::ImageView {
+        self.depth_image
+            .as_ref()
+            .expect("The depth buffer is created along with the swapchain")
+            .1
+    }
+
+    pub fn get_framebuffer(&mut self, index: usize) -> &vk::Framebuffer {
+        let framebuffers = self
+            .framebuffers
+            .as_ref()
+            .expect("No framebuffers have been created, but one has been requested");
+
+        framebuffers.get(index).unwrap()
+    }
+
+    /// Acquires the next image from the swapchain to render to
+    ///
+    /// If no image became available within the acquire timeout, or the swapchain is out of date, returns `None` and the frame should be skipped
+    ///
+    /// If the swapchain is out of date or suboptimal, [`Surface::needs_recreate()`] will return `true` afterwards
+    ///
+    /// If an image was acquired externally with [`Surface::set_external_acquire()`], its index is returned without acquiring anything
+    pub fn acquire_next_image(&mut self) -> Option<u32> {
+        if let Some(external_acquire) = self.external_acquire {
+            return Some(external_acquire.image_index);
+        }
+
+        let timeout = u64::try_from(self.acquire_timeout.as_nanos()).unwrap_or(u64::MAX);
+
+        let result = unsafe {
+            self.swapchain_extension
+                .as_ref()
+                .unwrap()
+                .acquire_next_image(
+                    self.swapchain.unwrap(),
+                    timeout,
+                    *self
+                        .image_available
+                        .get(self.current_framebuffer_index)
+                        .unwrap(),
+                    vk::Fence::null(),
+                )
+        };
+
+        match result {
+            Ok((image_index, suboptimal)) => {
+                if suboptimal {
+                    debug!("Swapchain is suboptimal, it should be recreated");
+                    self.needs_recreate = true;
+                }
+                Some(image_index)
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                debug!("Swapchain is out of date, skipping frame");
+                self.needs_recreate = true;
+                None
+            }
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY) => {
+                warn!(
+                    "No swapchain image was available after {} ms, skipping frame",
+                    self.acquire_timeout.as_millis()
+                );
+                None
+            }
+            Err(error) => panic!("Failed to acquire next image ({})", error),
+        }
+    }
+
+    /// Sets whether [`Surface::flip_buffers()`] blocks until the frame has finished rendering and been presented, instead of letting
+    /// the CPU start on the next frame straight away. This gives up throughput for evenly paced frames, which suits video capture and
+    /// reproducible benchmarks
+    ///
+    /// # Arguments
+    ///
+    /// * `deterministic_present`: Whether to wait for each frame to be presented
+    pub fn set_deterministic_present(&mut self, deterministic_present: bool) {
+        self.deterministic_present = deterministic_present;
+    }
+
+    /// Makes the next frame draw to and present a swapchain image that was acquired outside of the `Surface`, for interop with other
+    /// APIs which need to control acquisition themselves. The frame waits on the external semaphore rather than the `Surface`'s own,
+    /// and the image is presented as usual by [`Surface::flip_buffers()`]. This only applies to a single frame
+    ///
+    /// If the image index isn't one of the swapchain's images, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `external_acquire`: The image that was acquired, and the semaphores to synchronise with
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use ash::vk;
+    /// # use client::renderer::vulkan::{ExternalAcquire, Surface};
+    /// # fn example(
+    /// #     surface: &mut Surface,
+    /// #     swapchain_extension: &ash::extensions::khr::Swapchain,
+    /// #     semaphore: vk::Semaphore,
+    /// # ) -> Result<(), Box<dyn std::error::Error>> {
+    /// let (image_index, _suboptimal) = unsafe {
+    ///     swapchain_extension.acquire_next_image(surface.swapchain().unwrap(), u64::MAX, semaphore, vk::Fence::null())
+    /// }?;
+    /// surface.set_external_acquire(ExternalAcquire {
+    ///     image_index,
+    ///     wait_semaphore: semaphore,
+    ///     signal_semaphore: None,
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_external_acquire(
+        &mut self,
+        external_acquire: ExternalAcquire,
+    ) -> Result<(), &'static str> {
+        if external_acquire.image_index as usize >= self.image_views.len() {
+            return Err("Externally acquired image index is not part of the swapchain");
+        }
+        self.external_acquire = Some(external_acquire);
+        Ok(())
+    }
+
+    /// Gets the swapchain, so that images can be acquired from it externally, or `None` if it hasn't been created yet
+    pub fn swapchain(&self) -> Option<vk::SwapchainKHR> {
+        self.swapchain
+    }
+
+    /// Sets whether the swapchain images should also be usable as storage images, so that compute shaders can post-process them.
+    /// This only takes effect once the swapchain is next created or recreated, and is only honoured if the surface and its format
+    /// support it, which can be checked with [`Surface::is_storage_usage_enabled()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_usage`: Whether to request storage usage
+    pub fn set_storage_usage(&mut self, storage_usage: bool) {
+        if self.storage_usage_requested != storage_usage {
+            self.storage_usage_requested = storage_usage;
+            self.needs_recreate = true;
+        }
+    }
+
+    /// Gets the size of the swapchain images, or `None` if the swapchain hasn't been created yet
+    pub fn extent(&self) -> Option<vk::Extent2D> {
+        self.swapchain_parameters
+            .as_ref()
+            .map(|swapchain_parameters| swapchain_parameters.extent)
+    }
+
+    /// Gets the format of the swapchain images, or `None` if the swapchain hasn't been created yet
+    pub fn format(&self) -> Option<vk::Format> {
+        self.swapchain_parameters
+            .as_ref()
+            .map(|swapchain_parameters| swapchain_parameters.surface_format.format)
+    }
+
+    /// Gets whether the current swapchain images can be used as storage images
+    pub fn is_storage_usage_enabled(&self) -> bool {
+        self.storage_usage_enabled
+    }
+
+    /// Gets whether the current swapchain images can be copied from, so that frames can be read back with
+    /// [`Device::record_frame_readback()`]. Almost every surface supports this
+    pub fn is_transfer_source_enabled(&self) -> bool {
+        self.transfer_source_enabled
+    }
+
+    /// Gets a swapchain image and its view
+    ///
+    /// # Arguments
+    ///
+    /// * `image_index`: The index of the swapchain image
+    pub(crate) fn swapchain_image(&self, image_index: u32) -> (vk::Image, vk::ImageView) {
+        (
+            *self.swapchain_images.get(image_index as usize).unwrap(),
+            *self.image_views.get(image_index as usize).unwrap(),
+        )
+    }
+
+    /// Sets how many frames the CPU may queue ahead of the display, to bound input latency.
+    /// When the device supports VK_KHR_present_wait, [`Surface::flip_buffers()`] waits for an earlier frame to actually be presented.
+    /// Otherwise it falls back to waiting for an earlier frame to finish rendering, which can only tighten the limit below
+    /// `MAX_FRAMES_IN_FLIGHT`
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frame_latency`: The maximum number of queued frames (at least 1), or `None` to only be limited by the frames in flight
+    pub fn set_max_frame_latency(&mut self, max_frame_latency: Option<u32>) {
+        self.max_frame_latency = max_frame_latency.map(|latency| latency.max(1));
+    }
+
+    /// Sets how long [`Surface::acquire_next_image()`] waits for an image before giving up on the frame
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout`: The maximum time to wait. `Duration::MAX` waits indefinitely
+    pub fn set_acquire_timeout(&mut self, timeout: Duration) {
+        self.acquire_timeout = timeout;
+    }
+
+    /// Submits the frame's graphics command buffer and presents the swapchain image it drew to, then moves on to the next frame in flight
+    ///
+    /// Returns whether the image was presented, which it isn't if the swapchain turned out to be out of date
+    ///
+    /// # Arguments
+    ///
+    /// * `next_image`: The index of the swapchain image that was drawn to
+    /// * `dirty_rects`: The regions of the image which changed since the last frame, or `None` if the whole image may have changed.
+    ///   When the device supports VK_KHR_incremental_present, only these regions are updated on screen (saving bandwidth when little
+    ///   changes, eg. UI updates). This is only a hint, so the whole image must still be drawn. Rectangles are clipped to the image
+    pub fn flip_buffers(&mut self, next_image: u32, dirty_rects: Option<&[vk::Rect2D]>) -> bool {
+        let device_guard = self.device.as_ref().unwrap().read();
+        let device_lock = device_guard.unwrap();
+        let device = device_lock.deref();
+
+        let render_finished = *self.render_finished.get(next_image as usize).unwrap();
+
+        let external_acquire = self.external_acquire.take();
+        let image_available = match external_acquire {
+            Some(external_acquire) => external_acquire.wait_semaphore,
+            None => *self
+                .image_available
+                .get(self.current_framebuffer_index)
+                .unwrap(),
+        };
+        let mut signal_semaphores = vec![render_finished];
+        signal_semaphores.extend(external_acquire.and_then(|external| external.signal_semaphore));
+
+        device.submit_graphics_queue(
+            self.current_framebuffer_index,
+            &signal_semaphores,
+            &[image_available],
+            &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
+            self.frame_in_flight
+                .get(self.current_framebuffer_index)
+                .unwrap(),
+        );
+
+        // Present IDs start at 1, as 0 means the present has no ID
+        let present_id = self.frame_number + 1;
+        let present_ids = [present_id];
+        let mut present_id_info = vk::PresentIdKHR::builder()
+            .present_ids(&present_ids)
+            .build();
+
+        let extent = self.swapchain_parameters.as_ref().unwrap().extent;
+        let rectangles: Vec<vk::RectLayerKHR> = dirty_rects
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|rect| clip_rect(rect, extent))
+            .collect();
+        let present_regions = [vk::PresentRegionKHR::builder()
+            .rectangles(&rectangles)
+            .build()];
+        let mut present_regions_info = vk::PresentRegionsKHR::builder()
+            .regions(&present_regions)
+            .build();
+
+        let wait_semaphores = [render_finished];
+        let swapchains = [self.swapchain.unwrap()];
+        let image_indices = [next_image];
+        let mut present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(&wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+        if device.present_wait.is_some() {
+            present_info = present_info.push_next(&mut present_id_info);
+        }
+        // An empty list of rectangles would mean the whole image changed, which is the same as not passing any regions
+        if device.incremental_present_enabled && !rectangles.is_empty() {
+            present_info = present_info.push_next(&mut present_regions_info);
+        }
+        let present_info = present_info.build();
+
+        let presented =
+            match device.present_queue(self.swapchain_extension.as_ref().unwrap(), &present_info) {
+                Ok(suboptimal) => {
+                    if suboptimal {
+                        debug!("Swapchain is suboptimal, it should be recreated");
+                        self.needs_recreate = true;
+                    }
+                    true
+                }
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    debug!("Swapchain is out of date, so the frame was dropped");
+                    self.needs_recreate = true;
+                    false
+                }
+                Err(error) => panic!("Failed to present graphics queue ({})", error),
+            };
+
+        if self.deterministic_present {
+            // Rather than letting the next frame overlap this one, block until it's been rendered and handed to the presentation engine
+            let frame_in_flight = *self
+                .frame_in_flight
+                .get(self.current_framebuffer_index)
+                .unwrap();
+            unsafe {
+                device
+                    .logical_device
+                    .wait_for_fences(&[frame_in_flight], true, u64::MAX)
+            }
+            .expect("Device was removed or timed out whilst waiting for a fence");
+            device.wait_for_present_queue_idle();
+        }
+
+        // A frame that wasn't presented has no present ID to wait on
+        if let Some(max_frame_latency) = self.max_frame_latency.filter(|_| presented) {
+            if self.limit_frame_latency(device, present_id, max_frame_latency) {
+                debug!("Swapchain is out of date, it should be recreated");
+                self.needs_recreate = true;
+            }
+        }
+
+        self.current_framebuffer_index =
+            (self.current_framebuffer_index + 1) % MAX_FRAMES_IN_FLIGHT;
+        if presented {
+            self.frame_number += 1;
+        }
+        presented
+    }
+
+    /// Blocks until no more than `max_frame_latency` frames are queued ahead of the display, including the one just presented
+    ///
+    /// Returns whether the swapchain was found to be out of date whilst waiting
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` that presented the frame
+    /// * `present_id`: The present ID of the frame just presented
+    /// * `max_frame_latency`: The maximum number of queued frames
+    fn limit_frame_latency(
+        &self,
+        device: &Device,
+        present_id: u64,
+        max_frame_latency: u32,
+    ) -> bool {
+        // The next frame can start once the frame `max_frame_latency - 1` before this one has been presented
+        let Some(wait_present_id) = (present_id + 1).checked_sub(u64::from(max_frame_latency))
+        else {
+            return false;
+        };
+
+        if let Some(present_wait) = device.present_wait.as_ref() {
+            if wait_present_id == 0 {
+                return false;
+            }
+            let timeout = u64::try_from(self.acquire_timeout.as_nanos()).unwrap_or(u64::MAX);
+            match unsafe {
+                present_wait.wait_for_present(self.swapchain.unwrap(), wait_present_id, timeout)
+            } {
+                Ok(()) | Err(vk::Result::TIMEOUT) => false,
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => true,
+                Err(error) => panic!("Failed to wait for present ({})", error),
+            }
+        } else if (max_frame_latency as usize) < MAX_FRAMES_IN_FLIGHT {
+            // Without present wait, the best available signal is the frame having finished rendering
+            let frame_index = (self.current_framebuffer_index + MAX_FRAMES_IN_FLIGHT + 1
+                - max_frame_latency as usize)
+                % MAX_FRAMES_IN_FLIGHT;
+            let frame_in_flight = *self.frame_in_flight.get(frame_index).unwrap();
+            unsafe {
+                device
+                    .logical_device
+                    .wait_for_fences(&[frame_in_flight], true, u64::MAX)
+            }
+            .expect("Device was removed or timed out whilst waiting for a fence");
+            false
+        } else {
+            false
+        }
+    }
+
+    /// Submits a frame started with [`Device::begin_offscreen_frame()`] without presenting anything, and moves on to the next frame in flight.
+    /// The frame number is left unchanged, as it only counts presented frames
+    pub fn submit_offscreen(&mut self) {
+        let device_guard = self.device.as_ref().unwrap().read();
+        let device_lock = device_guard.unwrap();
+        let device = device_lock.deref();
+
+        device.submit_graphics_queue(
+            self.current_framebuffer_index,
+            &[],
+            &[],
+            &[],
+            self.frame_in_flight
+                .get(self.current_framebuffer_index)
+                .unwrap(),
+        );
+
+        self.current_framebuffer_index =
+            (self.current_framebuffer_index + 1) % MAX_FRAMES_IN_FLIGHT;
+    }
+
+    /// Gets the formats, present modes, and image limits that the surface supports on the current device
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Surface;
+    /// # fn example(surface: &Surface) {
+    /// let capabilities = surface.capabilities();
+    /// for present_mode in &capabilities.present_modes {
+    ///     println!("Supports {}", present_mode);
+    /// }
+    /// # }
+    /// ```
+    pub fn capabilities(&self) -> SurfaceCapabilities {
+        let device_guard = self
+            .device
+            .as_ref()
+            .expect("Surface capabilities can only be queried once a swapchain has been created")
+            .read();
+        let device_lock = device_guard.unwrap();
+        let device = device_lock.deref();
+
+        SurfaceCapabilities::from(&get_swapchain_info(
+            device,
+            &self.surface,
+            &self.surface_extension,
+        ))
+    }
+
+    /// Sets the surface format to prefer the next time the swapchain is created
+    ///
+    /// # Arguments
+    ///
+    /// * `surface_format`: One of the formats returned by [`Surface::capabilities()`], or `None` to use the default
+    pub fn set_preferred_surface_format(&mut self, surface_format: Option<SurfaceFormat>) {
+        self.preferred_surface_format = surface_format;
+    }
+
+    /// Sets the present mode to prefer the next time the swapchain is created
     ///
     /// # Arguments
     ///
-    /// * `context`: The `Context` which will render to the `Surface`
-    /// * `device`: The `Device` which will render to the `Surface`
-    /// * `window`: The `Window` that the surface should be created on
+    /// * `present_mode`: One of the present modes returned by [`Surface::capabilities()`], or `None` to use the default
+    pub fn set_preferred_present_mode(&mut self, present_mode: Option<PresentMode>) {
+        self.preferred_present_mode = present_mode;
+    }
+
+    /// Sets the buffering mode to use the next time the swapchain is created.
+    /// If a present mode has been set with [`Surface::set_preferred_present_mode()`], that takes priority over the one implied by the buffering mode
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
-    /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
-    /// use client::renderer::vulkan::{Context, Device, Surface};
+    /// * `buffering_mode`: The `BufferingMode` to use
+    pub fn set_buffering_mode(&mut self, buffering_mode: BufferingMode) {
+        self.buffering_mode = buffering_mode;
+    }
+
+    /// Sets the colour the swapchain image is cleared to at the start of each frame
     ///
-    /// let event_loop = EventLoopBuilder::new().build().unwrap();
-    /// let window = WindowBuilder::new().build(&event_loop).unwrap();
+    /// # Arguments
+    ///
+    /// * `color`: The clear colour, which is converted to suit the swapchain's format
     ///
-    /// let context = Context::new("my-application", (1.4.2));
-    /// let device = Device::new(&context);
-    /// let surface = Surface::new(&context, &device, &window);
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Surface;
+    /// # fn example(surface: &mut Surface) {
+    /// use common::color::Color;
+    ///
+    /// surface.set_clear_color(Color::srgb(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0));
+    /// # }
     /// ```
-    pub fn new(context: &Context, window: &winit::window::Window) -> Self {
-        let span = debug_span!("Vulkan/Surface");
-        let _guard = span.enter();
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
 
-        let extension = extensions::khr::Surface::new(&context.entry_point, &context.instance);
-        debug!("Creating SurfaceKHR");
-        let surface = unsafe {
-            ash_window::create_surface(
-                &context.entry_point,
-                &context.instance,
-                window.raw_display_handle(),
-                window.raw_window_handle(),
-                None,
-            )
-        }
-        .expect("Failed to create Vulkan surface");
-        debug!("Successfully created surface");
+    /// Gets the clear colour as the value the swapchain's format expects
+    pub(crate) fn clear_color_value(&self) -> vk::ClearColorValue {
+        clear_color_value(
+            &self.clear_color,
+            self.swapchain_parameters
+                .as_ref()
+                .map(|parameters| parameters.surface_format.format),
+        )
+    }
 
-        Surface {
-            device: None,
-            surface_extension: extension,
-            surface,
-            swapchain_extension: None,
-            swapchain: None,
-            swapchain_parameters: None,
-            _swapchain_images: vec![],
-            image_views: vec![],
-            framebuffers: None,
-            current_framebuffer_index: 0,
-            image_available: vec![],
-            render_finished: vec![],
-            frame_in_flight: vec![],
-        }
+    pub fn get_current_frame_index(&self) -> usize {
+        self.current_framebuffer_index
     }
 
-    pub fn create_swapchain(
-        &mut self,
-        context: &Context,
-        device: &Arc<RwLock<Device>>,
-        window: &winit::window::Window,
-    ) {
-        self.device = Some(device.clone());
+    /// Gets whether the swapchain no longer matches the surface (eg. after a resize) and should be recreated with [`Surface::recreate_swapchain()`].
+    /// Until it is, frames may be skipped or presented at the wrong size
+    pub fn needs_recreate(&self) -> bool {
+        self.needs_recreate
+    }
 
-        let device_guard = self.device.as_ref().unwrap().read();
+    /// Marks the swapchain as needing to be recreated, for changes which the presentation engine doesn't report (eg. the scale factor changing)
+    pub fn request_recreate(&mut self) {
+        self.needs_recreate = true;
+    }
+
+    /// Gets whether the GPU has finished with the current frame in flight's previous submission, without blocking.
+    /// If it has, starting the next frame won't have to wait for the GPU (although acquiring a swapchain image still might), so
+    /// the main loop can choose to do other work until it returns `true`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use client::renderer::vulkan::Surface;
+    /// # fn stream_in_next_chunk() {}
+    /// # fn example(surface: &Surface) {
+    /// while !surface.is_frame_in_flight_complete() {
+    ///     stream_in_next_chunk();
+    /// }
+    /// # }
+    /// ```
+    pub fn is_frame_in_flight_complete(&self) -> bool {
+        // No frames can be in flight before the swapchain has been created
+        let Some(device) = self.device.as_ref() else {
+            return true;
+        };
+        let device_guard = device.read();
         let device_lock = device_guard.unwrap();
         let device = device_lock.deref();
 
-        self.swapchain_extension = Some(extensions::khr::Swapchain::new(
-            &context.instance,
-            &device.logical_device,
-        ));
+        let frame_in_flight = *self
+            .frame_in_flight
+            .get(self.current_framebuffer_index)
+            .unwrap();
+        unsafe { device.logical_device.get_fence_status(frame_in_flight) }
+            .expect("Device was removed whilst checking a fence")
+    }
+
+    /// Gets the number of frames that have been presented so far.
+    /// Unlike [`Surface::get_current_frame_index()`] this never wraps, and frames which were skipped or dropped because the swapchain
+    /// was out of date aren't counted
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
 
+    /// Creates the swapchain and a view for each of its images, returning the parameters it was created with
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` which will render to the swapchain
+    /// * `window_size`: The physical size of the window, used when the surface doesn't dictate the extent itself
+    /// * `old_swapchain`: The swapchain being replaced, if any, which the caller must destroy afterwards
+    fn create_swapchain_images(
+        &mut self,
+        device: &Device,
+        window_size: winit::dpi::PhysicalSize<u32>,
+        old_swapchain: Option<vk::SwapchainKHR>,
+    ) -> Result<SwapChainParameters, RendererError> {
         let device_swapchain_info =
             get_swapchain_info(device, &self.surface, &self.surface_extension);
-        let swapchain_parameters =
-            get_swapchain_parameters(&device_swapchain_info, window, None, None);
+        let swapchain_parameters = get_swapchain_parameters(
+            &device_swapchain_info,
+            window_size,
+            self.preferred_surface_format
+                .map(|preferred| (preferred.format, preferred.color_space)),
+            self.preferred_present_mode
+                .or(self.buffering_mode.present_mode())
+                .map(vk::PresentModeKHR::from),
+        )?;
+        let min_image_count =
+            get_min_image_count(&device_swapchain_info.capabilities, self.buffering_mode);
+
+        self.storage_usage_enabled = self.storage_usage_requested
+            && device_swapchain_info
+                .capabilities
+                .supported_usage_flags
+                .contains(vk::ImageUsageFlags::STORAGE)
+            && device.supports_storage_image(swapchain_parameters.surface_format.format);
+        if self.storage_usage_requested && !self.storage_usage_enabled {
+            warn!(
+                "Swapchain images can't be used as storage images with format {:?}",
+                swapchain_parameters.surface_format.format
+            );
+        }
+        self.transfer_source_enabled = device_swapchain_info
+            .capabilities
+            .supported_usage_flags
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC);
+
+        let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        if self.storage_usage_enabled {
+            image_usage |= vk::ImageUsageFlags::STORAGE;
+        }
+        if self.transfer_source_enabled {
+            image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+        }
 
+        let queue_family_indices = device.swapchain_queue_family_indices();
+        let image_sharing_mode = if queue_family_indices.len() > 1 {
+            vk::SharingMode::CONCURRENT
+        } else {
+            vk::SharingMode::EXCLUSIVE
+        };
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
             .surface(self.surface)
             .image_format(swapchain_parameters.surface_format.format)
             .image_color_space(swapchain_parameters.surface_format.color_space)
             .present_mode(swapchain_parameters.present_mode)
             .image_extent(swapchain_parameters.extent)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(image_usage)
             .image_array_layers(1)
+            .image_sharing_mode(image_sharing_mode)
+            .queue_family_indices(&queue_family_indices)
             .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .min_image_count(if device_swapchain_info.capabilities.min_image_count <= 2 {
-                2
-            } else {
-                device_swapchain_info.capabilities.min_image_count
-            })
+            .min_image_count(min_image_count)
+            .old_swapchain(old_swapchain.unwrap_or_default())
             .build();
 
         debug!("Creating SwapchainKHR");
@@ -145,16 +1086,19 @@ impl Surface {
         debug!("Successfully created swapchain");
         self.swapchain = Some(swapchain);
 
-        self._swapchain_images = unsafe {
+        self.swapchain_images = unsafe {
             self.swapchain_extension
                 .as_ref()
                 .unwrap()
                 .get_swapchain_images(swapchain)
         }
         .expect("Failed to create swapchain images");
+        if self.swapchain_images.is_empty() {
+            return Err(RendererError::NoSwapchainImages);
+        }
 
         self.image_views = self
-            ._swapchain_images
+            .swapchain_images
             .iter()
             .map(|image| {
                 let image_view_create_info = vk::ImageViewCreateInfo::builder()
@@ -189,19 +1133,18 @@ impl Surface {
             })
             .collect::<Vec<vk::ImageView>>();
 
-        let semaphore_create_info = vk::SemaphoreCreateInfo::builder().build();
+        self.depth_format = device.find_depth_format();
+        debug!("Selected depth format is {:?}", self.depth_format);
+        self.depth_image = Some(create_depth_image(
+            device,
+            self.depth_format,
+            swapchain_parameters.extent,
+            vk::ImageUsageFlags::empty(),
+            "swapchain depth buffer",
+        ));
 
-        self.image_available = (0..MAX_FRAMES_IN_FLIGHT)
-            .map(|_| {
-                unsafe {
-                    device
-                        .logical_device
-                        .create_semaphore(&semaphore_create_info, None)
-                }
-                .expect("Failed to create semaphore for checking if framebuffer is available")
-            })
-            .collect();
-        self.render_finished = (0..MAX_FRAMES_IN_FLIGHT)
+        let semaphore_create_info = vk::SemaphoreCreateInfo::builder().build();
+        self.render_finished = (0..self.swapchain_images.len())
             .map(|_| {
                 unsafe {
                     device
@@ -212,109 +1155,66 @@ impl Surface {
             })
             .collect();
 
-        let fence_create_info = vk::FenceCreateInfo::builder()
-            .flags(vk::FenceCreateFlags::SIGNALED)
-            .build();
-
-        self.frame_in_flight = (0..MAX_FRAMES_IN_FLIGHT)
-            .map(|_| {
-                unsafe { device.logical_device.create_fence(&fence_create_info, None) }
-                    .expect("Failed to create fence for checking if frame is in flight")
-            })
-            .collect();
-
-        self.swapchain_parameters = Some(swapchain_parameters);
+        Ok(swapchain_parameters)
     }
 
-    pub fn create_framebuffers_for_pipeline(&mut self, device: &Device, pipeline: &Pipeline) {
-        let framebuffers = (0..self.image_views.len())
-            .map(|index| {
-                let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
-                    .render_pass(pipeline.render_pass)
-                    .width(self.swapchain_parameters.as_ref().unwrap().extent.width)
-                    .height(self.swapchain_parameters.as_ref().unwrap().extent.height)
-                    .attachments(&[self.image_views[index]])
-                    .layers(1)
-                    .build();
-
-                unsafe {
-                    device
-                        .logical_device
-                        .create_framebuffer(&framebuffer_create_info, None)
-                }
-                .expect("Failed to create framebuffer")
-            })
-            .collect::<Vec<vk::Framebuffer>>();
-
-        self.framebuffers = Some(framebuffers);
+    /// Destroys the framebuffers for the swapchain images, if there are any
+    fn destroy_framebuffers(&mut self, device: &Device) {
+        if let Some(framebuffers) = self.framebuffers.take() {
+            track_destroyed(ResourceKind::Framebuffer, framebuffers.len());
+            for framebuffer in framebuffers {
+                debug!("Destroying framebuffer {:?}", framebuffer);
+                unsafe { device.logical_device.destroy_framebuffer(framebuffer, None) };
+                debug!("Successfully destroyed framebuffer");
+            }
+        }
     }
 
-    pub fn get_framebuffer(&mut self, index: usize) -> &vk::Framebuffer {
-        let framebuffers = self
-            .framebuffers
-            .as_ref()
-            .expect("No framebuffers have been created, but one has been requested");
-
-        framebuffers.get(index).unwrap()
-    }
+    /// Destroys the framebuffers, image views, and swapchain, leaving the surface and synchronisation objects intact
+    fn destroy_swapchain_images(&mut self, device: &Device) {
+        self.destroy_swapchain_image_views(device);
 
-    pub fn acquire_next_image(&self) -> u32 {
-        unsafe {
-            self.swapchain_extension
-                .as_ref()
-                .unwrap()
-                .acquire_next_image(
-                    self.swapchain.unwrap(),
-                    u64::MAX,
-                    *self
-                        .image_available
-                        .get(self.current_framebuffer_index)
-                        .unwrap(),
-                    vk::Fence::null(),
-                )
+        if let Some(swapchain) = self.swapchain.take() {
+            self.destroy_swapchain(swapchain);
         }
-        .expect("Failed to acquire next image")
-        .0
     }
 
-    pub fn flip_buffers(&mut self, next_image: u32) {
-        let device_guard = self.device.as_ref().unwrap().read();
-        let device_lock = device_guard.unwrap();
-        let device = device_lock.deref();
-
-        device.submit_graphics_queue(
-            self.current_framebuffer_index,
-            &[*self
-                .render_finished
-                .get(self.current_framebuffer_index)
-                .unwrap()],
-            &[*self
-                .image_available
-                .get(self.current_framebuffer_index)
-                .unwrap()],
-            &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
-            self.frame_in_flight
-                .get(self.current_framebuffer_index)
-                .unwrap(),
-        );
+    /// Destroys the framebuffers, image views, and presentation semaphores, leaving the swapchain itself intact so that it can be
+    /// handed over to its replacement
+    fn destroy_swapchain_image_views(&mut self, device: &Device) {
+        self.destroy_framebuffers(device);
 
-        let present_info = vk::PresentInfoKHR::builder()
-            .wait_semaphores(&[*self
-                .render_finished
-                .get(self.current_framebuffer_index)
-                .unwrap()])
-            .swapchains(&[self.swapchain.unwrap()])
-            .image_indices(&[next_image])
-            .build();
+        for semaphore in self.render_finished.drain(..) {
+            unsafe { device.logical_device.destroy_semaphore(semaphore, None) };
+        }
 
-        device.present_queue(self.swapchain_extension.as_ref().unwrap(), &present_info);
+        for image_view in self.image_views.drain(..) {
+            debug!("Destroying image view {:?}", image_view);
+            unsafe { device.logical_device.destroy_image_view(image_view, None) };
+            debug!("Successfully destroyed image view");
+        }
 
-        self.current_framebuffer_index =
-            (self.current_framebuffer_index + 1) % MAX_FRAMES_IN_FLIGHT;
+        // The image is freed when it's dropped, after its view has been destroyed
+        if let Some((_image, depth_view)) = self.depth_image.take() {
+            debug!("Destroying depth buffer");
+            unsafe { device.logical_device.destroy_image_view(depth_view, None) };
+        }
     }
 
-    pub fn get_current_frame_index(&self) -> usize {
-        self.current_framebuffer_index
+    /// Destroys a swapchain whose image views have already been destroyed
+    ///
+    /// # Arguments
+    ///
+    /// * `swapchain`: The swapchain to destroy
+    fn destroy_swapchain(&self, swapchain: vk::SwapchainKHR) {
+        debug!("Destroying swapchain");
+        unsafe {
+            self.swapchain_extension
+                .as_ref()
+                .unwrap()
+                .destroy_swapchain(swapchain, None)
+        };
+        debug!("Successfully destroyed swapchain");
     }
 }
 
@@ -323,7 +1223,8 @@ impl Drop for Surface {
         let span = debug_span!("Vulkan/~Surface");
         let _guard = span.enter();
 
-        let device_guard = self.device.as_ref().unwrap().read();
+        let device_arc = self.device.clone().unwrap();
+        let device_guard = device_arc.read();
         let device_lock = device_guard.unwrap();
         let device = device_lock.deref();
 
@@ -333,11 +1234,6 @@ impl Drop for Surface {
                     .logical_device
                     .destroy_fence(*self.frame_in_flight.get(i).unwrap(), None)
             };
-            unsafe {
-                device
-                    .logical_device
-                    .destroy_semaphore(*self.render_finished.get(i).unwrap(), None)
-            };
             unsafe {
                 device
                     .logical_device
@@ -345,37 +1241,7 @@ impl Drop for Surface {
             };
         }
 
-        match &self.framebuffers {
-            Some(framebuffers) => {
-                for framebuffer in framebuffers {
-                    debug!("Destroying framebuffer {:?}", framebuffer);
-                    unsafe {
-                        device
-                            .logical_device
-                            .destroy_framebuffer(*framebuffer, None)
-                    };
-                    debug!("Successfully destroyed framebuffer");
-                }
-            }
-            None => {}
-        }
-
-        for image_view in &self.image_views {
-            debug!("Destroying image view {:?}", image_view);
-            unsafe { device.logical_device.destroy_image_view(*image_view, None) };
-            debug!("Successfully destroyed image view");
-        }
-
-        if self.swapchain.is_some() {
-            debug!("Destroying swapchain");
-            unsafe {
-                self.swapchain_extension
-                    .as_ref()
-                    .unwrap()
-                    .destroy_swapchain(self.swapchain.unwrap(), None)
-            };
-            debug!("Successfully destroyed swapchain");
-        }
+        self.destroy_swapchain_images(device);
 
         debug!("Destroying surface");
         unsafe { self.surface_extension.destroy_surface(self.surface, None) };
@@ -391,16 +1257,18 @@ impl Drop for Surface {
 ///
 /// The defaults can be overridden with the `preferred_*` variables
 ///
+/// If the surface doesn't support any formats or present modes, returns `Err`
+///
 /// # Arguments
 ///
 /// * `swapchain_info`: A `SwapChainInfo` struct containing information returned by [`get_swapchain_info()`]
-/// * `window`: The window that the swapchain is being created for
+/// * `window_size`: The physical size of the window that the swapchain is being created for
 /// * `preferred_surface_format`: If a different surface format to the ones described above is preferred, this can be set to try prioritise using something else
 /// * `preferred_present_mode`: If a different present mode to the ones described above is preferred, this can be set to try prioritise using something else
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// use client::renderer::vulkan::{Context, Device};
 /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
 /// use ash::{vk, extensions};
@@ -416,16 +1284,23 @@ impl Drop for Surface {
 ///     .expect("Failed to create Vulkan surface");
 ///
 /// let device_swapchain_info = get_swapchain_info(device, &surface, &extension);
-/// let swapchain_parameters = get_swapchain_parameters(&device_swapchain_info, window);
+/// let swapchain_parameters = get_swapchain_parameters(&device_swapchain_info, window.inner_size(), None, None);
 /// ```
 fn get_swapchain_parameters(
     swapchain_info: &SwapChainInfo,
-    window: &winit::window::Window,
+    window_size: winit::dpi::PhysicalSize<u32>,
     preferred_surface_format: Option<(vk::Format, vk::ColorSpaceKHR)>,
     preferred_present_mode: Option<vk::PresentModeKHR>,
-) -> SwapChainParameters {
+) -> Result<SwapChainParameters, RendererError> {
     debug!("Selecting most appropriate swapchain parameters");
 
+    if swapchain_info.formats.is_empty() {
+        return Err(RendererError::NoSurfaceFormats);
+    }
+    if swapchain_info.present_modes.is_empty() {
+        return Err(RendererError::NoPresentModes);
+    }
+
     // Only MacOS and iOS implement `UNDEFINED`, and only with `SRGB_NONLINEAR_KHR`, so `UNDEFINED`/<anything else> will never be found
     // https://www.vulkan.gpuinfo.org/listsurfaceformats.php
     let preferred = preferred_surface_format.unwrap_or((
@@ -447,8 +1322,7 @@ fn get_swapchain_parameters(
                 accum
             }
         })
-        .or(swapchain_info.formats.first())
-        .expect("The device should support at least one surface format");
+        .expect("The formats were checked to not be empty");
     debug!(
         "Selected image format is {:?} with colour space {:?}",
         format.format, format.color_space
@@ -471,14 +1345,19 @@ fn get_swapchain_parameters(
                 accum
             }
         })
-        .expect("Could not find a valid present mode - FIFO should be supported");
+        .expect("The present modes were checked to not be empty");
+    if preferred_present_mode.is_some_and(|preferred| preferred != *present_mode) {
+        warn!(
+            "Preferred present mode {:?} is not supported by the surface",
+            preferred
+        );
+    }
     debug!("Selected present mode is {:?}", present_mode);
 
     let extent = {
         if swapchain_info.capabilities.current_extent.width != u32::MAX {
             swapchain_info.capabilities.current_extent
         } else {
-            let window_size = window.inner_size();
             vk::Extent2D::builder()
                 .width(num::clamp(
                     window_size.width,
@@ -495,11 +1374,102 @@ fn get_swapchain_parameters(
     };
     debug!("Swapchain extent is {}x{}", extent.width, extent.height);
 
-    SwapChainParameters {
+    Ok(SwapChainParameters {
         surface_format: *format,
         present_mode: *present_mode,
         extent,
+    })
+}
+
+/// Clips a dirty rectangle to the swapchain image, returning `None` if none of it is within the image
+///
+/// # Arguments
+///
+/// * `rect`: The dirty rectangle
+/// * `extent`: The size of the swapchain image
+fn clip_rect(rect: &vk::Rect2D, extent: vk::Extent2D) -> Option<vk::RectLayerKHR> {
+    let left = rect.offset.x.clamp(0, extent.width as i32);
+    let top = rect.offset.y.clamp(0, extent.height as i32);
+    let right = (rect.offset.x as i64 + rect.extent.width as i64).clamp(0, extent.width as i64);
+    let bottom = (rect.offset.y as i64 + rect.extent.height as i64).clamp(0, extent.height as i64);
+    if right <= left as i64 || bottom <= top as i64 {
+        return None;
+    }
+
+    Some(
+        vk::RectLayerKHR::builder()
+            .offset(vk::Offset2D { x: left, y: top })
+            .extent(vk::Extent2D {
+                width: (right - left as i64) as u32,
+                height: (bottom - top as i64) as u32,
+            })
+            .layer(0)
+            .build(),
+    )
+}
+
+/// Converts a clear colour to the value a swapchain format expects.
+/// `*_SRGB` formats encode what's written to them, so take linear values, whereas `*_UNORM` formats are presented as-is so must be given sRGB values
+///
+/// # Arguments
+///
+/// * `color`: The clear colour
+/// * `format`: The format of the swapchain, or `None` if it hasn't been created yet
+fn clear_color_value(color: &Color, format: Option<vk::Format>) -> vk::ClearColorValue {
+    let float32 = if format.is_some_and(is_srgb_format) {
+        color.to_linear()
+    } else {
+        color.to_srgb()
+    };
+    vk::ClearColorValue { float32 }
+}
+
+/// Checks whether an image format applies the sRGB transfer function when written to and read from
+///
+/// # Arguments
+///
+/// * `format`: The format to check
+fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::R8_SRGB
+            | vk::Format::R8G8_SRGB
+            | vk::Format::R8G8B8_SRGB
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+    )
+}
+
+/// Gets the number of images the swapchain should be created with for the given buffering mode, clamped to what the surface supports
+///
+/// # Arguments
+///
+/// * `capabilities`: The capabilities of the surface, as returned by [`get_swapchain_info()`]
+/// * `buffering_mode`: The `BufferingMode` that has been requested
+fn get_min_image_count(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    buffering_mode: BufferingMode,
+) -> u32 {
+    let requested = buffering_mode.image_count();
+    // A maximum of 0 means that there's no limit
+    let max_image_count = if capabilities.max_image_count == 0 {
+        u32::MAX
+    } else {
+        capabilities.max_image_count
+    };
+
+    let image_count = num::clamp(requested, capabilities.min_image_count, max_image_count);
+    if image_count != requested {
+        warn!(
+            "{:?} buffering requires {} swapchain images, but the surface supports {} to {}, using {}",
+            buffering_mode, requested, capabilities.min_image_count, max_image_count, image_count
+        );
     }
+    debug!("Swapchain will use {} images", image_count);
+
+    image_count
 }
 
 /// Gets information about the swapchain, based on the surface and device, which can be used by [get_swapchain_parameters()]
@@ -512,7 +1482,7 @@ fn get_swapchain_parameters(
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// use client::renderer::vulkan::{Context, Device};
 /// use winit::{window::WindowBuilder, event_loop::EventLoopBuilder};
 /// use ash::{vk, extensions};
@@ -545,14 +1515,25 @@ fn get_swapchain_info(
         surface_extension.get_physical_device_surface_formats(device.physical_device, *surface)
     }
     .expect("Failed to get physical device surface formats");
-    debug!("Device supports {} surface formats", formats.len());
+    debug!(
+        "Device supports {} surface formats: {:?}",
+        formats.len(),
+        formats
+            .iter()
+            .map(|format| (format.format, format.color_space))
+            .collect::<Vec<_>>()
+    );
 
     let present_modes = unsafe {
         surface_extension
             .get_physical_device_surface_present_modes(device.physical_device, *surface)
     }
     .expect("Failed to get physical device present modes");
-    debug!("Device supports {} present modes", formats.len());
+    debug!(
+        "Device supports {} present modes: {:?}",
+        present_modes.len(),
+        present_modes
+    );
 
     SwapChainInfo {
         capabilities,
@@ -560,3 +1541,35 @@ fn get_swapchain_info(
         present_modes,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cornflower_blue() -> Color {
+        Color::srgb(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0)
+    }
+
+    fn assert_components_eq(actual: [f32; 4], expected: [f32; 4]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 0.001,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn unorm_clear_color_is_srgb() {
+        let value = clear_color_value(&cornflower_blue(), Some(vk::Format::B8G8R8A8_UNORM));
+        assert_components_eq(unsafe { value.float32 }, [0.392, 0.584, 0.929, 1.0]);
+    }
+
+    #[test]
+    fn srgb_clear_color_is_linear() {
+        let value = clear_color_value(&cornflower_blue(), Some(vk::Format::B8G8R8A8_SRGB));
+        assert_components_eq(unsafe { value.float32 }, cornflower_blue().to_linear());
+    }
+}
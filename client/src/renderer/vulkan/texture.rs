@@ -0,0 +1,409 @@
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use image::RgbaImage;
+use tracing::{debug, debug_span, warn};
+
+use crate::renderer::vulkan::surface::MAX_FRAMES_IN_FLIGHT;
+use crate::renderer::vulkan::{Device, Image, Sampler};
+
+/// A sampled 2D image, created with [`Device::create_texture()`] and bound for drawing with [`Device::bind_texture()`], or an array
+/// of 2D layers created with [`Device::create_texture_array()`] and bound with [`Device::bind_texture_array()`].
+/// Once uploaded, the image is left in `SHADER_READ_ONLY_OPTIMAL`
+pub struct Texture {
+    device: Weak<ash::Device>,
+    // Dropped after the view has been destroyed
+    image: Image,
+    view: vk::ImageView,
+    sampler: Sampler,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layer_count: u32,
+    // Whether the view is a `TYPE_2D_ARRAY`, which it is for array textures even if they only have one layer
+    is_array: bool,
+}
+
+impl Texture {
+    /// Constructs a new `Texture` from an image whose contents have already been uploaded, creating a view and sampler for it.
+    /// Note that the recommended way to create a texture is through [`Device::create_texture()`], which also uploads the texels
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` the image was created on
+    /// * `image`: The image, which must have been created with `SAMPLED` usage
+    /// * `format`: The format of the image
+    /// * `extent`: The size of the image
+    pub fn new(device: &Device, image: Image, format: vk::Format, extent: vk::Extent2D) -> Self {
+        Self::with_layers(device, image, format, extent, 1, false)
+    }
+
+    /// Constructs a new array `Texture` from an image whose layers have already been uploaded, creating a `TYPE_2D_ARRAY` view
+    /// and sampler for it, so that shaders can sample it as a `sampler2DArray`.
+    /// Note that the recommended way to create an array texture is through [`Device::create_texture_array()`]
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` the image was created on
+    /// * `image`: The image, which must have been created with `SAMPLED` usage and `layer_count` array layers
+    /// * `format`: The format of the image
+    /// * `extent`: The size of each layer
+    /// * `layer_count`: The number of layers in the image
+    pub fn new_array(
+        device: &Device,
+        image: Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        layer_count: u32,
+    ) -> Self {
+        Self::with_layers(device, image, format, extent, layer_count, true)
+    }
+
+    fn with_layers(
+        device: &Device,
+        image: Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        layer_count: u32,
+        is_array: bool,
+    ) -> Self {
+        let span = debug_span!("Vulkan/Texture");
+        let _guard = span.enter();
+
+        debug!(
+            "Creating {}x{}x{} texture with format {:?}",
+            extent.width, extent.height, layer_count, format
+        );
+        let view_type = if is_array {
+            vk::ImageViewType::TYPE_2D_ARRAY
+        } else {
+            vk::ImageViewType::TYPE_2D
+        };
+        let view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(image.image)
+            .view_type(view_type)
+            .format(format)
+            .subresource_range(texture_subresource_range(layer_count))
+            .build();
+        let view = unsafe {
+            device
+                .logical_device
+                .create_image_view(&view_create_info, None)
+        }
+        .expect("Failed to create texture image view");
+
+        let sampler = device
+            .create_sampler(&Default::default())
+            .expect("The default sampler parameters are valid");
+
+        Texture {
+            device: Rc::downgrade(&device.logical_device),
+            image,
+            view,
+            sampler,
+            format,
+            extent,
+            layer_count,
+            is_array,
+        }
+    }
+
+    /// Gets the view of the image, so that it can be bound for sampling
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    /// Gets the sampler the texture is bound with
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Gets the image, eg. so that barriers can be recorded against it
+    pub fn image(&self) -> vk::Image {
+        self.image.image
+    }
+
+    /// Gets the format of the image
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// Gets the size of the image
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Gets the number of layers in the image, which is 1 unless it was created as an array
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    /// Gets whether the texture was created as an array, so that it can be bound with [`Device::bind_texture_array()`]
+    pub fn is_array(&self) -> bool {
+        self.is_array
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~Texture");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying texture");
+        unsafe { device.destroy_image_view(self.view, None) };
+    }
+}
+
+/// Loads an image file (eg. a PNG) and converts it to 8-bit RGBA, which can be passed to [`Device::create_texture()`] with an
+/// `R8G8B8A8_SRGB` format (or `R8G8B8A8_UNORM` for data that isn't colour, such as normal maps)
+///
+/// If the file can't be read or decoded, returns `Err`
+///
+/// # Arguments
+///
+/// * `path`: The path of the image file
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use client::renderer::vulkan::Device;
+/// # fn example(device: &mut Device) -> Result<(), &'static str> {
+/// use ash::vk;
+/// use client::renderer::vulkan::load_texture_pixels;
+///
+/// let pixels = load_texture_pixels(&device.resolve_asset_path(Path::new("res/textures/grass.png")))?;
+/// let grass = device.create_texture(&pixels, pixels.width(), pixels.height(), vk::Format::R8G8B8A8_SRGB)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_texture_pixels(path: &Path) -> Result<RgbaImage, &'static str> {
+    let image = image::open(path).map_err(|error| {
+        warn!("Failed to load texture {:?} ({})", path, error);
+        "Failed to load texture"
+    })?;
+    Ok(image.into_rgba8())
+}
+
+/// Gets the number of bytes per texel of a format that textures can be created with, or `None` if it isn't supported
+///
+/// # Arguments
+///
+/// * `format`: The format of the texture
+pub(crate) fn texture_texel_size(format: vk::Format) -> Option<u64> {
+    match format {
+        vk::Format::R8_UNORM => Some(1),
+        vk::Format::R8G8_UNORM => Some(2),
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB => Some(4),
+        _ => None,
+    }
+}
+
+/// Allocates an image that texels can be copied into and then sampled from. The image is exclusive to one queue family, so
+/// [`Device::submit_transfer()`] moves it from the transfer queue to the graphics queue once it's uploaded
+///
+/// # Arguments
+///
+/// * `device`: The `Device` to create the image on
+/// * `format`: The format of the image
+/// * `extent`: The size of the image
+/// * `layer_count`: The number of array layers in the image
+pub(crate) fn create_texture_image(
+    device: &Device,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layer_count: u32,
+) -> Image {
+    let image_create_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(
+            vk::Extent3D::builder()
+                .width(extent.width)
+                .height(extent.height)
+                .depth(1)
+                .build(),
+        )
+        .mip_levels(1)
+        .array_layers(layer_count)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .build();
+    device.allocate_image(&image_create_info, MemoryLocation::GpuOnly, "texture")
+}
+
+/// Gets the subresource range covering the whole of a texture, which has a single mip level
+///
+/// # Arguments
+///
+/// * `layer_count`: The number of array layers in the texture
+pub(crate) fn texture_subresource_range(layer_count: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(layer_count)
+        .build()
+}
+
+/// The number of times [`Device::bind_texture_array()`] can be called in each frame
+const MAX_TEXTURE_ARRAY_BINDS_PER_FRAME: u32 = 64;
+
+/// The layout of the descriptor sets that array textures are bound with, and a pool of them for each frame in flight.
+/// Each bind allocates a new set, so that binding another texture doesn't change what earlier draws in the frame sample.
+/// A frame's pool is reset once its previous submission has finished, freeing every set allocated from it
+pub(crate) struct TextureArrayDescriptors {
+    device: Weak<ash::Device>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    // Indexed by frame in flight
+    descriptor_pools: Vec<vk::DescriptorPool>,
+}
+
+impl TextureArrayDescriptors {
+    /// Constructs a new `TextureArrayDescriptors`, with a layout holding a single combined image sampler at binding 0
+    ///
+    /// # Arguments
+    ///
+    /// * `device`: The `Device` to create the layout and pools on
+    pub(crate) fn new(device: &Device) -> Self {
+        let span = debug_span!("Vulkan/TextureArrayDescriptors");
+        let _guard = span.enter();
+
+        let bindings = [vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build()];
+        let descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&bindings)
+            .build();
+        let descriptor_set_layout = unsafe {
+            device
+                .logical_device
+                .create_descriptor_set_layout(&descriptor_set_layout_create_info, None)
+        }
+        .expect("Failed to create texture array descriptor set layout");
+
+        debug!(
+            "Creating {} texture array descriptor pools of {} sets",
+            MAX_FRAMES_IN_FLIGHT, MAX_TEXTURE_ARRAY_BINDS_PER_FRAME
+        );
+        let pool_sizes = [vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(MAX_TEXTURE_ARRAY_BINDS_PER_FRAME)
+            .build()];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(MAX_TEXTURE_ARRAY_BINDS_PER_FRAME)
+            .pool_sizes(&pool_sizes)
+            .build();
+        let descriptor_pools = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                unsafe {
+                    device
+                        .logical_device
+                        .create_descriptor_pool(&descriptor_pool_create_info, None)
+                }
+                .expect("Failed to create texture array descriptor pool")
+            })
+            .collect();
+
+        TextureArrayDescriptors {
+            device: Rc::downgrade(&device.logical_device),
+            descriptor_set_layout,
+            descriptor_pools,
+        }
+    }
+
+    /// Gets the layout of the descriptor sets, which pipelines list in
+    /// [`PipelineParameters::descriptor_set_layouts`](crate::renderer::vulkan::PipelineParameters::descriptor_set_layouts)
+    pub(crate) fn descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.descriptor_set_layout
+    }
+
+    /// Frees every descriptor set allocated for a frame in flight. The frame's previous submission must have finished
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight
+    pub(crate) fn reset(&self, frame_index: usize) {
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pools[frame_index],
+                vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to reset texture array descriptor pool");
+    }
+
+    /// Allocates a descriptor set for a frame in flight, pointing at an array texture
+    ///
+    /// If the frame has already allocated as many sets as its pool holds, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_index`: The index of the frame in flight
+    /// * `view`: The `TYPE_2D_ARRAY` view of the texture, which must be in `SHADER_READ_ONLY_OPTIMAL`
+    /// * `sampler`: The sampler to sample the texture with
+    pub(crate) fn allocate(
+        &self,
+        frame_index: usize,
+        view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Result<vk::DescriptorSet, &'static str> {
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        let set_layouts = [self.descriptor_set_layout];
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.descriptor_pools[frame_index])
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_set =
+            unsafe { device.allocate_descriptor_sets(&descriptor_set_allocate_info) }
+                .map_err(|_| "Too many texture arrays have been bound in this frame")?[0];
+
+        let image_infos = [vk::DescriptorImageInfo::builder()
+            .image_view(view)
+            .sampler(sampler)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build()];
+        let descriptor_writes = [vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos)
+            .build()];
+        unsafe { device.update_descriptor_sets(&descriptor_writes, &[]) };
+        Ok(descriptor_set)
+    }
+}
+
+impl Drop for TextureArrayDescriptors {
+    fn drop(&mut self) {
+        let span = debug_span!("Vulkan/~TextureArrayDescriptors");
+        let _guard = span.enter();
+
+        let device = self.device.upgrade().expect("Device should still exist");
+
+        debug!("Destroying texture array descriptor pools");
+        // Destroying the pools frees the sets allocated from them
+        unsafe {
+            for descriptor_pool in &self.descriptor_pools {
+                device.destroy_descriptor_pool(*descriptor_pool, None);
+            }
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+}
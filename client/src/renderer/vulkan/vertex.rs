@@ -0,0 +1,40 @@
+use ash::vk;
+
+/// A coloured vertex of general geometry, as it's laid out in the vertex buffer
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    /// The position in normalised device coordinates
+    pub position: [f32; 2],
+    /// The colour in linear space
+    pub color: [f32; 3],
+}
+
+impl Vertex {
+    /// Gets the description of the vertex buffer binding that pipelines drawing `Vertex` geometry read from
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<Vertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    /// Gets the descriptions of each field of the vertex, which match the inputs of `test_triangle.vert`
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        [
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(std::mem::offset_of!(Vertex, position) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(std::mem::offset_of!(Vertex, color) as u32)
+                .build(),
+        ]
+    }
+}
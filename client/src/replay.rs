@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::input::InputEvent;
+
+/// An input event, and when it was received
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedEvent {
+    /// The number of frames that had been rendered when the event was received
+    frame: u64,
+    /// The time since recording started, which is kept for reference but not used when replaying
+    elapsed: Duration,
+    event: InputEvent,
+}
+
+/// The contents of a recording file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Recording {
+    events: Vec<RecordedEvent>,
+}
+
+/// Records input events so that a session can be replayed with [`InputPlayback`], eg. to reproduce a bug.
+/// Events are stamped with the frame they arrived before, so that replaying them is independent of how long each frame took
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use client::input::InputState;
+/// # use winit::event::WindowEvent;
+/// # use winit::window::Window;
+/// # fn example(event: WindowEvent, window: &Window, input_state: &mut InputState) -> Result<(), &'static str> {
+/// use client::input::InputEvent;
+/// use client::replay::InputRecorder;
+///
+/// let mut recorder = InputRecorder::new();
+/// // For every window or device event
+/// if let Some(input_event) = InputEvent::from_window_event(&event) {
+///     recorder.record(&input_event);
+///     input_state.handle_event(&window, &input_event);
+/// }
+/// // Once per frame, after handling input
+/// recorder.next_frame();
+/// // When the session ends
+/// recorder.save(Path::new("session.toml"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct InputRecorder {
+    start: Instant,
+    frame: u64,
+    recording: Recording,
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            frame: 0,
+            recording: Recording::default(),
+        }
+    }
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an event as arriving before the current frame
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: The event to record
+    pub fn record(&mut self, event: &InputEvent) {
+        self.recording.events.push(RecordedEvent {
+            frame: self.frame,
+            elapsed: self.start.elapsed(),
+            event: event.clone(),
+        });
+    }
+
+    /// Moves on to the next frame, so that events recorded from now on are replayed before it
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Saves the events recorded so far to a TOML file, replacing it if it exists
+    ///
+    /// If the file can't be written, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the recording file
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let contents = toml::to_string(&self.recording)
+            .map_err(|_| "Failed to serialize the input recording")?;
+        std::fs::write(path, contents).map_err(|_| "Failed to write the input recording")?;
+
+        info!(
+            "Saved {} input events over {} frames to {:?}",
+            self.recording.events.len(),
+            self.frame,
+            path
+        );
+        Ok(())
+    }
+}
+
+/// Replays input recorded with [`InputRecorder`], handing back each frame's events in the order they were recorded.
+/// Live input should be ignored whilst replaying, so that the session plays out exactly as it was recorded
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use client::input::InputState;
+/// # use winit::window::Window;
+/// # fn example(window: &Window, input_state: &mut InputState) -> Result<(), &'static str> {
+/// use client::replay::InputPlayback;
+///
+/// let mut playback = InputPlayback::load(Path::new("session.toml"))?;
+/// // Once per frame, before reading the input state
+/// for input_event in playback.take_frame_events() {
+///     input_state.handle_event(&window, &input_event);
+/// }
+/// if playback.is_finished() {
+///     // Stop the session
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct InputPlayback {
+    frame: u64,
+    events: VecDeque<RecordedEvent>,
+}
+
+impl InputPlayback {
+    /// Loads a recording saved by [`InputRecorder::save()`]
+    ///
+    /// If the file can't be read or isn't a valid recording, returns `Err`
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the recording file
+    pub fn load(path: &Path) -> Result<Self, &'static str> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|_| "Failed to read the input recording")?;
+        let recording: Recording = toml::from_str(&contents).map_err(|error| {
+            warn!("Failed to parse input recording {:?} ({})", path, error);
+            "The input recording is invalid"
+        })?;
+
+        info!(
+            "Loaded {} input events from {:?}",
+            recording.events.len(),
+            path
+        );
+        Ok(Self {
+            frame: 0,
+            events: recording.events.into(),
+        })
+    }
+
+    /// Gets the events that were recorded before the current frame, and moves on to the next frame
+    pub fn take_frame_events(&mut self) -> Vec<InputEvent> {
+        let mut frame_events = vec![];
+        while self
+            .events
+            .front()
+            .is_some_and(|recorded| recorded.frame <= self.frame)
+        {
+            frame_events.push(self.events.pop_front().unwrap().event);
+        }
+
+        if !frame_events.is_empty() {
+            debug!(
+                "Replaying {} input events for frame {}",
+                frame_events.len(),
+                self.frame
+            );
+        }
+        self.frame += 1;
+        frame_events
+    }
+
+    /// Gets whether every recorded event has been replayed
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::MouseButton;
+
+    #[test]
+    fn saved_events_are_replayed_on_the_frames_they_were_recorded() {
+        let key_press = InputEvent::Key {
+            key: String::from("w"),
+            pressed: true,
+            repeat: false,
+        };
+        let click = InputEvent::MouseButton {
+            button: MouseButton::Left,
+            pressed: true,
+        };
+        let motion = InputEvent::MouseMotion {
+            delta: (1.5, -0.25),
+        };
+
+        let mut recorder = InputRecorder::new();
+        recorder.record(&InputEvent::Focused(true));
+        recorder.record(&key_press);
+        recorder.next_frame();
+        recorder.next_frame();
+        recorder.record(&click);
+        recorder.record(&motion);
+
+        let path = std::env::temp_dir().join(format!("replay-test-{}.toml", std::process::id()));
+        recorder.save(&path).unwrap();
+        let playback = InputPlayback::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut playback = playback.unwrap();
+
+        assert_eq!(
+            playback.take_frame_events(),
+            vec![InputEvent::Focused(true), key_press]
+        );
+        assert_eq!(playback.take_frame_events(), vec![]);
+        assert!(!playback.is_finished());
+        assert_eq!(playback.take_frame_events(), vec![click, motion]);
+        assert!(playback.is_finished());
+    }
+}
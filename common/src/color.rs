@@ -0,0 +1,114 @@
+//! Colours with an explicit colour space, so that they can be converted correctly for the target they're written to
+
+/// An RGBA colour, stored in linear space.
+///
+/// Colours picked in an image editor or from a colour chart are almost always in sRGB (gamma) space, so should be created with [`Color::srgb()`].
+/// Values which are already linear, such as lighting results, should be created with [`Color::linear()`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    red: f32,
+    green: f32,
+    blue: f32,
+    alpha: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::linear(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::linear(1.0, 1.0, 1.0);
+
+    /// Constructs an opaque colour from sRGB components in the range 0 to 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common::color::Color;
+    ///
+    /// let cornflower_blue = Color::srgb(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0);
+    /// ```
+    pub fn srgb(red: f32, green: f32, blue: f32) -> Self {
+        Self::srgba(red, green, blue, 1.0)
+    }
+
+    /// Constructs a colour from sRGB components in the range 0 to 1. Alpha is always linear
+    pub fn srgba(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+        Self {
+            red: srgb_to_linear(red),
+            green: srgb_to_linear(green),
+            blue: srgb_to_linear(blue),
+            alpha,
+        }
+    }
+
+    /// Constructs an opaque colour from linear components in the range 0 to 1
+    pub const fn linear(red: f32, green: f32, blue: f32) -> Self {
+        Self::linear_rgba(red, green, blue, 1.0)
+    }
+
+    /// Constructs a colour from linear components in the range 0 to 1
+    pub const fn linear_rgba(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    /// Gets the colour as linear RGBA components, which is what `*_SRGB` images expect to be written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common::color::Color;
+    ///
+    /// let [red, green, blue, alpha] = Color::srgb(0.5, 0.5, 0.5).to_linear();
+    /// assert!((red - 0.214).abs() < 0.001);
+    /// assert_eq!(alpha, 1.0);
+    /// ```
+    pub fn to_linear(&self) -> [f32; 4] {
+        [self.red, self.green, self.blue, self.alpha]
+    }
+
+    /// Gets the colour as sRGB-encoded RGBA components, which is what `*_UNORM` images presented as sRGB expect to be written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common::color::Color;
+    ///
+    /// let [red, ..] = Color::srgb(0.5, 0.5, 0.5).to_srgb();
+    /// assert!((red - 0.5).abs() < 0.001);
+    /// ```
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.red),
+            linear_to_srgb(self.green),
+            linear_to_srgb(self.blue),
+            self.alpha,
+        ]
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::BLACK
+    }
+}
+
+/// Converts a single sRGB-encoded component to linear, using the piecewise sRGB transfer function
+fn srgb_to_linear(component: f32) -> f32 {
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a single linear component to sRGB encoding, using the piecewise sRGB transfer function
+fn linear_to_srgb(component: f32) -> f32 {
+    if component <= 0.0031308 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    }
+}
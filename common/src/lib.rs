@@ -1,3 +1,5 @@
+pub mod color;
+pub mod math;
 
 
 pub fn version() -> &'static str{
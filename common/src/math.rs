@@ -0,0 +1,188 @@
+//! Transform helpers for building model, view, and projection matrices.
+//!
+//! Projections follow Vulkan's clip space conventions, where the Y axis points down and depth ranges from 0 to 1,
+//! rather than OpenGL's Y-up, -1 to 1 convention
+
+pub use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+/// Constructs a right-handed perspective projection matrix for Vulkan's clip space
+///
+/// # Arguments
+///
+/// * `vertical_fov`: The vertical field of view, in radians
+/// * `aspect_ratio`: The width of the viewport divided by its height
+/// * `near`: The distance to the near clipping plane, which maps to a depth of 0
+/// * `far`: The distance to the far clipping plane, which maps to a depth of 1
+///
+/// # Examples
+///
+/// ```
+/// use common::math::perspective;
+///
+/// let projection = perspective(70f32.to_radians(), 1280.0 / 720.0, 0.1, 1000.0);
+/// ```
+pub fn perspective(vertical_fov: f32, aspect_ratio: f32, near: f32, far: f32) -> Mat4 {
+    // glam's `_rh` projections already use a 0 to 1 depth range, so only Y needs flipping
+    flip_y(Mat4::perspective_rh(vertical_fov, aspect_ratio, near, far))
+}
+
+/// Constructs a right-handed perspective projection matrix with reversed depth and no far plane, for Vulkan's clip space.
+/// The near plane maps to a depth of 1 and infinity to 0, which spreads floating point depth precision far more evenly across the scene.
+/// Pipelines using it must compare depth with `GREATER`, and clear depth to 0
+///
+/// # Arguments
+///
+/// * `vertical_fov`: The vertical field of view, in radians
+/// * `aspect_ratio`: The width of the viewport divided by its height
+/// * `near`: The distance to the near clipping plane, which maps to a depth of 1
+///
+/// # Examples
+///
+/// ```
+/// use common::math::perspective_reverse_z;
+///
+/// let projection = perspective_reverse_z(70f32.to_radians(), 1280.0 / 720.0, 0.1);
+/// ```
+pub fn perspective_reverse_z(vertical_fov: f32, aspect_ratio: f32, near: f32) -> Mat4 {
+    flip_y(Mat4::perspective_infinite_reverse_rh(
+        vertical_fov,
+        aspect_ratio,
+        near,
+    ))
+}
+
+/// Constructs a right-handed orthographic projection matrix for Vulkan's clip space
+///
+/// # Arguments
+///
+/// * `left`, `right`: The horizontal bounds of the view volume
+/// * `bottom`, `top`: The vertical bounds of the view volume
+/// * `near`, `far`: The depth bounds of the view volume, which map to depths of 0 and 1 respectively
+///
+/// # Examples
+///
+/// ```
+/// use common::math::orthographic;
+///
+/// // Maps pixel coordinates to the screen, with the origin in the bottom left
+/// let projection = orthographic(0.0, 1280.0, 0.0, 720.0, 0.0, 1.0);
+/// ```
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    flip_y(Mat4::orthographic_rh(left, right, bottom, top, near, far))
+}
+
+/// Constructs a right-handed view matrix for a camera at `eye` looking towards `target`
+///
+/// # Arguments
+///
+/// * `eye`: The position of the camera
+/// * `target`: The position the camera is looking at
+/// * `up`: The direction that is up, usually `Vec3::Y`
+///
+/// # Examples
+///
+/// ```
+/// use common::math::{look_at, Vec3};
+///
+/// let view = look_at(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y);
+/// ```
+pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    Mat4::look_at_rh(eye, target, up)
+}
+
+/// Constructs a model matrix which scales, then rotates, then translates
+///
+/// # Arguments
+///
+/// * `translation`: The position of the model
+/// * `rotation`: The orientation of the model
+/// * `scale`: The scale of the model along each axis
+///
+/// # Examples
+///
+/// ```
+/// use common::math::{trs, Quat, Vec3};
+///
+/// let model = trs(Vec3::new(1.0, 0.0, 0.0), Quat::from_rotation_y(0.5), Vec3::ONE);
+/// ```
+pub fn trs(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+/// Negates the Y axis of a clip space transform, converting from glam's Y-up convention to Vulkan's Y-down convention
+fn flip_y(projection: Mat4) -> Mat4 {
+    Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)) * projection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    /// Transforms a point to normalised device coordinates, dividing by `w`
+    fn to_ndc(projection: Mat4, point: Vec3) -> Vec3 {
+        let clip = projection * point.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    #[test]
+    fn perspective_maps_near_to_0_and_far_to_1() {
+        let projection = perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+
+        // Right-handed, so the camera looks down -Z
+        let near = to_ndc(projection, Vec3::new(0.0, 0.0, -0.1));
+        let far = to_ndc(projection, Vec3::new(0.0, 0.0, -100.0));
+        assert!(near.z.abs() < EPSILON, "near maps to {}", near.z);
+        assert!((far.z - 1.0).abs() < EPSILON, "far maps to {}", far.z);
+    }
+
+    #[test]
+    fn perspective_maps_up_to_negative_y() {
+        let projection = perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+
+        let above = to_ndc(projection, Vec3::new(0.0, 1.0, -5.0));
+        assert!(above.y < 0.0, "up maps to {}", above.y);
+    }
+
+    #[test]
+    fn orthographic_maps_up_to_negative_y() {
+        let projection = orthographic(0.0, 1280.0, 0.0, 720.0, 0.0, 1.0);
+
+        let top = to_ndc(projection, Vec3::new(640.0, 720.0, 0.0));
+        let bottom = to_ndc(projection, Vec3::new(640.0, 0.0, 0.0));
+        assert!((top.y + 1.0).abs() < EPSILON, "the top maps to {}", top.y);
+        assert!(
+            (bottom.y - 1.0).abs() < EPSILON,
+            "the bottom maps to {}",
+            bottom.y
+        );
+    }
+
+    #[test]
+    fn perspective_reverse_z_maps_near_to_1() {
+        let projection = perspective_reverse_z(std::f32::consts::FRAC_PI_2, 1.0, 0.1);
+
+        let near = to_ndc(projection, Vec3::new(0.0, 0.0, -0.1));
+        let distant = to_ndc(projection, Vec3::new(0.0, 0.0, -1.0e6));
+        assert!((near.z - 1.0).abs() < EPSILON, "near maps to {}", near.z);
+        assert!(distant.z < EPSILON, "a distant point maps to {}", distant.z);
+    }
+
+    #[test]
+    fn trs_scales_then_rotates_then_translates() {
+        let model = trs(
+            Vec3::new(10.0, 0.0, 0.0),
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            Vec3::new(2.0, 1.0, 1.0),
+        );
+
+        // Scaled to (2, 0, 0), rotated to (0, 2, 0), then translated
+        let point = model.transform_point3(Vec3::X);
+        assert!(
+            point.abs_diff_eq(Vec3::new(10.0, 2.0, 0.0), EPSILON),
+            "the point was transformed to {}",
+            point
+        );
+    }
+}